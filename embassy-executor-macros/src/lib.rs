@@ -25,7 +25,8 @@ impl Parse for Args {
 }
 
 /// Declares an async task that can be run by `embassy-executor`. The optional `pool_size` parameter can be used to specify how
-/// many concurrent tasks can be spawned (default is 1) for the function.
+/// many concurrent tasks can be spawned (default is 1) for the function. The optional `priority` parameter selects the
+/// priority level the task is spawned at by a `PrioritySpawner`.
 ///
 ///
 /// The following restrictions apply:
@@ -54,6 +55,24 @@ impl Parse for Args {
 ///     // Function body
 /// }
 /// ```
+///
+/// Declaring a task returning a value, which can be retrieved by spawning it with `Spawner::spawn_joinable()`:
+///
+/// ``` rust
+/// #[embassy_executor::task]
+/// async fn mytask() -> u32 {
+///     42
+/// }
+/// ```
+///
+/// Declaring a task running at priority level 2:
+///
+/// ``` rust
+/// #[embassy_executor::task(priority = 2)]
+/// async fn mytask() {
+///     // Function body
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn task(args: TokenStream, item: TokenStream) -> TokenStream {
     let args = syn::parse_macro_input!(args as Args);
@@ -10,6 +10,8 @@ use crate::util::ctxt::Ctxt;
 struct Args {
     #[darling(default)]
     pool_size: Option<syn::Expr>,
+    #[darling(default)]
+    priority: Option<syn::Expr>,
 }
 
 pub fn run(args: &[NestedMeta], f: syn::ItemFn) -> Result<TokenStream, TokenStream> {
@@ -20,6 +22,8 @@ pub fn run(args: &[NestedMeta], f: syn::ItemFn) -> Result<TokenStream, TokenStre
         lit: Lit::Int(LitInt::new("1", Span::call_site())),
     }));
 
+    let spawn_token_priority = args.priority.map(|priority| quote!(.with_priority(#priority)));
+
     let ctxt = Ctxt::new();
 
     if f.sig.asyncness.is_none() {
@@ -37,17 +41,14 @@ pub fn run(args: &[NestedMeta], f: syn::ItemFn) -> Result<TokenStream, TokenStre
     if !f.sig.variadic.is_none() {
         ctxt.error_spanned_by(&f.sig, "task functions must not be variadic");
     }
-    match &f.sig.output {
-        ReturnType::Default => {}
+    // The output type is part of the SpawnToken, for JoinHandle. `!` can't be named there on stable.
+    let output = match &f.sig.output {
+        ReturnType::Default => quote!(()),
         ReturnType::Type(_, ty) => match &**ty {
-            Type::Tuple(tuple) if tuple.elems.is_empty() => {}
-            Type::Never(_) => {}
-            _ => ctxt.error_spanned_by(
-                &f.sig,
-                "task functions must either not return a value, return `()` or return `!`",
-            ),
+            Type::Never(_) => quote!(impl Sized),
+            _ => quote!(#ty),
         },
-    }
+    };
 
     let mut args = Vec::new();
     let mut fargs = f.sig.inputs.clone();
@@ -92,19 +93,21 @@ pub fn run(args: &[NestedMeta], f: syn::ItemFn) -> Result<TokenStream, TokenStre
 
     #[cfg(feature = "nightly")]
     let mut task_outer: ItemFn = parse_quote! {
-        #visibility fn #task_ident(#fargs) -> ::embassy_executor::SpawnToken<impl Sized> {
-            type Fut = impl ::core::future::Future + 'static;
+        #visibility fn #task_ident(#fargs) -> ::embassy_executor::SpawnToken<impl Sized, #output> {
+            type Fut = impl ::core::future::Future<Output = #output> + 'static;
             const POOL_SIZE: usize = #pool_size;
             static POOL: ::embassy_executor::raw::TaskPool<Fut, POOL_SIZE> = ::embassy_executor::raw::TaskPool::new();
-            unsafe { POOL._spawn_async_fn(move || #task_inner_ident(#(#full_args,)*)) }
+            let token = unsafe { POOL._spawn_async_fn(move || #task_inner_ident(#(#full_args,)*)) };
+            token #spawn_token_priority
         }
     };
     #[cfg(not(feature = "nightly"))]
     let mut task_outer: ItemFn = parse_quote! {
-        #visibility fn #task_ident(#fargs) -> ::embassy_executor::SpawnToken<impl Sized> {
+        #visibility fn #task_ident(#fargs) -> ::embassy_executor::SpawnToken<impl Sized, #output> {
             const POOL_SIZE: usize = #pool_size;
             static POOL: ::embassy_executor::_export::TaskPoolRef = ::embassy_executor::_export::TaskPoolRef::new();
-            unsafe { POOL.get::<_, POOL_SIZE>()._spawn_async_fn(move || #task_inner_ident(#(#full_args,)*)) }
+            let token = unsafe { POOL.get::<_, POOL_SIZE>()._spawn_async_fn(move || #task_inner_ident(#(#full_args,)*)) };
+            token #spawn_token_priority
         }
     };
 
@@ -38,6 +38,7 @@ def feature(name, default, min=None, max=None, pow2=None, vals=None, factors=[])
 feature(
     "task_arena_size", default=4096, min=64, max=1024 * 1024, pow2=True, factors=[3, 5]
 )
+feature("poll_budget", default=0, vals=[0, 1, 2, 4, 8, 16, 32, 64, 128])
 
 # ========= Update Cargo.toml
 
@@ -10,6 +10,7 @@ fn __pender(context: *mut ()) {
         #[cfg(feature = "executor-thread")]
         // Try to make Rust optimize the branching away if we only use thread mode.
         if !cfg!(feature = "executor-interrupt") || context == THREAD_PENDER {
+            SIGNAL_WORK_THREAD_MODE.store(true, core::sync::atomic::Ordering::SeqCst);
             core::arch::asm!("sev");
             return;
         }
@@ -49,12 +50,34 @@ mod thread {
     pub(super) const THREAD_PENDER: usize = usize::MAX;
 
     use core::arch::asm;
+    use core::cell::Cell;
     use core::marker::PhantomData;
+    use core::sync::atomic::{AtomicBool, Ordering};
 
+    use critical_section::Mutex;
     pub use embassy_executor_macros::main_cortex_m as main;
 
     use crate::{raw, Spawner};
 
+    /// Set by the pender when the thread-mode executor has work to do.
+    pub(super) static SIGNAL_WORK_THREAD_MODE: AtomicBool = AtomicBool::new(false);
+
+    static IDLE_HOOK: Mutex<Cell<Option<fn()>>> = Mutex::new(Cell::new(None));
+
+    /// Set the hook called by the thread-mode [`Executor`] when it has no more work to do.
+    ///
+    /// By default, or if `hook` is `None`, the executor sleeps with `WFE`. A hook allows
+    /// doing something else instead, like feeding a watchdog or entering a deeper sleep mode
+    /// than the one `WFE` triggers.
+    ///
+    /// The hook is only called when the run queue is empty, with interrupts disabled so that
+    /// a task can't be woken between this check and going to sleep. It must either return
+    /// right away, or sleep with an instruction that wakes up on pending interrupts even when
+    /// they're masked, such as `WFI`. Interrupts are serviced after it returns.
+    pub fn set_idle_hook(hook: Option<fn()>) {
+        critical_section::with(|cs| IDLE_HOOK.borrow(cs).set(hook));
+    }
+
     /// Thread mode executor, using WFE/SEV.
     ///
     /// This is the simplest and most common kind of executor. It runs on
@@ -64,7 +87,8 @@ mod thread {
     ///
     /// This executor allows for ultra low power consumption for chips where `WFE`
     /// triggers low-power sleep without extra steps. If your chip requires extra steps,
-    /// you may use [`raw::Executor`] directly to program custom behavior.
+    /// you may register them with [`set_idle_hook()`], or use [`raw::Executor`] directly
+    /// to program custom behavior.
     pub struct Executor {
         inner: raw::Executor,
         not_send: PhantomData<*mut ()>,
@@ -101,10 +125,17 @@ mod thread {
             init(self.inner.spawner());
 
             loop {
-                unsafe {
-                    self.inner.poll();
-                    asm!("wfe");
-                };
+                SIGNAL_WORK_THREAD_MODE.store(false, Ordering::SeqCst);
+                unsafe { self.inner.poll() };
+
+                match critical_section::with(|cs| IDLE_HOOK.borrow(cs).get()) {
+                    None => unsafe { asm!("wfe") },
+                    Some(hook) => cortex_m::interrupt::free(|_| {
+                        if !SIGNAL_WORK_THREAD_MODE.load(Ordering::SeqCst) {
+                            hook()
+                        }
+                    }),
+                }
             }
         }
     }
@@ -228,4 +259,99 @@ mod interrupt {
             executor.spawner().make_send()
         }
     }
+
+    /// Multi-priority executor, running `N` priority levels in interrupt mode.
+    ///
+    /// Each priority level is an [`InterruptExecutor`] running in its own software-pended
+    /// interrupt. Tasks declared with `#[embassy_executor::task(priority = n)]` are spawned at
+    /// level `n` by the [`PrioritySpawner`] returned by [`start()`](Self::start). Tasks without
+    /// a priority are spawned at level 0.
+    ///
+    /// Level 0 is the lowest priority. The interrupt of each level must have a strictly higher
+    /// NVIC priority (lower priority number) than the interrupt of the level below it, so that
+    /// higher levels preempt lower ones.
+    pub struct PriorityExecutor<const N: usize> {
+        levels: [InterruptExecutor; N],
+    }
+
+    impl<const N: usize> PriorityExecutor<N> {
+        /// Create a new, not started `PriorityExecutor`.
+        #[inline]
+        pub const fn new() -> Self {
+            #[allow(clippy::declare_interior_mutable_const)]
+            const NEW: InterruptExecutor = InterruptExecutor::new();
+            Self { levels: [NEW; N] }
+        }
+
+        /// Executor interrupt callback for priority level `level`.
+        ///
+        /// # Safety
+        ///
+        /// - You MUST call this from the interrupt handler of that level, and from nowhere else.
+        /// - You must not call this before calling `start()`.
+        pub unsafe fn on_interrupt(&'static self, level: usize) {
+            self.levels[level].on_interrupt()
+        }
+
+        /// Start the executor.
+        ///
+        /// `irqs[n]` is the interrupt running priority level `n`. As for [`InterruptExecutor::start()`],
+        /// you must write the interrupt handlers yourself, making them call
+        /// [`on_interrupt()`](Self::on_interrupt), and set the interrupt priorities before calling
+        /// this method.
+        pub fn start<I: InterruptNumber>(&'static self, irqs: [I; N]) -> PrioritySpawner<N> {
+            PrioritySpawner {
+                spawners: core::array::from_fn(|n| self.levels[n].start(irqs[n])),
+            }
+        }
+
+        /// Get a PrioritySpawner for this executor.
+        ///
+        /// This MUST only be called on an executor that has already been started.
+        /// The function will panic otherwise.
+        pub fn spawner(&'static self) -> PrioritySpawner<N> {
+            PrioritySpawner {
+                spawners: core::array::from_fn(|n| self.levels[n].spawner()),
+            }
+        }
+    }
+
+    /// Handle to spawn tasks into a [`PriorityExecutor`], at the priority level they were declared with.
+    #[derive(Copy, Clone)]
+    pub struct PrioritySpawner<const N: usize> {
+        spawners: [crate::SendSpawner; N],
+    }
+
+    impl<const N: usize> PrioritySpawner<N> {
+        /// Get a SendSpawner for priority level `level`, ignoring the priority tasks were declared with.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `level` is not lower than `N`.
+        pub fn level(&self, level: usize) -> crate::SendSpawner {
+            self.spawners[level]
+        }
+
+        /// Spawn a task at the priority level it was declared with, or at level 0 if it has none.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the task's priority level is not lower than `N`.
+        pub fn spawn<S: Send, T>(&self, token: crate::SpawnToken<S, T>) -> Result<(), crate::SpawnError> {
+            let level = token.priority().unwrap_or(0) as usize;
+            if level >= N {
+                panic!("Task priority level {} is out of range for a PriorityExecutor<{}>.", level, N);
+            }
+            self.spawners[level].spawn(token)
+        }
+
+        /// Spawn a task at the priority level it was declared with, panicking on failure.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the spawning fails.
+        pub fn must_spawn<S: Send, T>(&self, token: crate::SpawnToken<S, T>) {
+            unwrap!(self.spawn(token));
+        }
+    }
 }
@@ -36,6 +36,14 @@ mod thread {
             }
         }
 
+        /// Isolate task panics.
+        ///
+        /// A task that panics is dropped and reported to `supervisor`, while the other tasks keep
+        /// running. See [`raw::Executor::isolate_panics()`].
+        pub fn isolate_panics(&mut self, supervisor: fn(raw::TaskPanic)) {
+            self.inner.isolate_panics(supervisor)
+        }
+
         /// Run the executor.
         ///
         /// The `init` closure is called with a [`Spawner`] that spawns tasks on
@@ -0,0 +1,91 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::raw;
+
+/// Error returned when joining a task.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum JoinError {
+    /// The task was aborted before completing.
+    Aborted,
+    /// The task panicked. Only with panic isolation, see `Executor::isolate_panics()`.
+    Panicked,
+}
+
+/// Handle to a task spawned with [`Spawner::spawn_joinable()`](crate::Spawner::spawn_joinable).
+///
+/// Awaiting the handle waits for the task to complete, and returns its output. The handle can also
+/// be used to abort the task.
+///
+/// The task's storage is freed when the handle is dropped. Dropping the handle before the task
+/// completes detaches it: it keeps running, and its output is dropped when it completes.
+pub struct JoinHandle<T> {
+    task: raw::TaskRef,
+    done: bool,
+    phantom: PhantomData<fn() -> T>,
+}
+
+unsafe impl<T: Send> Send for JoinHandle<T> {}
+unsafe impl<T: Send> Sync for JoinHandle<T> {}
+
+impl<T> JoinHandle<T> {
+    /// # Safety
+    ///
+    /// `task` must be spawned and joinable, and its output must be a `T`.
+    pub(crate) unsafe fn new(task: raw::TaskRef) -> Self {
+        Self {
+            task,
+            done: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Abort the task.
+    ///
+    /// Abort is cooperative: the task is dropped the next time the executor would poll it, which
+    /// is at its current await point. Awaiting the handle then returns [`JoinError::Aborted`].
+    ///
+    /// Does nothing if the task has already completed.
+    pub fn abort(&self) {
+        if self.task.header().join.abort() {
+            raw::wake_task(self.task);
+        }
+    }
+
+    /// Return whether the task has finished, either by completing or by being aborted.
+    pub fn is_finished(&self) -> bool {
+        self.task.header().join.is_finished()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.done {
+            panic!("JoinHandle polled after completion");
+        }
+
+        match self.task.header().join.poll_finished(cx.waker()) {
+            None => Poll::Pending,
+            Some(result) => {
+                self.done = true;
+                Poll::Ready(result.map(|()| unsafe { raw::take_output(self.task) }))
+            }
+        }
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if let Some(has_output) = self.task.header().join.detach() {
+            if has_output {
+                drop(unsafe { raw::take_output::<T>(self.task) });
+            }
+            self.task.header().state.despawn();
+        }
+    }
+}
@@ -42,6 +42,9 @@ pub mod raw;
 mod spawner;
 pub use spawner::*;
 
+mod join;
+pub use join::*;
+
 mod config {
     #![allow(unused)]
     include!(concat!(env!("OUT_DIR"), "/config.rs"));
@@ -0,0 +1,153 @@
+use core::cell::Cell;
+use core::task::Waker;
+
+use critical_section::Mutex;
+
+use crate::JoinError;
+
+/// A `JoinHandle` exists for the task.
+const JOINABLE: u8 = 1 << 0;
+/// The task's future was dropped, either because it completed or because it was aborted.
+const FINISHED: u8 = 1 << 1;
+/// The task's output is stored, waiting to be taken by the `JoinHandle`.
+const HAS_OUTPUT: u8 = 1 << 2;
+/// Abort was requested by the `JoinHandle`.
+const ABORTED: u8 = 1 << 3;
+/// The task panicked. Only with panic isolation, see `Executor::isolate_panics()`.
+const PANICKED: u8 = 1 << 4;
+
+/// What the executor must do with a task it's about to poll.
+pub(crate) enum PollAction {
+    Poll,
+    Abort,
+    Skip,
+}
+
+/// Join state of a task.
+///
+/// A joinable task stays spawned after finishing, so its storage (and output) can't be reused
+/// until the `JoinHandle` is dropped.
+pub(crate) struct JoinState {
+    flags: Mutex<Cell<u8>>,
+    waker: Mutex<Cell<Option<Waker>>>,
+}
+
+impl JoinState {
+    pub const fn new() -> Self {
+        Self {
+            flags: Mutex::new(Cell::new(0)),
+            waker: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    /// Reset the state when the task is spawned.
+    pub fn reset(&self) {
+        critical_section::with(|cs| {
+            self.flags.borrow(cs).set(0);
+            self.waker.borrow(cs).set(None);
+        })
+    }
+
+    pub fn set_joinable(&self) {
+        critical_section::with(|cs| {
+            let flags = self.flags.borrow(cs);
+            flags.set(flags.get() | JOINABLE);
+        })
+    }
+
+    pub fn poll_action(&self) -> PollAction {
+        let flags = critical_section::with(|cs| self.flags.borrow(cs).get());
+        if flags & FINISHED != 0 {
+            PollAction::Skip
+        } else if flags & ABORTED != 0 {
+            PollAction::Abort
+        } else {
+            PollAction::Poll
+        }
+    }
+
+    /// Mark the task as finished, after its future was dropped.
+    ///
+    /// If the task is joinable, `store_output` is called to store the output, the joiner is
+    /// woken and this returns `true`. Otherwise, this returns `false` and the task must be
+    /// despawned.
+    pub fn finish(&self, panicked: bool, store_output: impl FnOnce() -> bool) -> bool {
+        let waker = critical_section::with(|cs| {
+            let flags = self.flags.borrow(cs).get();
+            if flags & JOINABLE == 0 {
+                return None;
+            }
+            let output = if store_output() { HAS_OUTPUT } else { 0 };
+            let panicked = if panicked { PANICKED } else { 0 };
+            self.flags.borrow(cs).set(flags | FINISHED | output | panicked);
+            Some(self.waker.borrow(cs).take())
+        });
+
+        match waker {
+            Some(waker) => {
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Request the task to be aborted. Returns `false` if it has already finished.
+    pub fn abort(&self) -> bool {
+        critical_section::with(|cs| {
+            let flags = self.flags.borrow(cs).get();
+            if flags & FINISHED != 0 {
+                return false;
+            }
+            self.flags.borrow(cs).set(flags | ABORTED);
+            true
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        critical_section::with(|cs| self.flags.borrow(cs).get() & FINISHED != 0)
+    }
+
+    /// Check whether the task has finished, registering `waker` to be woken when it does if it
+    /// has not.
+    ///
+    /// Returns `Some(Ok(()))` if the output is available, in which case it's marked as taken,
+    /// and `Some(Err(_))` if the task didn't complete.
+    pub fn poll_finished(&self, waker: &Waker) -> Option<Result<(), JoinError>> {
+        critical_section::with(|cs| {
+            let flags = self.flags.borrow(cs).get();
+            if flags & FINISHED == 0 {
+                self.waker.borrow(cs).set(Some(waker.clone()));
+                return None;
+            }
+            self.flags.borrow(cs).set(flags & !HAS_OUTPUT);
+            Some(if flags & HAS_OUTPUT != 0 {
+                Ok(())
+            } else if flags & PANICKED != 0 {
+                Err(JoinError::Panicked)
+            } else {
+                Err(JoinError::Aborted)
+            })
+        })
+    }
+
+    /// Detach the `JoinHandle`.
+    ///
+    /// Returns `None` if the task is still running, in which case it'll despawn itself when it
+    /// finishes. Otherwise, returns whether an output is stored and must be dropped, and the
+    /// task must be despawned.
+    pub fn detach(&self) -> Option<bool> {
+        critical_section::with(|cs| {
+            let flags = self.flags.borrow(cs).get();
+            self.waker.borrow(cs).set(None);
+            if flags & FINISHED == 0 {
+                self.flags.borrow(cs).set(flags & !JOINABLE);
+                return None;
+            }
+            self.flags.borrow(cs).set(0);
+            Some(flags & HAS_OUTPUT != 0)
+        })
+    }
+}
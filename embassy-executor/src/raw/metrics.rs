@@ -0,0 +1,139 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+/// Runtime statistics of a task.
+///
+/// Times are in ticks of the time driver, see [`embassy_time_driver::TICK_HZ`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TaskStats {
+    /// Number of times the task was polled.
+    pub poll_count: u32,
+    /// Total time spent polling the task.
+    pub poll_ticks: u64,
+}
+
+/// Runtime statistics of an executor.
+///
+/// Times are in ticks of the time driver, see [`embassy_time_driver::TICK_HZ`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ExecutorStats {
+    /// Number of task polls.
+    pub poll_count: u32,
+    /// Total time spent polling tasks.
+    pub busy_ticks: u64,
+    /// Time elapsed since the executor was first polled, or since the statistics were reset.
+    pub elapsed_ticks: u64,
+}
+
+impl ExecutorStats {
+    /// Percentage of the elapsed time the executor spent idle, from 0 to 100.
+    pub fn idle_percent(&self) -> f32 {
+        if self.elapsed_ticks == 0 {
+            return 100.0;
+        }
+        let busy = self.busy_ticks.min(self.elapsed_ticks);
+        (self.elapsed_ticks - busy) as f32 * 100.0 / self.elapsed_ticks as f32
+    }
+}
+
+pub(crate) struct TaskMetrics {
+    stats: Mutex<Cell<TaskStats>>,
+}
+
+impl TaskMetrics {
+    pub const fn new() -> Self {
+        Self {
+            stats: Mutex::new(Cell::new(TaskStats {
+                poll_count: 0,
+                poll_ticks: 0,
+            })),
+        }
+    }
+
+    pub fn record_poll(&self, ticks: u64) {
+        critical_section::with(|cs| {
+            let stats = self.stats.borrow(cs);
+            let mut s = stats.get();
+            s.poll_count = s.poll_count.wrapping_add(1);
+            s.poll_ticks += ticks;
+            stats.set(s);
+        })
+    }
+
+    pub fn stats(&self) -> TaskStats {
+        critical_section::with(|cs| self.stats.borrow(cs).get())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ExecutorState {
+    poll_count: u32,
+    busy_ticks: u64,
+    /// Timestamp of the first poll, or `None` if the executor was never polled.
+    started_at: Option<u64>,
+}
+
+pub(crate) struct ExecutorMetrics {
+    state: Mutex<Cell<ExecutorState>>,
+}
+
+impl ExecutorMetrics {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(Cell::new(ExecutorState {
+                poll_count: 0,
+                busy_ticks: 0,
+                started_at: None,
+            })),
+        }
+    }
+
+    /// Start measuring, if not done yet.
+    ///
+    /// This is done on the first poll and not on creation, because executors may be created
+    /// before the time driver is initialized.
+    pub fn start(&self) {
+        critical_section::with(|cs| {
+            let state = self.state.borrow(cs);
+            let mut s = state.get();
+            if s.started_at.is_none() {
+                s.started_at = Some(embassy_time_driver::now());
+                state.set(s);
+            }
+        })
+    }
+
+    pub fn record_poll(&self, ticks: u64) {
+        critical_section::with(|cs| {
+            let state = self.state.borrow(cs);
+            let mut s = state.get();
+            s.poll_count = s.poll_count.wrapping_add(1);
+            s.busy_ticks += ticks;
+            state.set(s);
+        })
+    }
+
+    pub fn stats(&self) -> ExecutorStats {
+        let now = embassy_time_driver::now();
+        let s = critical_section::with(|cs| self.state.borrow(cs).get());
+        ExecutorStats {
+            poll_count: s.poll_count,
+            busy_ticks: s.busy_ticks,
+            elapsed_ticks: s.started_at.map(|t| now.saturating_sub(t)).unwrap_or(0),
+        }
+    }
+
+    pub fn reset(&self) {
+        let now = embassy_time_driver::now();
+        critical_section::with(|cs| {
+            self.state.borrow(cs).set(ExecutorState {
+                poll_count: 0,
+                busy_ticks: 0,
+                started_at: Some(now),
+            })
+        })
+    }
+}
@@ -16,8 +16,12 @@ mod run_queue;
 #[cfg_attr(not(target_has_atomic = "8"), path = "state_critical_section.rs")]
 mod state;
 
+mod join;
+#[cfg(feature = "metrics")]
+mod metrics;
 #[cfg(feature = "integrated-timers")]
 mod timer_queue;
+pub mod trace;
 pub(crate) mod util;
 #[cfg_attr(feature = "turbowakers", path = "waker_turbo.rs")]
 mod waker;
@@ -31,12 +35,13 @@ use core::task::{Context, Poll};
 
 #[cfg(feature = "integrated-timers")]
 use embassy_time_driver::{self, AlarmHandle};
-#[cfg(feature = "rtos-trace")]
-use rtos_trace::trace;
 
 use self::run_queue::{RunQueue, RunQueueItem};
 use self::state::State;
 use self::util::{SyncUnsafeCell, UninitCell};
+use crate::config::POLL_BUDGET;
+#[cfg(feature = "metrics")]
+pub use self::metrics::{ExecutorStats, TaskStats};
 pub use self::waker::task_from_waker;
 use super::SpawnToken;
 
@@ -46,11 +51,17 @@ pub(crate) struct TaskHeader {
     pub(crate) run_queue_item: RunQueueItem,
     pub(crate) executor: SyncUnsafeCell<Option<&'static SyncExecutor>>,
     poll_fn: SyncUnsafeCell<Option<unsafe fn(TaskRef)>>,
+    pub(crate) join: join::JoinState,
+    /// Number of consecutive polls during which the task woke itself, for the poll budget.
+    poll_streak: SyncUnsafeCell<u32>,
 
     #[cfg(feature = "integrated-timers")]
     pub(crate) expires_at: SyncUnsafeCell<u64>,
     #[cfg(feature = "integrated-timers")]
     pub(crate) timer_queue_item: timer_queue::TimerQueueItem,
+
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: metrics::TaskMetrics,
 }
 
 /// This is essentially a `&'static TaskStorage<F>` where the type of the future has been erased.
@@ -84,6 +95,14 @@ impl TaskRef {
     pub(crate) fn as_ptr(self) -> *const TaskHeader {
         self.ptr.as_ptr()
     }
+
+    /// Get the runtime statistics of this task.
+    ///
+    /// Statistics are kept across respawns of the same task storage.
+    #[cfg(feature = "metrics")]
+    pub fn stats(self) -> TaskStats {
+        self.header().metrics.stats()
+    }
 }
 
 /// Raw storage in which a task can be spawned.
@@ -104,7 +123,27 @@ impl TaskRef {
 #[repr(C)]
 pub struct TaskStorage<F: Future + 'static> {
     raw: TaskHeader,
-    future: UninitCell<F>, // Valid if STATE_SPAWNED
+    output: UninitCell<F::Output>, // Valid if the join state has an output
+    future: UninitCell<F>,         // Valid if STATE_SPAWNED and not finished
+}
+
+/// Prefix of a [`TaskStorage`], to access the output of a task without knowing its future type.
+///
+/// This must be kept in sync with the layout of `TaskStorage`.
+#[repr(C)]
+struct TaskOutput<T> {
+    _raw: TaskHeader,
+    output: UninitCell<T>,
+}
+
+/// Move the output out of a finished task.
+///
+/// # Safety
+///
+/// The task's output must be a `T`, and must be stored.
+pub(crate) unsafe fn take_output<T>(task: TaskRef) -> T {
+    let this = &*(task.as_ptr() as *const TaskOutput<T>);
+    this.output.as_mut_ptr().read()
 }
 
 impl<F: Future + 'static> TaskStorage<F> {
@@ -119,12 +158,18 @@ impl<F: Future + 'static> TaskStorage<F> {
                 executor: SyncUnsafeCell::new(None),
                 // Note: this is lazily initialized so that a static `TaskStorage` will go in `.bss`
                 poll_fn: SyncUnsafeCell::new(None),
+                join: join::JoinState::new(),
+                poll_streak: SyncUnsafeCell::new(0),
 
                 #[cfg(feature = "integrated-timers")]
                 expires_at: SyncUnsafeCell::new(0),
                 #[cfg(feature = "integrated-timers")]
                 timer_queue_item: timer_queue::TimerQueueItem::new(),
+
+                #[cfg(feature = "metrics")]
+                metrics: metrics::TaskMetrics::new(),
             },
+            output: UninitCell::uninit(),
             future: UninitCell::uninit(),
         }
     }
@@ -142,7 +187,7 @@ impl<F: Future + 'static> TaskStorage<F> {
     ///
     /// Once the task has finished running, you may spawn it again. It is allowed to spawn it
     /// on a different executor.
-    pub fn spawn(&'static self, future: impl FnOnce() -> F) -> SpawnToken<impl Sized> {
+    pub fn spawn(&'static self, future: impl FnOnce() -> F) -> SpawnToken<impl Sized, F::Output> {
         let task = AvailableTask::claim(self);
         match task {
             Some(task) => task.initialize(future),
@@ -153,16 +198,44 @@ impl<F: Future + 'static> TaskStorage<F> {
     unsafe fn poll(p: TaskRef) {
         let this = &*(p.as_ptr() as *const TaskStorage<F>);
 
+        match this.raw.join.poll_action() {
+            join::PollAction::Poll => {}
+            join::PollAction::Abort => {
+                this.future.drop_in_place();
+                this.finish(None, false);
+                return;
+            }
+            // The task finished, but is kept spawned until its `JoinHandle` is dropped.
+            join::PollAction::Skip => return,
+        }
+
         let future = Pin::new_unchecked(this.future.as_mut());
         let waker = waker::from_task(p);
         let mut cx = Context::from_waker(&waker);
-        match future.poll(&mut cx) {
-            Poll::Ready(_) => {
-                this.future.drop_in_place();
-                this.raw.state.despawn();
 
-                #[cfg(feature = "integrated-timers")]
-                this.raw.expires_at.set(u64::MAX);
+        #[cfg(feature = "arch-std")]
+        let poll = match this.raw.executor.get().and_then(|e| e.panic_supervisor.get()) {
+            Some(supervisor) => {
+                match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| future.poll(&mut cx))) {
+                    Ok(poll) => poll,
+                    Err(payload) => {
+                        mem::forget(waker);
+                        this.future.drop_in_place();
+                        this.finish(None, true);
+                        supervisor(TaskPanic { task: p, payload });
+                        return;
+                    }
+                }
+            }
+            None => future.poll(&mut cx),
+        };
+        #[cfg(not(feature = "arch-std"))]
+        let poll = future.poll(&mut cx);
+
+        match poll {
+            Poll::Ready(output) => {
+                this.future.drop_in_place();
+                this.finish(Some(output), false);
             }
             Poll::Pending => {}
         }
@@ -172,6 +245,27 @@ impl<F: Future + 'static> TaskStorage<F> {
         mem::forget(waker);
     }
 
+    /// Called after dropping the future, with its output if it completed.
+    unsafe fn finish(&self, output: Option<F::Output>, panicked: bool) {
+        #[cfg(feature = "integrated-timers")]
+        self.raw.expires_at.set(u64::MAX);
+
+        let mut output = output;
+        let joinable = self.raw.join.finish(panicked, || match output.take() {
+            Some(output) => {
+                self.output.write_in_place(|| output);
+                true
+            }
+            None => false,
+        });
+
+        // Not joinable: drop the output right away, and free the storage.
+        if !joinable {
+            drop(output);
+            self.raw.state.despawn();
+        }
+    }
+
     #[doc(hidden)]
     #[allow(dead_code)]
     fn _assert_sync(self) {
@@ -194,9 +288,11 @@ impl<F: Future + 'static> AvailableTask<F> {
         task.raw.state.spawn().then(|| Self { task })
     }
 
-    fn initialize_impl<S>(self, future: impl FnOnce() -> F) -> SpawnToken<S> {
+    fn initialize_impl<S>(self, future: impl FnOnce() -> F) -> SpawnToken<S, F::Output> {
         unsafe {
             self.task.raw.poll_fn.set(Some(TaskStorage::<F>::poll));
+            self.task.raw.join.reset();
+            self.task.raw.poll_streak.set(0);
             self.task.future.write_in_place(future);
 
             let task = TaskRef::new(self.task);
@@ -206,7 +302,7 @@ impl<F: Future + 'static> AvailableTask<F> {
     }
 
     /// Initialize the [`TaskStorage`] to run the given future.
-    pub fn initialize(self, future: impl FnOnce() -> F) -> SpawnToken<F> {
+    pub fn initialize(self, future: impl FnOnce() -> F) -> SpawnToken<F, F::Output> {
         self.initialize_impl::<F>(future)
     }
 
@@ -217,7 +313,7 @@ impl<F: Future + 'static> AvailableTask<F> {
     /// `future` must be a closure of the form `move || my_async_fn(args)`, where `my_async_fn`
     /// is an `async fn`, NOT a hand-written `Future`.
     #[doc(hidden)]
-    pub unsafe fn __initialize_async_fn<FutFn>(self, future: impl FnOnce() -> F) -> SpawnToken<FutFn> {
+    pub unsafe fn __initialize_async_fn<FutFn>(self, future: impl FnOnce() -> F) -> SpawnToken<FutFn, F::Output> {
         // When send-spawning a task, we construct the future in this thread, and effectively
         // "send" it to the executor thread by enqueuing it in its queue. Therefore, in theory,
         // send-spawning should require the future `F` to be `Send`.
@@ -262,13 +358,38 @@ impl<F: Future + 'static, const N: usize> TaskPool<F, N> {
         }
     }
 
-    fn spawn_impl<T>(&'static self, future: impl FnOnce() -> F) -> SpawnToken<T> {
+    fn spawn_impl<T>(&'static self, future: impl FnOnce() -> F) -> SpawnToken<T, F::Output> {
+        match self.try_spawn_impl::<T>(future) {
+            Ok(token) => token,
+            Err(PoolFull) => SpawnToken::new_failed(),
+        }
+    }
+
+    fn try_spawn_impl<T>(&'static self, future: impl FnOnce() -> F) -> Result<SpawnToken<T, F::Output>, PoolFull> {
         match self.pool.iter().find_map(AvailableTask::claim) {
-            Some(task) => task.initialize_impl::<T>(future),
-            None => SpawnToken::new_failed(),
+            Some(task) => Ok(task.initialize_impl::<T>(future)),
+            None => Err(PoolFull),
         }
     }
 
+    /// Number of tasks the pool can hold.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of tasks of the pool that are currently spawned.
+    pub fn spawned(&self) -> usize {
+        self.pool.iter().filter(|task| task.raw.state.is_spawned()).count()
+    }
+
+    /// Number of tasks that can currently be spawned in the pool.
+    ///
+    /// This is only a snapshot: tasks may finish, or be spawned from another context, right
+    /// after it is taken.
+    pub fn available(&self) -> usize {
+        N - self.spawned()
+    }
+
     /// Try to spawn a task in the pool.
     ///
     /// See [`TaskStorage::spawn()`] for details.
@@ -276,10 +397,22 @@ impl<F: Future + 'static, const N: usize> TaskPool<F, N> {
     /// This will loop over the pool and spawn the task in the first storage that
     /// is currently free. If none is free, a "poisoned" SpawnToken is returned,
     /// which will cause [`Spawner::spawn()`](super::Spawner::spawn) to return the error.
-    pub fn spawn(&'static self, future: impl FnOnce() -> F) -> SpawnToken<impl Sized> {
+    pub fn spawn(&'static self, future: impl FnOnce() -> F) -> SpawnToken<impl Sized, F::Output> {
         self.spawn_impl::<F>(future)
     }
 
+    /// Try to spawn a task in the pool, failing right away if no storage is free.
+    ///
+    /// Unlike [`spawn()`](Self::spawn), which returns a "poisoned" SpawnToken when the pool is
+    /// full, this returns [`PoolFull`] so the caller can handle it before getting a spawner
+    /// involved, for instance by rejecting a connection when all handler tasks are busy.
+    pub fn try_spawn(
+        &'static self,
+        future: impl FnOnce() -> F,
+    ) -> Result<SpawnToken<impl Sized, F::Output>, PoolFull> {
+        self.try_spawn_impl::<F>(future)
+    }
+
     /// Like spawn(), but allows the task to be send-spawned if the args are Send even if
     /// the future is !Send.
     ///
@@ -289,7 +422,7 @@ impl<F: Future + 'static, const N: usize> TaskPool<F, N> {
     /// SAFETY: `future` must be a closure of the form `move || my_async_fn(args)`, where `my_async_fn`
     /// is an `async fn`, NOT a hand-written `Future`.
     #[doc(hidden)]
-    pub unsafe fn _spawn_async_fn<FutFn>(&'static self, future: FutFn) -> SpawnToken<impl Sized>
+    pub unsafe fn _spawn_async_fn<FutFn>(&'static self, future: FutFn) -> SpawnToken<impl Sized, F::Output>
     where
         FutFn: FnOnce() -> F,
     {
@@ -298,6 +431,20 @@ impl<F: Future + 'static, const N: usize> TaskPool<F, N> {
     }
 }
 
+/// Error returned by [`TaskPool::try_spawn()`] when all tasks of the pool are spawned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PoolFull;
+
+/// A task that panicked, reported to the supervisor set with [`Executor::isolate_panics()`].
+#[cfg(feature = "arch-std")]
+pub struct TaskPanic {
+    /// The task that panicked. Its storage is freed, unless it was spawned with a `JoinHandle`.
+    pub task: TaskRef,
+    /// The panic payload, as returned by [`std::panic::catch_unwind`].
+    pub payload: Box<dyn core::any::Any + Send>,
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct Pender(*mut ());
 
@@ -315,12 +462,20 @@ impl Pender {
 
 pub(crate) struct SyncExecutor {
     run_queue: RunQueue,
+    /// Tasks that exceeded the poll budget, waiting for the run queue to be empty.
+    deferred: RunQueue,
     pender: Pender,
 
     #[cfg(feature = "integrated-timers")]
     pub(crate) timer_queue: timer_queue::TimerQueue,
     #[cfg(feature = "integrated-timers")]
     alarm: AlarmHandle,
+
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: metrics::ExecutorMetrics,
+
+    #[cfg(feature = "arch-std")]
+    panic_supervisor: SyncUnsafeCell<Option<fn(TaskPanic)>>,
 }
 
 impl SyncExecutor {
@@ -330,12 +485,19 @@ impl SyncExecutor {
 
         Self {
             run_queue: RunQueue::new(),
+            deferred: RunQueue::new(),
             pender,
 
             #[cfg(feature = "integrated-timers")]
             timer_queue: timer_queue::TimerQueue::new(),
             #[cfg(feature = "integrated-timers")]
             alarm,
+
+            #[cfg(feature = "metrics")]
+            metrics: metrics::ExecutorMetrics::new(),
+
+            #[cfg(feature = "arch-std")]
+            panic_supervisor: SyncUnsafeCell::new(None),
         }
     }
 
@@ -347,8 +509,7 @@ impl SyncExecutor {
     /// - `task` must NOT be already enqueued (in this executor or another one).
     #[inline(always)]
     unsafe fn enqueue(&self, task: TaskRef) {
-        #[cfg(feature = "rtos-trace")]
-        trace::task_ready_begin(task.as_ptr() as u32);
+        trace::task_ready(self, task);
 
         if self.run_queue.enqueue(task) {
             self.pender.pend();
@@ -364,8 +525,7 @@ impl SyncExecutor {
     pub(super) unsafe fn spawn(&'static self, task: TaskRef) {
         task.header().executor.set(Some(self));
 
-        #[cfg(feature = "rtos-trace")]
-        trace::task_new(task.as_ptr() as u32);
+        trace::task_new(self, task);
 
         self.enqueue(task);
     }
@@ -377,6 +537,9 @@ impl SyncExecutor {
         #[cfg(feature = "integrated-timers")]
         embassy_time_driver::set_alarm_callback(self.alarm, Self::alarm_callback, self as *const _ as *mut ());
 
+        #[cfg(feature = "metrics")]
+        self.metrics.start();
+
         #[allow(clippy::never_loop)]
         loop {
             #[cfg(feature = "integrated-timers")]
@@ -386,6 +549,14 @@ impl SyncExecutor {
             self.run_queue.dequeue_all(|p| {
                 let task = p.header();
 
+                if POLL_BUDGET != 0 && task.poll_streak.get() as usize >= POLL_BUDGET {
+                    // The task keeps waking itself, let the others run first. It stays
+                    // run-queued, so wakes don't enqueue it again in the meantime.
+                    task.poll_streak.set(0);
+                    self.deferred.enqueue(p);
+                    return;
+                }
+
                 #[cfg(feature = "integrated-timers")]
                 task.expires_at.set(u64::MAX);
 
@@ -398,20 +569,45 @@ impl SyncExecutor {
                     return;
                 }
 
-                #[cfg(feature = "rtos-trace")]
-                trace::task_exec_begin(p.as_ptr() as u32);
+                trace::poll_start(self, p);
+
+                #[cfg(feature = "metrics")]
+                let poll_start = embassy_time_driver::now();
 
                 // Run the task
                 task.poll_fn.get().unwrap_unchecked()(p);
 
-                #[cfg(feature = "rtos-trace")]
-                trace::task_exec_end();
+                #[cfg(feature = "metrics")]
+                {
+                    let ticks = embassy_time_driver::now().saturating_sub(poll_start);
+                    task.metrics.record_poll(ticks);
+                    self.metrics.record_poll(ticks);
+                }
+
+                trace::poll_end(self, p);
+
+                if POLL_BUDGET != 0 {
+                    if task.state.is_run_queued() {
+                        task.poll_streak.set(task.poll_streak.get() + 1);
+                    } else {
+                        task.poll_streak.set(0);
+                    }
+                }
 
                 // Enqueue or update into timer_queue
                 #[cfg(feature = "integrated-timers")]
                 self.timer_queue.update(p);
             });
 
+            // Deferred tasks run again once no other task is ready.
+            if POLL_BUDGET != 0 && self.run_queue.is_empty() {
+                self.deferred.dequeue_all(|p| {
+                    if self.run_queue.enqueue(p) {
+                        self.pender.pend();
+                    }
+                });
+            }
+
             #[cfg(feature = "integrated-timers")]
             {
                 // If this is already in the past, set_alarm might return false
@@ -428,8 +624,7 @@ impl SyncExecutor {
             }
         }
 
-        #[cfg(feature = "rtos-trace")]
-        trace::system_idle();
+        trace::executor_idle(self);
     }
 }
 
@@ -533,6 +728,32 @@ impl Executor {
     pub fn spawner(&'static self) -> super::Spawner {
         super::Spawner::new(self)
     }
+
+    /// Isolate task panics.
+    ///
+    /// When a task panics, the panic is caught, the task is dropped and `supervisor` is called
+    /// with the panic, while the other tasks keep running. Joining the task returns
+    /// [`JoinError::Panicked`](crate::JoinError::Panicked).
+    ///
+    /// This requires panics to unwind, which is the default on `std` targets.
+    #[cfg(feature = "arch-std")]
+    pub fn isolate_panics(&mut self, supervisor: fn(TaskPanic)) {
+        unsafe { self.inner.panic_supervisor.set(Some(supervisor)) }
+    }
+
+    /// Get the runtime statistics of this executor.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> ExecutorStats {
+        self.inner.metrics.stats()
+    }
+
+    /// Reset the runtime statistics of this executor, starting a new measurement window.
+    ///
+    /// Task statistics are not reset.
+    #[cfg(feature = "metrics")]
+    pub fn reset_stats(&self) {
+        self.inner.metrics.reset()
+    }
 }
 
 /// Wake a task by `TaskRef`.
@@ -558,6 +779,7 @@ pub fn wake_task_no_pend(task: TaskRef) {
         // We have just marked the task as scheduled, so enqueue it.
         unsafe {
             let executor = header.executor.get().unwrap_unchecked();
+            trace::task_ready(executor, task);
             executor.run_queue.enqueue(task);
         }
     }
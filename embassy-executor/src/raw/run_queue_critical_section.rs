@@ -53,6 +53,11 @@ impl RunQueue {
         })
     }
 
+    /// Return whether the queue is empty.
+    pub(crate) fn is_empty(&self) -> bool {
+        critical_section::with(|cs| self.head.borrow(cs).get().is_none())
+    }
+
     /// Empty the queue, then call `on_task` for each task that was in the queue.
     /// NOTE: It is OK for `on_task` to enqueue more tasks. In this case they're left in the queue
     /// and will be processed by the *next* call to `dequeue_all`, *not* the current one.
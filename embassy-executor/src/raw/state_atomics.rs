@@ -27,6 +27,12 @@ impl State {
             .is_ok()
     }
 
+    /// Return whether the task is spawned.
+    #[inline(always)]
+    pub fn is_spawned(&self) -> bool {
+        self.state.load(Ordering::Acquire) & STATE_SPAWNED != 0
+    }
+
     /// Unmark the task as spawned.
     #[inline(always)]
     pub fn despawn(&self) {
@@ -49,6 +55,12 @@ impl State {
             .is_ok()
     }
 
+    /// Return whether the task is run-queued.
+    #[inline(always)]
+    pub fn is_run_queued(&self) -> bool {
+        self.state.load(Ordering::Acquire) & STATE_RUN_QUEUED != 0
+    }
+
     /// Unmark the task as run-queued. Return whether the task is spawned.
     #[inline(always)]
     pub fn run_dequeue(&self) -> bool {
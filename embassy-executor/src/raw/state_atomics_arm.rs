@@ -47,6 +47,14 @@ impl State {
         r
     }
 
+    /// Return whether the task is spawned.
+    #[inline(always)]
+    pub fn is_spawned(&self) -> bool {
+        let r = self.spawned.load(Ordering::Relaxed);
+        compiler_fence(Ordering::Acquire);
+        r
+    }
+
     /// Unmark the task as spawned.
     #[inline(always)]
     pub fn despawn(&self) {
@@ -77,6 +85,14 @@ impl State {
         }
     }
 
+    /// Return whether the task is run-queued.
+    #[inline(always)]
+    pub fn is_run_queued(&self) -> bool {
+        let r = self.run_queued.load(Ordering::Relaxed);
+        compiler_fence(Ordering::Acquire);
+        r
+    }
+
     /// Unmark the task as run-queued. Return whether the task is spawned.
     #[inline(always)]
     pub fn run_dequeue(&self) -> bool {
@@ -44,6 +44,12 @@ impl State {
         })
     }
 
+    /// Return whether the task is spawned.
+    #[inline(always)]
+    pub fn is_spawned(&self) -> bool {
+        self.update(|s| *s & STATE_SPAWNED != 0)
+    }
+
     /// Unmark the task as spawned.
     #[inline(always)]
     pub fn despawn(&self) {
@@ -63,6 +69,12 @@ impl State {
         })
     }
 
+    /// Return whether the task is run-queued.
+    #[inline(always)]
+    pub fn is_run_queued(&self) -> bool {
+        self.update(|s| *s & STATE_RUN_QUEUED != 0)
+    }
+
     /// Unmark the task as run-queued. Return whether the task is spawned.
     #[inline(always)]
     pub fn run_dequeue(&self) -> bool {
@@ -0,0 +1,104 @@
+//! Executor tracing hooks.
+//!
+//! With the `trace` feature, the executor calls the following functions, which the application
+//! must provide, to report what it is doing. They can forward the events to a tracing tool such as
+//! SEGGER SystemView. Executors and tasks are identified by their address.
+//!
+//! ```rust,ignore
+//! #[no_mangle]
+//! fn _embassy_trace_task_new(executor_id: u32, task_id: u32) {}
+//! #[no_mangle]
+//! fn _embassy_trace_task_ready(executor_id: u32, task_id: u32) {}
+//! #[no_mangle]
+//! fn _embassy_trace_poll_start(executor_id: u32, task_id: u32) {}
+//! #[no_mangle]
+//! fn _embassy_trace_poll_end(executor_id: u32, task_id: u32) {}
+//! #[no_mangle]
+//! fn _embassy_trace_executor_idle(executor_id: u32) {}
+//! ```
+//!
+//! - `task_new` is called when a task is spawned.
+//! - `task_ready` is called when a task is woken and put in the run queue.
+//! - `poll_start` and `poll_end` are called around each poll of a task.
+//! - `executor_idle` is called when the executor has polled all tasks in its run queue.
+//!
+//! Without the `trace` feature, the hooks compile to nothing.
+
+#[cfg(feature = "rtos-trace")]
+use rtos_trace::trace;
+
+use super::{SyncExecutor, TaskRef};
+
+#[cfg(feature = "trace")]
+extern "Rust" {
+    fn _embassy_trace_task_new(executor_id: u32, task_id: u32);
+    fn _embassy_trace_task_ready(executor_id: u32, task_id: u32);
+    fn _embassy_trace_poll_start(executor_id: u32, task_id: u32);
+    fn _embassy_trace_poll_end(executor_id: u32, task_id: u32);
+    fn _embassy_trace_executor_idle(executor_id: u32);
+}
+
+#[inline(always)]
+pub(crate) fn task_new(executor: &SyncExecutor, task: TaskRef) {
+    #[cfg(feature = "trace")]
+    unsafe {
+        _embassy_trace_task_new(executor as *const _ as u32, task.as_ptr() as u32)
+    }
+
+    #[cfg(feature = "rtos-trace")]
+    trace::task_new(task.as_ptr() as u32);
+
+    let _ = (executor, task);
+}
+
+#[inline(always)]
+pub(crate) fn task_ready(executor: &SyncExecutor, task: TaskRef) {
+    #[cfg(feature = "trace")]
+    unsafe {
+        _embassy_trace_task_ready(executor as *const _ as u32, task.as_ptr() as u32)
+    }
+
+    #[cfg(feature = "rtos-trace")]
+    trace::task_ready_begin(task.as_ptr() as u32);
+
+    let _ = (executor, task);
+}
+
+#[inline(always)]
+pub(crate) fn poll_start(executor: &SyncExecutor, task: TaskRef) {
+    #[cfg(feature = "trace")]
+    unsafe {
+        _embassy_trace_poll_start(executor as *const _ as u32, task.as_ptr() as u32)
+    }
+
+    #[cfg(feature = "rtos-trace")]
+    trace::task_exec_begin(task.as_ptr() as u32);
+
+    let _ = (executor, task);
+}
+
+#[inline(always)]
+pub(crate) fn poll_end(executor: &SyncExecutor, task: TaskRef) {
+    #[cfg(feature = "trace")]
+    unsafe {
+        _embassy_trace_poll_end(executor as *const _ as u32, task.as_ptr() as u32)
+    }
+
+    #[cfg(feature = "rtos-trace")]
+    trace::task_exec_end();
+
+    let _ = (executor, task);
+}
+
+#[inline(always)]
+pub(crate) fn executor_idle(executor: &SyncExecutor) {
+    #[cfg(feature = "trace")]
+    unsafe {
+        _embassy_trace_executor_idle(executor as *const _ as u32)
+    }
+
+    #[cfg(feature = "rtos-trace")]
+    trace::system_idle();
+
+    let _ = executor;
+}
@@ -1,9 +1,29 @@
+use core::cell::Cell;
 use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::mem;
 use core::task::Poll;
 
+use critical_section::Mutex;
+
 use super::raw;
+use crate::JoinHandle;
+
+/// Maximum number of cores executors can be registered for, see [`SendSpawner::register_core()`].
+pub const MAX_CORES: usize = 2;
+
+// The registry is a static of this program image, so it is only shared by cores that run the same
+// image, and it relies on the critical section excluding the other cores.
+static CORE_EXECUTORS: Mutex<Cell<[Option<&'static raw::SyncExecutor>; MAX_CORES]>> =
+    Mutex::new(Cell::new([None; MAX_CORES]));
+
+fn core_spawner(core: usize) -> SendSpawner {
+    assert!(core < MAX_CORES, "core {} out of range", core);
+    match critical_section::with(|cs| CORE_EXECUTORS.borrow(cs).get()[core]) {
+        Some(executor) => SendSpawner::new(executor),
+        None => panic!("no executor registered for core {}", core),
+    }
+}
 
 /// Token to spawn a newly-created task in an executor.
 ///
@@ -15,20 +35,25 @@ use super::raw;
 /// in other threads or not. If `S: Send`, it can, which allows spawning it into a [`SendSpawner`].
 /// If not, it can't, so it can only be spawned into the current thread's executor, with [`Spawner`].
 ///
+/// The generic parameter `T` is the task's output type. It can be retrieved by spawning the task
+/// with [`Spawner::spawn_joinable()`], and awaiting the returned [`JoinHandle`].
+///
 /// # Panics
 ///
 /// Dropping a SpawnToken instance panics. You may not "abort" spawning a task in this way.
 /// Once you've invoked a task function and obtained a SpawnToken, you *must* spawn it.
 #[must_use = "Calling a task function does nothing on its own. You must spawn the returned SpawnToken, typically with Spawner::spawn()"]
-pub struct SpawnToken<S> {
+pub struct SpawnToken<S, T = ()> {
     raw_task: Option<raw::TaskRef>,
-    phantom: PhantomData<*mut S>,
+    priority: Option<u8>,
+    phantom: PhantomData<(*mut S, fn() -> T)>,
 }
 
-impl<S> SpawnToken<S> {
+impl<S, T> SpawnToken<S, T> {
     pub(crate) unsafe fn new(raw_task: raw::TaskRef) -> Self {
         Self {
             raw_task: Some(raw_task),
+            priority: None,
             phantom: PhantomData,
         }
     }
@@ -37,12 +62,28 @@ impl<S> SpawnToken<S> {
     pub fn new_failed() -> Self {
         Self {
             raw_task: None,
+            priority: None,
             phantom: PhantomData,
         }
     }
+
+    /// Set the priority level this task should be spawned at.
+    ///
+    /// This is set by `#[embassy_executor::task(priority = N)]`, and only taken into account
+    /// by spawners that manage multiple priority levels, such as `PrioritySpawner`. Other
+    /// spawners ignore it.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Get the priority level this task should be spawned at, if any.
+    pub fn priority(&self) -> Option<u8> {
+        self.priority
+    }
 }
 
-impl<S> Drop for SpawnToken<S> {
+impl<S, T> Drop for SpawnToken<S, T> {
     fn drop(&mut self) {
         // TODO deallocate the task instead.
         panic!("SpawnToken instances may not be dropped. You must pass them to Spawner::spawn()")
@@ -61,6 +102,12 @@ pub enum SpawnError {
     Busy,
 }
 
+impl From<raw::PoolFull> for SpawnError {
+    fn from(_: raw::PoolFull) -> Self {
+        Self::Busy
+    }
+}
+
 /// Handle to spawn tasks into an executor.
 ///
 /// This Spawner can spawn any task (Send and non-Send ones), but it can
@@ -102,7 +149,7 @@ impl Spawner {
     /// Spawn a task into an executor.
     ///
     /// You obtain the `token` by calling a task function (i.e. one marked with `#[embassy_executor::task]`).
-    pub fn spawn<S>(&self, token: SpawnToken<S>) -> Result<(), SpawnError> {
+    pub fn spawn<S, T>(&self, token: SpawnToken<S, T>) -> Result<(), SpawnError> {
         let task = token.raw_task;
         mem::forget(token);
 
@@ -115,6 +162,24 @@ impl Spawner {
         }
     }
 
+    /// Spawn a task into an executor, returning a [`JoinHandle`] to wait for its output or abort it.
+    ///
+    /// The task's storage is only freed once the `JoinHandle` is dropped, so the task can't be
+    /// spawned again until then.
+    pub fn spawn_joinable<S, T>(&self, token: SpawnToken<S, T>) -> Result<JoinHandle<T>, SpawnError> {
+        let task = token.raw_task;
+        mem::forget(token);
+
+        match task {
+            Some(task) => {
+                task.header().join.set_joinable();
+                unsafe { self.executor.spawn(task) };
+                Ok(unsafe { JoinHandle::new(task) })
+            }
+            None => Err(SpawnError::Busy),
+        }
+    }
+
     // Used by the `embassy_executor_macros::main!` macro to throw an error when spawn
     // fails. This is here to allow conditional use of `defmt::unwrap!`
     // without introducing a `defmt` feature in the `embassy_executor_macros` package,
@@ -124,16 +189,33 @@ impl Spawner {
     /// # Panics
     ///
     /// Panics if the spawning fails.
-    pub fn must_spawn<S>(&self, token: SpawnToken<S>) {
+    pub fn must_spawn<S, T>(&self, token: SpawnToken<S, T>) {
         unwrap!(self.spawn(token));
     }
 
+    /// Spawn a task into the executor registered for another core.
+    ///
+    /// See [`SendSpawner::register_core()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no executor is registered for `core`.
+    pub fn spawn_on<S: Send, T>(&self, core: usize, token: SpawnToken<S, T>) -> Result<(), SpawnError> {
+        core_spawner(core).spawn(token)
+    }
+
     /// Convert this Spawner to a SendSpawner. This allows you to send the
     /// spawner to other threads, but the spawner loses the ability to spawn
     /// non-Send tasks.
     pub fn make_send(&self) -> SendSpawner {
         SendSpawner::new(&self.executor.inner)
     }
+
+    /// Get the runtime statistics of the executor this spawner spawns tasks in.
+    #[cfg(feature = "metrics")]
+    pub fn executor_stats(&self) -> raw::ExecutorStats {
+        self.executor.stats()
+    }
 }
 
 /// Handle to spawn tasks into an executor from any thread.
@@ -173,7 +255,7 @@ impl SendSpawner {
     /// Spawn a task into an executor.
     ///
     /// You obtain the `token` by calling a task function (i.e. one marked with `#[embassy_executor::task]`).
-    pub fn spawn<S: Send>(&self, token: SpawnToken<S>) -> Result<(), SpawnError> {
+    pub fn spawn<S: Send, T>(&self, token: SpawnToken<S, T>) -> Result<(), SpawnError> {
         let header = token.raw_task;
         mem::forget(token);
 
@@ -186,12 +268,75 @@ impl SendSpawner {
         }
     }
 
+    /// Spawn a task into an executor, returning a [`JoinHandle`] to wait for its output or abort it.
+    ///
+    /// See [`Spawner::spawn_joinable()`] for details.
+    pub fn spawn_joinable<S: Send, T: Send>(&self, token: SpawnToken<S, T>) -> Result<JoinHandle<T>, SpawnError> {
+        let header = token.raw_task;
+        mem::forget(token);
+
+        match header {
+            Some(header) => {
+                header.header().join.set_joinable();
+                unsafe { self.executor.spawn(header) };
+                Ok(unsafe { JoinHandle::new(header) })
+            }
+            None => Err(SpawnError::Busy),
+        }
+    }
+
     /// Spawn a task into an executor, panicking on failure.
     ///
     /// # Panics
     ///
     /// Panics if the spawning fails.
-    pub fn must_spawn<S: Send>(&self, token: SpawnToken<S>) {
+    pub fn must_spawn<S: Send, T>(&self, token: SpawnToken<S, T>) {
         unwrap!(self.spawn(token));
     }
+
+    /// Register this spawner's executor as the one running on `core`, so other cores can
+    /// spawn tasks into it with [`Spawner::spawn_on()`] or [`SendSpawner::spawn_on()`].
+    ///
+    /// This is only for multicore chips running a single program image on all cores, like the
+    /// RP2040, because:
+    /// - the registry is a static of the program image, so cores running separate images (like the
+    ///   two cores of an STM32H745/H755) each see their own registry,
+    /// - the `critical-section` implementation must exclude the other cores, as the one of
+    ///   `embassy-rp` does. A critical section that only disables interrupts on the current core
+    ///   isn't enough,
+    /// - the executor's pender must be able to wake it up from another core, for instance with
+    ///   `SEV` on Cortex-M chips whose cores share their event lines.
+    ///
+    /// Cores running separate images can't spawn tasks on each other. They can exchange data through
+    /// shared memory instead, and wake each other with a hardware mechanism such as the STM32 HSEM.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `core` is not lower than [`MAX_CORES`].
+    pub fn register_core(&self, core: usize) {
+        assert!(core < MAX_CORES, "core {} out of range", core);
+        critical_section::with(|cs| {
+            let executors = CORE_EXECUTORS.borrow(cs);
+            let mut e = executors.get();
+            e[core] = Some(self.executor);
+            executors.set(e);
+        })
+    }
+
+    /// Spawn a task into the executor registered for another core.
+    ///
+    /// See [`SendSpawner::register_core()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no executor is registered for `core`.
+    pub fn spawn_on<S: Send, T>(&self, core: usize, token: SpawnToken<S, T>) -> Result<(), SpawnError> {
+        core_spawner(core).spawn(token)
+    }
+
+    /// Get the runtime statistics of the executor this spawner spawns tasks in.
+    #[cfg(feature = "metrics")]
+    pub fn executor_stats(&self) -> raw::ExecutorStats {
+        self.executor.metrics.stats()
+    }
 }
@@ -1,12 +1,12 @@
 #![cfg_attr(feature = "nightly", feature(type_alias_impl_trait))]
 
 use std::boxed::Box;
-use std::future::poll_fn;
+use std::future::{poll_fn, ready, Ready};
 use std::sync::{Arc, Mutex};
 use std::task::Poll;
 
-use embassy_executor::raw::Executor;
-use embassy_executor::task;
+use embassy_executor::raw::{Executor, PoolFull, TaskPool};
+use embassy_executor::{task, JoinError, JoinHandle};
 
 #[export_name = "__pender"]
 fn __pender(context: *mut ()) {
@@ -149,3 +149,114 @@ fn executor_task_cfg_args() {
         let (_, _, _) = (a, b, c);
     }
 }
+
+#[test]
+fn task_pool_try_spawn() {
+    static POOL: TaskPool<Ready<()>, 2> = TaskPool::new();
+
+    let (executor, _) = setup();
+    assert_eq!(POOL.capacity(), 2);
+    assert_eq!(POOL.available(), 2);
+
+    executor.spawner().spawn(POOL.try_spawn(|| ready(())).unwrap()).unwrap();
+    executor.spawner().spawn(POOL.try_spawn(|| ready(())).unwrap()).unwrap();
+    assert_eq!(POOL.available(), 0);
+    assert!(matches!(POOL.try_spawn(|| ready(())), Err(PoolFull)));
+
+    // Finished tasks free their storage.
+    unsafe { executor.poll() };
+    assert_eq!(POOL.spawned(), 0);
+    executor.spawner().spawn(POOL.try_spawn(|| ready(())).unwrap()).unwrap();
+    assert_eq!(POOL.available(), 1);
+}
+
+#[test]
+fn executor_task_join() {
+    #[task]
+    async fn task1() -> u32 {
+        42
+    }
+
+    #[task]
+    async fn task2(handle: JoinHandle<u32>, trace: Trace) {
+        assert_eq!(handle.await, Ok(42));
+        trace.push("joined task1")
+    }
+
+    let (executor, trace) = setup();
+    let handle = executor.spawner().spawn_joinable(task1()).unwrap();
+    executor.spawner().spawn(task2(handle, trace.clone())).unwrap();
+
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    assert_eq!(trace.get().last(), Some(&"joined task1"));
+
+    // The storage was freed when the handle was dropped.
+    executor.spawner().spawn(task1()).unwrap();
+}
+
+#[test]
+fn executor_task_abort() {
+    #[task]
+    async fn task1() -> u32 {
+        poll_fn(|_| Poll::<u32>::Pending).await
+    }
+
+    #[task]
+    async fn task2(handle: JoinHandle<u32>, trace: Trace) {
+        assert_eq!(handle.await, Err(JoinError::Aborted));
+        trace.push("task1 aborted")
+    }
+
+    let (executor, trace) = setup();
+    let handle = executor.spawner().spawn_joinable(task1()).unwrap();
+    unsafe { executor.poll() };
+    assert!(!handle.is_finished());
+
+    handle.abort();
+    executor.spawner().spawn(task2(handle, trace.clone())).unwrap();
+
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    assert_eq!(trace.get().last(), Some(&"task1 aborted"));
+}
+
+#[cfg(feature = "arch-std")]
+#[test]
+fn executor_task_panic_isolated() {
+    use embassy_executor::raw::TaskPanic;
+
+    static PANICS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn supervisor(panic: TaskPanic) {
+        let msg = panic.payload.downcast_ref::<&str>().unwrap().to_string();
+        PANICS.lock().unwrap().push(msg);
+    }
+
+    #[task]
+    async fn task1() -> u32 {
+        panic!("task1 failed")
+    }
+
+    #[task]
+    async fn task2(handle: JoinHandle<u32>, trace: Trace) {
+        assert_eq!(handle.await, Err(JoinError::Panicked));
+        trace.push("task1 panicked")
+    }
+
+    let trace = Trace::new();
+    let context = Box::leak(Box::new(trace.clone())) as *mut _ as *mut ();
+    let executor = Box::leak(Box::new(Executor::new(context)));
+    executor.isolate_panics(supervisor);
+
+    let handle = executor.spawner().spawn_joinable(task1()).unwrap();
+    executor.spawner().spawn(task2(handle, trace.clone())).unwrap();
+
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    assert_eq!(trace.get().last(), Some(&"task1 panicked"));
+    assert_eq!(*PANICS.lock().unwrap(), ["task1 failed"]);
+}
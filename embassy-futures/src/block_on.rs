@@ -32,6 +32,41 @@ pub fn block_on<F: Future>(mut fut: F) -> F::Output {
     }
 }
 
+#[cfg(feature = "cortex-m")]
+static WFE_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |_| RawWaker::new(ptr::null(), &WFE_VTABLE),
+    |_| cortex_m::asm::sev(),
+    |_| cortex_m::asm::sev(),
+    |_| {},
+);
+
+/// Run a future to completion, sleeping with `WFE` while it's pending.
+///
+/// Waking the future's `Waker` signals an event with `SEV`, which wakes the core from `WFE`.
+/// Interrupts also wake the core, so futures woken from interrupt handlers work as expected.
+/// If the future is woken while it's being polled, the pending event makes the next `WFE` return
+/// right away, so no wakeup is lost.
+///
+/// Like [`block_on`], this runs a single future, but it lets the core sleep between polls. This
+/// is suitable for simple applications and bootloaders that don't need a full executor.
+///
+/// Requires the `cortex-m` feature.
+#[cfg(feature = "cortex-m")]
+pub fn block_on_wfe<F: Future>(mut fut: F) -> F::Output {
+    // safety: we don't move the future after this line.
+    let mut fut = unsafe { Pin::new_unchecked(&mut fut) };
+
+    let raw_waker = RawWaker::new(ptr::null(), &WFE_VTABLE);
+    let waker = unsafe { Waker::from_raw(raw_waker) };
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+            return res;
+        }
+        cortex_m::asm::wfe();
+    }
+}
+
 /// Poll a future once.
 pub fn poll_once<F: Future>(mut fut: F) -> Poll<F::Output> {
     // safety: we don't move the future after this line.
@@ -320,3 +320,86 @@ pub fn join_array<Fut: Future, const N: usize>(futures: [Fut; N]) -> JoinArray<F
         futures: futures.map(MaybeDone::Future),
     }
 }
+
+// =====================================================
+
+/// Future for the [`join_slice`] function.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct JoinSlice<'a, Fut: Future> {
+    futures: Pin<&'a mut [Fut]>,
+    outputs: &'a mut [Option<Fut::Output>],
+}
+
+impl<'a, Fut> fmt::Debug for JoinSlice<'a, Fut>
+where
+    Fut: Future + fmt::Debug,
+    Fut::Output: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinSlice")
+            .field("futures", &self.futures)
+            .field("outputs", &self.outputs)
+            .finish()
+    }
+}
+
+impl<'a, Fut: Future> Future for JoinSlice<'a, Fut> {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // Safety: the slice is pinned, so its elements are too. They are only accessed through pinned references.
+        let futures = unsafe { this.futures.as_mut().get_unchecked_mut() };
+        let mut all_done = true;
+        for (f, output) in futures.iter_mut().zip(this.outputs.iter_mut()) {
+            // A future is done once its output is written, it must not be polled again.
+            if output.is_none() {
+                match unsafe { Pin::new_unchecked(f) }.poll(cx) {
+                    Poll::Ready(res) => *output = Some(res),
+                    Poll::Pending => all_done = false,
+                }
+            }
+        }
+
+        if all_done {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Joins the result of a slice of futures, waiting for them all to complete.
+///
+/// This function will return a new future which awaits all futures to
+/// complete. The output of each future is written to `outputs` at the same index.
+/// This allows waiting on a set of futures whose size is only known at runtime,
+/// without allocating.
+///
+/// `outputs` must be all `None` when the future is first polled. The futures are pinned, since they
+/// may not move once polled, even after the returned future is dropped.
+///
+/// # Panics
+///
+/// Panics if `futures` and `outputs` don't have the same length.
+///
+/// # Examples
+///
+/// ```
+/// # embassy_futures::block_on(async {
+/// use core::pin::pin;
+///
+/// async fn foo(n: u32) -> u32 { n }
+/// let futures = pin!([foo(1), foo(2), foo(3)]);
+/// let mut outputs = [None; 3];
+/// embassy_futures::join::join_slice(futures, &mut outputs).await;
+///
+/// assert_eq!(outputs, [Some(1), Some(2), Some(3)]);
+/// # });
+/// ```
+pub fn join_slice<'a, Fut: Future>(
+    futures: Pin<&'a mut [Fut]>,
+    outputs: &'a mut [Option<Fut::Output>],
+) -> JoinSlice<'a, Fut> {
+    assert_eq!(futures.len(), outputs.len());
+    JoinSlice { futures, outputs }
+}
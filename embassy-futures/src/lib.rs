@@ -6,10 +6,18 @@
 pub(crate) mod fmt;
 
 mod block_on;
+#[cfg(feature = "shared")]
+mod shared;
+#[cfg(feature = "time")]
+mod timeout;
 mod yield_now;
 
 pub mod join;
 pub mod select;
 
 pub use block_on::*;
+#[cfg(feature = "shared")]
+pub use shared::*;
+#[cfg(feature = "time")]
+pub use timeout::*;
 pub use yield_now::*;
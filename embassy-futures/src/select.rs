@@ -1,4 +1,9 @@
 //! Wait for the first of several futures to complete.
+//!
+//! [`select`], [`select3`] and [`select4`] wait on a fixed number of futures of different types.
+//! To wait on a set of futures of the same type whose size is only known at runtime, such as one
+//! future per open connection, use [`select_array`] or [`select_slice`]. They complete with the
+//! output of the first ready future and its index, without allocating.
 
 use core::future::Future;
 use core::pin::Pin;
@@ -14,12 +19,54 @@ pub enum Either<A, B> {
     Second(B),
 }
 
+impl<A, B> Either<A, B> {
+    /// Map the output of the first future.
+    pub fn map_a<U>(self, f: impl FnOnce(A) -> U) -> Either<U, B> {
+        match self {
+            Either::First(x) => Either::First(f(x)),
+            Either::Second(x) => Either::Second(x),
+        }
+    }
+
+    /// Map the output of the second future.
+    pub fn map_b<U>(self, f: impl FnOnce(B) -> U) -> Either<A, U> {
+        match self {
+            Either::First(x) => Either::First(x),
+            Either::Second(x) => Either::Second(f(x)),
+        }
+    }
+}
+
+impl<T, A, B> Either<(T, A), (T, B)> {
+    /// Factor out the first element of the output tuple, which all futures have in common.
+    pub fn factor_first(self) -> (T, Either<A, B>) {
+        match self {
+            Either::First((t, x)) => (t, Either::First(x)),
+            Either::Second((t, x)) => (t, Either::Second(x)),
+        }
+    }
+}
+
+impl<T> Either<T, T> {
+    /// Get the output of the future that completed, when all futures have the same output type.
+    pub fn into_inner(self) -> T {
+        match self {
+            Either::First(x) | Either::Second(x) => x,
+        }
+    }
+}
+
 /// Wait for one of two futures to complete.
 ///
 /// This function returns a new future which polls all the futures.
 /// When one of them completes, it will complete with its result value.
 ///
 /// The other future is dropped.
+///
+/// The futures are always polled in argument order, and polling stops at the first ready one.
+/// If both are ready, the result is always [`Either::First`]. This makes it possible to rely on
+/// one future taking precedence over the other, for example a shutdown signal over incoming data.
+/// A future that is always ready starves the following ones.
 pub fn select<A, B>(a: A, b: B) -> Select<A, B>
 where
     A: Future,
@@ -73,7 +120,58 @@ pub enum Either3<A, B, C> {
     Third(C),
 }
 
+impl<A, B, C> Either3<A, B, C> {
+    /// Map the output of the first future.
+    pub fn map_a<U>(self, f: impl FnOnce(A) -> U) -> Either3<U, B, C> {
+        match self {
+            Either3::First(x) => Either3::First(f(x)),
+            Either3::Second(x) => Either3::Second(x),
+            Either3::Third(x) => Either3::Third(x),
+        }
+    }
+
+    /// Map the output of the second future.
+    pub fn map_b<U>(self, f: impl FnOnce(B) -> U) -> Either3<A, U, C> {
+        match self {
+            Either3::First(x) => Either3::First(x),
+            Either3::Second(x) => Either3::Second(f(x)),
+            Either3::Third(x) => Either3::Third(x),
+        }
+    }
+
+    /// Map the output of the third future.
+    pub fn map_c<U>(self, f: impl FnOnce(C) -> U) -> Either3<A, B, U> {
+        match self {
+            Either3::First(x) => Either3::First(x),
+            Either3::Second(x) => Either3::Second(x),
+            Either3::Third(x) => Either3::Third(f(x)),
+        }
+    }
+}
+
+impl<T, A, B, C> Either3<(T, A), (T, B), (T, C)> {
+    /// Factor out the first element of the output tuple, which all futures have in common.
+    pub fn factor_first(self) -> (T, Either3<A, B, C>) {
+        match self {
+            Either3::First((t, x)) => (t, Either3::First(x)),
+            Either3::Second((t, x)) => (t, Either3::Second(x)),
+            Either3::Third((t, x)) => (t, Either3::Third(x)),
+        }
+    }
+}
+
+impl<T> Either3<T, T, T> {
+    /// Get the output of the future that completed, when all futures have the same output type.
+    pub fn into_inner(self) -> T {
+        match self {
+            Either3::First(x) | Either3::Second(x) | Either3::Third(x) => x,
+        }
+    }
+}
+
 /// Same as [`select`], but with more futures.
+///
+/// The futures are polled in argument order, and the first ready one wins.
 pub fn select3<A, B, C>(a: A, b: B, c: C) -> Select3<A, B, C>
 where
     A: Future,
@@ -134,7 +232,72 @@ pub enum Either4<A, B, C, D> {
     Fourth(D),
 }
 
+impl<A, B, C, D> Either4<A, B, C, D> {
+    /// Map the output of the first future.
+    pub fn map_a<U>(self, f: impl FnOnce(A) -> U) -> Either4<U, B, C, D> {
+        match self {
+            Either4::First(x) => Either4::First(f(x)),
+            Either4::Second(x) => Either4::Second(x),
+            Either4::Third(x) => Either4::Third(x),
+            Either4::Fourth(x) => Either4::Fourth(x),
+        }
+    }
+
+    /// Map the output of the second future.
+    pub fn map_b<U>(self, f: impl FnOnce(B) -> U) -> Either4<A, U, C, D> {
+        match self {
+            Either4::First(x) => Either4::First(x),
+            Either4::Second(x) => Either4::Second(f(x)),
+            Either4::Third(x) => Either4::Third(x),
+            Either4::Fourth(x) => Either4::Fourth(x),
+        }
+    }
+
+    /// Map the output of the third future.
+    pub fn map_c<U>(self, f: impl FnOnce(C) -> U) -> Either4<A, B, U, D> {
+        match self {
+            Either4::First(x) => Either4::First(x),
+            Either4::Second(x) => Either4::Second(x),
+            Either4::Third(x) => Either4::Third(f(x)),
+            Either4::Fourth(x) => Either4::Fourth(x),
+        }
+    }
+
+    /// Map the output of the fourth future.
+    pub fn map_d<U>(self, f: impl FnOnce(D) -> U) -> Either4<A, B, C, U> {
+        match self {
+            Either4::First(x) => Either4::First(x),
+            Either4::Second(x) => Either4::Second(x),
+            Either4::Third(x) => Either4::Third(x),
+            Either4::Fourth(x) => Either4::Fourth(f(x)),
+        }
+    }
+}
+
+impl<T, A, B, C, D> Either4<(T, A), (T, B), (T, C), (T, D)> {
+    /// Factor out the first element of the output tuple, which all futures have in common.
+    pub fn factor_first(self) -> (T, Either4<A, B, C, D>) {
+        match self {
+            Either4::First((t, x)) => (t, Either4::First(x)),
+            Either4::Second((t, x)) => (t, Either4::Second(x)),
+            Either4::Third((t, x)) => (t, Either4::Third(x)),
+            Either4::Fourth((t, x)) => (t, Either4::Fourth(x)),
+        }
+    }
+}
+
+impl<T> Either4<T, T, T, T> {
+    /// Get the output of the future that completed, when all futures have the same output type.
+    pub fn into_inner(self) -> T {
+        match self {
+            Either4::First(x) | Either4::Second(x) | Either4::Third(x) | Either4::Fourth(x) => x,
+        }
+    }
+}
+
 /// Same as [`select`], but with more futures.
+///
+/// The futures are polled in argument order, and the first ready one wins.
 pub fn select4<A, B, C, D>(a: A, b: B, c: C, d: D) -> Select4<A, B, C, D>
 where
     A: Future,
@@ -201,6 +364,9 @@ pub struct SelectArray<Fut, const N: usize> {
 /// completion the item resolved will be returned, along with the index of the
 /// future that was ready.
 ///
+/// The futures are polled in order, so if several are ready, the one with the
+/// lowest index wins.
+///
 /// If the array is empty, the resulting future will be Pending forever.
 pub fn select_array<Fut: Future, const N: usize>(arr: [Fut; N]) -> SelectArray<Fut, N> {
     SelectArray { inner: arr }
@@ -246,6 +412,9 @@ pub struct SelectSlice<'a, Fut> {
 /// completion the item resolved will be returned, along with the index of the
 /// future that was ready.
 ///
+/// The futures are polled in order, so if several are ready, the one with the
+/// lowest index wins.
+///
 /// If the slice is empty, the resulting future will be Pending forever.
 pub fn select_slice<'a, Fut: Future>(slice: &'a mut [Fut]) -> SelectSlice<'a, Fut> {
     SelectSlice { inner: slice }
@@ -0,0 +1,137 @@
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::waitqueue::MultiWakerRegistration;
+
+/// A future that can be awaited by multiple tasks.
+///
+/// The underlying future is driven by whichever waiter is polled, and every waiter receives a
+/// clone of its output. Once it has completed, waiting returns the output right away. This is
+/// useful for "wait until initialization is complete" gates.
+///
+/// Up to `N` tasks can wait at the same time without being spuriously woken: the wakers of waiting
+/// tasks are stored in a fixed-size [`MultiWakerRegistration`], so no allocation is needed.
+///
+/// The mutex kind `M` protects the shared state. Use `NoopRawMutex` if all waiters run in the same
+/// executor, `CriticalSectionRawMutex` otherwise. The underlying future is polled with the mutex
+/// locked, so with `CriticalSectionRawMutex`, it should be quick to poll.
+///
+/// Since the underlying future must not move once polled, waiting requires a pinned reference. For
+/// a `static`, use [`Pin::static_ref`].
+///
+/// ```rust,ignore
+/// static INIT: StaticCell<Shared<CriticalSectionRawMutex, InitFuture, 4>> = StaticCell::new();
+/// let init = Pin::static_ref(INIT.init(Shared::new(init_peripherals())));
+///
+/// // In each task:
+/// let config = init.wait().await;
+/// ```
+pub struct Shared<M: RawMutex, F: Future, const N: usize> {
+    inner: Mutex<M, RefCell<Inner<F, N>>>,
+}
+
+struct Inner<F: Future, const N: usize> {
+    state: State<F>,
+    wakers: MultiWakerRegistration<N>,
+}
+
+enum State<F: Future> {
+    Pending(F),
+    Done(F::Output),
+}
+
+impl<M: RawMutex, F: Future, const N: usize> Shared<M, F, N> {
+    /// Create a new `Shared` driving `fut`.
+    pub const fn new(fut: F) -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(Inner {
+                state: State::Pending(fut),
+                wakers: MultiWakerRegistration::new(),
+            })),
+        }
+    }
+
+    /// Return whether the underlying future has completed.
+    pub fn is_done(&self) -> bool {
+        self.inner.lock(|inner| matches!(inner.borrow().state, State::Done(_)))
+    }
+}
+
+impl<M: RawMutex, F: Future, const N: usize> Shared<M, F, N>
+where
+    F::Output: Clone,
+{
+    /// Wait for the underlying future to complete, and return a clone of its output.
+    ///
+    /// The underlying future must not wait on this `Shared` itself.
+    pub fn wait(self: Pin<&Self>) -> Wait<'_, M, F, N> {
+        Wait {
+            shared: self.get_ref(),
+            registered: false,
+        }
+    }
+}
+
+/// Future for the [`Shared::wait`] method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Wait<'a, M: RawMutex, F: Future, const N: usize> {
+    shared: &'a Shared<M, F, N>,
+    registered: bool,
+}
+
+impl<'a, M: RawMutex, F: Future, const N: usize> Unpin for Wait<'a, M, F, N> {}
+
+impl<'a, M: RawMutex, F: Future, const N: usize> Future for Wait<'a, M, F, N>
+where
+    F::Output: Clone,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = self.shared.inner.lock(|inner| {
+            let mut inner = inner.borrow_mut();
+            let inner = &mut *inner;
+            match &mut inner.state {
+                State::Done(output) => Poll::Ready(output.clone()),
+                State::Pending(fut) => {
+                    // Safety: `Shared::wait` requires `Shared` to be pinned, so the future doesn't
+                    // move until it's dropped, which happens in place when it completes.
+                    let fut = unsafe { Pin::new_unchecked(fut) };
+                    match fut.poll(cx) {
+                        Poll::Ready(output) => {
+                            inner.state = State::Done(output.clone());
+                            inner.wakers.wake();
+                            Poll::Ready(output)
+                        }
+                        Poll::Pending => {
+                            inner.wakers.register(cx.waker());
+                            Poll::Pending
+                        }
+                    }
+                }
+            }
+        });
+
+        self.registered = res.is_pending();
+        res
+    }
+}
+
+impl<'a, M: RawMutex, F: Future, const N: usize> Drop for Wait<'a, M, F, N> {
+    fn drop(&mut self) {
+        if self.registered {
+            // The underlying future may have registered this waiter's waker only. Wake the other
+            // waiters, so one of them polls it again and registers its own.
+            self.shared.inner.lock(|inner| {
+                let mut inner = inner.borrow_mut();
+                if let State::Pending(_) = inner.state {
+                    inner.wakers.wake();
+                }
+            })
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use core::future::Future;
+
+use embassy_time::{Duration, Timer};
+
+use crate::select::{select, Either};
+
+/// Output of [`select_timeout`] when the timeout expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timeout;
+
+/// Wait for a future to complete, or for a timeout to expire.
+///
+/// Returns [`Either::First`] with the output of the future if it completes before the timeout,
+/// or [`Either::Second`] with [`Timeout`] otherwise, in which case the future is dropped.
+///
+/// The future is polled before the timer, so it wins if both are ready.
+///
+/// Requires the `time` feature.
+pub async fn select_timeout<F: Future>(fut: F, timeout: Duration) -> Either<F::Output, Timeout> {
+    select(fut, Timer::after(timeout)).await.map_b(|_| Timeout)
+}
@@ -41,3 +41,56 @@ impl Future for YieldNowFuture {
         }
     }
 }
+
+/// Create a [`YieldEvery`], yielding on every `n`-th call to [`tick`](YieldEvery::tick).
+///
+/// `n = 0` is treated as 1, yielding on every call.
+pub const fn yield_every(n: u32) -> YieldEvery {
+    YieldEvery {
+        n: if n == 0 { 1 } else { n },
+        count: 0,
+    }
+}
+
+/// Yield budget for compute-heavy loops.
+///
+/// Yielding on every iteration of a tight loop with [`yield_now`] keeps other tasks responsive, but
+/// costs a trip through the executor each time. `YieldEvery` only yields every `n` iterations:
+///
+/// ```rust,no_run
+/// use embassy_futures::yield_every;
+/// # fn process(_: &[u8]) {}
+/// # async fn f(data: &[u8]) {
+///
+/// let mut budget = yield_every(16);
+/// for chunk in data.chunks(64) {
+///     process(chunk);
+///     budget.tick().await;
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct YieldEvery {
+    n: u32,
+    count: u32,
+}
+
+impl YieldEvery {
+    /// Count one call, yielding from the current task if it's the `n`-th one.
+    ///
+    /// Calls that don't yield complete on the first poll.
+    pub fn tick(&mut self) -> impl Future<Output = ()> {
+        self.count += 1;
+        let yield_now = self.count >= self.n;
+        if yield_now {
+            self.count = 0;
+        }
+        YieldNowFuture { yielded: !yield_now }
+    }
+
+    /// Reset the count, so the next yield happens after `n` more calls.
+    pub fn reset(&mut self) {
+        self.count = 0;
+    }
+}
@@ -53,7 +53,7 @@ impl<'a> Runner<'a> {
                     if let Ok(mac_event) = self.mac_subsystem.read().await {
                         match mac_event {
                             MacEvent::McpsDataInd(_) => {
-                                self.rx_channel.send(mac_event).await;
+                                unwrap!(self.rx_channel.send(mac_event).await);
                             }
                             _ => {
                                 self.rx_event_channel.lock(|s| {
@@ -73,7 +73,7 @@ impl<'a> Runner<'a> {
                 let mut msdu_handle = 0x02;
 
                 loop {
-                    let (buf, len) = self.tx_channel.receive().await;
+                    let (buf, len) = unwrap!(self.tx_channel.receive().await);
                     let _wm = self.write_mutex.lock().await;
 
                     // The mutex should be dropped on the next loop iteration
@@ -198,6 +198,8 @@ fn main() {
         Some("tim22") => "TIM22",
         Some("tim23") => "TIM23",
         Some("tim24") => "TIM24",
+        Some("lptim1") => "LPTIM1",
+        Some("lptim2") => "LPTIM2",
         Some("any") => {
             // Order of TIM candidators:
             // 1. 2CH -> 2CH_CMP -> GP16 -> GP32 -> ADV
@@ -848,6 +850,7 @@ fn main() {
         (("eth", "TXD2"), quote!(crate::eth::TXD2Pin)),
         (("eth", "TXD3"), quote!(crate::eth::TXD3Pin)),
         (("eth", "TX_EN"), quote!(crate::eth::TXEnPin)),
+        (("eth", "PPS_OUT"), quote!(crate::eth::PPSOutPin)),
         (("fmc", "A0"), quote!(crate::fmc::A0Pin)),
         (("fmc", "A1"), quote!(crate::fmc::A1Pin)),
         (("fmc", "A2"), quote!(crate::fmc::A2Pin)),
@@ -996,6 +999,7 @@ fn main() {
         (("quadspi", "BK2_IO3"), quote!(crate::qspi::BK2D3Pin)),
         (("quadspi", "BK2_NCS"), quote!(crate::qspi::BK2NSSPin)),
         (("quadspi", "CLK"), quote!(crate::qspi::SckPin)),
+        (("dfsdm", "CKOUT"), quote!(crate::dfsdm::CkOutPin)),
              ].into();
 
     for p in METADATA.peripherals {
@@ -1063,6 +1067,37 @@ fn main() {
                     }
                 }
 
+                // SDADC positive inputs carry the channel number
+                if regs.kind == "sdadc" {
+                    if let Some(ch) = pin.signal.strip_prefix("AIN").and_then(|s| s.strip_suffix('P')) {
+                        let peri = format_ident!("{}", p.name);
+                        let pin_name = format_ident!("{}", pin.pin);
+                        let ch: u8 = ch.parse().unwrap();
+                        g.extend(quote! {
+                            impl_sdadc_pin!( #peri, #pin_name, #ch);
+                        })
+                    }
+                }
+
+                // DFSDM channel pins carry the channel number
+                if regs.kind == "dfsdm" {
+                    let peri = format_ident!("{}", p.name);
+                    let pin_name = format_ident!("{}", pin.pin);
+                    let af = pin.af.unwrap_or(0);
+
+                    if let Some(ch) = pin.signal.strip_prefix("DATIN") {
+                        let ch: u8 = ch.parse().unwrap();
+                        g.extend(quote! {
+                            impl_dfsdm_datin_pin!( #peri, #pin_name, #ch, #af);
+                        })
+                    } else if let Some(ch) = pin.signal.strip_prefix("CKIN") {
+                        let ch: u8 = ch.parse().unwrap();
+                        g.extend(quote! {
+                            impl_dfsdm_ckin_pin!( #peri, #pin_name, #ch, #af);
+                        })
+                    }
+                }
+
                 if regs.kind == "opamp" {
                     if pin.signal.starts_with("VP") {
                         // Impl NonInvertingPin for the VP* signals (VP0, VP1, VP2, etc)
@@ -1121,10 +1156,25 @@ fn main() {
         (("dac", "CH2"), quote!(crate::dac::DacDma2)),
         (("timer", "UP"), quote!(crate::timer::UpDma)),
         (("hash", "IN"), quote!(crate::hash::Dma)),
+        (("cryp", "IN"), quote!(crate::cryp::DmaIn)),
+        (("cryp", "OUT"), quote!(crate::cryp::DmaOut)),
         (("timer", "CH1"), quote!(crate::timer::Ch1Dma)),
         (("timer", "CH2"), quote!(crate::timer::Ch2Dma)),
         (("timer", "CH3"), quote!(crate::timer::Ch3Dma)),
         (("timer", "CH4"), quote!(crate::timer::Ch4Dma)),
+        (("sdadc", "SDADC1"), quote!(crate::sdadc::RxDma)),
+        (("sdadc", "SDADC2"), quote!(crate::sdadc::RxDma)),
+        (("sdadc", "SDADC3"), quote!(crate::sdadc::RxDma)),
+        (("adc", "ADC"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC1"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC2"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC3"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC4"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC5"), quote!(crate::adc::RxDma)),
+        (("dfsdm", "FLT0"), quote!(crate::dfsdm::FilterDma<Flt0>)),
+        (("dfsdm", "FLT1"), quote!(crate::dfsdm::FilterDma<Flt1>)),
+        (("dfsdm", "FLT2"), quote!(crate::dfsdm::FilterDma<Flt2>)),
+        (("dfsdm", "FLT3"), quote!(crate::dfsdm::FilterDma<Flt3>)),
     ]
     .into();
 
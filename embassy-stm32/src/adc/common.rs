@@ -0,0 +1,57 @@
+//! Ownership of the internal channel enable bits.
+//!
+//! The VREFINT, temperature sensor and VBAT enable bits live in the common register block that is
+//! shared by several ADCs, so every handle returned by `enable_*` holds a reference and the bit is
+//! only cleared once the last handle of that block is dropped. Chips with more than one common block
+//! (like ADC12_COMMON and ADC3_COMMON on H7) have a separate count for each of them.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+/// The most common register blocks a chip has.
+const COMMON_BLOCKS: usize = 4;
+
+/// Number of live handles to an internal channel, for each common register block.
+pub(crate) struct ChannelRefCount(Mutex<RefCell<[(&'static str, u8); COMMON_BLOCKS]>>);
+
+impl ChannelRefCount {
+    pub(crate) const fn new() -> Self {
+        Self(Mutex::new(RefCell::new([("", 0); COMMON_BLOCKS])))
+    }
+
+    /// Take a reference on the common block named `block`, calling `enable` if this is its first one.
+    pub(crate) fn acquire(&self, block: &'static str, enable: impl FnOnce()) {
+        critical_section::with(|cs| {
+            let mut counts = self.0.borrow_ref_mut(cs);
+            let count = match counts.iter().position(|(name, _)| *name == block) {
+                Some(i) => &mut counts[i],
+                None => {
+                    let i = unwrap!(counts.iter().position(|(_, count)| *count == 0));
+                    counts[i] = (block, 0);
+                    &mut counts[i]
+                }
+            };
+            if count.1 == 0 {
+                enable();
+            }
+            count.1 += 1;
+        })
+    }
+
+    /// Drop a reference on the common block named `block`, calling `disable` if this was its last one.
+    pub(crate) fn release(&self, block: &'static str, disable: impl FnOnce()) {
+        critical_section::with(|cs| {
+            let mut counts = self.0.borrow_ref_mut(cs);
+            let count = unwrap!(counts.iter_mut().find(|(name, _)| *name == block));
+            count.1 -= 1;
+            if count.1 == 0 {
+                disable();
+            }
+        })
+    }
+}
+
+pub(crate) static VREF_COUNT: ChannelRefCount = ChannelRefCount::new();
+pub(crate) static TEMP_COUNT: ChannelRefCount = ChannelRefCount::new();
+pub(crate) static VBAT_COUNT: ChannelRefCount = ChannelRefCount::new();
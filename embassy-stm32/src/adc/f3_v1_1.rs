@@ -79,30 +79,89 @@ impl<T: Instance> Vref<T> {
 
     pub async fn calibrate(&mut self, adc: &mut Adc<'_, T>) -> Calibration {
         let vref_val = adc.read(self).await;
-        Calibration {
-            vref_cal: self.calibrated_value(),
-            vref_val,
-        }
+        Calibration::from_parts(self.calibrated_value(), vref_val)
     }
 }
 
+/// VDDA calibration, derived from a VREFINT measurement or a known reference voltage.
+///
+/// This can be stored (see [`Calibration::from_parts`]) and later restored with
+/// [`Adc::apply_calibration`] to skip measuring VREFINT at boot.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Calibration {
     vref_cal: u16,
     vref_val: u16,
+    vref_uv: Option<u32>,
 }
 
 impl Calibration {
     /// The millivolts that the calibration value was measured at
     pub const CALIBRATION_UV: u32 = 3_000_000;
 
+    /// Create a calibration from previously stored raw values.
+    pub const fn from_parts(vref_cal: u16, vref_val: u16) -> Self {
+        Self {
+            vref_cal,
+            vref_val,
+            vref_uv: None,
+        }
+    }
+
+    /// Create a calibration from a known reference voltage in microvolts (uV).
+    ///
+    /// Use this on boards with a precision external reference, where measuring VREFINT is
+    /// unnecessary or less accurate.
+    pub const fn from_vref_uv(vref_uv: u32) -> Self {
+        Self {
+            vref_cal: 0,
+            vref_val: 0,
+            vref_uv: Some(vref_uv),
+        }
+    }
+
+    /// The factory VREFINT calibration value, measured with VDDA at [`Self::CALIBRATION_UV`].
+    ///
+    /// This is 0 for calibrations created with [`Self::from_vref_uv`].
+    pub const fn vref_cal(&self) -> u16 {
+        self.vref_cal
+    }
+
+    /// The raw VREFINT reading this calibration was measured with.
+    ///
+    /// This is 0 for calibrations created with [`Self::from_vref_uv`].
+    pub const fn vref_val(&self) -> u16 {
+        self.vref_val
+    }
+
+    /// The known reference voltage, for calibrations created with [`Self::from_vref_uv`].
+    pub const fn vref_uv(&self) -> Option<u32> {
+        self.vref_uv
+    }
+
     /// Returns the measured VddA in microvolts (uV)
     pub fn vdda_uv(&self) -> u32 {
-        (Self::CALIBRATION_UV * self.vref_cal as u32) / self.vref_val as u32
+        match self.vref_uv {
+            Some(vref_uv) => vref_uv,
+            None => (Self::CALIBRATION_UV * self.vref_cal as u32) / self.vref_val as u32,
+        }
     }
 
     /// Returns the measured VddA as an f32
     pub fn vdda_f32(&self) -> f32 {
-        (Self::CALIBRATION_UV as f32 / 1_000.0) * (self.vref_cal as f32 / self.vref_val as f32)
+        match self.vref_uv {
+            Some(vref_uv) => vref_uv as f32 / 1_000.0,
+            None => (Self::CALIBRATION_UV as f32 / 1_000.0) * (self.vref_cal as f32 / self.vref_val as f32),
+        }
+    }
+
+    /// Returns a calibrated voltage value in the requested representation.
+    ///
+    /// For example `let v: MilliVolts = cal.calibrate_value(raw, res);`, or an `f32` in volts.
+    pub fn calibrate_value<V: FromMicroVolts>(&self, raw: u16, resolution: super::Resolution) -> V {
+        let uv = self.vdda_uv() as i64 * raw as i64 / super::resolution_to_max_count(resolution) as i64;
+        V::from_uv(uv)
     }
 
     /// Returns a calibrated voltage value as in microvolts (uV)
@@ -116,6 +175,49 @@ impl Calibration {
     }
 }
 
+/// Voltage in microvolts (uV).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MicroVolts(pub i32);
+
+/// Voltage in millivolts (mV).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MilliVolts(pub i32);
+
+/// Output representation of [`Calibration::calibrate_value`].
+pub trait FromMicroVolts {
+    /// Convert a voltage in microvolts.
+    fn from_uv(uv: i64) -> Self;
+}
+
+impl FromMicroVolts for MicroVolts {
+    fn from_uv(uv: i64) -> Self {
+        MicroVolts(uv as i32)
+    }
+}
+
+impl FromMicroVolts for MilliVolts {
+    fn from_uv(uv: i64) -> Self {
+        MilliVolts((uv / 1_000) as i32)
+    }
+}
+
+/// Volts.
+impl FromMicroVolts for f32 {
+    fn from_uv(uv: i64) -> Self {
+        uv as f32 / 1_000_000.0
+    }
+}
+
+/// Volts.
+#[cfg(feature = "fixed")]
+impl<Frac: fixed::types::extra::LeEqU32> FromMicroVolts for fixed::FixedI32<Frac> {
+    fn from_uv(uv: i64) -> Self {
+        (fixed::types::I32F32::from_num(uv) / 1_000_000).to_num()
+    }
+}
+
 impl<T: Instance> Drop for Vref<T> {
     fn drop(&mut self) {
         update_vref::<T>(-1)
@@ -153,7 +255,17 @@ impl<'d, T: Instance> Adc<'d, T> {
             T::Interrupt::enable();
         }
 
-        Self { adc }
+        Self { adc, calibration: None }
+    }
+
+    /// Use `calibration` for calibrated readings, such as [`Adc::read_uv`].
+    pub fn apply_calibration(&mut self, calibration: Calibration) {
+        self.calibration = Some(calibration);
+    }
+
+    /// The calibration currently in use, if any.
+    pub fn calibration(&self) -> Option<Calibration> {
+        self.calibration
     }
 
     fn freq() -> Hertz {
@@ -276,6 +388,15 @@ impl<'d, T: Instance> Adc<'d, T> {
         self.convert().await
     }
 
+    /// Read a pin and convert the result to microvolts (uV) using the applied calibration.
+    ///
+    /// Returns `None` if no calibration has been applied.
+    pub async fn read_uv(&mut self, pin: &mut impl AdcPin<T>) -> Option<u32> {
+        let calibration = self.calibration?;
+        let raw = self.read(pin).await;
+        Some(calibration.cal_uv(raw, self.resolution()))
+    }
+
     async fn wait_sample_ready(&self) {
         //trace!("Waiting for sample channel to be ready");
         while T::regs().sr().read().rcnr() {
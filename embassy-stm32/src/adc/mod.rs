@@ -18,6 +18,9 @@ mod _version;
 #[cfg(not(adc_f3_v2))]
 pub use _version::*;
 
+#[cfg(any(adc_v3, adc_g0, adc_h5, adc_v4))]
+mod common;
+
 #[cfg(not(any(adc_f1, adc_f3_v2)))]
 pub use crate::pac::adc::vals::Res as Resolution;
 pub use crate::pac::adc::vals::SampleTime;
@@ -29,6 +32,8 @@ pub struct Adc<'d, T: Instance> {
     adc: crate::PeripheralRef<'d, T>,
     #[cfg(not(any(adc_f3_v2, adc_f3_v1_1)))]
     sample_time: SampleTime,
+    #[cfg(adc_f3_v1_1)]
+    calibration: Option<Calibration>,
 }
 
 pub(crate) mod sealed {
@@ -59,6 +64,9 @@ pub(crate) mod sealed {
         fn common_regs() -> crate::pac::adccommon::AdcCommon;
         #[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
         fn state() -> &'static State;
+        /// Name of the common register block shared with other instances
+        #[cfg(any(adc_v3, adc_g0, adc_h5, adc_v4))]
+        fn common_block() -> &'static str;
     }
 
     pub trait AdcPin<T: Instance> {
@@ -80,6 +88,8 @@ pub trait Instance: sealed::Instance + crate::Peripheral<P = Self> {}
 #[cfg(any(adc_f1, adc_v1, adc_l0, adc_v2, adc_v3, adc_v4, adc_f3, adc_f3_v1_1, adc_g0, adc_h5))]
 pub trait Instance: sealed::Instance + crate::Peripheral<P = Self> + crate::rcc::RccPeripheral {}
 
+dma_trait!(RxDma, Instance);
+
 /// ADC pin.
 pub trait AdcPin<T: Instance>: sealed::AdcPin<T> {}
 /// ADC internal channel.
@@ -102,6 +112,11 @@ foreach_adc!(
                 static STATE: sealed::State = sealed::State::new();
                 &STATE
             }
+
+            #[cfg(any(adc_v3, adc_g0, adc_h5, adc_v4))]
+            fn common_block() -> &'static str {
+                stringify!($common_inst)
+            }
         }
 
         foreach_interrupt!(
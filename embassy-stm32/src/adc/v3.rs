@@ -1,7 +1,10 @@
+use core::marker::PhantomData;
+
 use cfg_if::cfg_if;
 use embassy_hal_internal::into_ref;
 use embedded_hal_02::blocking::delay::DelayUs;
 
+use super::common::{TEMP_COUNT, VBAT_COUNT, VREF_COUNT};
 use crate::adc::{Adc, AdcPin, Instance, Resolution, SampleTime};
 use crate::Peripheral;
 
@@ -10,9 +13,12 @@ pub const VREF_DEFAULT_MV: u32 = 3300;
 /// VREF voltage used for factory calibration of VREFINTCAL register.
 pub const VREF_CALIB_MV: u32 = 3000;
 
-pub struct VrefInt;
-impl<T: Instance> AdcPin<T> for VrefInt {}
-impl<T: Instance> super::sealed::AdcPin<T> for VrefInt {
+/// Internal voltage reference channel.
+///
+/// The reference stays enabled until the last handle is dropped.
+pub struct VrefInt<T: Instance>(PhantomData<T>);
+impl<T: Instance> AdcPin<T> for VrefInt<T> {}
+impl<T: Instance> super::sealed::AdcPin<T> for VrefInt<T> {
     fn channel(&self) -> u8 {
         cfg_if! {
             if #[cfg(adc_g0)] {
@@ -27,9 +33,31 @@ impl<T: Instance> super::sealed::AdcPin<T> for VrefInt {
     }
 }
 
-pub struct Temperature;
-impl<T: Instance> AdcPin<T> for Temperature {}
-impl<T: Instance> super::sealed::AdcPin<T> for Temperature {
+impl<T: Instance> VrefInt<T> {
+    fn set_enabled(enabled: bool) {
+        #[cfg(not(adc_g0))]
+        T::common_regs().ccr().modify(|reg| {
+            reg.set_vrefen(enabled);
+        });
+        #[cfg(adc_g0)]
+        T::regs().ccr().modify(|reg| {
+            reg.set_vrefen(enabled);
+        });
+    }
+}
+
+impl<T: Instance> Drop for VrefInt<T> {
+    fn drop(&mut self) {
+        VREF_COUNT.release(T::common_block(), || Self::set_enabled(false));
+    }
+}
+
+/// Internal temperature sensor channel.
+///
+/// The sensor stays enabled until the last handle is dropped.
+pub struct Temperature<T: Instance>(PhantomData<T>);
+impl<T: Instance> AdcPin<T> for Temperature<T> {}
+impl<T: Instance> super::sealed::AdcPin<T> for Temperature<T> {
     fn channel(&self) -> u8 {
         cfg_if! {
             if #[cfg(adc_g0)] {
@@ -44,9 +72,38 @@ impl<T: Instance> super::sealed::AdcPin<T> for Temperature {
     }
 }
 
-pub struct Vbat;
-impl<T: Instance> AdcPin<T> for Vbat {}
-impl<T: Instance> super::sealed::AdcPin<T> for Vbat {
+impl<T: Instance> Temperature<T> {
+    fn set_enabled(enabled: bool) {
+        cfg_if! {
+            if #[cfg(adc_g0)] {
+                T::regs().ccr().modify(|reg| {
+                    reg.set_tsen(enabled);
+                });
+            } else if #[cfg(adc_h5)] {
+                T::common_regs().ccr().modify(|reg| {
+                    reg.set_tsen(enabled);
+                });
+            } else {
+                T::common_regs().ccr().modify(|reg| {
+                    reg.set_ch17sel(enabled);
+                });
+            }
+        }
+    }
+}
+
+impl<T: Instance> Drop for Temperature<T> {
+    fn drop(&mut self) {
+        TEMP_COUNT.release(T::common_block(), || Self::set_enabled(false));
+    }
+}
+
+/// Internal battery voltage channel.
+///
+/// The VBAT bridge stays enabled until the last handle is dropped.
+pub struct Vbat<T: Instance>(PhantomData<T>);
+impl<T: Instance> AdcPin<T> for Vbat<T> {}
+impl<T: Instance> super::sealed::AdcPin<T> for Vbat<T> {
     fn channel(&self) -> u8 {
         cfg_if! {
             if #[cfg(adc_g0)] {
@@ -61,18 +118,57 @@ impl<T: Instance> super::sealed::AdcPin<T> for Vbat {
     }
 }
 
-cfg_if! {
-    if #[cfg(adc_h5)] {
-        pub struct VddCore;
-        impl<T: Instance> AdcPin<T> for VddCore {}
-        impl<T: Instance> super::sealed::AdcPin<T> for VddCore {
-            fn channel(&self) -> u8 {
-                6
+impl<T: Instance> Vbat<T> {
+    fn set_enabled(enabled: bool) {
+        cfg_if! {
+            if #[cfg(adc_g0)] {
+                T::regs().ccr().modify(|reg| {
+                    reg.set_vbaten(enabled);
+                });
+            } else if #[cfg(adc_h5)] {
+                T::common_regs().ccr().modify(|reg| {
+                    reg.set_vbaten(enabled);
+                });
+            } else {
+                T::common_regs().ccr().modify(|reg| {
+                    reg.set_ch18sel(enabled);
+                });
             }
         }
     }
 }
 
+impl<T: Instance> Drop for Vbat<T> {
+    fn drop(&mut self) {
+        VBAT_COUNT.release(T::common_block(), || Self::set_enabled(false));
+    }
+}
+
+/// Internal core supply (VDDCORE) channel.
+///
+/// Only wired to ADC2, enable it with [`Adc::enable_vddcore`].
+#[cfg(adc_h5)]
+pub struct VddCore;
+#[cfg(adc_h5)]
+impl<T: Instance> AdcPin<T> for VddCore {}
+#[cfg(adc_h5)]
+impl<T: Instance> super::sealed::AdcPin<T> for VddCore {
+    fn channel(&self) -> u8 {
+        6
+    }
+}
+
+#[cfg(adc_h5)]
+impl VddCore {
+    /// Convert a raw sample of this channel into millivolts.
+    ///
+    /// `vref_mv` is the ADC reference voltage, either [`VREF_DEFAULT_MV`] or a value measured
+    /// through [`VrefInt`].
+    pub fn to_millivolts(sample: u16, vref_mv: u32, resolution: Resolution) -> u32 {
+        sample as u32 * vref_mv / super::resolution_to_max_count(resolution)
+    }
+}
+
 impl<'d, T: Instance> Adc<'d, T> {
     pub fn new(adc: impl Peripheral<P = T> + 'd, delay: &mut impl DelayUs<u32>) -> Self {
         into_ref!(adc);
@@ -106,63 +202,38 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 
-    pub fn enable_vrefint(&self, delay: &mut impl DelayUs<u32>) -> VrefInt {
-        #[cfg(not(adc_g0))]
-        T::common_regs().ccr().modify(|reg| {
-            reg.set_vrefen(true);
-        });
-        #[cfg(adc_g0)]
-        T::regs().ccr().modify(|reg| {
-            reg.set_vrefen(true);
-        });
+    pub fn enable_vrefint(&self, delay: &mut impl DelayUs<u32>) -> VrefInt<T> {
+        VREF_COUNT.acquire(T::common_block(), || VrefInt::<T>::set_enabled(true));
 
         // "Table 24. Embedded internal voltage reference" states that it takes a maximum of 12 us
         // to stabilize the internal voltage reference, we wait a little more.
-        // TODO: delay 15us
-        //cortex_m::asm::delay(20_000_000);
         delay.delay_us(15);
 
-        VrefInt {}
+        VrefInt(PhantomData)
     }
 
-    pub fn enable_temperature(&self) -> Temperature {
-        cfg_if! {
-            if #[cfg(adc_g0)] {
-                T::regs().ccr().modify(|reg| {
-                    reg.set_tsen(true);
-                });
-            } else if #[cfg(adc_h5)] {
-                T::common_regs().ccr().modify(|reg| {
-                    reg.set_tsen(true);
-                });
-            } else {
-                T::common_regs().ccr().modify(|reg| {
-                    reg.set_ch17sel(true);
-                });
-            }
-        }
+    pub fn enable_temperature(&self) -> Temperature<T> {
+        TEMP_COUNT.acquire(T::common_block(), || Temperature::<T>::set_enabled(true));
 
-        Temperature {}
+        Temperature(PhantomData)
     }
 
-    pub fn enable_vbat(&self) -> Vbat {
-        cfg_if! {
-            if #[cfg(adc_g0)] {
-                T::regs().ccr().modify(|reg| {
-                    reg.set_vbaten(true);
-                });
-            } else if #[cfg(adc_h5)] {
-                T::common_regs().ccr().modify(|reg| {
-                    reg.set_vbaten(true);
-                });
-            } else {
-                T::common_regs().ccr().modify(|reg| {
-                    reg.set_ch18sel(true);
-                });
-            }
-        }
+    pub fn enable_vbat(&self) -> Vbat<T> {
+        VBAT_COUNT.acquire(T::common_block(), || Vbat::<T>::set_enabled(true));
+
+        Vbat(PhantomData)
+    }
+
+    /// Enable the VDDCORE monitoring channel.
+    ///
+    /// The channel is connected to ADC2 only, on ADC1 this has no effect.
+    #[cfg(adc_h5)]
+    pub fn enable_vddcore(&self) -> VddCore {
+        T::regs().or().modify(|reg| {
+            reg.set_op0(true);
+        });
 
-        Vbat {}
+        VddCore {}
     }
 
     pub fn set_sample_time(&mut self, sample_time: SampleTime) {
@@ -187,6 +258,11 @@ impl<'d, T: Instance> Adc<'d, T> {
 
     /// Perform a single conversion.
     fn convert(&mut self) -> u16 {
+        Self::start_conversion();
+        Self::wait_conversion()
+    }
+
+    fn start_conversion() {
         T::regs().isr().modify(|reg| {
             reg.set_eos(true);
             reg.set_eoc(true);
@@ -196,7 +272,9 @@ impl<'d, T: Instance> Adc<'d, T> {
         T::regs().cr().modify(|reg| {
             reg.set_adstart(true);
         });
+    }
 
+    fn wait_conversion() -> u16 {
         while !T::regs().isr().read().eos() {
             // spin
         }
@@ -205,6 +283,68 @@ impl<'d, T: Instance> Adc<'d, T> {
     }
 
     pub fn read(&mut self, pin: &mut impl AdcPin<T>) -> u16 {
+        Self::enable();
+        self.setup_channel(pin.channel());
+
+        // Some models are affected by an erratum:
+        // If we perform conversions slower than 1 kHz, the first read ADC value can be
+        // corrupted, so we discard it and measure again.
+        //
+        // STM32L471xx: Section 2.7.3
+        // STM32G4: Section 2.7.3
+        #[cfg(any(rcc_l4, rcc_g4))]
+        let _ = self.convert();
+
+        let val = self.convert();
+
+        Self::disable();
+
+        val
+    }
+
+    /// Read two ADC pins on two different ADC instances at nearly the same time.
+    ///
+    /// Both channels are configured first, then the two conversions are started back-to-back
+    /// from within a critical section. The skew between the two sampling instants is therefore
+    /// bounded by a single register write instead of a full conversion time, which is what
+    /// paired measurements such as voltage and current (for power) need.
+    ///
+    /// For the skew to stay constant both ADCs should run from the same clock with the same
+    /// sample time and resolution.
+    pub fn read_simultaneous<U: Instance>(
+        &mut self,
+        other: &mut Adc<'_, U>,
+        pin: &mut impl AdcPin<T>,
+        other_pin: &mut impl AdcPin<U>,
+    ) -> (u16, u16) {
+        Self::enable();
+        Adc::<'_, U>::enable();
+
+        self.setup_channel(pin.channel());
+        other.setup_channel(other_pin.channel());
+
+        // See the erratum in `read`, the first conversion is discarded on affected models.
+        #[cfg(any(rcc_l4, rcc_g4))]
+        let _ = Self::convert_simultaneous::<U>();
+
+        let val = Self::convert_simultaneous::<U>();
+
+        Self::disable();
+        Adc::<'_, U>::disable();
+
+        val
+    }
+
+    fn convert_simultaneous<U: Instance>() -> (u16, u16) {
+        critical_section::with(|_| {
+            Self::start_conversion();
+            Adc::<'_, U>::start_conversion();
+        });
+
+        (Self::wait_conversion(), Adc::<'_, U>::wait_conversion())
+    }
+
+    fn enable() {
         // Make sure bits are off
         while T::regs().cr().read().addis() {
             // spin
@@ -221,30 +361,21 @@ impl<'d, T: Instance> Adc<'d, T> {
         while !T::regs().isr().read().adrdy() {
             // spin
         }
+    }
 
+    fn disable() {
+        T::regs().cr().modify(|reg| reg.set_addis(true));
+    }
+
+    fn setup_channel(&mut self, channel: u8) {
         // Configure channel
-        Self::set_channel_sample_time(pin.channel(), self.sample_time);
+        Self::set_channel_sample_time(channel, self.sample_time);
 
         // Select channel
         #[cfg(not(adc_g0))]
-        T::regs().sqr1().write(|reg| reg.set_sq(0, pin.channel()));
+        T::regs().sqr1().write(|reg| reg.set_sq(0, channel));
         #[cfg(adc_g0)]
-        T::regs().chselr().write(|reg| reg.set_chsel(1 << pin.channel()));
-
-        // Some models are affected by an erratum:
-        // If we perform conversions slower than 1 kHz, the first read ADC value can be
-        // corrupted, so we discard it and measure again.
-        //
-        // STM32L471xx: Section 2.7.3
-        // STM32G4: Section 2.7.3
-        #[cfg(any(rcc_l4, rcc_g4))]
-        let _ = self.convert();
-
-        let val = self.convert();
-
-        T::regs().cr().modify(|reg| reg.set_addis(true));
-
-        val
+        T::regs().chselr().write(|reg| reg.set_chsel(1 << channel));
     }
 
     fn set_channel_sample_time(_ch: u8, sample_time: SampleTime) {
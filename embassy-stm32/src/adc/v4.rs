@@ -1,9 +1,15 @@
+use core::marker::PhantomData;
+
+use embassy_hal_internal::into_ref;
 use embedded_hal_02::blocking::delay::DelayUs;
 #[allow(unused)]
 use pac::adc::vals::{Adcaldif, Boost, Difsel, Exten, Pcsel};
 use pac::adccommon::vals::Presc;
 
-use super::{Adc, AdcPin, Instance, InternalChannel, Resolution, SampleTime};
+use super::common::{TEMP_COUNT, VBAT_COUNT, VREF_COUNT};
+use super::{Adc, AdcPin, Instance, InternalChannel, Resolution, RxDma, SampleTime};
+use crate::dma::ringbuffer::OverrunError;
+use crate::dma::{ReadableRingBuffer, TransferOptions};
 use crate::time::Hertz;
 use crate::{pac, Peripheral};
 
@@ -33,32 +39,62 @@ const VBAT_CHANNEL: u8 = 17;
 
 // NOTE: Vrefint/Temperature/Vbat are not available on all ADCs, this currently cannot be modeled with stm32-data, so these are available from the software on all ADCs
 /// Internal voltage reference channel.
-pub struct VrefInt;
-impl<T: Instance> InternalChannel<T> for VrefInt {}
-impl<T: Instance> super::sealed::InternalChannel<T> for VrefInt {
+///
+/// The reference stays enabled until the last handle is dropped.
+pub struct VrefInt<T: Instance>(PhantomData<T>);
+impl<T: Instance> InternalChannel<T> for VrefInt<T> {}
+impl<T: Instance> super::sealed::InternalChannel<T> for VrefInt<T> {
     fn channel(&self) -> u8 {
         VREF_CHANNEL
     }
 }
 
+impl<T: Instance> Drop for VrefInt<T> {
+    fn drop(&mut self) {
+        VREF_COUNT.release(T::common_block(), || {
+            T::common_regs().ccr().modify(|reg| reg.set_vrefen(false))
+        });
+    }
+}
+
 /// Internal temperature channel.
-pub struct Temperature;
-impl<T: Instance> InternalChannel<T> for Temperature {}
-impl<T: Instance> super::sealed::InternalChannel<T> for Temperature {
+///
+/// The sensor stays enabled until the last handle is dropped.
+pub struct Temperature<T: Instance>(PhantomData<T>);
+impl<T: Instance> InternalChannel<T> for Temperature<T> {}
+impl<T: Instance> super::sealed::InternalChannel<T> for Temperature<T> {
     fn channel(&self) -> u8 {
         TEMP_CHANNEL
     }
 }
 
+impl<T: Instance> Drop for Temperature<T> {
+    fn drop(&mut self) {
+        TEMP_COUNT.release(T::common_block(), || {
+            T::common_regs().ccr().modify(|reg| reg.set_vsenseen(false))
+        });
+    }
+}
+
 /// Internal battery voltage channel.
-pub struct Vbat;
-impl<T: Instance> InternalChannel<T> for Vbat {}
-impl<T: Instance> super::sealed::InternalChannel<T> for Vbat {
+///
+/// The VBAT bridge stays enabled until the last handle is dropped.
+pub struct Vbat<T: Instance>(PhantomData<T>);
+impl<T: Instance> InternalChannel<T> for Vbat<T> {}
+impl<T: Instance> super::sealed::InternalChannel<T> for Vbat<T> {
     fn channel(&self) -> u8 {
         VBAT_CHANNEL
     }
 }
 
+impl<T: Instance> Drop for Vbat<T> {
+    fn drop(&mut self) {
+        VBAT_COUNT.release(T::common_block(), || {
+            T::common_regs().ccr().modify(|reg| reg.set_vbaten(false))
+        });
+    }
+}
+
 // NOTE (unused): The prescaler enum closely copies the hardware capabilities,
 // but high prescaling doesn't make a lot of sense in the current implementation and is ommited.
 #[allow(unused)]
@@ -216,31 +252,65 @@ impl<'d, T: Instance> Adc<'d, T> {
         });
     }
 
+    /// Fully power down the ADC, for example before entering STOP2.
+    ///
+    /// The configuration is saved and restored by [`SuspendedAdc::resume`], which also runs the
+    /// calibration again, since it is lost in deep power-down.
+    pub fn suspend(self) -> SuspendedAdc<'d, T> {
+        let r = T::regs();
+        let saved = SavedConfig {
+            cfgr: r.cfgr().read(),
+            cfgr2: r.cfgr2().read(),
+            smpr: [r.smpr(0).read(), r.smpr(1).read()],
+            sqr1: r.sqr1().read(),
+            sqr2: r.sqr2().read(),
+            sqr3: r.sqr3().read(),
+            sqr4: r.sqr4().read(),
+            difsel: r.difsel().read(),
+            #[cfg(stm32h7)]
+            pcsel: r.pcsel().read(),
+        };
+
+        if r.cr().read().adstart() {
+            r.cr().modify(|w| w.set_adstp(true));
+            while r.cr().read().adstart() {}
+        }
+        if r.cr().read().aden() {
+            r.cr().modify(|w| w.set_addis(true));
+            while r.cr().read().aden() {}
+        }
+
+        r.cr().modify(|w| w.set_advregen(false));
+        r.cr().modify(|w| w.set_deeppwd(true));
+
+        SuspendedAdc { adc: self, saved }
+    }
+
     /// Enable reading the voltage reference internal channel.
-    pub fn enable_vrefint(&self) -> VrefInt {
-        T::common_regs().ccr().modify(|reg| {
-            reg.set_vrefen(true);
+    pub fn enable_vrefint(&self) -> VrefInt<T> {
+        VREF_COUNT.acquire(T::common_block(), || {
+            T::common_regs().ccr().modify(|reg| reg.set_vrefen(true))
         });
 
-        VrefInt {}
+        VrefInt(PhantomData)
     }
 
     /// Enable reading the temperature internal channel.
-    pub fn enable_temperature(&self) -> Temperature {
-        T::common_regs().ccr().modify(|reg| {
-            reg.set_vsenseen(true);
+    pub fn enable_temperature(&self) -> Temperature<T> {
+        TEMP_COUNT.acquire(T::common_block(), || {
+            T::common_regs().ccr().modify(|reg| reg.set_vsenseen(true))
         });
 
-        Temperature {}
+        Temperature(PhantomData)
     }
 
     /// Enable reading the vbat internal channel.
-    pub fn enable_vbat(&self) -> Vbat {
-        T::common_regs().ccr().modify(|reg| {
-            reg.set_vbaten(true);
+    pub fn enable_vbat(&self) -> Vbat<T> {
+        VBAT_COUNT.acquire(T::common_block(), || {
+            T::common_regs().ccr().modify(|reg| reg.set_vbaten(true))
         });
 
-        Vbat {}
+        Vbat(PhantomData)
     }
 
     /// Set the ADC sample time.
@@ -255,6 +325,11 @@ impl<'d, T: Instance> Adc<'d, T> {
 
     /// Perform a single conversion.
     fn convert(&mut self) -> u16 {
+        Self::start_conversion();
+        Self::wait_conversion()
+    }
+
+    fn start_conversion() {
         T::regs().isr().modify(|reg| {
             reg.set_eos(true);
             reg.set_eoc(true);
@@ -264,7 +339,9 @@ impl<'d, T: Instance> Adc<'d, T> {
         T::regs().cr().modify(|reg| {
             reg.set_adstart(true);
         });
+    }
 
+    fn wait_conversion() -> u16 {
         while !T::regs().isr().read().eos() {
             // spin
         }
@@ -288,7 +365,89 @@ impl<'d, T: Instance> Adc<'d, T> {
         self.read_channel(channel.channel())
     }
 
+    /// Read two ADC pins on two different ADC instances at nearly the same time.
+    ///
+    /// Both channels are configured first, then the two conversions are started back-to-back
+    /// from within a critical section. The skew between the two sampling instants is therefore
+    /// bounded by a single register write instead of a full conversion time, which is what
+    /// paired measurements such as voltage and current (for power) need.
+    ///
+    /// For the skew to stay constant both ADCs should run from the same clock with the same
+    /// sample time and resolution.
+    pub fn read_simultaneous<U, P, Q>(&mut self, other: &mut Adc<'_, U>, pin: &mut P, other_pin: &mut Q) -> (u16, u16)
+    where
+        U: Instance,
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+        Q: AdcPin<U>,
+        Q: crate::gpio::sealed::Pin,
+    {
+        pin.set_as_analog();
+        other_pin.set_as_analog();
+
+        self.setup_channel(pin.channel());
+        other.setup_channel(other_pin.channel());
+
+        critical_section::with(|_| {
+            Self::start_conversion();
+            Adc::<'_, U>::start_conversion();
+        });
+
+        (Self::wait_conversion(), Adc::<'_, U>::wait_conversion())
+    }
+
+    /// Read an ADC pin, guarded by the analog watchdog.
+    ///
+    /// Returns [`Error::OutOfRange`] with the converted value if it falls outside `low..=high`.
+    pub fn guarded_read<P>(&mut self, pin: &mut P, low: u16, high: u16) -> Result<u16, Error>
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        pin.set_as_analog();
+
+        let channel = pin.channel();
+        Self::set_watchdog(Some(channel), low, high);
+        let value = self.read_channel(channel);
+        let tripped = T::regs().isr().read().awd1();
+        Self::set_watchdog(None, 0, 0);
+
+        if tripped {
+            Err(Error::OutOfRange(value))
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Arm analog watchdog 1 on `channel`, or disarm it with `None`.
+    fn set_watchdog(channel: Option<u8>, low: u16, high: u16) {
+        let r = T::regs();
+
+        #[cfg(stm32g4)]
+        r.tr1().modify(|w| {
+            w.set_lt1(low);
+            w.set_ht1(high);
+        });
+        #[cfg(stm32h7)]
+        {
+            r.ltr1().write(|w| w.set_ltr1(low as u32));
+            r.htr1().write(|w| w.set_htr1(high as u32));
+        }
+
+        r.cfgr().modify(|w| {
+            w.set_awd1en(channel.is_some());
+            w.set_awd1sgl(true);
+            w.set_awd1ch(channel.unwrap_or(0));
+        });
+        r.isr().write(|w| w.set_awd1(true));
+    }
+
     fn read_channel(&mut self, channel: u8) -> u16 {
+        self.setup_channel(channel);
+        self.convert()
+    }
+
+    fn setup_channel(&mut self, channel: u8) {
         // Configure channel
         Self::set_channel_sample_time(channel, self.sample_time);
 
@@ -304,8 +463,6 @@ impl<'d, T: Instance> Adc<'d, T> {
             reg.set_sq(0, channel);
             reg.set_l(0);
         });
-
-        self.convert()
     }
 
     fn set_channel_sample_time(ch: u8, sample_time: SampleTime) {
@@ -317,3 +474,551 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 }
+
+struct SavedConfig {
+    cfgr: pac::adc::regs::Cfgr,
+    cfgr2: pac::adc::regs::Cfgr2,
+    smpr: [pac::adc::regs::Smpr; 2],
+    sqr1: pac::adc::regs::Sqr1,
+    sqr2: pac::adc::regs::Sqr2,
+    sqr3: pac::adc::regs::Sqr3,
+    sqr4: pac::adc::regs::Sqr4,
+    difsel: pac::adc::regs::Difsel,
+    #[cfg(stm32h7)]
+    pcsel: pac::adc::regs::Pcsel,
+}
+
+/// Powered-down ADC, created with [`Adc::suspend`].
+pub struct SuspendedAdc<'d, T: Instance> {
+    adc: Adc<'d, T>,
+    saved: SavedConfig,
+}
+
+impl<'d, T: Instance> SuspendedAdc<'d, T> {
+    /// Power the ADC back up, recalibrate it and restore the configuration saved by
+    /// [`Adc::suspend`].
+    pub fn resume(self, delay: &mut impl DelayUs<u16>) -> Adc<'d, T> {
+        let Self { mut adc, saved } = self;
+        let r = T::regs();
+
+        adc.power_up(delay);
+        // DIFSEL must match the calibration mode, restore it before calibrating.
+        r.difsel().write_value(saved.difsel);
+        adc.calibrate();
+        delay.delay_us(1);
+
+        r.cfgr().write_value(saved.cfgr);
+        r.cfgr2().write_value(saved.cfgr2);
+        r.smpr(0).write_value(saved.smpr[0]);
+        r.smpr(1).write_value(saved.smpr[1]);
+        r.sqr1().write_value(saved.sqr1);
+        r.sqr2().write_value(saved.sqr2);
+        r.sqr3().write_value(saved.sqr3);
+        r.sqr4().write_value(saved.sqr4);
+        #[cfg(stm32h7)]
+        r.pcsel().write_value(saved.pcsel);
+
+        adc.enable();
+
+        adc
+    }
+}
+
+/// ADC error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The analog watchdog tripped, the value is the offending conversion result.
+    OutOfRange(u16),
+    /// The DMA ring buffer was overrun.
+    Overrun,
+}
+
+impl From<OverrunError> for Error {
+    fn from(_: OverrunError) -> Self {
+        Error::Overrun
+    }
+}
+
+/// Half of the DMA buffer a block of samples was taken from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Half {
+    /// First half of the buffer.
+    First,
+    /// Second half of the buffer.
+    Second,
+}
+
+/// Regular conversion sequence, for [`Adc::into_ring_buffered_sequence`].
+pub struct Sequence<T: Instance> {
+    channels: [u8; 16],
+    len: usize,
+    discontinuous: Option<u8>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> Sequence<T> {
+    /// Create an empty sequence.
+    pub const fn new() -> Self {
+        Self {
+            channels: [0; 16],
+            len: 0,
+            discontinuous: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Append an ADC pin to the sequence.
+    ///
+    /// Panics if the sequence already holds 16 channels.
+    pub fn push<P>(&mut self, pin: &mut P) -> &mut Self
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        pin.set_as_analog();
+        self.push_channel(pin.channel())
+    }
+
+    /// Append an ADC internal channel to the sequence.
+    ///
+    /// Panics if the sequence already holds 16 channels.
+    pub fn push_internal(&mut self, channel: &mut impl InternalChannel<T>) -> &mut Self {
+        self.push_channel(channel.channel())
+    }
+
+    /// Convert the sequence in sub-groups of `n` channels (1..=8), one sub-group per trigger.
+    ///
+    /// Combined with an external [`Trigger`] this lets a long sequence be sampled round-robin,
+    /// in step with a control loop.
+    pub fn set_discontinuous(&mut self, n: u8) -> &mut Self {
+        assert!((1..=8).contains(&n));
+        self.discontinuous = Some(n);
+        self
+    }
+
+    fn push_channel(&mut self, channel: u8) -> &mut Self {
+        assert!(self.len < self.channels.len());
+        self.channels[self.len] = channel;
+        self.len += 1;
+        self
+    }
+
+    fn channels(&self) -> &[u8] {
+        &self.channels[..self.len]
+    }
+}
+
+impl<T: Instance> Default for Sequence<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Edge of the external trigger signal that starts a conversion.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerEdge {
+    /// Rising edge.
+    Rising,
+    /// Falling edge.
+    Falling,
+    /// Both edges.
+    Both,
+}
+
+/// External trigger of regular conversions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Trigger {
+    /// Trigger source, the EXTSEL value from the reference manual (for example a timer TRGO).
+    pub source: u8,
+    /// Active edge.
+    pub edge: TriggerEdge,
+}
+
+/// ADC continuously converting a regular sequence into a DMA ring buffer.
+///
+/// Created with [`Adc::into_ring_buffered`] or [`Adc::into_ring_buffered_sequence`].
+pub struct RingBufferedAdc<'d, T: Instance> {
+    _inner: Adc<'d, T>,
+    ring_buf: ReadableRingBuffer<'d, u16>,
+    next_half: Half,
+    discontinuous: bool,
+    trigger: Option<Trigger>,
+    watchdog_auto_stop: Option<bool>,
+}
+
+impl<'d, T: Instance> Adc<'d, T> {
+    /// Turn the ADC into a continuously converting stream of `pin`, backed by `dma_buf` in
+    /// circular DMA mode.
+    ///
+    /// The DMA signals both the half-transfer and transfer-complete events, so one half of
+    /// `dma_buf` can be processed while the other half is being filled.
+    pub fn into_ring_buffered<P>(
+        self,
+        dma: impl crate::Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        pin: &mut P,
+    ) -> RingBufferedAdc<'d, T>
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        let mut sequence = Sequence::new();
+        sequence.push(pin);
+
+        self.into_ring_buffered_sequence(dma, dma_buf, &sequence)
+    }
+
+    /// Turn the ADC into a continuously converting stream of `sequence`, backed by `dma_buf` in
+    /// circular DMA mode.
+    ///
+    /// Samples are stored in sequence order, so `dma_buf` should be a multiple of the sequence
+    /// length.
+    pub fn into_ring_buffered_sequence(
+        mut self,
+        dma: impl crate::Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        sequence: &Sequence<T>,
+    ) -> RingBufferedAdc<'d, T> {
+        assert!(dma_buf.len() >= 2 && dma_buf.len() % 2 == 0);
+        assert!(!sequence.channels().is_empty());
+        into_ref!(dma);
+
+        self.setup_sequence(sequence);
+
+        let request = dma.request();
+        let opts = TransferOptions {
+            half_transfer_ir: true,
+            ..Default::default()
+        };
+        let ring_buf =
+            unsafe { ReadableRingBuffer::new(dma, request, T::regs().dr().as_ptr() as *mut u16, dma_buf, opts) };
+
+        RingBufferedAdc {
+            _inner: self,
+            ring_buf,
+            next_half: Half::First,
+            discontinuous: sequence.discontinuous.is_some(),
+            trigger: None,
+            watchdog_auto_stop: None,
+        }
+    }
+
+    /// Like [`into_ring_buffered`](Self::into_ring_buffered), but also return a handle for
+    /// injected conversions.
+    ///
+    /// The regular stream and the injected conversions can then be driven from different tasks.
+    /// The hardware arbitrates between both groups: an injected conversion interrupts the regular
+    /// sequence, which resumes once it's done.
+    pub fn into_ring_buffered_with_injected<P>(
+        self,
+        dma: impl crate::Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        pin: &mut P,
+    ) -> (RingBufferedAdc<'d, T>, InjectedAdc<'d, T>)
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        let sample_time = self.sample_time;
+        let regular = self.into_ring_buffered(dma, dma_buf, pin);
+
+        let injected = InjectedAdc {
+            sample_time,
+            _phantom: PhantomData,
+        };
+
+        (regular, injected)
+    }
+
+    fn setup_sequence(&mut self, sequence: &Sequence<T>) {
+        let channels = sequence.channels();
+
+        #[cfg(stm32h7)]
+        T::regs().cfgr2().modify(|w| w.set_lshift(0));
+
+        for &channel in channels {
+            Self::set_channel_sample_time(channel, self.sample_time);
+
+            #[cfg(stm32h7)]
+            T::regs()
+                .pcsel()
+                .modify(|w| w.set_pcsel(channel as _, Pcsel::PRESELECTED));
+        }
+
+        // SQ1..SQ4 live in SQR1 next to the length, the remaining ones are split over SQR2..SQR4.
+        T::regs().sqr1().write(|w| {
+            w.set_l((channels.len() - 1) as u8);
+            for (i, &channel) in channels.iter().take(4).enumerate() {
+                w.set_sq(i, channel);
+            }
+        });
+        T::regs().sqr2().write(|w| {
+            for (i, &channel) in channels.iter().skip(4).take(5).enumerate() {
+                w.set_sq(i, channel);
+            }
+        });
+        T::regs().sqr3().write(|w| {
+            for (i, &channel) in channels.iter().skip(9).take(5).enumerate() {
+                w.set_sq(i, channel);
+            }
+        });
+        T::regs().sqr4().write(|w| {
+            for (i, &channel) in channels.iter().skip(14).take(2).enumerate() {
+                w.set_sq(i, channel);
+            }
+        });
+
+        T::regs().cfgr().modify(|w| match sequence.discontinuous {
+            Some(n) => {
+                w.set_discen(true);
+                w.set_discnum(n - 1);
+            }
+            None => w.set_discen(false),
+        });
+    }
+}
+
+/// Handle for injected conversions running alongside a regular [`RingBufferedAdc`] stream.
+///
+/// Created with [`Adc::into_ring_buffered_with_injected`].
+pub struct InjectedAdc<'d, T: Instance> {
+    sample_time: SampleTime,
+    _phantom: PhantomData<&'d mut T>,
+}
+
+impl<'d, T: Instance> InjectedAdc<'d, T> {
+    /// Set the sample time used for injected conversions.
+    pub fn set_sample_time(&mut self, sample_time: SampleTime) {
+        self.sample_time = sample_time;
+    }
+
+    /// Read an ADC pin with an injected conversion.
+    pub fn read<P>(&mut self, pin: &mut P) -> u16
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        pin.set_as_analog();
+
+        self.read_channel(pin.channel())
+    }
+
+    /// Read an ADC internal channel with an injected conversion.
+    pub fn read_internal(&mut self, channel: &mut impl InternalChannel<T>) -> u16 {
+        self.read_channel(channel.channel())
+    }
+
+    /// Select `pin` for automatic injected conversion.
+    ///
+    /// With JAUTO set, the injected channel is converted after every regular conversion without a
+    /// separate trigger; fetch the latest result with [`latest`](Self::latest). Don't mix this
+    /// with [`read`](Self::read).
+    pub fn set_auto<P>(&mut self, pin: &mut P)
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        pin.set_as_analog();
+        self.setup_channel(pin.channel());
+
+        T::regs().cfgr().modify(|w| w.set_jauto(true));
+    }
+
+    /// Stop automatic injected conversions.
+    pub fn clear_auto(&mut self) {
+        T::regs().cfgr().modify(|w| w.set_jauto(false));
+    }
+
+    /// The most recent injected conversion result.
+    pub fn latest(&self) -> u16 {
+        T::regs().jdr(0).read().jdata()
+    }
+
+    fn setup_channel(&mut self, channel: u8) {
+        // The sample time and preselection registers are shared with the regular group.
+        critical_section::with(|_| {
+            Adc::<'d, T>::set_channel_sample_time(channel, self.sample_time);
+
+            #[cfg(stm32h7)]
+            T::regs()
+                .pcsel()
+                .modify(|w| w.set_pcsel(channel as _, Pcsel::PRESELECTED));
+        });
+
+        T::regs().jsqr().write(|w| {
+            w.set_jsq(0, channel);
+            // software trigger
+            w.set_jl(0);
+        });
+    }
+
+    fn read_channel(&mut self, channel: u8) -> u16 {
+        self.setup_channel(channel);
+
+        T::regs().isr().write(|reg| {
+            reg.set_jeos(true);
+            reg.set_jeoc(true);
+        });
+        T::regs().cr().modify(|reg| reg.set_jadstart(true));
+
+        while !T::regs().isr().read().jeos() {
+            // spin
+        }
+
+        T::regs().jdr(0).read().jdata()
+    }
+}
+
+impl<'d, T: Instance> RingBufferedAdc<'d, T> {
+    /// Start conversions from an external trigger instead of converting back-to-back.
+    ///
+    /// `None` selects software triggering. Takes effect on the next [`start`](Self::start).
+    pub fn set_trigger(&mut self, trigger: Option<Trigger>) {
+        self.trigger = trigger;
+    }
+
+    /// Start conversion.
+    ///
+    /// Without a trigger and in continuous (non-discontinuous) mode, the sequence is converted
+    /// back-to-back. Otherwise every trigger, or every call to [`trigger`](Self::trigger) with
+    /// software triggering in discontinuous mode, converts the next sub-group.
+    pub fn start(&mut self) {
+        self.ring_buf.clear();
+        self.next_half = Half::First;
+        self.ring_buf.start();
+
+        let continuous = self.trigger.is_none() && !self.discontinuous;
+
+        T::regs().cfgr().modify(|w| {
+            w.set_cont(continuous);
+            match self.trigger {
+                Some(trigger) => {
+                    w.set_extsel(trigger.source);
+                    w.set_exten(match trigger.edge {
+                        TriggerEdge::Rising => Exten::RISINGEDGE,
+                        TriggerEdge::Falling => Exten::FALLINGEDGE,
+                        TriggerEdge::Both => Exten::BOTHEDGES,
+                    });
+                }
+                None => w.set_exten(Exten::DISABLED),
+            }
+            #[cfg(stm32h7)]
+            w.set_dmngt(pac::adc::vals::Dmngt::DMA_CIRCULAR);
+            #[cfg(stm32g4)]
+            {
+                w.set_dmacfg(pac::adc::vals::Dmacfg::CIRCULAR);
+                w.set_dmaen(true);
+            }
+        });
+
+        T::regs().isr().modify(|reg| {
+            reg.set_eos(true);
+            reg.set_eoc(true);
+            reg.set_ovr(true);
+        });
+        T::regs().cr().modify(|reg| reg.set_adstart(true));
+    }
+
+    /// Convert the next sub-group of a discontinuous sequence, with software triggering.
+    pub fn trigger(&mut self) {
+        T::regs().cr().modify(|reg| reg.set_adstart(true));
+    }
+
+    /// Stop conversion.
+    pub fn stop(&mut self) {
+        if T::regs().cr().read().adstart() {
+            T::regs().cr().modify(|reg| reg.set_adstp(true));
+            while T::regs().cr().read().adstart() {}
+        }
+
+        T::regs().cfgr().modify(|w| {
+            w.set_cont(false);
+            w.set_exten(Exten::DISABLED);
+            #[cfg(stm32h7)]
+            w.set_dmngt(pac::adc::vals::Dmngt::REGULAR);
+            #[cfg(stm32g4)]
+            w.set_dmaen(false);
+        });
+
+        self.ring_buf.request_stop();
+    }
+
+    /// Guard `channel` of the running stream with the analog watchdog.
+    ///
+    /// Once a conversion falls outside `low..=high`, [`read`](Self::read) and
+    /// [`next_block`](Self::next_block) return [`Error::OutOfRange`]. With `auto_stop`, conversions
+    /// are stopped at that point as well, and the stream has to be restarted with
+    /// [`start`](Self::start).
+    pub fn set_watchdog<P>(&mut self, pin: &P, low: u16, high: u16, auto_stop: bool)
+    where
+        P: AdcPin<T>,
+    {
+        Adc::<'d, T>::set_watchdog(Some(pin.channel()), low, high);
+        self.watchdog_auto_stop = Some(auto_stop);
+    }
+
+    /// Disarm the analog watchdog.
+    pub fn clear_watchdog(&mut self) {
+        Adc::<'d, T>::set_watchdog(None, 0, 0);
+        self.watchdog_auto_stop = None;
+    }
+
+    fn check_watchdog(&mut self) -> Result<(), Error> {
+        let Some(auto_stop) = self.watchdog_auto_stop else {
+            return Ok(());
+        };
+
+        if !T::regs().isr().read().awd1() {
+            return Ok(());
+        }
+
+        let value = T::regs().dr().read().0 as u16;
+        T::regs().isr().write(|w| w.set_awd1(true));
+        if auto_stop {
+            self.stop();
+        }
+        Err(Error::OutOfRange(value))
+    }
+
+    /// Read exactly `buf.len()` samples, waiting for them to be converted.
+    ///
+    /// Returns the number of samples remaining in the ring buffer.
+    pub async fn read(&mut self, buf: &mut [u16]) -> Result<usize, Error> {
+        let remaining = self.ring_buf.read_exact(buf).await?;
+        self.check_watchdog()?;
+        Ok(remaining)
+    }
+
+    /// Wait for the next half of the DMA buffer to be filled and copy it into `block`.
+    ///
+    /// `block` must be exactly half the size of the DMA buffer. Halves are returned in order,
+    /// alternating between [`Half::First`] and [`Half::Second`]; while one is being processed
+    /// the DMA keeps filling the other one. If processing takes longer than the DMA needs to
+    /// fill a half, [`Error::Overrun`] is returned and the stream must be restarted with
+    /// [`start`](Self::start).
+    ///
+    /// Mixing this with [`read`](Self::read) breaks the alignment to the buffer halves.
+    pub async fn next_block(&mut self, block: &mut [u16]) -> Result<Half, Error> {
+        assert_eq!(block.len() * 2, self.ring_buf.capacity());
+
+        self.ring_buf.read_exact(block).await?;
+        self.check_watchdog()?;
+
+        let half = self.next_half;
+        self.next_half = match half {
+            Half::First => Half::Second,
+            Half::Second => Half::First,
+        };
+        Ok(half)
+    }
+}
+
+impl<'d, T: Instance> Drop for RingBufferedAdc<'d, T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -418,7 +418,7 @@ impl<'c, 'd, T: Instance> CanRx<'c, 'd, T> {
         poll_fn(|cx| {
             T::state().err_waker.register(cx.waker());
             if let Poll::Ready(envelope) = T::state().rx_queue.receive().poll_unpin(cx) {
-                return Poll::Ready(Ok(envelope));
+                return Poll::Ready(Ok(unwrap!(envelope)));
             } else if let Some(err) = self.curr_error() {
                 return Poll::Ready(Err(err));
             }
@@ -62,11 +62,11 @@ impl Registers {
         }
     }
 
-    pub fn put_tx_frame(&self, bufidx: usize, header: &Header, buffer: &[u8]) {
+    pub fn put_tx_frame(&self, bufidx: usize, header: &Header, buffer: &[u8], event: Event) {
         let mailbox = self.tx_buffer_element(bufidx);
 
         mailbox.reset();
-        put_tx_header(mailbox, header);
+        put_tx_header(mailbox, header, event);
         put_tx_data(mailbox, &buffer[..header.len() as usize]);
 
         // Set <idx as Mailbox> as ready to transmit
@@ -204,6 +204,16 @@ impl Registers {
     }
 
     pub fn write<F: embedded_can::Frame + CanHeader>(&self, frame: &F) -> nb::Result<Option<F>, Infallible> {
+        self.write_with_event(frame, Event::NoEvent)
+    }
+
+    /// Queue a frame, storing an event with the given marker in the TX event FIFO once it has
+    /// been sent, if `event` is [`Event::Event`].
+    pub fn write_with_event<F: embedded_can::Frame + CanHeader>(
+        &self,
+        frame: &F,
+        event: Event,
+    ) -> nb::Result<Option<F>, Infallible> {
         let (idx, pending_frame) = if self.tx_queue_is_full() {
             if self.tx_queue_mode() == TxBufferMode::Fifo {
                 // Does not make sense to cancel a pending frame when using FIFO
@@ -230,11 +240,31 @@ impl Registers {
             (idx, None)
         };
 
-        self.put_tx_frame(idx as usize, frame.header(), frame.data());
+        self.put_tx_frame(idx as usize, frame.header(), frame.data(), event);
 
         Ok(pending_frame)
     }
 
+    /// Pop the oldest element of the TX event FIFO, returning the frame ID, message marker and
+    /// transmit timestamp.
+    pub fn read_tx_event(&self) -> Option<(embedded_can::Id, u8, u16)> {
+        let status = self.regs.txefs().read();
+        if status.effl() < 1 {
+            return None;
+        }
+
+        let idx = status.efgi();
+        let element = self.msg_ram_mut().transmit.efsa[idx as usize].read();
+        let id = make_id(element.id().bits(), element.xtd().bits());
+        let marker = element.mm().bits();
+        let ts = element.txts().bits();
+
+        // Release the element.
+        self.regs.txefa().modify(|w| w.set_efai(idx));
+
+        Some((id, marker, ts))
+    }
+
     #[inline]
     fn reset_msg_ram(&mut self) {
         self.msg_ram_mut().reset();
@@ -371,6 +401,7 @@ impl Registers {
             w.set_rfne(0, true); // Rx Fifo 0 New Msg
             w.set_rfne(1, true); // Rx Fifo 1 New Msg
             w.set_tce(true); //  Tx Complete
+            w.set_tefne(true); // Tx Event FIFO New Entry
         });
         self.regs.ile().modify(|w| {
             w.set_eint0(true); // Interrupt Line 0
@@ -700,7 +731,7 @@ fn make_id(id: u32, extended: bool) -> embedded_can::Id {
     }
 }
 
-fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header) {
+fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header, event: Event) {
     let (id, id_type) = match header.id() {
         // A standard identifier has to be written to ID[28:18].
         embedded_can::Id::Standard(id) => ((id.as_raw() as u32) << 18, IdType::StandardId),
@@ -722,7 +753,7 @@ fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header) {
             .xtd()
             .set_id_type(id_type)
             .set_len(DataLength::new(header.len(), frame_format))
-            .set_event(Event::NoEvent)
+            .set_event(event)
             .fdf()
             .set_format(frame_format)
             .brs()
@@ -32,6 +32,36 @@ pub type Timestamp = embassy_time::Instant;
 #[cfg(not(feature = "time"))]
 pub type Timestamp = u16;
 
+/// Receive FIFO selection.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RxFifo {
+    /// Receive FIFO 0.
+    Fifo0,
+    /// Receive FIFO 1.
+    Fifo1,
+}
+
+impl RxFifo {
+    fn index(&self) -> usize {
+        match self {
+            RxFifo::Fifo0 => 0,
+            RxFifo::Fifo1 => 1,
+        }
+    }
+}
+
+/// Entry of the TX event FIFO, stored once a frame queued with a message marker has been sent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TxEvent {
+    /// ID of the frame that was sent.
+    pub id: embedded_can::Id,
+    /// Message marker given when queueing the frame.
+    pub marker: u8,
+    /// Time the frame was sent at.
+    pub timestamp: Timestamp,
+}
+
 /// Interrupt handler channel 0.
 pub struct IT0InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -50,6 +80,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::IT0Interrupt> for IT0Interrup
             }
             if ir.tefn() {
                 regs.ir().write(|w| w.set_tefn(true));
+                T::state().tx_event_waker.wake();
             }
 
             match &T::state().tx_mode {
@@ -86,10 +117,12 @@ impl<T: Instance> interrupt::typelevel::Handler<T::IT0Interrupt> for IT0Interrup
 
         if ir.rfn(0) {
             T::state().rx_mode.on_interrupt::<T>(0);
+            T::state().fifo_wakers[0].wake();
         }
 
         if ir.rfn(1) {
             T::state().rx_mode.on_interrupt::<T>(1);
+            T::state().fifo_wakers[1].wake();
         }
     }
 }
@@ -352,6 +385,38 @@ impl<'d, T: Instance> Fdcan<'d, T> {
         T::state().rx_mode.read_fd::<T>().await
     }
 
+    /// Returns the next received message frame from the given FIFO.
+    ///
+    /// Combined with filters storing into different FIFOs, this lets separate tasks handle
+    /// different kinds of traffic. Not available with buffered receive.
+    pub async fn read_fifo(&mut self, fifo: RxFifo) -> Result<(ClassicFrame, Timestamp), BusError> {
+        T::state().rx_mode.read_fifo::<T, _>(fifo.index()).await
+    }
+
+    /// Returns the next received FD message frame from the given FIFO.
+    ///
+    /// See [`read_fifo`](Self::read_fifo).
+    pub async fn read_fd_fifo(&mut self, fifo: RxFifo) -> Result<(FdFrame, Timestamp), BusError> {
+        T::state().rx_mode.read_fifo::<T, _>(fifo.index()).await
+    }
+
+    /// Queues the message to be sent, like [`write`](Self::write), and stores an entry with
+    /// `marker` in the TX event FIFO once it has been sent. See [`read_tx_event`](Self::read_tx_event).
+    pub async fn write_with_marker(&mut self, frame: &ClassicFrame, marker: u8) -> Option<ClassicFrame> {
+        T::state().tx_mode.write_with_marker::<T, _>(frame, marker).await
+    }
+
+    /// Queues the message to be sent, like [`write_fd`](Self::write_fd), and stores an entry with
+    /// `marker` in the TX event FIFO once it has been sent. See [`read_tx_event`](Self::read_tx_event).
+    pub async fn write_fd_with_marker(&mut self, frame: &FdFrame, marker: u8) -> Option<FdFrame> {
+        T::state().tx_mode.write_with_marker::<T, _>(frame, marker).await
+    }
+
+    /// Returns the next entry of the TX event FIFO, with the time the frame was actually sent.
+    pub async fn read_tx_event(&mut self) -> TxEvent {
+        sealed::read_tx_event::<T>().await
+    }
+
     /// Split instance into separate Tx(write) and Rx(read) portions
     pub fn split(self) -> (FdcanTx<'d, T>, FdcanRx<'d, T>) {
         (
@@ -430,7 +495,8 @@ impl BufferedCanSender {
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: ClassicFrame) {
-        self.tx_buf.send(frame).await;
+        // The driver never closes its tx channel
+        unwrap!(self.tx_buf.send(frame).await.ok());
         (self.waker)();
     }
 
@@ -481,13 +547,14 @@ impl<'c, 'd, T: Instance, const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize>
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: ClassicFrame) {
-        self.tx_buf.send(frame).await;
+        // The driver never closes its tx channel
+        unwrap!(self.tx_buf.send(frame).await.ok());
         T::IT0Interrupt::pend(); // Wake for Tx
     }
 
     /// Async read frame from RX buffer.
     pub async fn read(&mut self) -> Result<(ClassicFrame, Timestamp), BusError> {
-        self.rx_buf.receive().await
+        unwrap!(self.rx_buf.receive().await)
     }
 
     /// Returns a sender that can be used for sending CAN frames.
@@ -548,7 +615,8 @@ impl BufferedFdCanSender {
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: FdFrame) {
-        self.tx_buf.send(frame).await;
+        // The driver never closes its tx channel
+        unwrap!(self.tx_buf.send(frame).await.ok());
         (self.waker)();
     }
 
@@ -599,13 +667,14 @@ impl<'c, 'd, T: Instance, const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize>
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: FdFrame) {
-        self.tx_buf.send(frame).await;
+        // The driver never closes its tx channel
+        unwrap!(self.tx_buf.send(frame).await.ok());
         T::IT0Interrupt::pend(); // Wake for Tx
     }
 
     /// Async read frame from RX buffer.
     pub async fn read(&mut self) -> Result<(FdFrame, Timestamp), BusError> {
-        self.rx_buf.receive().await
+        unwrap!(self.rx_buf.receive().await)
     }
 
     /// Returns a sender that can be used for sending CAN frames.
@@ -663,6 +732,23 @@ impl<'c, 'd, T: Instance> FdcanTx<'d, T> {
     pub async fn write_fd(&mut self, frame: &FdFrame) -> Option<FdFrame> {
         T::state().tx_mode.write_fd::<T>(frame).await
     }
+
+    /// Queues the message to be sent, like [`write`](Self::write), and stores an entry with
+    /// `marker` in the TX event FIFO once it has been sent. See [`read_tx_event`](Self::read_tx_event).
+    pub async fn write_with_marker(&mut self, frame: &ClassicFrame, marker: u8) -> Option<ClassicFrame> {
+        T::state().tx_mode.write_with_marker::<T, _>(frame, marker).await
+    }
+
+    /// Queues the message to be sent, like [`write_fd`](Self::write_fd), and stores an entry with
+    /// `marker` in the TX event FIFO once it has been sent. See [`read_tx_event`](Self::read_tx_event).
+    pub async fn write_fd_with_marker(&mut self, frame: &FdFrame, marker: u8) -> Option<FdFrame> {
+        T::state().tx_mode.write_with_marker::<T, _>(frame, marker).await
+    }
+
+    /// Returns the next entry of the TX event FIFO, with the time the frame was actually sent.
+    pub async fn read_tx_event(&mut self) -> TxEvent {
+        sealed::read_tx_event::<T>().await
+    }
 }
 
 impl<'c, 'd, T: Instance> FdcanRx<'d, T> {
@@ -675,6 +761,21 @@ impl<'c, 'd, T: Instance> FdcanRx<'d, T> {
     pub async fn read_fd(&mut self) -> Result<(FdFrame, Timestamp), BusError> {
         T::state().rx_mode.read_fd::<T>().await
     }
+
+    /// Returns the next received message frame from the given FIFO.
+    ///
+    /// Combined with filters storing into different FIFOs, this lets separate tasks handle
+    /// different kinds of traffic. Not available with buffered receive.
+    pub async fn read_fifo(&mut self, fifo: RxFifo) -> Result<(ClassicFrame, Timestamp), BusError> {
+        T::state().rx_mode.read_fifo::<T, _>(fifo.index()).await
+    }
+
+    /// Returns the next received FD message frame from the given FIFO.
+    ///
+    /// See [`read_fifo`](Self::read_fifo).
+    pub async fn read_fd_fifo(&mut self, fifo: RxFifo) -> Result<(FdFrame, Timestamp), BusError> {
+        T::state().rx_mode.read_fifo::<T, _>(fifo.index()).await
+    }
 }
 
 pub(crate) mod sealed {
@@ -685,6 +786,7 @@ pub(crate) mod sealed {
     use embassy_sync::waitqueue::AtomicWaker;
 
     use super::CanHeader;
+    use crate::can::fd::message_ram::enums::Event;
     use crate::can::_version::{BusError, Timestamp};
     use crate::can::frame::{ClassicFrame, FdFrame};
 
@@ -771,6 +873,27 @@ pub(crate) mod sealed {
         pub async fn read_fd<T: Instance>(&self) -> Result<(FdFrame, Timestamp), BusError> {
             self.read_async::<T, _>().await
         }
+
+        pub async fn read_fifo<T: Instance, F: CanHeader>(&self, fifonr: usize) -> Result<(F, Timestamp), BusError> {
+            if !matches!(self, RxMode::NonBuffered(_)) {
+                panic!("Bad Mode")
+            }
+
+            poll_fn(|cx| {
+                T::state().err_waker.register(cx.waker());
+                T::state().fifo_wakers[fifonr].register(cx.waker());
+
+                if let Some((msg, ts)) = T::registers().read(fifonr) {
+                    let ts = T::calc_timestamp(T::state().ns_per_timer_tick, ts);
+                    Poll::Ready(Ok((msg, ts)))
+                } else if let Some(err) = T::registers().curr_error() {
+                    Poll::Ready(Err(err))
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await
+        }
     }
 
     pub enum TxMode {
@@ -825,6 +948,40 @@ pub(crate) mod sealed {
         pub async fn write_fd<T: Instance>(&self, frame: &FdFrame) -> Option<FdFrame> {
             self.write_generic::<T, _>(frame).await
         }
+
+        /// Like `write_generic`, also storing a TX event with `marker` once the frame is sent.
+        pub async fn write_with_marker<T: Instance, F: embedded_can::Frame + CanHeader>(
+            &self,
+            frame: &F,
+            marker: u8,
+        ) -> Option<F> {
+            poll_fn(|cx| {
+                self.register(cx.waker());
+
+                if let Ok(dropped) = T::registers().write_with_event(frame, Event::Event(marker)) {
+                    return Poll::Ready(dropped);
+                }
+
+                Poll::Pending
+            })
+            .await
+        }
+    }
+
+    pub async fn read_tx_event<T: Instance>() -> super::TxEvent {
+        poll_fn(|cx| {
+            T::state().tx_event_waker.register(cx.waker());
+
+            match T::registers().read_tx_event() {
+                Some((id, marker, ts)) => Poll::Ready(super::TxEvent {
+                    id,
+                    marker,
+                    timestamp: T::calc_timestamp(T::state().ns_per_timer_tick, ts),
+                }),
+                None => Poll::Pending,
+            }
+        })
+        .await
     }
 
     pub struct State {
@@ -833,6 +990,8 @@ pub(crate) mod sealed {
         pub ns_per_timer_tick: u64,
 
         pub err_waker: AtomicWaker,
+        pub fifo_wakers: [AtomicWaker; 2],
+        pub tx_event_waker: AtomicWaker,
     }
 
     impl State {
@@ -842,6 +1001,8 @@ pub(crate) mod sealed {
                 tx_mode: TxMode::NonBuffered(AtomicWaker::new()),
                 ns_per_timer_tick: 0,
                 err_waker: AtomicWaker::new(),
+                fifo_wakers: [AtomicWaker::new(), AtomicWaker::new()],
+                tx_event_waker: AtomicWaker::new(),
             }
         }
     }
@@ -0,0 +1,100 @@
+//! [RustCrypto](https://github.com/RustCrypto/traits) `aead` trait implementations.
+use ::aead::consts::{U0, U12, U16};
+use ::aead::{AeadCore, AeadInPlace, Error, Nonce, Tag};
+
+use super::{AesGcm, CipherSized, Cryp, Direction, Instance, AES_BLOCK_SIZE};
+
+/// AES-GCM with a 96-bit nonce and 128-bit tag, usable through [`AeadInPlace`].
+///
+/// Created with [`Cryp::aes_gcm`]. Supports 128, 192 and 256-bit keys. Only the blocking driver
+/// functions are used, so the CRYP driver doesn't need DMA channels.
+pub struct AesGcmAead<'a, 'd, 'k, T: Instance, DmaIn, DmaOut, const KEY_SIZE: usize> {
+    cryp: &'a Cryp<'d, T, DmaIn, DmaOut>,
+    key: &'k [u8; KEY_SIZE],
+}
+
+impl<'d, T: Instance, DmaIn, DmaOut> Cryp<'d, T, DmaIn, DmaOut> {
+    /// Use the peripheral as an AES-GCM [`AeadInPlace`] cipher with the given key.
+    pub fn aes_gcm<'a, 'k, const KEY_SIZE: usize>(
+        &'a self,
+        key: &'k [u8; KEY_SIZE],
+    ) -> AesGcmAead<'a, 'd, 'k, T, DmaIn, DmaOut, KEY_SIZE>
+    where
+        for<'c> AesGcm<'c, KEY_SIZE>: CipherSized,
+    {
+        AesGcmAead { cryp: self, key }
+    }
+}
+
+impl<'a, 'd, 'k, T: Instance, DmaIn, DmaOut, const KEY_SIZE: usize> AesGcmAead<'a, 'd, 'k, T, DmaIn, DmaOut, KEY_SIZE>
+where
+    for<'c> AesGcm<'c, KEY_SIZE>: CipherSized,
+{
+    fn process(&self, nonce: &Nonce<Self>, associated_data: &[u8], buffer: &mut [u8], dir: Direction) -> [u8; 16] {
+        let iv: &[u8; 12] = nonce.as_ref();
+        let cipher = AesGcm::new(self.key, iv);
+        let mut ctx = self.cryp.start(&cipher, dir);
+
+        if !associated_data.is_empty() {
+            self.cryp.aad_blocking(&mut ctx, associated_data, true);
+        }
+
+        // The driver can't process in place, so feed the buffer through block by block.
+        let len = buffer.len();
+        for (i, chunk) in buffer.chunks_mut(AES_BLOCK_SIZE).enumerate() {
+            let mut input = [0; AES_BLOCK_SIZE];
+            input[..chunk.len()].copy_from_slice(chunk);
+            let last_block = (i + 1) * AES_BLOCK_SIZE >= len;
+            self.cryp
+                .payload_blocking(&mut ctx, &input[..chunk.len()], chunk, last_block);
+        }
+
+        self.cryp.finish_blocking(ctx)
+    }
+}
+
+impl<'a, 'd, 'k, T: Instance, DmaIn, DmaOut, const KEY_SIZE: usize> AeadCore
+    for AesGcmAead<'a, 'd, 'k, T, DmaIn, DmaOut, KEY_SIZE>
+{
+    type NonceSize = U12;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+impl<'a, 'd, 'k, T: Instance, DmaIn, DmaOut, const KEY_SIZE: usize> AeadInPlace
+    for AesGcmAead<'a, 'd, 'k, T, DmaIn, DmaOut, KEY_SIZE>
+where
+    for<'c> AesGcm<'c, KEY_SIZE>: CipherSized,
+{
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag<Self>, Error> {
+        let tag = self.process(nonce, associated_data, buffer, Direction::Encrypt);
+        Ok(tag.into())
+    }
+
+    /// Decrypt the buffer in place and check the tag.
+    ///
+    /// The hardware decrypts before the tag can be checked, so on a mismatch the buffer is
+    /// zeroed rather than left holding unauthenticated plaintext.
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag<Self>,
+    ) -> Result<(), Error> {
+        let computed = self.process(nonce, associated_data, buffer, Direction::Decrypt);
+
+        let diff = computed.iter().zip(tag.iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            buffer.fill(0);
+            return Err(Error);
+        }
+
+        Ok(())
+    }
+}
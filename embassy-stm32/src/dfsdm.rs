@@ -0,0 +1,544 @@
+//! Digital Filter for Sigma-Delta Modulators (DFSDM)
+#![macro_use]
+
+use core::marker::PhantomData;
+
+use embassy_hal_internal::{into_ref, PeripheralRef};
+
+use crate::dma::ringbuffer::OverrunError;
+use crate::dma::{ReadableRingBuffer, Transfer, TransferOptions};
+use crate::gpio::sealed::{AFType, Pin as _};
+use crate::gpio::{AnyPin, Speed};
+use crate::rcc::RccPeripheral;
+use crate::{peripherals, Peripheral};
+
+/// Serial interface type of a channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SerialInterface {
+    /// SPI, data sampled on the rising clock edge.
+    SpiRisingEdge,
+    /// SPI, data sampled on the falling clock edge.
+    SpiFallingEdge,
+    /// Manchester coded, rising edge = logic 0.
+    ManchesterRisingIsZero,
+    /// Manchester coded, rising edge = logic 1.
+    ManchesterRisingIsOne,
+}
+
+impl SerialInterface {
+    fn sitp(&self) -> u8 {
+        match self {
+            SerialInterface::SpiRisingEdge => 0b00,
+            SerialInterface::SpiFallingEdge => 0b01,
+            SerialInterface::ManchesterRisingIsZero => 0b10,
+            SerialInterface::ManchesterRisingIsOne => 0b11,
+        }
+    }
+}
+
+/// Clock source of the serial interface of a channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SpiClock {
+    /// Clock comes from the channel's CKIN pin.
+    External,
+    /// Clock is the internal CKOUT signal.
+    Internal,
+    /// Clock is CKOUT / 2, data sampled on the falling edge of CKOUT.
+    InternalDiv2Falling,
+    /// Clock is CKOUT / 2, data sampled on the rising edge of CKOUT.
+    InternalDiv2Rising,
+}
+
+impl SpiClock {
+    fn spicksel(&self) -> u8 {
+        match self {
+            SpiClock::External => 0b00,
+            SpiClock::Internal => 0b01,
+            SpiClock::InternalDiv2Falling => 0b10,
+            SpiClock::InternalDiv2Rising => 0b11,
+        }
+    }
+}
+
+/// Sinc filter order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum FilterOrder {
+    FastSinc,
+    Sinc1,
+    Sinc2,
+    Sinc3,
+    Sinc4,
+    Sinc5,
+}
+
+impl FilterOrder {
+    fn ford(&self) -> u8 {
+        match self {
+            FilterOrder::FastSinc => 0,
+            FilterOrder::Sinc1 => 1,
+            FilterOrder::Sinc2 => 2,
+            FilterOrder::Sinc3 => 3,
+            FilterOrder::Sinc4 => 4,
+            FilterOrder::Sinc5 => 5,
+        }
+    }
+}
+
+/// Filter configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct Config {
+    /// Serial interface type of the input channel.
+    pub interface: SerialInterface,
+    /// Clock source of the input channel.
+    pub clock: SpiClock,
+    /// Sinc filter order.
+    pub order: FilterOrder,
+    /// Sinc filter oversampling ratio, 1..=1024.
+    pub oversampling: u16,
+    /// Integrator oversampling ratio, 1..=256.
+    pub integrator_oversampling: u16,
+    /// Offset subtracted from every sample, 24 bits signed.
+    pub offset: i32,
+    /// Right bit shift applied to the output, 0..=31.
+    pub right_shift: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            interface: SerialInterface::SpiRisingEdge,
+            clock: SpiClock::Internal,
+            order: FilterOrder::Sinc3,
+            oversampling: 64,
+            integrator_oversampling: 1,
+            offset: 0,
+            right_shift: 0,
+        }
+    }
+}
+
+/// DFSDM filter instance.
+pub struct FilterBlock<'d, T: Instance, F: FilterInstance> {
+    peri: PeripheralRef<'d, T>,
+    _phantom: PhantomData<F>,
+}
+
+/// All filters of a DFSDM peripheral, see [`split_filters`].
+#[allow(missing_docs)]
+pub struct Filters<'d, T: Instance> {
+    pub flt0: FilterBlock<'d, T, Flt0>,
+    pub flt1: FilterBlock<'d, T, Flt1>,
+    pub flt2: FilterBlock<'d, T, Flt2>,
+    pub flt3: FilterBlock<'d, T, Flt3>,
+}
+
+/// Split the DFSDM peripheral into its filters, driving the shared clock output on `ckout`.
+///
+/// The output clock is the DFSDM kernel clock divided by `clock_divider` (2..=256), and is used by
+/// channels configured with one of the internal [`SpiClock`] sources.
+pub fn split_filters<'d, T: Instance>(
+    peri: impl Peripheral<P = T> + 'd,
+    ckout: impl Peripheral<P = impl CkOutPin<T>> + 'd,
+    clock_divider: u16,
+) -> Filters<'d, T> {
+    into_ref!(ckout);
+    assert!((2..=256).contains(&clock_divider));
+
+    ckout.set_as_af(ckout.af_num(), AFType::OutputPushPull);
+    ckout.set_speed(Speed::VeryHigh);
+
+    split_filters_inner(peri, (clock_divider - 1) as u8)
+}
+
+/// Split the DFSDM peripheral into its filters, without a clock output.
+///
+/// Use this when all modulators are clocked externally through the channel CKIN pins.
+pub fn split_filters_without_clock_output<'d, T: Instance>(peri: impl Peripheral<P = T> + 'd) -> Filters<'d, T> {
+    split_filters_inner(peri, 0)
+}
+
+fn split_filters_inner<'d, T: Instance>(peri: impl Peripheral<P = T> + 'd, ckoutdiv: u8) -> Filters<'d, T> {
+    into_ref!(peri);
+    T::enable_and_reset();
+
+    let ch0 = T::regs().ch(0);
+    ch0.cfgr1().modify(|w| w.set_dfsdmen(false));
+    ch0.cfgr1().modify(|w| {
+        w.set_ckoutsrc(false);
+        w.set_ckoutdiv(ckoutdiv);
+    });
+    ch0.cfgr1().modify(|w| w.set_dfsdmen(true));
+
+    unsafe {
+        Filters {
+            flt0: FilterBlock {
+                peri: peri.clone_unchecked(),
+                _phantom: PhantomData,
+            },
+            flt1: FilterBlock {
+                peri: peri.clone_unchecked(),
+                _phantom: PhantomData,
+            },
+            flt2: FilterBlock {
+                peri: peri.clone_unchecked(),
+                _phantom: PhantomData,
+            },
+            flt3: FilterBlock {
+                peri,
+                _phantom: PhantomData,
+            },
+        }
+    }
+}
+
+/// DFSDM filter driver, converting a single input channel.
+pub struct Filter<'d, T: Instance, F: FilterInstance> {
+    _peri: PeripheralRef<'d, T>,
+    channel: u8,
+    datin: PeripheralRef<'d, AnyPin>,
+    ckin: Option<PeripheralRef<'d, AnyPin>>,
+    _phantom: PhantomData<F>,
+}
+
+impl<'d, T: Instance, F: FilterInstance> Filter<'d, T, F> {
+    /// Create a new filter driver reading the channel attached to `datin`.
+    pub fn new(filter: FilterBlock<'d, T, F>, datin: impl Peripheral<P = impl DatinPin<T>> + 'd, config: Config) -> Self {
+        into_ref!(datin);
+        assert!(config.clock != SpiClock::External);
+
+        datin.set_as_af(datin.af_num(), AFType::Input);
+        datin.set_speed(Speed::VeryHigh);
+
+        Self::new_inner(filter, datin.channel(), datin.map_into(), None, config)
+    }
+
+    /// Create a new filter driver reading the channel attached to `datin`, clocked by the
+    /// modulator through `ckin`.
+    ///
+    /// Both pins must belong to the same channel.
+    pub fn new_with_clock_input(
+        filter: FilterBlock<'d, T, F>,
+        datin: impl Peripheral<P = impl DatinPin<T>> + 'd,
+        ckin: impl Peripheral<P = impl CkinPin<T>> + 'd,
+        mut config: Config,
+    ) -> Self {
+        into_ref!(datin, ckin);
+        assert_eq!(datin.channel(), ckin.channel());
+        config.clock = SpiClock::External;
+
+        datin.set_as_af(datin.af_num(), AFType::Input);
+        datin.set_speed(Speed::VeryHigh);
+        ckin.set_as_af(ckin.af_num(), AFType::Input);
+        ckin.set_speed(Speed::VeryHigh);
+
+        Self::new_inner(filter, datin.channel(), datin.map_into(), Some(ckin.map_into()), config)
+    }
+
+    fn new_inner(
+        filter: FilterBlock<'d, T, F>,
+        channel: u8,
+        datin: PeripheralRef<'d, AnyPin>,
+        ckin: Option<PeripheralRef<'d, AnyPin>>,
+        config: Config,
+    ) -> Self {
+        assert!((1..=1024).contains(&config.oversampling));
+        assert!((1..=256).contains(&config.integrator_oversampling));
+        assert!(config.right_shift < 32);
+
+        let ch = T::regs().ch(channel as usize);
+        ch.cfgr1().modify(|w| w.set_chen(false));
+        ch.cfgr1().modify(|w| {
+            w.set_sitp(config.interface.sitp());
+            w.set_spicksel(config.clock.spicksel());
+            // Take the input from the channel's own pins, as a serial stream.
+            w.set_chinsel(false);
+            w.set_datmpx(0);
+            w.set_datpack(0);
+        });
+        ch.cfgr2().modify(|w| {
+            w.set_offset(config.offset as u32 & 0x00FF_FFFF);
+            w.set_dtrbs(config.right_shift);
+        });
+        ch.cfgr1().modify(|w| w.set_chen(true));
+
+        let flt = Self::regs();
+        flt.cr1().modify(|w| w.set_dfen(false));
+        flt.fcr().write(|w| {
+            w.set_ford(config.order.ford());
+            w.set_fosr(config.oversampling - 1);
+            w.set_iosr((config.integrator_oversampling - 1) as u8);
+        });
+        flt.cr1().modify(|w| {
+            w.set_rch(channel);
+            w.set_fast(true);
+            w.set_rsync(false);
+            w.set_rcont(false);
+            w.set_rdmaen(false);
+        });
+        flt.cr1().modify(|w| w.set_dfen(true));
+
+        Self {
+            _peri: filter.peri,
+            channel,
+            datin,
+            ckin,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn regs() -> crate::pac::dfsdm::Flt {
+        T::regs().flt(F::INDEX)
+    }
+
+    /// Perform a single blocking conversion.
+    pub fn blocking_read(&mut self) -> i32 {
+        let flt = Self::regs();
+        flt.cr1().modify(|w| {
+            w.set_rcont(false);
+            w.set_rdmaen(false);
+        });
+        flt.cr1().modify(|w| w.set_rswstart(true));
+
+        while !flt.isr().read().reocf() {}
+
+        sample_value(flt.rdatar().read().0)
+    }
+
+    /// Fill `buf` with consecutive conversions, using DMA.
+    pub async fn read(&mut self, dma: impl Peripheral<P = impl FilterDma<T, F>> + 'd, buf: &mut [i32]) {
+        into_ref!(dma);
+        let request = dma.request();
+
+        let flt = Self::regs();
+        flt.cr1().modify(|w| {
+            w.set_rcont(true);
+            w.set_rdmaen(true);
+        });
+
+        // Safety: i32 and u32 have the same layout, values are sign-extended below.
+        let raw = unsafe { core::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u32, buf.len()) };
+        let transfer = unsafe {
+            Transfer::new_read(
+                dma,
+                request,
+                flt.rdatar().as_ptr() as *mut u32,
+                raw,
+                TransferOptions::default(),
+            )
+        };
+
+        flt.cr1().modify(|w| w.set_rswstart(true));
+        transfer.await;
+
+        flt.cr1().modify(|w| {
+            w.set_rcont(false);
+            w.set_rdmaen(false);
+        });
+
+        for v in buf.iter_mut() {
+            *v = sample_value(*v as u32);
+        }
+    }
+
+    /// Turn the filter into a continuously converting stream, backed by a DMA ring buffer.
+    ///
+    /// `dma_buf` should be large enough to absorb the latency of the consumer.
+    pub fn into_ring_buffered(
+        self,
+        dma: impl Peripheral<P = impl FilterDma<T, F>> + 'd,
+        dma_buf: &'d mut [u32],
+    ) -> RingBufferedFilter<'d, T, F> {
+        into_ref!(dma);
+        let request = dma.request();
+        let opts = TransferOptions {
+            half_transfer_ir: true,
+            ..Default::default()
+        };
+
+        let ring_buf = unsafe {
+            ReadableRingBuffer::new(dma, request, Self::regs().rdatar().as_ptr() as *mut u32, dma_buf, opts)
+        };
+
+        RingBufferedFilter {
+            filter: self,
+            ring_buf,
+        }
+    }
+}
+
+impl<'d, T: Instance, F: FilterInstance> Drop for Filter<'d, T, F> {
+    fn drop(&mut self) {
+        Self::regs().cr1().modify(|w| w.set_dfen(false));
+        T::regs().ch(self.channel as usize).cfgr1().modify(|w| w.set_chen(false));
+        self.datin.set_as_disconnected();
+        self.ckin.as_ref().map(|x| x.set_as_disconnected());
+    }
+}
+
+/// DFSDM filter streaming conversions into a DMA ring buffer.
+pub struct RingBufferedFilter<'d, T: Instance, F: FilterInstance> {
+    filter: Filter<'d, T, F>,
+    ring_buf: ReadableRingBuffer<'d, u32>,
+}
+
+impl<'d, T: Instance, F: FilterInstance> RingBufferedFilter<'d, T, F> {
+    /// Start continuous conversion.
+    pub fn start(&mut self) {
+        self.ring_buf.clear();
+        self.ring_buf.start();
+
+        let flt = Filter::<'d, T, F>::regs();
+        flt.cr1().modify(|w| {
+            w.set_rcont(true);
+            w.set_rdmaen(true);
+        });
+        flt.cr1().modify(|w| w.set_rswstart(true));
+    }
+
+    /// Stop continuous conversion.
+    pub fn stop(&mut self) {
+        Filter::<'d, T, F>::regs().cr1().modify(|w| {
+            w.set_rcont(false);
+            w.set_rdmaen(false);
+        });
+        self.ring_buf.request_stop();
+    }
+
+    /// Read exactly `buf.len()` samples, waiting for them to be converted.
+    ///
+    /// Returns the number of samples remaining in the ring buffer.
+    pub async fn read(&mut self, buf: &mut [i32]) -> Result<usize, OverrunError> {
+        // Safety: i32 and u32 have the same layout, values are sign-extended below.
+        let raw = unsafe { core::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u32, buf.len()) };
+        let remaining = self.ring_buf.read_exact(raw).await?;
+
+        for v in buf.iter_mut() {
+            *v = sample_value(*v as u32);
+        }
+
+        Ok(remaining)
+    }
+
+    /// The input channel converted by this filter.
+    pub fn channel(&self) -> u8 {
+        self.filter.channel
+    }
+}
+
+impl<'d, T: Instance, F: FilterInstance> Drop for RingBufferedFilter<'d, T, F> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Convert a raw RDATAR value into a signed sample.
+///
+/// The 24 bit result lives in the upper bits, the lower bits hold the channel number.
+fn sample_value(raw: u32) -> i32 {
+    (raw as i32) >> 8
+}
+
+pub(crate) mod sealed {
+    pub trait Instance {
+        fn regs() -> crate::pac::dfsdm::Dfsdm;
+    }
+
+    pub trait FilterInstance {
+        const INDEX: usize;
+    }
+
+    pub trait DatinPin<T: Instance> {
+        fn channel(&self) -> u8;
+    }
+
+    pub trait CkinPin<T: Instance> {
+        fn channel(&self) -> u8;
+    }
+}
+
+/// DFSDM instance trait.
+pub trait Instance: Peripheral<P = Self> + sealed::Instance + RccPeripheral + 'static {}
+
+/// DFSDM filter instance trait.
+pub trait FilterInstance: sealed::FilterInstance {}
+
+macro_rules! impl_filter {
+    ($name:ident, $index:expr) => {
+        #[doc = concat!("Filter ", stringify!($index), ".")]
+        pub enum $name {}
+        impl sealed::FilterInstance for $name {
+            const INDEX: usize = $index;
+        }
+        impl FilterInstance for $name {}
+    };
+}
+
+impl_filter!(Flt0, 0);
+impl_filter!(Flt1, 1);
+impl_filter!(Flt2, 2);
+impl_filter!(Flt3, 3);
+
+/// Channel data input pin.
+pub trait DatinPin<T: Instance>: sealed::DatinPin<T> + crate::gpio::Pin {
+    /// Get the AF number needed to use this pin as a data input.
+    fn af_num(&self) -> u8;
+}
+
+/// Channel clock input pin.
+pub trait CkinPin<T: Instance>: sealed::CkinPin<T> + crate::gpio::Pin {
+    /// Get the AF number needed to use this pin as a clock input.
+    fn af_num(&self) -> u8;
+}
+
+pin_trait!(CkOutPin, Instance);
+
+dma_trait!(FilterDma, Instance, FilterInstance);
+
+foreach_peripheral!(
+    (dfsdm, $inst:ident) => {
+        impl sealed::Instance for peripherals::$inst {
+            fn regs() -> crate::pac::dfsdm::Dfsdm {
+                crate::pac::$inst
+            }
+        }
+
+        impl Instance for peripherals::$inst {}
+    };
+);
+
+#[allow(unused_macros)]
+macro_rules! impl_dfsdm_datin_pin {
+    ($inst:ident, $pin:ident, $ch:expr, $af:expr) => {
+        impl crate::dfsdm::DatinPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn af_num(&self) -> u8 {
+                $af
+            }
+        }
+        impl crate::dfsdm::sealed::DatinPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! impl_dfsdm_ckin_pin {
+    ($inst:ident, $pin:ident, $ch:expr, $af:expr) => {
+        impl crate::dfsdm::CkinPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn af_num(&self) -> u8 {
+                $af
+            }
+        }
+        impl crate::dfsdm::sealed::CkinPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
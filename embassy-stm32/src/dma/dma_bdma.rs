@@ -55,6 +55,9 @@ pub struct TransferOptions {
     pub half_transfer_ir: bool,
     /// Enable transfer complete interrupt
     pub complete_transfer_ir: bool,
+    /// Synchronize the DMA requests to a DMAMUX synchronization input.
+    #[cfg(dmamux)]
+    pub sync: Option<super::SyncConfig>,
 }
 
 impl Default for TransferOptions {
@@ -71,6 +74,8 @@ impl Default for TransferOptions {
             circular: false,
             half_transfer_ir: false,
             complete_transfer_ir: true,
+            #[cfg(dmamux)]
+            sync: None,
         }
     }
 }
@@ -303,10 +308,19 @@ impl AnyChannel {
         let info = self.info();
 
         #[cfg(dmamux)]
-        super::dmamux::configure_dmamux(&info.dmamux, _request);
+        super::dmamux::configure_dmamux(&info.dmamux, _request, options.sync);
 
         assert!(mem_len > 0 && mem_len <= 0xFFFF);
 
+        // On H7 the BDMA sits in the D3 domain, and can only access SRAM4 and the backup SRAM.
+        #[cfg(all(bdma, stm32h7))]
+        if let DmaInfo::Bdma(_) = info.dma {
+            assert!(
+                (0x3800_0000..0x3900_0000).contains(&(mem_addr as u32)),
+                "BDMA buffers must be placed in SRAM4 or the backup SRAM"
+            );
+        }
+
         match self.info().dma {
             #[cfg(dma)]
             DmaInfo::Dma(r) => {
@@ -654,6 +668,251 @@ impl<'a> Future for Transfer<'a> {
     }
 }
 
+/// Double-buffered DMA transfer.
+///
+/// The DMA alternates between two buffers, so one can be copied out (or refilled) while the other
+/// is being filled (or drained). Only available on DMA streams, BDMA channels don't support it.
+///
+/// Like the ring buffers, the data is copied out of and into the DMA buffers, so no reference to a
+/// buffer the DMA may be accessing is ever handed out.
+#[cfg(dma)]
+pub struct DoubleBuffered<'a, W: Word> {
+    channel: PeripheralRef<'a, AnyChannel>,
+    dir: Dir,
+    buffers: [*mut W; 2],
+    len: usize,
+}
+
+#[cfg(dma)]
+impl<'a, W: Word> DoubleBuffered<'a, W> {
+    /// Create a new double-buffered read transfer (peripheral to memory), and start it.
+    ///
+    /// # Safety
+    ///
+    /// - `peri_addr` must be the data register of the peripheral that issues `request`, and be
+    ///   readable with words of type `W`.
+    /// - The transfer must not be leaked (e.g. with `mem::forget`). Dropping it stops the DMA, but a
+    ///   leaked transfer keeps writing to the buffers after their borrow ended.
+    pub unsafe fn new_read(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        request: Request,
+        peri_addr: *mut W,
+        buf0: &'a mut [W],
+        buf1: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(
+            channel,
+            request,
+            Dir::PeripheralToMemory,
+            peri_addr,
+            [buf0.as_mut_ptr(), buf1.as_mut_ptr()],
+            buf0.len(),
+            buf1.len(),
+            options,
+        )
+    }
+
+    /// Create a new double-buffered write transfer (memory to peripheral), and start it.
+    ///
+    /// Both buffers should be filled before calling this.
+    ///
+    /// # Safety
+    ///
+    /// - `peri_addr` must be the data register of the peripheral that issues `request`, and be
+    ///   writable with words of type `W`.
+    /// - The transfer must not be leaked (e.g. with `mem::forget`). Dropping it stops the DMA, but a
+    ///   leaked transfer keeps reading from the buffers after their borrow ended.
+    pub unsafe fn new_write(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        request: Request,
+        buf0: &'a mut [W],
+        buf1: &'a mut [W],
+        peri_addr: *mut W,
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(
+            channel,
+            request,
+            Dir::MemoryToPeripheral,
+            peri_addr,
+            [buf0.as_mut_ptr(), buf1.as_mut_ptr()],
+            buf0.len(),
+            buf1.len(),
+            options,
+        )
+    }
+
+    unsafe fn new_inner(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        request: Request,
+        dir: Dir,
+        peri_addr: *mut W,
+        buffers: [*mut W; 2],
+        len0: usize,
+        len1: usize,
+        mut options: TransferOptions,
+    ) -> Self {
+        into_ref!(channel);
+        let channel: PeripheralRef<'a, AnyChannel> = channel.map_into();
+
+        assert_eq!(len0, len1, "Both buffers must have the same length");
+        assert!(len0 > 0 && len0 <= 0xFFFF);
+
+        // The stream switches buffers on each transfer complete.
+        options.circular = true;
+        options.half_transfer_ir = false;
+        options.complete_transfer_ir = true;
+
+        let ch = match channel.info().dma {
+            DmaInfo::Dma(r) => r.st(channel.info().num),
+            #[cfg(bdma)]
+            DmaInfo::Bdma(_) => panic!("Double-buffered transfers need a DMA stream"),
+        };
+
+        channel.configure(
+            request,
+            dir,
+            peri_addr as *const u32,
+            buffers[0] as *mut u32,
+            len0,
+            true,
+            W::size(),
+            options,
+        );
+        STATE[channel.id as usize].complete_count.store(0, Ordering::Release);
+        ch.m1ar().write_value(buffers[1] as u32);
+        ch.cr().modify(|w| w.set_dbm(true));
+
+        channel.start();
+
+        Self {
+            channel,
+            dir,
+            buffers,
+            len: len0,
+        }
+    }
+
+    /// Wait for the DMA to complete a buffer, and copy it into `buf`.
+    ///
+    /// Returns the amount of elements copied, the buffer length or the length of `buf` if that is
+    /// shorter, in which case the rest of the buffer is dropped. Only for read transfers.
+    ///
+    /// Returns `OverrunError` if more than one buffer completed since the last call, or if the DMA
+    /// completed the next buffer while this one was being copied. In both cases data was lost. The
+    /// transfer keeps running.
+    pub async fn read(&mut self, buf: &mut [W]) -> Result<usize, OverrunError> {
+        assert_eq!(self.dir, Dir::PeripheralToMemory, "Not a read transfer");
+
+        let done = self.next_done().await?;
+        let len = buf.len().min(self.len);
+        // NOTE(unsafe) the DMA doesn't write this buffer until the other one completes, which is checked below.
+        unsafe { core::ptr::copy_nonoverlapping(done, buf.as_mut_ptr(), len) };
+        self.check_not_overrun()?;
+        Ok(len)
+    }
+
+    /// Wait for the DMA to complete a buffer, and refill it from `data`.
+    ///
+    /// Returns the amount of elements copied, the buffer length or the length of `data` if that is
+    /// shorter, in which case the rest of the buffer is sent again. Only for write transfers.
+    ///
+    /// Returns `OverrunError` if more than one buffer completed since the last call, or if the DMA
+    /// completed the next buffer while this one was being refilled. In both cases old or partly
+    /// written data was sent. The transfer keeps running.
+    pub async fn write(&mut self, data: &[W]) -> Result<usize, OverrunError> {
+        assert_eq!(self.dir, Dir::MemoryToPeripheral, "Not a write transfer");
+
+        let done = self.next_done().await?;
+        let len = data.len().min(self.len);
+        // NOTE(unsafe) the DMA doesn't read this buffer until the other one completes, which is checked below.
+        unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), done, len) };
+        self.check_not_overrun()?;
+        Ok(len)
+    }
+
+    /// Wait for the DMA to complete a buffer, and return a pointer to it.
+    async fn next_done(&mut self) -> Result<*mut W, OverrunError> {
+        let state = &STATE[self.channel.id as usize];
+
+        let completed = core::future::poll_fn(|cx| {
+            state.waker.register(cx.waker());
+
+            let completed = reset_complete_count(state);
+            if completed > 0 {
+                Poll::Ready(completed)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+
+        if completed > 1 {
+            return Err(OverrunError);
+        }
+
+        // The DMA is now working on the current target, the other buffer is done.
+        let current = match self.channel.info().dma {
+            DmaInfo::Dma(r) => r.st(self.channel.info().num).cr().read().ct() as usize,
+            #[cfg(bdma)]
+            DmaInfo::Bdma(_) => unreachable!(),
+        };
+        Ok(self.buffers[1 - current])
+    }
+
+    /// Check that the DMA didn't move on to the buffer that was just copied, while it was being copied.
+    fn check_not_overrun(&self) -> Result<(), OverrunError> {
+        // "Preceding reads and writes cannot be moved past subsequent writes."
+        fence(Ordering::SeqCst);
+
+        // The count is left alone, so the next call still sees the completed buffer.
+        if STATE[self.channel.id as usize].complete_count.load(Ordering::Acquire) > 0 {
+            Err(OverrunError)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Request the transfer to stop.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_stop(&mut self) {
+        self.channel.request_stop()
+    }
+
+    /// Return whether this transfer is still running.
+    pub fn is_running(&mut self) -> bool {
+        self.channel.is_running()
+    }
+}
+
+#[cfg(dma)]
+impl<'a, W: Word> Drop for DoubleBuffered<'a, W> {
+    fn drop(&mut self) {
+        self.request_stop();
+        while self.is_running() {}
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(dma)]
+fn reset_complete_count(state: &ChannelState) -> usize {
+    #[cfg(not(armv6m))]
+    return state.complete_count.swap(0, Ordering::AcqRel);
+    #[cfg(armv6m)]
+    return critical_section::with(|_| {
+        let x = state.complete_count.load(Ordering::Acquire);
+        state.complete_count.store(0, Ordering::Release);
+        x
+    });
+}
+
 // ==============================
 
 struct DmaCtrlImpl<'a>(PeripheralRef<'a, AnyChannel>);
@@ -1,34 +1,166 @@
 #![macro_use]
 
+use super::Request;
 use crate::pac;
 
+// CCR and RGCR fields not covered by the named accessors.
+const CCR_SE: u32 = 1 << 16;
+const CCR_SPOL_SHIFT: u32 = 17;
+const CCR_SYNC_ID_SHIFT: u32 = 24;
+const RGCR_SIG_ID_SHIFT: u32 = 0;
+const RGCR_GE: u32 = 1 << 16;
+const RGCR_GPOL_SHIFT: u32 = 17;
+const RGCR_GNBREQ_SHIFT: u32 = 19;
+
 pub(crate) struct DmamuxInfo {
     pub(crate) mux: pac::dmamux::Dmamux,
     pub(crate) num: usize,
 }
 
-pub(crate) fn configure_dmamux(info: &DmamuxInfo, request: u8) {
+/// Active edge of a DMAMUX synchronization or trigger input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// Rising edge.
+    Rising = 1,
+    /// Falling edge.
+    Falling = 2,
+    /// Both edges.
+    Both = 3,
+}
+
+/// DMAMUX request line synchronization.
+///
+/// While synchronization is enabled, the DMA requests of the channel are only forwarded after an
+/// edge on the synchronization input, `requests` at a time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncConfig {
+    /// Synchronization input, see the DMAMUX section of the reference manual for the mapping.
+    pub sync_id: u8,
+    /// Active edge of the synchronization input.
+    pub edge: Edge,
+    /// Number of requests forwarded after each synchronization event, 1 to 32.
+    pub requests: u8,
+}
+
+pub(crate) fn configure_dmamux(info: &DmamuxInfo, request: u8, sync: Option<SyncConfig>) {
     let ch_mux_regs = info.mux.ccr(info.num);
     ch_mux_regs.write(|reg| {
         reg.set_nbreq(0);
         reg.set_dmareq_id(request);
     });
 
+    if let Some(sync) = sync {
+        assert!(sync.requests >= 1 && sync.requests <= 32);
+        ch_mux_regs.modify(|reg| {
+            reg.set_nbreq(sync.requests - 1);
+            reg.0 |= (sync.sync_id as u32) << CCR_SYNC_ID_SHIFT | (sync.edge as u32) << CCR_SPOL_SHIFT;
+        });
+        ch_mux_regs.modify(|reg| reg.0 |= CCR_SE);
+    }
+
     ch_mux_regs.modify(|reg| {
         reg.set_ege(true);
     });
 }
 
+/// DMAMUX request generator configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestGeneratorConfig {
+    /// Trigger input, see the DMAMUX section of the reference manual for the mapping.
+    pub signal_id: u8,
+    /// Active edge of the trigger input.
+    pub edge: Edge,
+    /// Number of DMA requests generated for each trigger event, 1 to 32.
+    pub requests: u8,
+}
+
+/// DMAMUX request generator.
+///
+/// Generates DMA requests from a trigger input such as an EXTI line, LPTIM output or another
+/// channel's event output, so a DMA transfer can be paced by a signal that isn't a peripheral
+/// request. Pass [`request`](Self::request) as the request of the transfer.
+pub struct RequestGenerator {
+    mux: pac::dmamux::Dmamux,
+    index: usize,
+}
+
+impl RequestGenerator {
+    /// Configure and enable request generator `index` of the DMAMUX.
+    pub fn new<M: MuxInstance>(index: usize, config: RequestGeneratorConfig) -> Self {
+        assert!(config.requests >= 1 && config.requests <= 32);
+        let mux = M::regs();
+
+        mux.rgcr(index).write(|w| w.0 = 0);
+        mux.rgcr(index).write(|w| {
+            w.0 = (config.signal_id as u32) << RGCR_SIG_ID_SHIFT
+                | (config.edge as u32) << RGCR_GPOL_SHIFT
+                | (config.requests as u32 - 1) << RGCR_GNBREQ_SHIFT
+        });
+        mux.rgcr(index).modify(|w| w.0 |= RGCR_GE);
+
+        Self { mux, index }
+    }
+
+    /// DMA request line of this generator.
+    pub fn request(&self) -> Request {
+        // Request 0 is unused, the generators come right after it.
+        self.index as Request + 1
+    }
+
+    /// Return true if a trigger event occurred before the previous requests were all served.
+    ///
+    /// The flag is cleared by this function.
+    pub fn check_overrun(&mut self) -> bool {
+        let overrun = self.mux.rgsr().read().0 & (1 << self.index) != 0;
+        if overrun {
+            self.mux.rgcfr().write(|w| w.0 = 1 << self.index);
+        }
+        overrun
+    }
+}
+
+impl Drop for RequestGenerator {
+    fn drop(&mut self) {
+        self.mux.rgcr(self.index).modify(|w| w.0 &= !RGCR_GE);
+    }
+}
+
 pub(crate) mod dmamux_sealed {
     pub trait MuxChannel {}
+
+    pub trait MuxInstance {
+        fn regs() -> crate::pac::dmamux::Dmamux;
+    }
 }
 
+/// DMAMUX instance trait.
+pub trait MuxInstance: dmamux_sealed::MuxInstance {}
+
 /// DMAMUX1 instance.
 pub struct DMAMUX1;
 /// DMAMUX2 instance.
 #[cfg(stm32h7)]
 pub struct DMAMUX2;
 
+impl dmamux_sealed::MuxInstance for DMAMUX1 {
+    fn regs() -> pac::dmamux::Dmamux {
+        pac::DMAMUX1
+    }
+}
+impl MuxInstance for DMAMUX1 {}
+
+#[cfg(stm32h7)]
+impl dmamux_sealed::MuxInstance for DMAMUX2 {
+    fn regs() -> pac::dmamux::Dmamux {
+        pac::DMAMUX2
+    }
+}
+#[cfg(stm32h7)]
+impl MuxInstance for DMAMUX2 {}
+
 /// DMAMUX channel trait.
 pub trait MuxChannel: dmamux_sealed::MuxChannel {
     /// DMAMUX instance this channel is on.
@@ -222,7 +222,7 @@ impl<'a> Transfer<'a> {
         let this = Self { channel };
 
         #[cfg(dmamux)]
-        super::dmamux::configure_dmamux(&*this.channel, request);
+        super::dmamux::configure_dmamux(&*this.channel, request, None);
 
         ch.cr().write(|w| w.set_reset(true));
         ch.fcr().write(|w| w.0 = 0xFFFF_FFFF); // clear all irqs
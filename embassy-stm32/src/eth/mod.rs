@@ -209,3 +209,4 @@ pin_trait!(TXD1Pin, Instance);
 pin_trait!(TXD2Pin, Instance);
 pin_trait!(TXD3Pin, Instance);
 pin_trait!(TXEnPin, Instance);
+pin_trait!(PPSOutPin, Instance);
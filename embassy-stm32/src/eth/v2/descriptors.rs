@@ -1,7 +1,9 @@
-use core::sync::atomic::{fence, Ordering};
+use core::ptr;
+use core::sync::atomic::{fence, AtomicPtr, Ordering};
 
 use vcell::VolatileCell;
 
+use super::ptp::{self, Timestamp};
 use crate::eth::{Packet, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
 use crate::pac::ETH;
 
@@ -16,7 +18,11 @@ mod emac_consts {
     pub const EMAC_DES0_BUF1AP: u32 = 0xFFFF_FFFF;
 
     pub const EMAC_TDES2_IOC: u32 = 0x8000_0000;
+    pub const EMAC_TDES2_TTSE: u32 = 0x4000_0000;
     pub const EMAC_TDES2_B1L: u32 = 0x0000_3FFF;
+    pub const EMAC_TDES3_TTSS: u32 = 0x0002_0000;
+
+    pub const EMAC_RDES1_TSA: u32 = 0x0000_4000;
 
     pub const EMAC_RDES3_IOC: u32 = 0x4000_0000;
     pub const EMAC_RDES3_PL: u32 = 0x0000_7FFF;
@@ -25,6 +31,31 @@ mod emac_consts {
 }
 use emac_consts::*;
 
+/// The last descriptor transmitted with timestamping enabled, until its timestamp is collected.
+static TIMESTAMPED_TDES: AtomicPtr<TDes> = AtomicPtr::new(ptr::null_mut());
+
+/// Collect the timestamp of the last timestamped transmission, once the DMA has written it back.
+pub(crate) fn collect_tx_timestamp() {
+    let td = TIMESTAMPED_TDES.load(Ordering::Acquire);
+    if td.is_null() {
+        return;
+    }
+
+    // NOTE(unsafe) the descriptor lives in the packet queue, which outlives the driver.
+    let td = unsafe { &*td };
+    let tdes3 = td.tdes3.get();
+    if tdes3 & EMAC_DES3_OWN != 0 {
+        return;
+    }
+    if TIMESTAMPED_TDES
+        .compare_exchange(td as *const _ as *mut _, ptr::null_mut(), Ordering::AcqRel, Ordering::Relaxed)
+        .is_ok()
+        && tdes3 & EMAC_TDES3_TTSS != 0
+    {
+        ptp::store_tx_timestamp(Timestamp::new(td.tdes1.get(), td.tdes0.get()));
+    }
+}
+
 /// Transmit Descriptor representation
 ///
 /// * tdes0: transmit buffer address
@@ -107,7 +138,12 @@ impl<'a> TDesRing<'a> {
 
         // Read format
         td.tdes0.set(self.buffers[self.index].0.as_ptr() as u32);
-        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC);
+        if ptp::PTP_ENABLED.load(Ordering::Relaxed) {
+            td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC | EMAC_TDES2_TTSE);
+            TIMESTAMPED_TDES.store(td as *mut _, Ordering::Release);
+        } else {
+            td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC);
+        }
 
         // FD: Contains first buffer of packet
         // LD: Contains last buffer of packet
@@ -168,6 +204,12 @@ impl RDes {
         self.rdes3.get() & EMAC_DES3_OWN == 0 // Owned by us
     }
 
+    /// Return true if the DMA wrote a context descriptor with the packet timestamp after this one
+    #[inline(always)]
+    fn has_timestamp(&self) -> bool {
+        self.rdes1.get() & EMAC_RDES1_TSA != 0
+    }
+
     #[inline(always)]
     fn set_ready(&mut self, buf: *mut u8) {
         self.rdes0.set(buf as u32);
@@ -225,6 +267,14 @@ impl<'a> RDesRing<'a> {
                 continue;
             }
 
+            // Wait for the context descriptor holding the timestamp, it's popped with the packet.
+            if descriptor.has_timestamp() {
+                let next = (self.index + 1) % self.descriptors.len();
+                if !self.descriptors[next].available() {
+                    return None;
+                }
+            }
+
             break;
         }
 
@@ -238,8 +288,20 @@ impl<'a> RDesRing<'a> {
         let rd = &mut self.descriptors[self.index];
         assert!(rd.available());
 
+        let has_timestamp = rd.valid() && rd.has_timestamp();
         rd.set_ready(self.buffers[self.index].0.as_mut_ptr());
 
+        // Give the context descriptor holding the timestamp back along with the packet.
+        if has_timestamp {
+            self.index = (self.index + 1) % self.descriptors.len();
+            let ctx = &mut self.descriptors[self.index];
+            if ctx.rdes3.get() & EMAC_DES3_CTXT != 0 {
+                ptp::store_rx_timestamp(Timestamp::new(ctx.rdes1.get(), ctx.rdes0.get()));
+            }
+            ctx.set_ready(self.buffers[self.index].0.as_mut_ptr());
+        }
+        let rd = &self.descriptors[self.index];
+
         // "Preceding reads and writes cannot be moved past subsequent writes."
         fence(Ordering::Release);
 
@@ -1,4 +1,5 @@
 mod descriptors;
+mod ptp;
 
 use core::marker::PhantomData;
 use core::sync::atomic::{fence, Ordering};
@@ -6,6 +7,7 @@ use core::sync::atomic::{fence, Ordering};
 use embassy_hal_internal::{into_ref, PeripheralRef};
 
 pub(crate) use self::descriptors::{RDes, RDesRing, TDes, TDesRing};
+pub use self::ptp::{PpsFrequency, Ptp, TargetTimeMode, Timestamp};
 use super::*;
 use crate::gpio::sealed::{AFType, Pin as _};
 use crate::gpio::{AnyPin, Speed};
@@ -21,6 +23,11 @@ impl interrupt::typelevel::Handler<interrupt::typelevel::ETH> for InterruptHandl
     unsafe fn on_interrupt() {
         WAKER.wake();
 
+        if ptp::PTP_ENABLED.load(Ordering::Relaxed) {
+            descriptors::collect_tx_timestamp();
+            ptp::on_interrupt();
+        }
+
         // TODO: Check and clear more flags
         let dma = ETH.ethernet_dma();
 
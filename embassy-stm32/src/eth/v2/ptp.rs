@@ -0,0 +1,310 @@
+//! IEEE-1588 precision time protocol (PTP) support.
+use core::cell::Cell;
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use critical_section::Mutex;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::{Ethernet, Instance, PPSOutPin, PHY};
+use crate::gpio::sealed::{AFType, Pin as _};
+use crate::gpio::{AnyPin, Speed};
+use crate::pac::ETH;
+use crate::rcc::sealed::RccPeripheral;
+use crate::Peripheral;
+
+// MACTSCR bits
+const TSENA: u32 = 1 << 0;
+const TSCFUPDT: u32 = 1 << 1;
+const TSINIT: u32 = 1 << 2;
+const TSUPDT: u32 = 1 << 3;
+const TSADDREG: u32 = 1 << 5;
+const TSCTRLSSR: u32 = 1 << 9;
+const TSVER2ENA: u32 = 1 << 10;
+const TSIPENA: u32 = 1 << 11;
+const TSIPV4ENA: u32 = 1 << 13;
+const TSEVNTENA: u32 = 1 << 14;
+
+// MACTSSR bits
+const TSTARGT0: u32 = 1 << 1;
+
+// MACIER bits
+const TSIE: u32 = 1 << 12;
+
+// MACPPSCR bits
+const PPSCTRL_MASK: u32 = 0xF;
+const PPSEN0: u32 = 1 << 4;
+const TRGTMODSEL0_SHIFT: u32 = 5;
+const TRGTMODSEL0_MASK: u32 = 0b11 << TRGTMODSEL0_SHIFT;
+
+// MACPPSTTNR bits
+const TRGTBUSY0: u32 = 1 << 31;
+
+// MACSTNUR value to subtract zero nanoseconds: ADDSUB set, and 10^9 with digital rollover.
+const STNUR_SUBTRACT: u32 = 0x8000_0000 | NANOS_PER_SECOND;
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+pub(crate) static PTP_ENABLED: AtomicBool = AtomicBool::new(false);
+static LAST_TX_TIMESTAMP: Mutex<Cell<Option<Timestamp>>> = Mutex::new(Cell::new(None));
+static LAST_RX_TIMESTAMP: Mutex<Cell<Option<Timestamp>>> = Mutex::new(Cell::new(None));
+static TARGET_WAKER: AtomicWaker = AtomicWaker::new();
+static TARGET_REACHED: AtomicBool = AtomicBool::new(false);
+
+/// A PTP timestamp, counted from the epoch the clock was set to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    /// Seconds.
+    pub seconds: u32,
+    /// Nanoseconds, below 10^9.
+    pub nanoseconds: u32,
+}
+
+impl Timestamp {
+    /// Create a timestamp.
+    pub const fn new(seconds: u32, nanoseconds: u32) -> Self {
+        Self { seconds, nanoseconds }
+    }
+
+    /// Total nanoseconds.
+    pub const fn as_nanos(&self) -> u64 {
+        self.seconds as u64 * NANOS_PER_SECOND as u64 + self.nanoseconds as u64
+    }
+}
+
+/// Frequency of the PPS output, when it isn't driven by the target time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PpsFrequency {
+    /// One pulse per second.
+    Hz1,
+    /// A square wave of 2^n Hz, n from 1 to 15.
+    Pow2(u8),
+}
+
+/// What happens when the system time reaches the target time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TargetTimeMode {
+    /// Only raise the interrupt, see [`Ptp::wait_for_target_time`].
+    Interrupt,
+    /// Raise the interrupt and start a pulse on the PPS output.
+    InterruptAndPps,
+    /// Only start a pulse on the PPS output.
+    Pps,
+}
+
+pub(crate) fn on_interrupt() {
+    let mac = ETH.ethernet_mac();
+
+    if mac.macier().read().0 & TSIE != 0 {
+        // Reading MACTSSR clears the flags.
+        let status = mac.mactssr().read().0;
+        if status & TSTARGT0 != 0 {
+            TARGET_REACHED.store(true, Ordering::Release);
+            TARGET_WAKER.wake();
+        }
+    }
+}
+
+pub(crate) fn store_tx_timestamp(timestamp: Timestamp) {
+    critical_section::with(|cs| LAST_TX_TIMESTAMP.borrow(cs).set(Some(timestamp)));
+}
+
+pub(crate) fn store_rx_timestamp(timestamp: Timestamp) {
+    critical_section::with(|cs| LAST_RX_TIMESTAMP.borrow(cs).set(Some(timestamp)));
+}
+
+/// PTP hardware clock and timestamping.
+///
+/// Created with [`Ethernet::enable_ptp`]. Only PTP event messages (Sync, Delay_Req, ...) over
+/// Ethernet and UDP/IPv4 are timestamped.
+pub struct Ptp<'d, T: Instance> {
+    _peri: PhantomData<&'d mut T>,
+    base_addend: u32,
+    #[allow(dead_code)]
+    pps: Option<PeripheralRef<'d, AnyPin>>,
+}
+
+impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
+    /// Enable PTP timestamping, and start the PTP clock at zero.
+    ///
+    /// Panics if PTP was already enabled.
+    pub fn enable_ptp(&mut self) -> Ptp<'d, T> {
+        assert!(
+            !PTP_ENABLED.swap(true, Ordering::Relaxed),
+            "PTP has already been enabled"
+        );
+
+        let mac = ETH.ethernet_mac();
+
+        // Run the sub-second counter at half the HCLK frequency, rounded to whole nanoseconds, and
+        // use the fine update method to trim it.
+        let hclk = <T as RccPeripheral>::frequency().0 as u64;
+        let ssinc = ((2 * NANOS_PER_SECOND as u64 + hclk - 1) / hclk) as u32;
+        let base_addend = (((NANOS_PER_SECOND / ssinc) as u64) << 32) / hclk;
+        let base_addend = base_addend as u32;
+
+        mac.macier().modify(|w| w.0 &= !TSIE);
+        mac.mactscr().write(|w| {
+            w.0 = TSENA | TSCFUPDT | TSCTRLSSR | TSVER2ENA | TSIPENA | TSIPV4ENA | TSEVNTENA;
+        });
+        mac.macssir().write(|w| w.0 = ssinc << 16);
+
+        mac.mactsar().write(|w| w.0 = base_addend);
+        mac.mactscr().modify(|w| w.0 |= TSADDREG);
+        while mac.mactscr().read().0 & TSADDREG != 0 {}
+
+        mac.macstsur().write(|w| w.0 = 0);
+        mac.macstnur().write(|w| w.0 = 0);
+        mac.mactscr().modify(|w| w.0 |= TSINIT);
+        while mac.mactscr().read().0 & TSINIT != 0 {}
+
+        Ptp {
+            _peri: PhantomData,
+            base_addend,
+            pps: None,
+        }
+    }
+}
+
+impl<'d, T: Instance> Ptp<'d, T> {
+    /// Read the current time.
+    pub fn now(&self) -> Timestamp {
+        let mac = ETH.ethernet_mac();
+        loop {
+            let seconds = mac.macstsr().read().0;
+            let nanoseconds = mac.macstnr().read().0 & 0x7FFF_FFFF;
+            // Retry if the seconds rolled over in between.
+            if mac.macstsr().read().0 == seconds {
+                return Timestamp { seconds, nanoseconds };
+            }
+        }
+    }
+
+    /// Set the current time.
+    pub fn set_time(&mut self, time: Timestamp) {
+        assert!(time.nanoseconds < NANOS_PER_SECOND);
+        let mac = ETH.ethernet_mac();
+
+        while mac.mactscr().read().0 & (TSINIT | TSUPDT) != 0 {}
+        mac.macstsur().write(|w| w.0 = time.seconds);
+        mac.macstnur().write(|w| w.0 = time.nanoseconds);
+        mac.mactscr().modify(|w| w.0 |= TSINIT);
+        while mac.mactscr().read().0 & TSINIT != 0 {}
+    }
+
+    /// Step the clock forward or backward by `offset` nanoseconds.
+    pub fn adjust_time(&mut self, offset: i64) {
+        let mac = ETH.ethernet_mac();
+        let abs = offset.unsigned_abs();
+        let seconds = (abs / NANOS_PER_SECOND as u64) as u32;
+        let nanoseconds = (abs % NANOS_PER_SECOND as u64) as u32;
+
+        while mac.mactscr().read().0 & (TSINIT | TSUPDT) != 0 {}
+        if offset >= 0 {
+            mac.macstsur().write(|w| w.0 = seconds);
+            mac.macstnur().write(|w| w.0 = nanoseconds);
+        } else {
+            // Subtraction takes the complement of the seconds and nanoseconds.
+            mac.macstsur().write(|w| w.0 = 0u32.wrapping_sub(seconds));
+            mac.macstnur().write(|w| w.0 = STNUR_SUBTRACT - nanoseconds);
+        }
+        mac.mactscr().modify(|w| w.0 |= TSUPDT);
+        while mac.mactscr().read().0 & TSUPDT != 0 {}
+    }
+
+    /// Set the addend of the fine correction accumulator directly.
+    pub fn set_addend(&mut self, addend: u32) {
+        let mac = ETH.ethernet_mac();
+
+        while mac.mactscr().read().0 & TSADDREG != 0 {}
+        mac.mactsar().write(|w| w.0 = addend);
+        mac.mactscr().modify(|w| w.0 |= TSADDREG);
+    }
+
+    /// The addend for which the clock runs at the nominal HCLK rate.
+    pub fn base_addend(&self) -> u32 {
+        self.base_addend
+    }
+
+    /// Speed up (positive) or slow down (negative) the clock by `ppb` parts per billion, relative
+    /// to the nominal rate.
+    pub fn adjust_frequency(&mut self, ppb: i32) {
+        let adjustment = self.base_addend as i64 * ppb as i64 / NANOS_PER_SECOND as i64;
+        let addend = (self.base_addend as i64 + adjustment).clamp(0, u32::MAX as i64);
+        self.set_addend(addend as u32);
+    }
+
+    /// Program the target time, and what happens when it's reached.
+    pub fn set_target_time(&mut self, time: Timestamp, mode: TargetTimeMode) {
+        assert!(time.nanoseconds < NANOS_PER_SECOND);
+        let mac = ETH.ethernet_mac();
+
+        while mac.macppsttnr().read().0 & TRGTBUSY0 != 0 {}
+
+        TARGET_REACHED.store(false, Ordering::Relaxed);
+        let trgtmodsel = match mode {
+            TargetTimeMode::Interrupt => 0b00,
+            TargetTimeMode::InterruptAndPps => 0b10,
+            TargetTimeMode::Pps => 0b11,
+        };
+        mac.macppscr()
+            .modify(|w| w.0 = (w.0 & !TRGTMODSEL0_MASK) | (trgtmodsel << TRGTMODSEL0_SHIFT));
+        mac.macppsttsr().write(|w| w.0 = time.seconds);
+        mac.macppsttnr().write(|w| w.0 = time.nanoseconds);
+
+        if mode != TargetTimeMode::Pps {
+            mac.macier().modify(|w| w.0 |= TSIE);
+        }
+    }
+
+    /// Wait until the target time set with [`set_target_time`](Self::set_target_time) is reached.
+    pub async fn wait_for_target_time(&mut self) {
+        poll_fn(|cx| {
+            TARGET_WAKER.register(cx.waker());
+            if TARGET_REACHED.swap(false, Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Output a fixed frequency on the PPS pin, synchronized to the PTP clock.
+    pub fn enable_pps_output(&mut self, pin: impl Peripheral<P = impl PPSOutPin<T>> + 'd, frequency: PpsFrequency) {
+        into_ref!(pin);
+
+        critical_section::with(|_| {
+            pin.set_as_af(pin.af_num(), AFType::OutputPushPull);
+            pin.set_speed(Speed::VeryHigh);
+        });
+        self.pps = Some(pin.map_into());
+
+        let ppsctrl = match frequency {
+            PpsFrequency::Hz1 => 0,
+            PpsFrequency::Pow2(n) => {
+                assert!(n >= 1 && n <= 15);
+                n as u32
+            }
+        };
+        ETH.ethernet_mac()
+            .macppscr()
+            .modify(|w| w.0 = (w.0 & !(PPSCTRL_MASK | PPSEN0)) | ppsctrl);
+    }
+
+    /// Timestamp of the most recently sent PTP event message, if it hasn't been read yet.
+    pub fn tx_timestamp(&mut self) -> Option<Timestamp> {
+        critical_section::with(|cs| LAST_TX_TIMESTAMP.borrow(cs).take())
+    }
+
+    /// Timestamp of the most recently received PTP event message, if it hasn't been read yet.
+    pub fn rx_timestamp(&mut self) -> Option<Timestamp> {
+        critical_section::with(|cs| LAST_RX_TIMESTAMP.borrow(cs).take())
+    }
+}
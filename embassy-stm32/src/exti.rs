@@ -226,14 +226,73 @@ impl<'d> embedded_hal_async::digital::Wait for ExtiInput<'d> {
     }
 }
 
+/// Waits for edges on a set of EXTI inputs at once.
+///
+/// Each input still owns its EXTI channel, so the inputs are guaranteed to be on different
+/// lines. This allows a single task to watch a keypad or a group of buttons.
+pub struct PortWatcher<'d, const N: usize> {
+    inputs: [ExtiInput<'d>; N],
+}
+
+impl<'d, const N: usize> PortWatcher<'d, N> {
+    /// Create a watcher over the given inputs.
+    pub fn new(inputs: [ExtiInput<'d>; N]) -> Self {
+        Self { inputs }
+    }
+
+    /// Get the inputs, for instance to read their levels.
+    pub fn inputs(&self) -> &[ExtiInput<'d>; N] {
+        &self.inputs
+    }
+
+    /// Release the inputs.
+    pub fn into_inputs(self) -> [ExtiInput<'d>; N] {
+        self.inputs
+    }
+
+    /// Wait for a rising edge on any of the inputs, and return the index of the input.
+    pub async fn wait_for_rising_edge(&mut self) -> usize {
+        self.wait_for_edge(true, false).await
+    }
+
+    /// Wait for a falling edge on any of the inputs, and return the index of the input.
+    pub async fn wait_for_falling_edge(&mut self) -> usize {
+        self.wait_for_edge(false, true).await
+    }
+
+    /// Wait for any edge on any of the inputs, and return the index of the input.
+    pub async fn wait_for_any_edge(&mut self) -> usize {
+        self.wait_for_edge(true, true).await
+    }
+
+    /// If several inputs fire at the same time, the lowest index is returned and the other
+    /// edges are dropped.
+    async fn wait_for_edge(&mut self, rising: bool, falling: bool) -> usize {
+        let mut futures: [ExtiInputFuture<'_>; N] = core::array::from_fn(|i| {
+            let pin = &self.inputs[i].pin.pin.pin;
+            ExtiInputFuture::new(pin.pin(), pin.port(), rising, falling)
+        });
+
+        core::future::poll_fn(|cx| {
+            for (i, fut) in futures.iter_mut().enumerate() {
+                if Pin::new(fut).poll(cx).is_ready() {
+                    return Poll::Ready(i);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-struct ExtiInputFuture<'a> {
+pub(crate) struct ExtiInputFuture<'a> {
     pin: u8,
     phantom: PhantomData<&'a mut AnyPin>,
 }
 
 impl<'a> ExtiInputFuture<'a> {
-    fn new(pin: u8, port: u8, rising: bool, falling: bool) -> Self {
+    pub(crate) fn new(pin: u8, port: u8, rising: bool, falling: bool) -> Self {
         critical_section::with(|_| {
             let pin = pin as usize;
             exticr_regs().exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
@@ -1,13 +1,19 @@
 use core::convert::TryInto;
+use core::future::poll_fn;
 use core::ptr::write_volatile;
 use core::sync::atomic::{fence, Ordering};
+use core::task::Poll;
 
 use cortex_m::interrupt;
+use embassy_sync::waitqueue::AtomicWaker;
 
-use super::{FlashRegion, FlashSector, FLASH_REGIONS, WRITE_SIZE};
+#[allow(unused_imports)]
+use super::{FlashBank, FlashRegion, FlashSector, FLASH_REGIONS, WRITE_SIZE};
 use crate::flash::Error;
 use crate::pac;
 
+static WAKER: AtomicWaker = AtomicWaker::new();
+
 pub(crate) const fn is_default_layout() -> bool {
     true
 }
@@ -16,6 +22,16 @@ pub(crate) const fn get_flash_regions() -> &'static [&'static FlashRegion] {
     &FLASH_REGIONS
 }
 
+pub(crate) unsafe fn on_interrupt() {
+    // Clear IRQ flags
+    pac::FLASH.sr().write(|w| {
+        w.set_operr(true);
+        w.set_eop(true);
+    });
+
+    WAKER.wake();
+}
+
 pub(crate) unsafe fn lock() {
     pac::FLASH.cr().modify(|w| w.set_lock(true));
 }
@@ -30,6 +46,23 @@ pub(crate) unsafe fn unlock() {
     }
 }
 
+pub(crate) unsafe fn enable_write() {
+    assert_eq!(0, WRITE_SIZE % 4);
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(true);
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+}
+
+pub(crate) unsafe fn disable_write() {
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+}
+
 pub(crate) unsafe fn enable_blocking_write() {
     assert_eq!(0, WRITE_SIZE % 4);
     pac::FLASH.cr().write(|w| w.set_pg(true));
@@ -39,7 +72,17 @@ pub(crate) unsafe fn disable_blocking_write() {
     pac::FLASH.cr().write(|w| w.set_pg(false));
 }
 
+pub(crate) async unsafe fn write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready().await
+}
+
 pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready_blocking()
+}
+
+unsafe fn write_start(start_address: u32, buf: &[u8; WRITE_SIZE]) {
     let mut address = start_address;
     for val in buf.chunks(4) {
         write_volatile(address as *mut u32, u32::from_le_bytes(val.try_into().unwrap()));
@@ -48,33 +91,86 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
         // prevents parallelism errors
         fence(Ordering::SeqCst);
     }
-
-    wait_ready_blocking()
 }
 
-pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), Error> {
-    let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
+pub(crate) async unsafe fn erase_sector(sector: &FlashSector) -> Result<(), Error> {
     while pac::FLASH.sr().read().bsy() {}
     clear_all_err();
 
     interrupt::free(|_| {
         pac::FLASH.cr().modify(|w| {
-            w.set_per(true);
-            w.set_pnb(idx as u8);
-            w.set_strt(true);
+            start_erase(w, sector);
+            w.set_eopie(true);
+            w.set_errie(true);
         });
     });
 
+    let ret: Result<(), Error> = wait_ready().await;
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+    ret
+}
+
+pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    while pac::FLASH.sr().read().bsy() {}
+    clear_all_err();
+
+    interrupt::free(|_| {
+        pac::FLASH.cr().modify(|w| start_erase(w, sector));
+    });
+
     let ret: Result<(), Error> = wait_ready_blocking();
     pac::FLASH.cr().modify(|w| w.set_per(false));
     ret
 }
 
+/// Select the page to erase, in the bank it belongs to, and start the erase.
+fn start_erase(w: &mut pac::flash::regs::Cr, sector: &FlashSector) {
+    w.set_per(true);
+
+    // On G0, pages of the second bank follow the ones of the first bank.
+    #[cfg(flash_g0)]
+    {
+        let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
+        w.set_pnb(idx as u8);
+    }
+
+    // On G4, pages are numbered per bank and the bank is selected separately. The other bank
+    // stays readable while this one is erased, so code can keep executing from it.
+    #[cfg(flash_g4)]
+    {
+        w.set_pnb(sector.index_in_bank);
+        #[cfg(any(stm32g473, stm32g474, stm32g483, stm32g484))]
+        w.set_bker(sector.bank == FlashBank::Bank2);
+    }
+
+    w.set_strt(true);
+}
+
+pub(crate) async fn wait_ready() -> Result<(), Error> {
+    poll_fn(|cx| {
+        WAKER.register(cx.waker());
+
+        let sr = pac::FLASH.sr().read();
+        if !sr.bsy() {
+            Poll::Ready(get_result(sr))
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
 pub(crate) unsafe fn wait_ready_blocking() -> Result<(), Error> {
     while pac::FLASH.sr().read().bsy() {}
 
-    let sr = pac::FLASH.sr().read();
+    get_result(pac::FLASH.sr().read())
+}
 
+fn get_result(sr: pac::flash::regs::Sr) -> Result<(), Error> {
     if sr.progerr() {
         return Err(Error::Prog);
     }
@@ -1,12 +1,12 @@
 //! Flash memory (FLASH)
 use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
 
-#[cfg(flash_f4)]
+#[cfg(any(flash_f4, flash_g0, flash_g4))]
 mod asynch;
 #[cfg(flash)]
 mod common;
 
-#[cfg(flash_f4)]
+#[cfg(any(flash_f4, flash_g0, flash_g4))]
 pub use asynch::InterruptHandler;
 #[cfg(flash)]
 pub use common::*;
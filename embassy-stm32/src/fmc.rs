@@ -5,6 +5,7 @@ use embassy_hal_internal::into_ref;
 
 use crate::gpio::sealed::AFType;
 use crate::gpio::{Pull, Speed};
+use crate::pac::fmc::vals::{Mtyp, Mwid};
 use crate::Peripheral;
 
 /// FMC driver
@@ -73,6 +74,192 @@ where
     }
 }
 
+/// NOR/PSRAM/SRAM sub-bank, selected by the NEx chip select pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NorSramBank {
+    /// Sub-bank 1, selected with NE1, mapped at `0x6000_0000`.
+    Bank1 = 0,
+    /// Sub-bank 2, selected with NE2, mapped at `0x6400_0000`.
+    Bank2 = 1,
+    /// Sub-bank 3, selected with NE3, mapped at `0x6800_0000`.
+    Bank3 = 2,
+    /// Sub-bank 4, selected with NE4, mapped at `0x6C00_0000`.
+    Bank4 = 3,
+}
+
+impl NorSramBank {
+    fn base_address(&self) -> usize {
+        0x6000_0000 + (*self as usize) * 0x0400_0000
+    }
+}
+
+/// NOR/PSRAM/SRAM memory type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NorSramMemoryType {
+    /// Static RAM.
+    Sram,
+    /// Pseudo-static RAM (CellularRAM).
+    Psram,
+    /// Parallel NOR flash.
+    Nor,
+}
+
+/// External memory data bus width.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataWidth {
+    /// 8-bit data bus.
+    Bits8,
+    /// 16-bit data bus.
+    Bits16,
+    /// 32-bit data bus.
+    Bits32,
+}
+
+/// Asynchronous access timings of a NOR/PSRAM/SRAM memory (mode 1), in FMC kernel clock cycles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NorSramTiming {
+    /// Address setup phase duration, 0 to 15 cycles.
+    pub address_setup: u8,
+    /// Address hold phase duration, 1 to 15 cycles. Only used in multiplexed modes.
+    pub address_hold: u8,
+    /// Data phase duration, 1 to 255 cycles.
+    pub data_setup: u8,
+    /// Bus turnaround duration between consecutive accesses, 0 to 15 cycles.
+    pub bus_turnaround: u8,
+}
+
+impl Default for NorSramTiming {
+    fn default() -> Self {
+        // Reset values, the slowest possible access.
+        Self {
+            address_setup: 15,
+            address_hold: 15,
+            data_setup: 255,
+            bus_turnaround: 15,
+        }
+    }
+}
+
+/// NOR/PSRAM/SRAM bank configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NorSramConfig {
+    /// Memory type.
+    pub memory_type: NorSramMemoryType,
+    /// Data bus width.
+    pub data_width: DataWidth,
+    /// Access timings.
+    pub timing: NorSramTiming,
+    /// Allow writes to the memory.
+    pub write_enable: bool,
+    /// Memory size in bytes, at most 64 MiB.
+    pub size: usize,
+}
+
+impl Default for NorSramConfig {
+    fn default() -> Self {
+        Self {
+            memory_type: NorSramMemoryType::Sram,
+            data_width: DataWidth::Bits16,
+            timing: Default::default(),
+            write_enable: true,
+            size: 0,
+        }
+    }
+}
+
+impl<'d, T: Instance> Fmc<'d, T> {
+    /// Configure a NOR/PSRAM/SRAM sub-bank and return its mapped memory region.
+    ///
+    /// The pins must have been configured already, see [`new_raw`](Self::new_raw). Consuming the
+    /// driver ensures the region is only handed out once; with an owned peripheral the returned
+    /// slice is `&'static mut`.
+    pub fn nor_sram(mut self, bank: NorSramBank, config: NorSramConfig) -> &'d mut [u8] {
+        assert!(config.size <= 0x0400_0000, "NOR/SRAM sub-banks are 64 MiB");
+        assert!(config.timing.address_setup <= 15);
+        assert!(config.timing.address_hold >= 1 && config.timing.address_hold <= 15);
+        assert!(config.timing.data_setup >= 1);
+        assert!(config.timing.bus_turnaround <= 15);
+
+        self.enable();
+
+        let mtyp = match config.memory_type {
+            NorSramMemoryType::Sram => Mtyp::SRAM,
+            NorSramMemoryType::Psram => Mtyp::PSRAM,
+            NorSramMemoryType::Nor => Mtyp::FLASH,
+        };
+        let mwid = match config.data_width {
+            DataWidth::Bits8 => Mwid::BITS8,
+            DataWidth::Bits16 => Mwid::BITS16,
+            DataWidth::Bits32 => Mwid::BITS32,
+        };
+
+        #[cfg(not(fmc_v4))]
+        let regs = T::REGS;
+        #[cfg(fmc_v4)]
+        let regs = T::REGS.nor_psram();
+
+        regs.btr(bank as usize).modify(|w| {
+            w.set_addset(config.timing.address_setup);
+            w.set_addhld(config.timing.address_hold);
+            w.set_datast(config.timing.data_setup);
+            w.set_busturn(config.timing.bus_turnaround);
+        });
+
+        // Only the asynchronous, non-multiplexed mode is supported. The other bits are kept, BCR1 also
+        // holds the global FMCEN and BMAP bits, and reserved bit 7 has to stay at its reset value.
+        macro_rules! set_bank {
+            ($w:ident) => {
+                $w.set_muxen(false);
+                $w.set_mtyp(mtyp);
+                $w.set_mwid(mwid);
+                $w.set_faccen(config.memory_type == NorSramMemoryType::Nor);
+                $w.set_wren(config.write_enable);
+                $w.set_mbken(true);
+            };
+        }
+        match bank {
+            NorSramBank::Bank1 => regs.bcr1().modify(|w| {
+                set_bank!(w);
+            }),
+            // BCR2 to BCR4
+            _ => regs.bcr(bank as usize - 1).modify(|w| {
+                set_bank!(w);
+            }),
+        }
+
+        self.memory_controller_enable();
+
+        // NOTE(unsafe) the sub-bank is now mapped, and owned by the returned slice.
+        unsafe { core::slice::from_raw_parts_mut(bank.base_address() as *mut u8, config.size) }
+    }
+}
+
+/// Initialize an SDRAM and return its mapped memory region.
+///
+/// Consuming the SDRAM driver ensures the region is only handed out once; with an owned
+/// peripheral the returned slice is `&'static mut`.
+pub fn sdram_memory<'d, T: Instance, CHIP: stm32_fmc::SdramChip>(
+    mut sdram: stm32_fmc::Sdram<Fmc<'d, T>, CHIP>,
+    delay: &mut impl embedded_hal_02::blocking::delay::DelayUs<u8>,
+) -> &'d mut [u8] {
+    let ptr = sdram.init(delay);
+
+    let config = CHIP::CONFIG;
+    let size = (1usize << config.column_bits)
+        * (1usize << config.row_bits)
+        * config.internal_banks as usize
+        * (config.memory_data_width as usize / 8);
+
+    // NOTE(unsafe) the SDRAM is now initialized and mapped, and owned by the returned slice.
+    unsafe { core::slice::from_raw_parts_mut(ptr as *mut u8, size) }
+}
+
 macro_rules! config_pins {
     ($($pin:ident),*) => {
         into_ref!($($pin),*);
@@ -123,6 +310,80 @@ macro_rules! fmc_sdram_constructor {
     };
 }
 
+macro_rules! fmc_nor_sram_constructor {
+    ($name:ident: (
+        bank: $bank:expr,
+        addr: [$(($addr_pin_name:ident: $addr_signal:ident)),*],
+        d: [$(($d_pin_name:ident: $d_signal:ident)),*],
+        nbl: [$(($nbl_pin_name:ident: $nbl_signal:ident)),*],
+        ctrl: [$(($ctrl_pin_name:ident: $ctrl_signal:ident)),*]
+    )) => {
+        /// Create a new FMC instance for a NOR/PSRAM/SRAM memory, and return its mapped memory region.
+        ///
+        /// See [`nor_sram`](Self::nor_sram).
+        pub fn $name(
+            _instance: impl Peripheral<P = T> + 'd,
+            $($addr_pin_name: impl Peripheral<P = impl $addr_signal<T>> + 'd),*,
+            $($d_pin_name: impl Peripheral<P = impl $d_signal<T>> + 'd),*,
+            $($nbl_pin_name: impl Peripheral<P = impl $nbl_signal<T>> + 'd),*,
+            $($ctrl_pin_name: impl Peripheral<P = impl $ctrl_signal<T>> + 'd),*,
+            config: NorSramConfig
+        ) -> &'d mut [u8] {
+
+        critical_section::with(|_| {
+            config_pins!(
+                $($addr_pin_name),*,
+                $($d_pin_name),*,
+                $($nbl_pin_name),*,
+                $($ctrl_pin_name),*
+            );
+        });
+
+            let fmc = Self { peri: PhantomData };
+            fmc.nor_sram($bank, config)
+        }
+    };
+}
+
+impl<'d, T: Instance> Fmc<'d, T> {
+    fmc_nor_sram_constructor!(nor_sram_a19bits_d16bits_bank1: (
+        bank: NorSramBank::Bank1,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin),
+            (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin)
+        ],
+        ctrl: [
+            (noe: NOEPin), (nwe: NWEPin), (ne: NE1Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(nor_sram_a23bits_d16bits_bank1: (
+        bank: NorSramBank::Bank1,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin),
+            (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin),
+            (a19: A19Pin), (a20: A20Pin), (a21: A21Pin), (a22: A22Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin)
+        ],
+        ctrl: [
+            (noe: NOEPin), (nwe: NWEPin), (ne: NE1Pin)
+        ]
+    ));
+}
+
 impl<'d, T: Instance> Fmc<'d, T> {
     fmc_sdram_constructor!(sdram_a12bits_d16bits_4banks_bank1: (
         bank: stm32_fmc::SdramTargetBank::Bank1,
@@ -20,6 +20,11 @@ use crate::peripherals::HASH;
 use crate::rcc::sealed::RccPeripheral;
 use crate::{interrupt, pac, peripherals, Peripheral};
 
+#[cfg(feature = "digest")]
+mod rustcrypto;
+#[cfg(feature = "digest")]
+pub use rustcrypto::{DigestAlgorithm, Hasher, Sha1, Sha224, Sha256};
+
 #[cfg(hash_v1)]
 const NUM_CONTEXT_REGS: usize = 51;
 #[cfg(hash_v3)]
@@ -0,0 +1,78 @@
+//! [RustCrypto](https://github.com/RustCrypto/traits) `digest` trait implementations.
+use core::marker::PhantomData;
+
+use digest::consts::{U20, U28, U32};
+use digest::generic_array::ArrayLength;
+use digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Update};
+
+use super::{Algorithm, Context, DataType, Hash, Instance};
+
+mod sealed {
+    pub trait DigestAlgorithm {}
+}
+
+/// A digest algorithm supported by the HASH peripheral, for use with [`Hasher`].
+pub trait DigestAlgorithm: sealed::DigestAlgorithm {
+    /// Digest size in bytes.
+    type OutputSize: ArrayLength<u8> + 'static;
+    /// Peripheral algorithm selection.
+    const ALGORITHM: Algorithm;
+}
+
+macro_rules! impl_digest_algorithm {
+    ($name:ident, $size:ty, $algo:expr, $doc:expr) => {
+        #[doc = $doc]
+        pub struct $name;
+
+        impl sealed::DigestAlgorithm for $name {}
+
+        impl DigestAlgorithm for $name {
+            type OutputSize = $size;
+            const ALGORITHM: Algorithm = $algo;
+        }
+    };
+}
+
+impl_digest_algorithm!(Sha1, U20, Algorithm::SHA1, "SHA-1 digest algorithm.");
+impl_digest_algorithm!(Sha224, U28, Algorithm::SHA224, "SHA-224 digest algorithm.");
+impl_digest_algorithm!(Sha256, U32, Algorithm::SHA256, "SHA-256 digest algorithm.");
+
+/// A running digest computation, usable through the `digest` traits.
+///
+/// Created with [`Hash::hasher`]. Implements [`Update`] and [`FixedOutput`], but not `Default`,
+/// since it borrows the peripheral; the full `Digest` trait is therefore not available.
+pub struct Hasher<'a, 'd, T: Instance, D, A: DigestAlgorithm> {
+    hash: &'a mut Hash<'d, T, D>,
+    ctx: Context<'static>,
+    _algorithm: PhantomData<A>,
+}
+
+impl<'d, T: Instance, D> Hash<'d, T, D> {
+    /// Start a digest computation with the given algorithm, for use with the `digest` traits.
+    pub fn hasher<A: DigestAlgorithm>(&mut self) -> Hasher<'_, 'd, T, D, A> {
+        let ctx = self.start(A::ALGORITHM, DataType::Width8, None);
+        Hasher {
+            hash: self,
+            ctx,
+            _algorithm: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'd, T: Instance, D, A: DigestAlgorithm> HashMarker for Hasher<'a, 'd, T, D, A> {}
+
+impl<'a, 'd, T: Instance, D, A: DigestAlgorithm> OutputSizeUser for Hasher<'a, 'd, T, D, A> {
+    type OutputSize = A::OutputSize;
+}
+
+impl<'a, 'd, T: Instance, D, A: DigestAlgorithm> Update for Hasher<'a, 'd, T, D, A> {
+    fn update(&mut self, data: &[u8]) {
+        self.hash.update_blocking(&mut self.ctx, data);
+    }
+}
+
+impl<'a, 'd, T: Instance, D, A: DigestAlgorithm> FixedOutput for Hasher<'a, 'd, T, D, A> {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        self.hash.finish_blocking(self.ctx, out);
+    }
+}
@@ -0,0 +1,272 @@
+//! Hardware Semaphore (HSEM)
+//!
+//! HSEM provides 32 semaphores shared by the cores of multi-core chips. Besides mutual exclusion,
+//! unlocking a semaphore can raise an interrupt on the other core, which is used here as a
+//! cross-core waker: a task on one core waits with [`HardwareSemaphore::wait_unlocked`], and is
+//! woken when the other core calls [`HardwareSemaphore::notify`] or unlocks the semaphore.
+//!
+//! Dual-core STM32 chips usually run a separate program image on each core, so they can't spawn tasks
+//! into each other's executor. Instead, the cores share data through a memory region both images
+//! agree on, and use these semaphores to guard it and to wake each other up.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_hal_internal::{into_ref, PeripheralRef};
+
+use crate::{interrupt, Peripheral};
+
+/// Number of semaphores.
+pub const SEMAPHORE_COUNT: usize = 32;
+
+/// HSEM error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The semaphore is locked by another core or process.
+    Locked,
+}
+
+/// CPU core.
+///
+/// The values are the bus master IDs of the cores, which HSEM uses to identify the lock owner.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum CoreId {
+    #[cfg(any(stm32h745, stm32h747, stm32h755, stm32h757))]
+    Core0 = 0x3,
+    #[cfg(any(stm32h745, stm32h747, stm32h755, stm32h757))]
+    Core1 = 0x1,
+    #[cfg(not(any(stm32h745, stm32h747, stm32h755, stm32h757)))]
+    Core0 = 0x4,
+    #[cfg(not(any(stm32h745, stm32h747, stm32h755, stm32h757)))]
+    Core1 = 0x8,
+}
+
+impl CoreId {
+    /// Get the core this code is running on.
+    pub fn current() -> Self {
+        // Cortex-M PartNo from CPUID: 0xC27 is Cortex-M7, 0xC24 is Cortex-M4 and 0xC60 is Cortex-M0+.
+        let cpuid = unsafe { cortex_m::Peripherals::steal().CPUID.base.read() };
+        match (cpuid & 0x0000_FFF0) >> 4 {
+            #[cfg(any(stm32h745, stm32h747, stm32h755, stm32h757))]
+            0xC27 => CoreId::Core0,
+            #[cfg(any(stm32h745, stm32h747, stm32h755, stm32h757))]
+            _ => CoreId::Core1,
+            #[cfg(not(any(stm32h745, stm32h747, stm32h755, stm32h757)))]
+            0xC24 => CoreId::Core0,
+            #[cfg(not(any(stm32h745, stm32h747, stm32h755, stm32h757)))]
+            _ => CoreId::Core1,
+        }
+    }
+
+    /// Index of the interrupt registers of this core.
+    fn index(self) -> usize {
+        match self {
+            CoreId::Core0 => 0,
+            CoreId::Core1 => 1,
+        }
+    }
+}
+
+/// HSEM interrupt handler.
+///
+/// Bind it to the HSEM interrupt of the core the driver runs on.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance, I: interrupt::typelevel::Interrupt> interrupt::typelevel::Handler<I> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let core = CoreId::current().index();
+        let regs = T::regs();
+
+        let misr = regs.misr(core).read();
+        for sem in 0..SEMAPHORE_COUNT {
+            if misr.misf(sem) {
+                // Disable the interrupt, the waiting task re-enables it if it needs to wait again.
+                regs.ier(core).modify(|w| w.set_ise(sem, false));
+                regs.icr(core).write(|w| w.set_isc(sem, true));
+                T::state().wakers[sem].wake();
+            }
+        }
+    }
+}
+
+/// HSEM driver.
+pub struct HardwareSemaphore<'d, T: Instance> {
+    _peri: PeripheralRef<'d, T>,
+}
+
+impl<'d, T: Instance> HardwareSemaphore<'d, T> {
+    /// Create a new HSEM driver, enabling and resetting the peripheral.
+    ///
+    /// On multi-core chips, this must be done by a single core, before the others use HSEM. They
+    /// must then use [`new_shared`](Self::new_shared) instead, so the locks aren't reset.
+    pub fn new<I: interrupt::typelevel::Interrupt>(
+        peri: impl Peripheral<P = T> + 'd,
+        irq: impl interrupt::typelevel::Binding<I, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        T::enable_and_reset();
+        Self::new_shared(peri, irq)
+    }
+
+    /// Create a new HSEM driver, for a peripheral already enabled by another core with
+    /// [`new`](Self::new).
+    pub fn new_shared<I: interrupt::typelevel::Interrupt>(
+        peri: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<I, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        into_ref!(peri);
+
+        I::unpend();
+        unsafe { I::enable() };
+
+        Self { _peri: peri }
+    }
+
+    /// Lock a semaphore with the 2-step procedure, as process `process_id` of this core.
+    pub fn two_step_lock(&mut self, sem: usize, process_id: u8) -> Result<(), Error> {
+        let core = CoreId::current() as u8;
+        T::regs().r(sem).write(|w| {
+            w.set_procid(process_id);
+            w.set_coreid(core);
+            w.set_lock(true);
+        });
+
+        let r = T::regs().r(sem).read();
+        if r.lock() && r.coreid() == core && r.procid() == process_id {
+            Ok(())
+        } else {
+            Err(Error::Locked)
+        }
+    }
+
+    /// Lock a semaphore with the 1-step procedure, as process 0 of this core.
+    pub fn one_step_lock(&mut self, sem: usize) -> Result<(), Error> {
+        let core = CoreId::current() as u8;
+        let r = T::regs().rlr(sem).read();
+        if r.lock() && r.coreid() == core && r.procid() == 0 {
+            Ok(())
+        } else {
+            Err(Error::Locked)
+        }
+    }
+
+    /// Unlock a semaphore locked by process `process_id` of this core.
+    ///
+    /// This raises the unlock interrupt on the cores waiting for the semaphore.
+    pub fn unlock(&mut self, sem: usize, process_id: u8) {
+        let core = CoreId::current() as u8;
+        T::regs().r(sem).write(|w| {
+            w.set_procid(process_id);
+            w.set_coreid(core);
+            w.set_lock(false);
+        });
+    }
+
+    /// Return whether a semaphore is locked.
+    pub fn is_locked(&self, sem: usize) -> bool {
+        T::regs().r(sem).read().lock()
+    }
+
+    /// Wake the tasks of other cores waiting on a semaphore with [`wait_unlocked`](Self::wait_unlocked),
+    /// by locking and unlocking it.
+    pub fn notify(&mut self, sem: usize) -> Result<(), Error> {
+        self.one_step_lock(sem)?;
+        self.unlock(sem, 0);
+        Ok(())
+    }
+
+    /// Wait for a semaphore to be unlocked by another core.
+    ///
+    /// Returns right away if the semaphore is not locked, unless `wait_for_notify` is set, in
+    /// which case this waits for the next unlock, such as the one done by [`notify`](Self::notify).
+    pub async fn wait_unlocked(&mut self, sem: usize, wait_for_notify: bool) {
+        let core = CoreId::current().index();
+        let regs = T::regs();
+
+        regs.icr(core).write(|w| w.set_isc(sem, true));
+        if !wait_for_notify && !self.is_locked(sem) {
+            return;
+        }
+
+        let mut armed = false;
+        poll_fn(|cx| {
+            T::state().wakers[sem].register(cx.waker());
+
+            if armed {
+                // The interrupt handler disables the interrupt when it fires.
+                if !regs.ier(core).read().ise(sem) {
+                    return Poll::Ready(());
+                }
+            } else {
+                armed = true;
+                regs.ier(core).modify(|w| w.set_ise(sem, true));
+
+                // The semaphore may have been unlocked before the interrupt was enabled.
+                if !wait_for_notify && !self.is_locked(sem) {
+                    regs.ier(core).modify(|w| w.set_ise(sem, false));
+                    return Poll::Ready(());
+                }
+            }
+
+            Poll::Pending
+        })
+        .await;
+    }
+}
+
+impl<'d, T: Instance> Drop for HardwareSemaphore<'d, T> {
+    fn drop(&mut self) {
+        let core = CoreId::current().index();
+        T::regs().ier(core).write(|_| {});
+    }
+}
+
+pub(crate) mod sealed {
+    use embassy_sync::waitqueue::AtomicWaker;
+
+    use super::SEMAPHORE_COUNT;
+
+    pub struct State {
+        pub wakers: [AtomicWaker; SEMAPHORE_COUNT],
+    }
+
+    impl State {
+        pub const fn new() -> Self {
+            const WAKER: AtomicWaker = AtomicWaker::new();
+            Self {
+                wakers: [WAKER; SEMAPHORE_COUNT],
+            }
+        }
+    }
+
+    pub trait Instance {
+        fn regs() -> crate::pac::hsem::Hsem;
+        fn state() -> &'static State;
+    }
+}
+
+/// HSEM instance trait.
+pub trait Instance: sealed::Instance + crate::rcc::RccPeripheral + 'static + Send {}
+
+foreach_peripheral!(
+    (hsem, $inst:ident) => {
+        impl sealed::Instance for crate::peripherals::$inst {
+            fn regs() -> crate::pac::hsem::Hsem {
+                crate::pac::$inst
+            }
+
+            fn state() -> &'static sealed::State {
+                static STATE: sealed::State = sealed::State::new();
+                &STATE
+            }
+        }
+
+        impl Instance for crate::peripherals::$inst {}
+    };
+);
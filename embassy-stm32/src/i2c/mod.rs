@@ -5,6 +5,9 @@
 #[cfg_attr(i2c_v2, path = "v2.rs")]
 mod _version;
 
+#[cfg(i2c_v2)]
+pub use _version::{SlaveCommand, SlaveCommandKind};
+
 use core::future::Future;
 use core::marker::PhantomData;
 
@@ -14,8 +17,8 @@ use embassy_sync::waitqueue::AtomicWaker;
 use embassy_time::{Duration, Instant};
 
 use crate::dma::NoDma;
-use crate::gpio::sealed::AFType;
-use crate::gpio::Pull;
+use crate::gpio::sealed::{AFType, Pin as _};
+use crate::gpio::{AnyPin, Flex, Pin as _, Pull, Speed};
 use crate::interrupt::typelevel::Interrupt;
 use crate::time::Hertz;
 use crate::{interrupt, peripherals};
@@ -73,6 +76,12 @@ impl Default for Config {
 /// I2C driver.
 pub struct I2c<'d, T: Instance, TXDMA = NoDma, RXDMA = NoDma> {
     _peri: PeripheralRef<'d, T>,
+    scl: PeripheralRef<'d, AnyPin>,
+    scl_af: u8,
+    sda: PeripheralRef<'d, AnyPin>,
+    sda_af: u8,
+    freq: Hertz,
+    config: Config,
     #[allow(dead_code)]
     tx_dma: PeripheralRef<'d, TXDMA>,
     #[allow(dead_code)]
@@ -121,17 +130,117 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
 
         let mut this = Self {
             _peri: peri,
+            scl_af: scl.af_num(),
+            scl: scl.map_into(),
+            sda_af: sda.af_num(),
+            sda: sda.map_into(),
+            freq,
+            config,
             tx_dma,
             rx_dma,
             #[cfg(feature = "time")]
             timeout: config.timeout,
         };
 
+        // A slave may still be holding SDA low if the MCU was reset in the middle of a
+        // transaction.
+        if this.sda_is_low() {
+            let _ = this.recover_bus();
+        }
+
         this.init(freq, config);
 
         this
     }
 
+    /// Recover a bus stuck by a slave holding SDA low.
+    ///
+    /// SCL is clocked by hand, up to 9 times, until the slave releases SDA, then a STOP condition
+    /// is sent and the peripheral is reinitialized. Call this after a transaction failed with
+    /// [`Error::Timeout`] or [`Error::Bus`].
+    ///
+    /// Returns [`Error::Bus`] if SDA or SCL are still held low afterwards.
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        T::regs().cr1().modify(|w| w.set_pe(false));
+
+        let scl_pull = if self.config.scl_pullup { Pull::Up } else { Pull::None };
+        let sda_pull = if self.config.sda_pullup { Pull::Up } else { Pull::None };
+
+        let released = {
+            let mut scl = Flex::new(&mut *self.scl);
+            let mut sda = Flex::new(&mut *self.sda);
+            scl.set_high();
+            sda.set_high();
+            scl.set_as_input_output(Speed::Low, scl_pull);
+            sda.set_as_input_output(Speed::Low, sda_pull);
+
+            let half_period = || Self::bus_delay_us(5);
+            // Let a stretching slave release the clock, bounded to a few milliseconds.
+            let wait_scl_high = |scl: &Flex| {
+                for _ in 0..1000 {
+                    if scl.is_high() {
+                        break;
+                    }
+                    Self::bus_delay_us(5);
+                }
+            };
+
+            half_period();
+            for _ in 0..9 {
+                if sda.is_high() {
+                    break;
+                }
+                scl.set_low();
+                half_period();
+                scl.set_high();
+                wait_scl_high(&scl);
+                half_period();
+            }
+
+            // STOP condition: SDA rising while SCL is high.
+            scl.set_low();
+            half_period();
+            sda.set_low();
+            half_period();
+            scl.set_high();
+            wait_scl_high(&scl);
+            half_period();
+            sda.set_high();
+            half_period();
+
+            scl.is_high() && sda.is_high()
+        };
+
+        self.scl.set_as_af_pull(self.scl_af, AFType::OutputOpenDrain, scl_pull);
+        self.sda.set_as_af_pull(self.sda_af, AFType::OutputOpenDrain, sda_pull);
+
+        // The peripheral may still consider the bus busy, reset it.
+        #[cfg(i2c_v1)]
+        {
+            T::regs().cr1().modify(|w| w.set_swrst(true));
+            T::regs().cr1().modify(|w| w.set_swrst(false));
+        }
+        self.init(self.freq, self.config);
+
+        if released {
+            Ok(())
+        } else {
+            Err(Error::Bus)
+        }
+    }
+
+    fn sda_is_low(&self) -> bool {
+        let state = self.sda.block().idr().read().idr(self.sda.pin() as _);
+        state == crate::pac::gpio::vals::Idr::LOW
+    }
+
+    fn bus_delay_us(us: u32) {
+        #[cfg(feature = "time")]
+        embassy_time::block_for(Duration::from_micros(us as u64));
+        #[cfg(not(feature = "time"))]
+        cortex_m::asm::delay(unsafe { crate::rcc::get_freqs() }.sys.unwrap().0 / 1_000_000 * us);
+    }
+
     fn timeout(&self) -> Timeout {
         Timeout {
             #[cfg(feature = "time")]
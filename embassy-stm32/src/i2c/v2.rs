@@ -17,10 +17,21 @@ pub(crate) unsafe fn on_interrupt<T: Instance>() {
     if isr.tcr() || isr.tc() {
         T::state().waker.wake();
     }
+    if isr.addr() || isr.rxne() || isr.txis() || isr.stopf() || isr.nackf() || isr.berr() || isr.arlo() || isr.ovr() {
+        T::state().waker.wake();
+    }
     // The flag can only be cleared by writting to nbytes, we won't do that here, so disable
     // the interrupt
     critical_section::with(|_| {
-        regs.cr1().modify(|w| w.set_tcie(false));
+        regs.cr1().modify(|w| {
+            w.set_tcie(false);
+            // Slave mode flags are handled by the waiting task.
+            w.set_addrie(false);
+            w.set_rxie(false);
+            w.set_txie(false);
+            w.set_stopie(false);
+            w.set_nackie(false);
+        });
     });
 }
 
@@ -658,6 +669,197 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
     }
 }
 
+/// Direction of a transaction addressed to this device, as seen from the bus master.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveCommandKind {
+    /// The master writes to this device, answer with [`I2c::respond_to_write`].
+    Write,
+    /// The master reads from this device, answer with [`I2c::respond_to_read`].
+    Read,
+}
+
+/// Transaction addressed to this device, returned by [`I2c::listen`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SlaveCommand {
+    /// Direction of the transaction.
+    pub kind: SlaveCommandKind,
+    /// Address that matched (7 bit).
+    pub address: u8,
+}
+
+impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
+    /// Set the 7 bit address this device responds to as a slave, or `None` to stop responding.
+    ///
+    /// Master transfers keep working while an own address is set.
+    pub fn set_own_address(&mut self, address: Option<u8>) {
+        let regs = T::regs();
+        // OA1 can only be written while OA1EN is cleared.
+        regs.oar1().modify(|w| w.set_oa1en(false));
+        if let Some(address) = address {
+            regs.oar1().write(|w| {
+                w.set_oa1((address << 1) as u16);
+                w.set_oa1mode(i2c::vals::Addmode::BIT7);
+                w.set_oa1en(true);
+            });
+        }
+    }
+
+    /// Enable or disable clock stretching in slave mode.
+    ///
+    /// Clock stretching is enabled by default, and is what lets [`listen`](Self::listen) hand the
+    /// transaction over to the application before the master continues. Without it, data must
+    /// be ready as soon as the device is addressed, or the transaction fails with an overrun.
+    pub fn set_clock_stretching(&mut self, enabled: bool) {
+        let regs = T::regs();
+        regs.cr1().modify(|w| w.set_pe(false));
+        regs.cr1().modify(|w| {
+            w.set_nostretch(!enabled);
+            w.set_pe(true);
+        });
+    }
+
+    /// Wait until the master addresses this device.
+    ///
+    /// The clock is stretched until the transaction is answered with
+    /// [`respond_to_write`](Self::respond_to_write) or [`respond_to_read`](Self::respond_to_read).
+    pub async fn listen(&mut self) -> Result<SlaveCommand, Error> {
+        self.slave_wait(|isr| isr.addr()).await?;
+
+        let isr = T::regs().isr().read();
+        let kind = match isr.dir() {
+            i2c::vals::Dir::WRITE => SlaveCommandKind::Write,
+            i2c::vals::Dir::READ => SlaveCommandKind::Read,
+        };
+
+        Ok(SlaveCommand {
+            kind,
+            address: isr.addcode(),
+        })
+    }
+
+    /// Receive the data of a write transaction into `buffer`.
+    ///
+    /// Returns the number of bytes received, once the master sends a STOP or a repeated START.
+    /// Bytes beyond the end of `buffer` are NACKed.
+    pub async fn respond_to_write(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let regs = T::regs();
+        regs.icr().write(|w| w.set_addrcf(true));
+
+        let mut len = 0;
+        loop {
+            self.slave_wait(|isr| isr.rxne() || isr.stopf() || isr.addr()).await?;
+
+            let isr = regs.isr().read();
+            if isr.rxne() {
+                let byte = regs.rxdr().read().rxdata();
+                if len < buffer.len() {
+                    buffer[len] = byte;
+                    len += 1;
+                } else {
+                    regs.cr2().modify(|w| w.set_nack(true));
+                }
+                continue;
+            }
+
+            if isr.stopf() {
+                regs.icr().write(|w| w.set_stopcf(true));
+            }
+            // A repeated START leaves ADDR set, to be picked up by the next `listen`.
+            return Ok(len);
+        }
+    }
+
+    /// Send `buffer` as the data of a read transaction.
+    ///
+    /// If the master reads more than `buffer.len()` bytes, `0xFF` is sent for the extra bytes.
+    /// Returns the number of bytes from `buffer` that were sent, once the master NACKs and ends
+    /// the transaction.
+    pub async fn respond_to_read(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        let regs = T::regs();
+
+        // Discard data left over from a previous transaction.
+        regs.isr().write(|w| w.set_txe(true));
+        regs.icr().write(|w| w.set_addrcf(true));
+
+        let mut len = 0;
+        loop {
+            self.slave_wait(|isr| isr.txis() || isr.nackf() || isr.stopf()).await?;
+
+            let isr = regs.isr().read();
+            if isr.nackf() || isr.stopf() {
+                if isr.nackf() {
+                    // The last byte loaded in TXDR was never sent.
+                    regs.icr().write(|w| w.set_nackcf(true));
+                    len = len.saturating_sub(1);
+                }
+                if isr.stopf() {
+                    regs.icr().write(|w| w.set_stopcf(true));
+                    return Ok(len.min(buffer.len()));
+                }
+                continue;
+            }
+
+            let byte = buffer.get(len).copied().unwrap_or(0xFF);
+            regs.txdr().write(|w| w.set_txdata(byte));
+            len += 1;
+        }
+    }
+
+    async fn slave_wait(&mut self, f: impl Fn(i2c::regs::Isr) -> bool) -> Result<(), Error> {
+        let state = T::state();
+
+        let on_drop = OnDrop::new(|| {
+            T::regs().cr1().modify(|w| {
+                w.set_addrie(false);
+                w.set_rxie(false);
+                w.set_txie(false);
+                w.set_stopie(false);
+                w.set_nackie(false);
+            })
+        });
+
+        let res = poll_fn(|cx| {
+            state.waker.register(cx.waker());
+
+            let regs = T::regs();
+            let isr = regs.isr().read();
+            if isr.berr() {
+                regs.icr().write(|w| w.set_berrcf(true));
+                return Poll::Ready(Err(Error::Bus));
+            }
+            if isr.arlo() {
+                regs.icr().write(|w| w.set_arlocf(true));
+                return Poll::Ready(Err(Error::Arbitration));
+            }
+            if isr.ovr() {
+                regs.icr().write(|w| w.set_ovrcf(true));
+                return Poll::Ready(Err(Error::Overrun));
+            }
+            if f(isr) {
+                return Poll::Ready(Ok(()));
+            }
+
+            // The interrupt handler disables these again before waking us up.
+            critical_section::with(|_| {
+                regs.cr1().modify(|w| {
+                    w.set_addrie(true);
+                    w.set_rxie(true);
+                    w.set_txie(true);
+                    w.set_stopie(true);
+                    w.set_nackie(true);
+                })
+            });
+            Poll::Pending
+        })
+        .await;
+
+        drop(on_drop);
+        res
+    }
+}
+
 impl<'d, T: Instance, TXDMA, RXDMA> Drop for I2c<'d, T, TXDMA, RXDMA> {
     fn drop(&mut self) {
         T::disable();
@@ -792,6 +994,7 @@ impl<'d, T: Instance> SetConfig for I2c<'d, T> {
     type Config = Hertz;
     type ConfigError = ();
     fn set_config(&mut self, config: &Self::Config) -> Result<(), ()> {
+        self.freq = *config;
         let timings = Timings::new(T::frequency(), *config);
         T::regs().timingr().write(|reg| {
             reg.set_presc(timings.prescale);
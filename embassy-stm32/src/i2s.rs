@@ -1,6 +1,9 @@
 //! Inter-IC Sound (I2S)
 use embassy_hal_internal::into_ref;
 
+#[cfg(any(spi_v1, spi_f1))]
+use crate::dma::{ringbuffer, NoDma, ReadableRingBuffer, WritableRingBuffer};
+
 use crate::gpio::sealed::{AFType, Pin as _};
 use crate::gpio::AnyPin;
 use crate::pac::spi::vals;
@@ -327,3 +330,172 @@ fn compute_baud_rate(i2s_clock: Hertz, request_freq: Hertz, mclk: bool, data_for
         ((division & 1) == 1, (division >> 1) as u8)
     }
 }
+
+#[cfg(any(spi_v1, spi_f1))]
+impl From<ringbuffer::OverrunError> for Error {
+    fn from(_: ringbuffer::OverrunError) -> Self {
+        Self::Overrun
+    }
+}
+
+#[cfg(any(spi_v1, spi_f1))]
+enum RingBuffer<'d> {
+    Writable(WritableRingBuffer<'d, u16>),
+    Readable(ReadableRingBuffer<'d, u16>),
+}
+
+/// I2S driver streaming audio through a circular DMA buffer.
+///
+/// The DMA runs continuously over `dma_buf`, which acts as a double buffer: one half is
+/// transferred while the other is being filled (or drained) by [`write`](Self::write) or
+/// [`read`](Self::read), so there are no gaps in the audio stream as long as the application
+/// keeps up.
+///
+/// Samples are 16-bit half-words, 24 and 32-bit formats take two half-words per sample, most
+/// significant half first.
+#[cfg(any(spi_v1, spi_f1))]
+pub struct RingBufferedI2S<'d, T: Instance> {
+    _i2s: I2S<'d, T, NoDma, NoDma>,
+    ring_buffer: RingBuffer<'d>,
+}
+
+#[cfg(any(spi_v1, spi_f1))]
+impl<'d, T: Instance> RingBufferedI2S<'d, T> {
+    /// Create a new ring-buffered I2S transmitter.
+    ///
+    /// `config.function` is ignored. Call [`start`](Self::start) to start the stream, after
+    /// optionally pre-filling it with [`write_immediate`](Self::write_immediate).
+    pub fn new_tx(
+        peri: impl Peripheral<P = T> + 'd,
+        sd: impl Peripheral<P = impl MosiPin<T>> + 'd,
+        ws: impl Peripheral<P = impl WsPin<T>> + 'd,
+        ck: impl Peripheral<P = impl CkPin<T>> + 'd,
+        mck: impl Peripheral<P = impl MckPin<T>> + 'd,
+        txdma: impl Peripheral<P = impl TxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        freq: Hertz,
+        mut config: Config,
+    ) -> Self {
+        into_ref!(txdma);
+
+        config.function = Function::Transmit;
+        let i2s = I2S::new(peri, sd, ws, ck, mck, NoDma, NoDma, freq, config);
+
+        let request = txdma.request();
+        let ring_buffer = unsafe {
+            WritableRingBuffer::new(txdma, request, T::REGS.tx_ptr(), dma_buf, Default::default())
+        };
+        set_txdmaen(T::REGS, true);
+
+        Self {
+            _i2s: i2s,
+            ring_buffer: RingBuffer::Writable(ring_buffer),
+        }
+    }
+
+    /// Create a new ring-buffered I2S receiver.
+    ///
+    /// `config.function` is ignored. Call [`start`](Self::start) to start the stream.
+    pub fn new_rx(
+        peri: impl Peripheral<P = T> + 'd,
+        sd: impl Peripheral<P = impl MosiPin<T>> + 'd,
+        ws: impl Peripheral<P = impl WsPin<T>> + 'd,
+        ck: impl Peripheral<P = impl CkPin<T>> + 'd,
+        mck: impl Peripheral<P = impl MckPin<T>> + 'd,
+        rxdma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        freq: Hertz,
+        mut config: Config,
+    ) -> Self {
+        into_ref!(rxdma);
+
+        config.function = Function::Receive;
+        let i2s = I2S::new(peri, sd, ws, ck, mck, NoDma, NoDma, freq, config);
+
+        let request = rxdma.request();
+        let ring_buffer = unsafe {
+            ReadableRingBuffer::new(rxdma, request, T::REGS.rx_ptr(), dma_buf, Default::default())
+        };
+        set_rxdmaen(T::REGS, true);
+
+        Self {
+            _i2s: i2s,
+            ring_buffer: RingBuffer::Readable(ring_buffer),
+        }
+    }
+
+    /// Start the audio stream.
+    pub fn start(&mut self) {
+        match &mut self.ring_buffer {
+            RingBuffer::Writable(rb) => rb.start(),
+            RingBuffer::Readable(rb) => rb.start(),
+        }
+    }
+
+    /// Write samples directly to the DMA buffer, without waiting.
+    ///
+    /// This can be used to pre-fill the buffer before calling [`start`](Self::start). Returns
+    /// the number of samples written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a receiver.
+    pub fn write_immediate(&mut self, samples: &[i16]) -> Result<usize, Error> {
+        match &mut self.ring_buffer {
+            RingBuffer::Writable(rb) => Ok(rb.write_immediate(as_u16(samples))?.0),
+            RingBuffer::Readable(_) => panic!("write_immediate called on an I2S receiver"),
+        }
+    }
+
+    /// Write samples to the stream.
+    ///
+    /// If there's not enough free space in the DMA buffer, this waits until there is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a receiver.
+    pub async fn write(&mut self, samples: &[i16]) -> Result<(), Error> {
+        match &mut self.ring_buffer {
+            RingBuffer::Writable(rb) => {
+                rb.write_exact(as_u16(samples)).await?;
+                Ok(())
+            }
+            RingBuffer::Readable(_) => panic!("write called on an I2S receiver"),
+        }
+    }
+
+    /// Read samples from the stream.
+    ///
+    /// If there are less than `samples.len()` samples in the DMA buffer, this waits until there
+    /// are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a transmitter.
+    pub async fn read(&mut self, samples: &mut [i16]) -> Result<(), Error> {
+        match &mut self.ring_buffer {
+            RingBuffer::Readable(rb) => {
+                // Safety: i16 and u16 have the same layout.
+                let samples =
+                    unsafe { core::slice::from_raw_parts_mut(samples.as_mut_ptr() as *mut u16, samples.len()) };
+                rb.read_exact(samples).await?;
+                Ok(())
+            }
+            RingBuffer::Writable(_) => panic!("read called on an I2S transmitter"),
+        }
+    }
+}
+
+#[cfg(any(spi_v1, spi_f1))]
+fn as_u16(samples: &[i16]) -> &[u16] {
+    // Safety: i16 and u16 have the same layout.
+    unsafe { core::slice::from_raw_parts(samples.as_ptr() as *const u16, samples.len()) }
+}
+
+#[cfg(any(spi_v1, spi_f1))]
+impl<'d, T: Instance> Drop for RingBufferedI2S<'d, T> {
+    fn drop(&mut self) {
+        set_txdmaen(T::REGS, false);
+        set_rxdmaen(T::REGS, false);
+    }
+}
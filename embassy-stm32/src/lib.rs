@@ -23,6 +23,7 @@ pub mod dma;
 pub mod gpio;
 pub mod rcc;
 #[cfg(feature = "_time-driver")]
+#[cfg_attr(any(time_driver_lptim1, time_driver_lptim2), path = "time_driver_lptim.rs")]
 mod time_driver;
 pub mod timer;
 
@@ -40,6 +41,8 @@ pub mod cryp;
 pub mod dac;
 #[cfg(dcmi)]
 pub mod dcmi;
+#[cfg(all(dfsdm, not(gpdma)))]
+pub mod dfsdm;
 #[cfg(eth)]
 pub mod eth;
 #[cfg(feature = "exti")]
@@ -51,6 +54,8 @@ pub mod fmc;
 pub mod hash;
 #[cfg(hrtim)]
 pub mod hrtim;
+#[cfg(hsem)]
+pub mod hsem;
 #[cfg(i2c)]
 pub mod i2c;
 #[cfg(all(spi_v1, rcc_f4))]
@@ -69,6 +74,8 @@ pub mod rng;
 pub mod rtc;
 #[cfg(sai)]
 pub mod sai;
+#[cfg(sdadc)]
+pub mod sdadc;
 #[cfg(sdmmc)]
 pub mod sdmmc;
 #[cfg(spi)]
@@ -5,6 +5,9 @@
 //! can use knowledge of which peripherals are currently blocked upon to transparently and safely
 //! enter such low-power modes (currently, only `STOP2`) when idle.
 //!
+//! On STM32L4, STM32U5 and STM32WB, the clock configuration (HSE and PLLs) is restored
+//! automatically when leaving `STOP2`.
+//!
 //! The executor determines which peripherals are active by their RCC state; consequently,
 //! low-power states can only be entered if all peripherals have been `drop`'d. There are a few
 //! exceptions to this rule:
@@ -16,6 +19,10 @@
 //! low-power executor will only attempt to enter when the next timer event is at least
 //! [`time_driver::MIN_STOP_PAUSE`] in the future.
 //!
+//! The RTC alarms ([`Rtc::wait_for_alarm`](crate::rtc::Rtc::wait_for_alarm)) keep working in
+//! `STOP2` and wake the core, so a task can sleep until a calendar time while the chip stays in
+//! its lowest power mode.
+//!
 //! Currently there is no macro analogous to `embassy_executor::main` for this executor;
 //! consequently one must define their entrypoint manually. Moveover, you must relinquish control
 //! of the `RTC` peripheral to the executor. This will typically look like
@@ -72,7 +79,8 @@ foreach_interrupt! {
         #[interrupt]
         #[allow(non_snake_case)]
         unsafe fn $irq() {
-            EXECUTOR.as_mut().unwrap().on_wakeup_irq();
+            // Also handles the RTC alarms on chips where they share the wakeup interrupt.
+            crate::rtc::on_interrupt();
         }
     };
 }
@@ -109,10 +117,10 @@ pub enum StopMode {
     Stop2,
 }
 
-#[cfg(stm32l5)]
+#[cfg(any(stm32l4, stm32l5, stm32u5, stm32wb))]
 use stm32_metapac::pwr::vals::Lpms;
 
-#[cfg(stm32l5)]
+#[cfg(any(stm32l4, stm32l5, stm32u5, stm32wb))]
 impl Into<Lpms> for StopMode {
     fn into(self) -> Lpms {
         match self {
@@ -157,6 +165,9 @@ impl Executor {
     }
 
     unsafe fn on_wakeup_irq(&mut self) {
+        #[cfg(any(stm32l4, stm32u5, stm32wb))]
+        critical_section::with(|cs| clocks::restore(cs));
+
         self.time_driver.resume_time();
         trace!("low power: resume");
     }
@@ -181,8 +192,11 @@ impl Executor {
 
     #[allow(unused_variables)]
     fn configure_stop(&mut self, stop_mode: StopMode) {
-        #[cfg(stm32l5)]
+        #[cfg(any(stm32l4, stm32l5, stm32u5, stm32wb))]
         crate::pac::PWR.cr1().modify(|m| m.set_lpms(stop_mode.into()));
+
+        #[cfg(any(stm32l4, stm32u5, stm32wb))]
+        critical_section::with(|cs| clocks::save(cs));
     }
 
     fn configure_pwr(&mut self) {
@@ -234,7 +248,89 @@ impl Executor {
                 EXECUTOR.as_mut().unwrap().inner.poll();
                 self.configure_pwr();
                 asm!("wfe");
+
+                // The core may have been woken by an interrupt that doesn't go through
+                // `on_wakeup_irq`.
+                #[cfg(any(stm32l4, stm32u5, stm32wb))]
+                critical_section::with(|cs| clocks::restore(cs));
             };
         }
     }
 }
+
+/// Clock tree restoration after STOP2.
+///
+/// The core wakes up from STOP modes running from MSI (or HSI16), with HSE and the PLLs
+/// disabled. The oscillator and system clock configuration is saved before entering STOP and
+/// re-applied on wake up, so peripherals keep running at the frequencies configured in
+/// [`crate::rcc::Config`]. Flash wait states and voltage scaling are retained in STOP2.
+#[cfg(any(stm32l4, stm32u5, stm32wb))]
+mod clocks {
+    use core::cell::Cell;
+
+    use critical_section::{CriticalSection, Mutex};
+
+    use crate::pac::rcc::regs::Cr;
+    #[cfg(not(stm32u5))]
+    use crate::pac::rcc::regs::Cfgr;
+    #[cfg(stm32u5)]
+    use crate::pac::rcc::regs::Cfgr1 as Cfgr;
+    use crate::pac::RCC;
+
+    static SAVED: Mutex<Cell<Option<(Cr, Cfgr)>>> = Mutex::new(Cell::new(None));
+
+    #[cfg(not(stm32u5))]
+    fn cfgr() -> crate::pac::common::Reg<Cfgr, crate::pac::common::RW> {
+        RCC.cfgr()
+    }
+
+    #[cfg(stm32u5)]
+    fn cfgr() -> crate::pac::common::Reg<Cfgr, crate::pac::common::RW> {
+        RCC.cfgr1()
+    }
+
+    pub(super) fn save(cs: CriticalSection) {
+        SAVED.borrow(cs).set(Some((RCC.cr().read(), cfgr().read())));
+    }
+
+    pub(super) fn restore(cs: CriticalSection) {
+        let Some((cr, saved_cfgr)) = SAVED.borrow(cs).take() else {
+            return;
+        };
+
+        if cr.hsion() {
+            RCC.cr().modify(|w| w.set_hsion(true));
+            while !RCC.cr().read().hsirdy() {}
+        }
+
+        if cr.hseon() {
+            RCC.cr().modify(|w| w.set_hseon(true));
+            while !RCC.cr().read().hserdy() {}
+        }
+
+        #[cfg(not(stm32u5))]
+        {
+            if cr.pllon() {
+                RCC.cr().modify(|w| w.set_pllon(true));
+                while !RCC.cr().read().pllrdy() {}
+            }
+
+            if cr.pllsai1on() {
+                RCC.cr().modify(|w| w.set_pllsai1on(true));
+                while !RCC.cr().read().pllsai1rdy() {}
+            }
+        }
+
+        #[cfg(stm32u5)]
+        for n in 0..3 {
+            if cr.pllon(n) {
+                RCC.cr().modify(|w| w.set_pllon(n, true));
+                while !RCC.cr().read().pllrdy(n) {}
+            }
+        }
+
+        let sw = saved_cfgr.sw();
+        cfgr().modify(|w| w.set_sw(sw));
+        while cfgr().read().sws() != sw {}
+    }
+}
@@ -2,6 +2,8 @@
 #![macro_use]
 
 use embassy_hal_internal::{into_ref, PeripheralRef};
+#[cfg(opamp_g4)]
+use embedded_hal_02::blocking::delay::DelayUs;
 
 use crate::pac::opamp::vals::*;
 use crate::Peripheral;
@@ -35,6 +37,17 @@ impl From<OpAmpSpeed> for crate::pac::opamp::vals::Opahsm {
     }
 }
 
+/// Offset trim values of an opamp, see [`OpAmp::calibrate`].
+#[cfg(opamp_g4)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OpAmpTrim {
+    /// NMOS differential pair trim (TRIMOFFSETN).
+    pub n: u8,
+    /// PMOS differential pair trim (TRIMOFFSETP).
+    pub p: u8,
+}
+
 /// OpAmp external outputs, wired to a GPIO pad.
 ///
 /// This struct can also be used as an ADC input.
@@ -69,6 +82,72 @@ impl<'d, T: Instance> OpAmp<'d, T> {
         Self { _inner: opamp }
     }
 
+    /// Calibrate the input offset of the opamp, and keep using the resulting trim values.
+    ///
+    /// This follows the procedure from the reference manual: both differential pairs are
+    /// trimmed in turn by a binary search on the calibration comparator output. The opamp is
+    /// disabled and must not be in use while calibrating.
+    #[cfg(opamp_g4)]
+    pub fn calibrate(&mut self, delay: &mut impl DelayUs<u32>) -> OpAmpTrim {
+        let r = T::regs();
+
+        r.csr().modify(|w| {
+            w.set_usertrim(true);
+            w.set_calon(true);
+            w.set_opampen(true);
+        });
+
+        // NMOS pair, calibrated against 0.9 * VDDA.
+        r.csr().modify(|w| w.set_calsel(Calsel::from_bits(0b11)));
+        let n = Self::trim_search(delay, |trim| r.csr().modify(|w| w.set_trimoffsetn(trim)));
+
+        // PMOS pair, calibrated against 0.1 * VDDA.
+        r.csr().modify(|w| w.set_calsel(Calsel::from_bits(0b01)));
+        let p = Self::trim_search(delay, |trim| r.csr().modify(|w| w.set_trimoffsetp(trim)));
+
+        r.csr().modify(|w| {
+            w.set_calon(false);
+            w.set_opampen(false);
+        });
+
+        OpAmpTrim { n, p }
+    }
+
+    /// Apply previously stored trim values, for example from [`OpAmp::calibrate`].
+    #[cfg(opamp_g4)]
+    pub fn set_trim(&mut self, trim: OpAmpTrim) {
+        T::regs().csr().modify(|w| {
+            w.set_usertrim(true);
+            w.set_trimoffsetn(trim.n);
+            w.set_trimoffsetp(trim.p);
+        });
+    }
+
+    #[cfg(opamp_g4)]
+    fn trim_search(delay: &mut impl DelayUs<u32>, set_trim: impl Fn(u8)) -> u8 {
+        let mut trim = 16;
+        let mut delta = 8;
+
+        loop {
+            set_trim(trim);
+            // The calibration output needs up to 1 ms to settle after a trim change.
+            delay.delay_us(2_000);
+
+            if T::regs().csr().read().calout() {
+                trim += delta;
+            } else {
+                trim -= delta;
+            }
+
+            if delta == 0 {
+                break;
+            }
+            delta >>= 1;
+        }
+
+        trim.min(31)
+    }
+
     /// Configure the OpAmp as a buffer for the provided input pin,
     /// outputting to the provided output pin, and enable the opamp.
     ///
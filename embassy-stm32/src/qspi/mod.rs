@@ -3,9 +3,11 @@
 #![macro_use]
 
 pub mod enums;
+mod nor;
 
 use embassy_hal_internal::{into_ref, PeripheralRef};
 use enums::*;
+pub use nor::{Error, QspiNorFlash};
 
 use crate::dma::Transfer;
 use crate::gpio::sealed::AFType;
@@ -348,6 +350,101 @@ impl<'d, T: Instance, Dma> Qspi<'d, T, Dma> {
         transfer.blocking_wait();
     }
 
+    /// Read data, using DMA.
+    pub async fn read_dma(&mut self, buf: &mut [u8], transaction: TransferConfig)
+    where
+        Dma: QuadDma<T>,
+    {
+        self.setup_transaction(QspiMode::IndirectWrite, &transaction);
+
+        T::REGS.ccr().modify(|v| {
+            v.set_fmode(QspiMode::IndirectRead.into());
+        });
+        let current_ar = T::REGS.ar().read().address();
+        T::REGS.ar().write(|v| {
+            v.set_address(current_ar);
+        });
+
+        let request = self.dma.request();
+        let transfer = unsafe {
+            Transfer::new_read(
+                &mut self.dma,
+                request,
+                T::REGS.dr().as_ptr() as *mut u8,
+                buf,
+                Default::default(),
+            )
+        };
+
+        // STM32H7 does not have dmaen
+        #[cfg(not(stm32h7))]
+        T::REGS.cr().modify(|v| v.set_dmaen(true));
+
+        transfer.await;
+
+        while !T::REGS.sr().read().tcf() {}
+        T::REGS.fcr().modify(|v| v.set_ctcf(true));
+    }
+
+    /// Write data, using DMA.
+    pub async fn write_dma(&mut self, buf: &[u8], transaction: TransferConfig)
+    where
+        Dma: QuadDma<T>,
+    {
+        self.setup_transaction(QspiMode::IndirectWrite, &transaction);
+
+        T::REGS.ccr().modify(|v| {
+            v.set_fmode(QspiMode::IndirectWrite.into());
+        });
+
+        let request = self.dma.request();
+        let transfer = unsafe {
+            Transfer::new_write(
+                &mut self.dma,
+                request,
+                buf,
+                T::REGS.dr().as_ptr() as *mut u8,
+                Default::default(),
+            )
+        };
+
+        // STM32H7 does not have dmaen
+        #[cfg(not(stm32h7))]
+        T::REGS.cr().modify(|v| v.set_dmaen(true));
+
+        transfer.await;
+
+        while !T::REGS.sr().read().tcf() {}
+        T::REGS.fcr().modify(|v| v.set_ctcf(true));
+    }
+
+    /// Switch to memory-mapped mode, where the flash can be read (and executed from) directly
+    /// through the QUADSPI memory region, `0x9000_0000` on most chips.
+    ///
+    /// `transaction` is the read command issued for each access. Its address and data length
+    /// are ignored, they're taken from the access itself.
+    /// Indirect mode functions must not be used until [`disable_memory_mapped_mode`](Self::disable_memory_mapped_mode)
+    /// is called.
+    pub fn enable_memory_mapped_mode(&mut self, transaction: TransferConfig) {
+        #[cfg(not(stm32h7))]
+        T::REGS.cr().modify(|v| v.set_dmaen(false));
+
+        let transaction = TransferConfig {
+            address: None,
+            data_len: None,
+            ..transaction
+        };
+        self.setup_transaction(QspiMode::MemoryMapped, &transaction);
+    }
+
+    /// Leave memory-mapped mode, aborting any prefetch in progress, so indirect mode functions
+    /// can be used again.
+    pub fn disable_memory_mapped_mode(&mut self) {
+        T::REGS.cr().modify(|v| v.set_abort(true));
+        while T::REGS.cr().read().abort() {}
+        while T::REGS.sr().read().busy() {}
+    }
+
     fn setup_transaction(&mut self, fmode: QspiMode, transaction: &TransferConfig) {
         T::REGS.fcr().modify(|v| {
             v.set_csmf(true);
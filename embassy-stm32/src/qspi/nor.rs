@@ -0,0 +1,182 @@
+//! Async NOR flash access through the indirect mode, for standard serial NOR flash chips.
+use embassy_futures::yield_now;
+use embedded_storage::nor_flash::NorFlashErrorKind;
+use embedded_storage_async::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use super::enums::{DummyCycles, QspiWidth};
+use super::{Instance, QuadDma, Qspi, TransferConfig};
+
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_READ_STATUS: u8 = 0x05;
+const CMD_QUAD_OUTPUT_READ: u8 = 0x6B;
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_SECTOR_ERASE: u8 = 0x20;
+
+const STATUS_BUSY: u8 = 0x01;
+
+const PAGE_SIZE: usize = 256;
+const SECTOR_SIZE: usize = 4096;
+
+/// NOR flash error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The access is outside the flash capacity.
+    OutOfBounds,
+    /// The access isn't aligned to the erase sector size.
+    Unaligned,
+}
+
+impl embedded_storage::nor_flash::NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Self::Unaligned => NorFlashErrorKind::NotAligned,
+        }
+    }
+}
+
+/// Serial NOR flash on the QSPI bus, accessed in indirect mode with DMA.
+///
+/// Uses the command set common to serial NOR flash chips: quad output fast read (`0x6B`), single
+/// line page program (`0x02`) and 4 KiB sector erase (`0x20`), with 24-bit addresses. The QSPI
+/// driver must be configured with [`AddressSize::_24bit`](super::enums::AddressSize::_24bit).
+pub struct QspiNorFlash<'d, T: Instance, Dma> {
+    qspi: Qspi<'d, T, Dma>,
+    capacity: usize,
+}
+
+impl<'d, T: Instance, Dma: QuadDma<T>> QspiNorFlash<'d, T, Dma> {
+    /// Create a new NOR flash of `capacity` bytes.
+    pub fn new(qspi: Qspi<'d, T, Dma>, capacity: usize) -> Self {
+        Self { qspi, capacity }
+    }
+
+    /// Release the QSPI driver, for instance to switch to memory-mapped mode.
+    pub fn into_inner(self) -> Qspi<'d, T, Dma> {
+        self.qspi
+    }
+
+    fn check_bounds(&self, offset: u32, len: usize) -> Result<(), Error> {
+        if offset as usize + len > self.capacity {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    fn write_enable(&mut self) {
+        self.qspi.command(TransferConfig {
+            iwidth: QspiWidth::SING,
+            instruction: CMD_WRITE_ENABLE,
+            ..Default::default()
+        });
+    }
+
+    async fn wait_ready(&mut self) {
+        loop {
+            let mut status = [0; 1];
+            self.qspi.blocking_read(
+                &mut status,
+                TransferConfig {
+                    iwidth: QspiWidth::SING,
+                    dwidth: QspiWidth::SING,
+                    instruction: CMD_READ_STATUS,
+                    data_len: Some(1),
+                    ..Default::default()
+                },
+            );
+            if status[0] & STATUS_BUSY == 0 {
+                return;
+            }
+            yield_now().await;
+        }
+    }
+}
+
+impl<'d, T: Instance, Dma> ErrorType for QspiNorFlash<'d, T, Dma> {
+    type Error = Error;
+}
+
+impl<'d, T: Instance, Dma: QuadDma<T>> ReadNorFlash for QspiNorFlash<'d, T, Dma> {
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_bounds(offset, bytes.len())?;
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let transaction = TransferConfig {
+            iwidth: QspiWidth::SING,
+            awidth: QspiWidth::SING,
+            dwidth: QspiWidth::QUAD,
+            instruction: CMD_QUAD_OUTPUT_READ,
+            address: Some(offset),
+            dummy: DummyCycles::_8,
+            data_len: Some(bytes.len()),
+        };
+        self.qspi.read_dma(bytes, transaction).await;
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<'d, T: Instance, Dma: QuadDma<T>> NorFlash for QspiNorFlash<'d, T, Dma> {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check_bounds(offset, bytes.len())?;
+
+        let mut address = offset as usize;
+        let mut remaining = bytes;
+        while !remaining.is_empty() {
+            // Page programs wrap around at the page boundary, so split the write there.
+            let len = remaining.len().min(PAGE_SIZE - address % PAGE_SIZE);
+            let (chunk, rest) = remaining.split_at(len);
+
+            self.write_enable();
+            let transaction = TransferConfig {
+                iwidth: QspiWidth::SING,
+                awidth: QspiWidth::SING,
+                dwidth: QspiWidth::SING,
+                instruction: CMD_PAGE_PROGRAM,
+                address: Some(address as u32),
+                data_len: Some(len),
+                ..Default::default()
+            };
+            self.qspi.write_dma(chunk, transaction).await;
+            self.wait_ready().await;
+
+            address += len;
+            remaining = rest;
+        }
+        Ok(())
+    }
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if from > to {
+            return Err(Error::OutOfBounds);
+        }
+        self.check_bounds(from, (to - from) as usize)?;
+        if from as usize % SECTOR_SIZE != 0 || to as usize % SECTOR_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+
+        for address in (from..to).step_by(SECTOR_SIZE) {
+            self.write_enable();
+            self.qspi.command(TransferConfig {
+                iwidth: QspiWidth::SING,
+                awidth: QspiWidth::SING,
+                instruction: CMD_SECTOR_ERASE,
+                address: Some(address),
+                ..Default::default()
+            });
+            self.wait_ready().await;
+        }
+        Ok(())
+    }
+}
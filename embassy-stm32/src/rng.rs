@@ -3,6 +3,7 @@
 
 use core::future::poll_fn;
 use core::marker::PhantomData;
+use core::num::NonZeroU32;
 use core::task::Poll;
 
 use embassy_hal_internal::{into_ref, PeripheralRef};
@@ -118,21 +119,82 @@ impl<'d, T: Instance> Rng<'d, T> {
     }
 
     /// Try to recover from a seed error.
+    ///
+    /// Follows the procedure of the reference manual: clear the error, discard the words
+    /// already in the output pipeline, and only reset the RNG if the error persists.
     pub fn recover_seed_error(&mut self) {
-        self.reset();
-        // reset should also clear the SEIS flag
+        T::regs().sr().modify(|reg| reg.set_seis(false));
+        // the pipeline holds up to 12 words that may have been generated from the faulty seed
+        for _ in 0..12 {
+            let _ = T::regs().dr().read();
+        }
+
         if T::regs().sr().read().seis() {
-            warn!("recovering from seed error failed");
-            return;
+            self.reset();
+            // reset should also clear the SEIS flag
+            if T::regs().sr().read().seis() {
+                warn!("recovering from seed error failed");
+                return;
+            }
         }
         // wait for SECS to be cleared by RNG
         while T::regs().sr().read().secs() {}
     }
 
+    /// Read a random word if one is available, handling seed and clock errors.
+    fn try_read_word(&mut self) -> Option<Result<u32, Error>> {
+        let bits = T::regs().sr().read();
+        if bits.seis() {
+            // in case of noise-source or seed error we try to recover here
+            // but we must not use the data in DR and we return an error
+            // to leave retry-logic to the application
+            self.recover_seed_error();
+            Some(Err(Error::SeedError))
+        } else if bits.ceis() {
+            // clock error detected, DR could still be used but keep it safe,
+            // clear the error and abort
+            T::regs().sr().modify(|sr| sr.set_ceis(false));
+            Some(Err(Error::ClockError))
+        } else if bits.drdy() {
+            // DR can be read up to four times until the output buffer is empty
+            // DRDY is cleared automatically when that happens
+            let random_word = T::regs().dr().read();
+            // reference manual: always check if DR is zero
+            if random_word == 0 {
+                Some(Err(Error::SeedError))
+            } else {
+                Some(Ok(random_word))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Read a random word, blocking until one is available.
+    pub fn blocking_next_u32(&mut self) -> Result<u32, Error> {
+        loop {
+            if let Some(res) = self.try_read_word() {
+                return res;
+            }
+        }
+    }
+
+    /// Fill the given slice with random values, blocking until they are available.
+    pub fn blocking_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        for chunk in dest.chunks_mut(4) {
+            let random_word = self.blocking_next_u32()?;
+            for (dest, src) in chunk.iter_mut().zip(random_word.to_ne_bytes().iter()) {
+                *dest = *src
+            }
+        }
+
+        Ok(())
+    }
+
     /// Fill the given slice with random values.
     pub async fn async_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
         for chunk in dest.chunks_mut(4) {
-            let mut bits = T::regs().sr().read();
+            let bits = T::regs().sr().read();
             if !bits.seis() && !bits.ceis() && !bits.drdy() {
                 // wait for interrupt
                 poll_fn(|cx| {
@@ -153,29 +215,10 @@ impl<'d, T: Instance> Rng<'d, T> {
                     }
                 })
                 .await;
-
-                // Re-read the status register after wait.
-                bits = T::regs().sr().read()
             }
-            if bits.seis() {
-                // in case of noise-source or seed error we try to recover here
-                // but we must not use the data in DR and we return an error
-                // to leave retry-logic to the application
-                self.recover_seed_error();
-                return Err(Error::SeedError);
-            } else if bits.ceis() {
-                // clock error detected, DR could still be used but keep it safe,
-                // clear the error and abort
-                T::regs().sr().modify(|sr| sr.set_ceis(false));
-                return Err(Error::ClockError);
-            } else if bits.drdy() {
-                // DR can be read up to four times until the output buffer is empty
-                // DRDY is cleared automatically when that happens
-                let random_word = T::regs().dr().read();
-                // reference manual: always check if DR is zero
-                if random_word == 0 {
-                    return Err(Error::SeedError);
-                }
+
+            if let Some(res) = self.try_read_word() {
+                let random_word = res?;
                 // write bytes to chunk
                 for (dest, src) in chunk.iter_mut().zip(random_word.to_ne_bytes().iter()) {
                     *dest = *src
@@ -187,14 +230,22 @@ impl<'d, T: Instance> Rng<'d, T> {
     }
 }
 
+impl From<Error> for rand_core::Error {
+    fn from(err: Error) -> Self {
+        let code = match err {
+            Error::SeedError => 0,
+            Error::ClockError => 1,
+        };
+        unwrap!(NonZeroU32::new(rand_core::Error::CUSTOM_START + code)).into()
+    }
+}
+
 impl<'d, T: Instance> RngCore for Rng<'d, T> {
+    /// Read a random word, recovering from seed and clock errors until one is available.
     fn next_u32(&mut self) -> u32 {
         loop {
-            let sr = T::regs().sr().read();
-            if sr.seis() | sr.ceis() {
-                self.reset();
-            } else if sr.drdy() {
-                return T::regs().dr().read();
+            if let Ok(random_word) = self.blocking_next_u32() {
+                return random_word;
             }
         }
     }
@@ -215,8 +266,7 @@ impl<'d, T: Instance> RngCore for Rng<'d, T> {
     }
 
     fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
-        self.fill_bytes(dest);
-        Ok(())
+        self.blocking_fill_bytes(dest).map_err(Into::into)
     }
 }
 
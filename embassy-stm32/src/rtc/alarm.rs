@@ -0,0 +1,410 @@
+//! RTC alarms and periodic wakeup timer.
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::datetime::{day_of_week_to_u8, Error as DateTimeError};
+use super::{byte_to_bcd2, DayOfWeek, Rtc, RtcError};
+use crate::interrupt;
+use crate::interrupt::typelevel::Interrupt;
+use crate::peripherals::RTC;
+use crate::rtc::sealed::Instance;
+
+const NEW_AW: AtomicWaker = AtomicWaker::new();
+const NEW_PENDING: AtomicBool = AtomicBool::new(false);
+
+static ALARM_WAKERS: [AtomicWaker; 2] = [NEW_AW; 2];
+static ALARM_PENDING: [AtomicBool; 2] = [NEW_PENDING; 2];
+
+#[cfg(all(
+    not(feature = "low-power"),
+    any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5)
+))]
+static WAKEUP_WAKER: AtomicWaker = AtomicWaker::new();
+#[cfg(all(
+    not(feature = "low-power"),
+    any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5)
+))]
+static WAKEUP_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// EXTI lines the RTC alarm and wakeup events are routed through, on chips where these events
+/// only reach the NVIC (and wake the core from stop modes) through EXTI.
+#[cfg(any(stm32f0, stm32f3, stm32l0, stm32l1, stm32g4))]
+const EXTI_LINES: &[usize] = &[17, 20];
+#[cfg(any(stm32f2, stm32f4, stm32f7))]
+const EXTI_LINES: &[usize] = &[17, 22];
+#[cfg(stm32l4)]
+const EXTI_LINES: &[usize] = &[18, 20];
+#[cfg(any(stm32wb, stm32h7))]
+const EXTI_LINES: &[usize] = &[17, 19];
+#[cfg(not(any(
+    stm32f0, stm32f3, stm32l0, stm32l1, stm32g4, stm32f2, stm32f4, stm32f7, stm32l4, stm32wb, stm32h7
+)))]
+const EXTI_LINES: &[usize] = &[];
+
+/// RTC alarm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alarm {
+    /// Alarm A
+    A,
+    /// Alarm B
+    B,
+}
+
+impl Alarm {
+    fn index(&self) -> usize {
+        match self {
+            Alarm::A => 0,
+            Alarm::B => 1,
+        }
+    }
+}
+
+/// Day matched by an alarm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlarmDay {
+    /// Day of the month, `1..=31`.
+    Date(u8),
+    /// Day of the week.
+    Weekday(DayOfWeek),
+}
+
+/// Alarm configuration.
+///
+/// The alarm fires whenever all the fields that are `Some` match the calendar. Fields left to
+/// `None` are ignored, so for example only setting `second` to `Some(0)` fires the alarm once
+/// every minute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct AlarmConfig {
+    /// Day to match.
+    pub day: Option<AlarmDay>,
+    /// Hour to match, `0..=23`.
+    pub hour: Option<u8>,
+    /// Minute to match, `0..=59`.
+    pub minute: Option<u8>,
+    /// Second to match, `0..=59`.
+    pub second: Option<u8>,
+}
+
+/// RTC interrupt handler.
+///
+/// Bind this to the interrupt(s) the RTC alarm, wakeup timer and tamper events are routed to, for
+/// example `RTC_ALARM` and `RTC_WKUP` on STM32F4 or `RTC` on STM32L0, and pass the binding to
+/// [`Rtc::enable_interrupts`].
+///
+/// With the `low-power` feature, the wakeup interrupt is owned by the low-power executor, which
+/// also handles the alarms if they share the same interrupt.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl<I: interrupt::typelevel::Interrupt> interrupt::typelevel::Handler<I> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        on_interrupt()
+    }
+}
+
+pub(crate) unsafe fn on_interrupt() {
+    #[cfg(feature = "low-power")]
+    crate::low_power::on_wakeup_irq();
+
+    let regs = RTC::regs();
+    let cr = regs.cr().read();
+
+    for n in 0..2 {
+        #[cfg(any(
+            rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+        ))]
+        let fired = regs.isr().read().alrf(n);
+        #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+        let fired = regs.sr().read().alrf(n);
+
+        if fired && cr.alrie(n) {
+            // The status flags aren't write protected.
+            #[cfg(any(
+                rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+            ))]
+            regs.isr().modify(|w| w.set_alrf(n, false));
+            #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+            regs.scr().write(|w| w.set_calrf(n, crate::pac::rtc::vals::Calrf::CLEAR));
+
+            ALARM_PENDING[n].store(true, Ordering::Release);
+            ALARM_WAKERS[n].wake();
+        }
+    }
+
+    #[cfg(all(
+        not(feature = "low-power"),
+        any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5)
+    ))]
+    {
+        #[cfg(any(
+            rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+        ))]
+        let fired = regs.isr().read().wutf();
+        #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+        let fired = regs.sr().read().wutf();
+
+        if fired && cr.wutie() {
+            #[cfg(any(
+                rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+            ))]
+            regs.isr().modify(|w| w.set_wutf(false));
+            #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+            regs.scr().write(|w| w.set_cwutf(crate::pac::rtc::vals::Calrf::CLEAR));
+
+            WAKEUP_PENDING.store(true, Ordering::Release);
+            WAKEUP_WAKER.wake();
+        }
+    }
+
+    #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+    super::tamper::on_interrupt();
+
+    #[cfg(not(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50)))]
+    for &line in EXTI_LINES {
+        crate::pac::EXTI.pr(0).write(|w| w.set_line(line, true));
+    }
+}
+
+fn check_range(value: Option<u8>, max: u8, err: DateTimeError) -> Result<(), RtcError> {
+    match value {
+        Some(v) if v > max => Err(RtcError::InvalidDateTime(err)),
+        _ => Ok(()),
+    }
+}
+
+impl Rtc {
+    /// Enable the RTC interrupts, routing them through EXTI if needed.
+    ///
+    /// This is required for [`wait_for_alarm`](Self::wait_for_alarm) and
+    /// [`wait_for_wakeup`](Self::wait_for_wakeup), and allows the alarms to wake the core from
+    /// stop modes.
+    pub fn enable_interrupts<I: interrupt::typelevel::Interrupt>(
+        &mut self,
+        _irq: impl interrupt::typelevel::Binding<I, InterruptHandler>,
+    ) {
+        #[cfg(exti_w)]
+        let imr = crate::pac::EXTI.cpu(crate::pac::CORE_INDEX).imr(0);
+        #[cfg(not(exti_w))]
+        let imr = crate::pac::EXTI.imr(0);
+
+        critical_section::with(|_| {
+            for &line in EXTI_LINES {
+                crate::pac::EXTI.rtsr(0).modify(|w| w.set_line(line, true));
+                imr.modify(|w| w.set_line(line, true));
+            }
+        });
+
+        I::unpend();
+        unsafe { I::enable() };
+    }
+
+    /// Configure and enable an alarm.
+    ///
+    /// # Errors
+    ///
+    /// Will return `RtcError::InvalidDateTime` if a field of the configuration is out of range.
+    pub fn set_alarm(&mut self, alarm: Alarm, config: AlarmConfig) -> Result<(), RtcError> {
+        use crate::pac::rtc::vals::{AlrmrMsk, AlrmrPm, AlrmrWdsel};
+
+        check_range(config.hour, 23, DateTimeError::InvalidHour)?;
+        check_range(config.minute, 59, DateTimeError::InvalidMinute)?;
+        check_range(config.second, 59, DateTimeError::InvalidSecond)?;
+        if let Some(AlarmDay::Date(day)) = config.day {
+            if !(1..=31).contains(&day) {
+                return Err(RtcError::InvalidDateTime(DateTimeError::InvalidDay));
+            }
+        }
+
+        let mask = |v: bool| if v { AlrmrMsk::TOMATCH } else { AlrmrMsk::NOTMATCH };
+        let n = alarm.index();
+
+        self.write(false, |regs| {
+            regs.cr().modify(|w| {
+                w.set_alre(n, false);
+                w.set_alrie(n, false);
+            });
+
+            #[cfg(any(
+                rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+            ))]
+            while !regs.isr().read().alrwf(n) {}
+
+            regs.alrmr(n).write(|w| {
+                let (st, su) = byte_to_bcd2(config.second.unwrap_or(0));
+                w.set_msk1(mask(config.second.is_some()));
+                w.set_st(st);
+                w.set_su(su);
+
+                let (mnt, mnu) = byte_to_bcd2(config.minute.unwrap_or(0));
+                w.set_msk2(mask(config.minute.is_some()));
+                w.set_mnt(mnt);
+                w.set_mnu(mnu);
+
+                let (ht, hu) = byte_to_bcd2(config.hour.unwrap_or(0));
+                w.set_msk3(mask(config.hour.is_some()));
+                w.set_ht(ht);
+                w.set_hu(hu);
+                w.set_pm(AlrmrPm::AM);
+
+                w.set_msk4(mask(config.day.is_some()));
+                match config.day {
+                    Some(AlarmDay::Weekday(weekday)) => {
+                        w.set_wdsel(AlrmrWdsel::WEEKDAY);
+                        w.set_du(day_of_week_to_u8(weekday));
+                    }
+                    day => {
+                        let (dt, du) = byte_to_bcd2(match day {
+                            Some(AlarmDay::Date(day)) => day,
+                            _ => 1,
+                        });
+                        w.set_wdsel(AlrmrWdsel::DATEUNITS);
+                        w.set_dt(dt);
+                        w.set_du(du);
+                    }
+                }
+            });
+
+            #[cfg(any(
+                rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+            ))]
+            regs.isr().modify(|w| w.set_alrf(n, false));
+            #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+            regs.scr().write(|w| w.set_calrf(n, crate::pac::rtc::vals::Calrf::CLEAR));
+
+            ALARM_PENDING[n].store(false, Ordering::Relaxed);
+
+            regs.cr().modify(|w| {
+                w.set_alre(n, true);
+                w.set_alrie(n, true);
+            });
+        });
+
+        Ok(())
+    }
+
+    /// Disable an alarm.
+    pub fn disable_alarm(&mut self, alarm: Alarm) {
+        let n = alarm.index();
+
+        self.write(false, |regs| {
+            regs.cr().modify(|w| {
+                w.set_alre(n, false);
+                w.set_alrie(n, false);
+            });
+        });
+
+        ALARM_PENDING[n].store(false, Ordering::Relaxed);
+    }
+
+    /// Wait until the given alarm fires.
+    ///
+    /// Returns immediately if the alarm fired since it was configured or last waited for.
+    /// Requires the RTC interrupts to be enabled with [`enable_interrupts`](Self::enable_interrupts).
+    pub async fn wait_for_alarm(&self, alarm: Alarm) {
+        let n = alarm.index();
+
+        poll_fn(|cx| {
+            ALARM_WAKERS[n].register(cx.waker());
+
+            if ALARM_PENDING[n].swap(false, Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Start the periodic wakeup timer.
+    ///
+    /// Periods below 32 seconds use the RTC clock divided by 16, longer periods the 1 Hz calendar
+    /// clock, with up to 18 hours. Not available with the `low-power` feature, where the
+    /// low-power executor uses the wakeup timer itself.
+    #[cfg(all(
+        not(feature = "low-power"),
+        any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5)
+    ))]
+    pub fn enable_wakeup_timer(&mut self, period: core::time::Duration) {
+        use crate::pac::rtc::vals::Wucksel;
+
+        let rtc_hz = Self::frequency().0 as u128;
+        let div16_ticks = period.as_micros() * rtc_hz / 16 / 1_000_000;
+
+        let (wucksel, ticks) = if div16_ticks <= u16::MAX as u128 + 1 {
+            (super::WakeupPrescaler::Div16.into(), div16_ticks as u64)
+        } else {
+            (Wucksel::CLOCKSPARE, period.as_secs())
+        };
+        let ticks = ticks.clamp(1, u16::MAX as u64 + 1) - 1;
+
+        self.write(false, |regs| {
+            regs.cr().modify(|w| {
+                w.set_wute(false);
+                w.set_wutie(false);
+            });
+
+            #[cfg(any(rtc_v2f4, rtc_v2l0, rtc_v2l4, rtc_v2wb))]
+            {
+                regs.isr().modify(|w| w.set_wutf(false));
+                while !regs.isr().read().wutwf() {}
+            }
+
+            #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+            {
+                regs.scr().write(|w| w.set_cwutf(crate::pac::rtc::vals::Calrf::CLEAR));
+                while !regs.icsr().read().wutwf() {}
+            }
+
+            WAKEUP_PENDING.store(false, Ordering::Relaxed);
+
+            regs.cr().modify(|w| w.set_wucksel(wucksel));
+            regs.wutr().write(|w| w.set_wut(ticks as u16));
+            regs.cr().modify(|w| {
+                w.set_wute(true);
+                w.set_wutie(true);
+            });
+        });
+    }
+
+    /// Stop the periodic wakeup timer.
+    #[cfg(all(
+        not(feature = "low-power"),
+        any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5)
+    ))]
+    pub fn disable_wakeup_timer(&mut self) {
+        self.write(false, |regs| {
+            regs.cr().modify(|w| {
+                w.set_wute(false);
+                w.set_wutie(false);
+            });
+        });
+
+        WAKEUP_PENDING.store(false, Ordering::Relaxed);
+    }
+
+    /// Wait for the next period of the wakeup timer.
+    ///
+    /// Returns immediately if a period elapsed since the last call. Requires the RTC interrupts
+    /// to be enabled with [`enable_interrupts`](Self::enable_interrupts).
+    #[cfg(all(
+        not(feature = "low-power"),
+        any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5)
+    ))]
+    pub async fn wait_for_wakeup(&self) {
+        poll_fn(|cx| {
+            WAKEUP_WAKER.register(cx.waker());
+
+            if WAKEUP_PENDING.swap(false, Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -83,6 +83,8 @@ pub enum Error {
     InvalidMinute,
     /// The [DateTime] contains an invalid second value. Must be between `0..=59`.
     InvalidSecond,
+    /// The [DateTime] contains an invalid microsecond value. Must be between `0..=999_999`.
+    InvalidMicrosecond,
 }
 
 /// Structure containing date and time information
@@ -101,6 +103,8 @@ pub struct DateTime {
     minute: u8,
     /// 0..59
     second: u8,
+    /// 0..999_999
+    microsecond: u32,
 }
 
 impl DateTime {
@@ -139,6 +143,22 @@ impl DateTime {
         self.second
     }
 
+    /// Get the microsecond (0..=999_999)
+    ///
+    /// The resolution is limited by the RTC subsecond counter frequency.
+    pub const fn microsecond(&self) -> u32 {
+        self.microsecond
+    }
+
+    /// Set the microsecond (0..=999_999) of this DateTime.
+    pub fn with_microsecond(self, microsecond: u32) -> Result<Self, Error> {
+        if microsecond > 999_999 {
+            Err(Error::InvalidMicrosecond)
+        } else {
+            Ok(Self { microsecond, ..self })
+        }
+    }
+
     /// Create a new DateTime with the given information.
     pub fn from(
         year: u16,
@@ -170,6 +190,7 @@ impl DateTime {
                 hour,
                 minute,
                 second,
+                microsecond: 0,
             })
         }
     }
@@ -186,6 +207,7 @@ impl From<chrono::NaiveDateTime> for DateTime {
             hour: date_time.hour() as u8,
             minute: date_time.minute() as u8,
             second: date_time.second() as u8,
+            microsecond: date_time.timestamp_subsec_micros().min(999_999),
         }
     }
 }
@@ -195,7 +217,12 @@ impl From<DateTime> for chrono::NaiveDateTime {
     fn from(date_time: DateTime) -> Self {
         NaiveDate::from_ymd_opt(date_time.year as i32, date_time.month as u32, date_time.day as u32)
             .unwrap()
-            .and_hms_opt(date_time.hour as u32, date_time.minute as u32, date_time.second as u32)
+            .and_hms_micro_opt(
+                date_time.hour as u32,
+                date_time.minute as u32,
+                date_time.second as u32,
+                date_time.microsecond,
+            )
             .unwrap()
     }
 }
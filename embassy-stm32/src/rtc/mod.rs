@@ -1,5 +1,8 @@
 //! Real Time Clock (RTC)
+mod alarm;
 mod datetime;
+#[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+mod tamper;
 
 #[cfg(feature = "low-power")]
 use core::cell::Cell;
@@ -12,7 +15,12 @@ use embassy_sync::blocking_mutex::Mutex;
 #[cfg(not(rtc_v2f2))]
 use self::datetime::RtcInstant;
 use self::datetime::{day_of_week_from_u8, day_of_week_to_u8};
+#[cfg(feature = "low-power")]
+pub(crate) use self::alarm::on_interrupt;
+pub use self::alarm::{Alarm, AlarmConfig, AlarmDay, InterruptHandler};
 pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError};
+#[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+pub use self::tamper::{Tamper, TamperConfig, TamperEdge};
 use crate::pac::rtc::regs::{Dr, Tr};
 use crate::time::Hertz;
 
@@ -43,7 +51,7 @@ pub(crate) enum WakeupPrescaler {
     Div16 = 16,
 }
 
-#[cfg(any(stm32wb, stm32f4, stm32l0, stm32g4, stm32l5, stm32g0))]
+#[cfg(any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5))]
 impl From<WakeupPrescaler> for crate::pac::rtc::vals::Wucksel {
     fn from(val: WakeupPrescaler) -> Self {
         use crate::pac::rtc::vals::Wucksel;
@@ -57,7 +65,7 @@ impl From<WakeupPrescaler> for crate::pac::rtc::vals::Wucksel {
     }
 }
 
-#[cfg(any(stm32wb, stm32f4, stm32l0, stm32g4, stm32l5, stm32g0))]
+#[cfg(any(stm32wb, stm32f4, stm32l0, stm32l4, stm32g4, stm32l5, stm32g0, stm32u5))]
 impl From<crate::pac::rtc::vals::Wucksel> for WakeupPrescaler {
     fn from(val: crate::pac::rtc::vals::Wucksel) -> Self {
         use crate::pac::rtc::vals::Wucksel;
@@ -123,7 +131,10 @@ impl RtcTimeProvider {
     ///
     /// Will return an `RtcError::InvalidDateTime` if the stored value in the system is not a valid [`DayOfWeek`].
     pub fn now(&self) -> Result<DateTime, RtcError> {
-        self.read(|dr, tr, _| {
+        #[cfg(not(rtc_v2f2))]
+        let prediv_s = RTC::regs().prer().read().prediv_s() as u32;
+
+        self.read(|dr, tr, _ss| {
             let second = bcd2_to_byte((tr.st(), tr.su()));
             let minute = bcd2_to_byte((tr.mnt(), tr.mnu()));
             let hour = bcd2_to_byte((tr.ht(), tr.hu()));
@@ -133,7 +144,19 @@ impl RtcTimeProvider {
             let month = bcd2_to_byte((dr.mt() as u8, dr.mu()));
             let year = bcd2_to_byte((dr.yt(), dr.yu())) as u16 + 2000_u16;
 
-            DateTime::from(year, month, day, weekday, hour, minute, second).map_err(RtcError::InvalidDateTime)
+            let t = DateTime::from(year, month, day, weekday, hour, minute, second)
+                .map_err(RtcError::InvalidDateTime)?;
+
+            // The subsecond counter counts down from PREDIV_S. It may be above PREDIV_S right
+            // after a shift operation, in which case the second hasn't ticked over yet.
+            #[cfg(not(rtc_v2f2))]
+            let t = {
+                let elapsed = prediv_s.saturating_sub(_ss as u32) as u64;
+                let microsecond = (elapsed * 1_000_000 / (prediv_s as u64 + 1)) as u32;
+                t.with_microsecond(microsecond).map_err(RtcError::InvalidDateTime)?
+            };
+
+            Ok(t)
         })
     }
 
@@ -164,6 +187,71 @@ impl RtcTimeProvider {
     }
 }
 
+/// A value that can be stored in the backup registers.
+///
+/// See [`Rtc::read_backup`] and [`Rtc::write_backup`].
+pub trait BackupValue: Sized {
+    /// Backup registers holding the value, typically `[u32; N]`.
+    type Registers: AsRef<[u32]> + AsMut<[u32]> + Default;
+
+    /// Convert back from the content of the backup registers.
+    fn from_registers(regs: Self::Registers) -> Self;
+
+    /// Convert into the content of the backup registers.
+    fn into_registers(self) -> Self::Registers;
+}
+
+macro_rules! impl_backup_value_u32 {
+    ($($ty:ty => |$v:ident| $from:expr, $into:expr;)*) => {
+        $(
+            impl BackupValue for $ty {
+                type Registers = [u32; 1];
+
+                fn from_registers([$v]: [u32; 1]) -> Self {
+                    $from
+                }
+
+                fn into_registers(self) -> [u32; 1] {
+                    let $v = self;
+                    [$into]
+                }
+            }
+        )*
+    };
+}
+
+impl_backup_value_u32! {
+    u32 => |v| v, v;
+    i32 => |v| v as i32, v as u32;
+    f32 => |v| f32::from_bits(v), v.to_bits();
+    bool => |v| v != 0, v as u32;
+    [u8; 4] => |v| v.to_le_bytes(), u32::from_le_bytes(v);
+}
+
+impl BackupValue for u64 {
+    type Registers = [u32; 2];
+
+    fn from_registers([lo, hi]: [u32; 2]) -> Self {
+        (hi as u64) << 32 | lo as u64
+    }
+
+    fn into_registers(self) -> [u32; 2] {
+        [self as u32, (self >> 32) as u32]
+    }
+}
+
+impl BackupValue for i64 {
+    type Registers = [u32; 2];
+
+    fn from_registers(regs: [u32; 2]) -> Self {
+        u64::from_registers(regs) as i64
+    }
+
+    fn into_registers(self) -> [u32; 2] {
+        (self as u64).into_registers()
+    }
+}
+
 /// RTC driver.
 pub struct Rtc {
     #[cfg(feature = "low-power")]
@@ -288,9 +376,40 @@ impl Rtc {
             });
         });
 
+        #[cfg(not(rtc_v2f2))]
+        if t.microsecond() != 0 {
+            self.shift_subsecond(t.microsecond());
+        }
+
         Ok(())
     }
 
+    /// Advance the calendar by a fraction of a second, using the shift control register.
+    #[cfg(not(rtc_v2f2))]
+    fn shift_subsecond(&mut self, microsecond: u32) {
+        let prediv_s = RTC::regs().prer().read().prediv_s() as u64;
+        let ticks = (microsecond as u64 * (prediv_s + 1) / 1_000_000) as u16;
+        if ticks == 0 {
+            return;
+        }
+
+        self.write(false, |rtc| {
+            #[cfg(any(
+                rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+            ))]
+            while rtc.isr().read().shpf() {}
+            #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+            while rtc.icsr().read().shpf() {}
+
+            // Adding one second and subtracting the complement of the fraction advances the
+            // clock by the fraction.
+            rtc.shiftr().write(|w| {
+                w.set_add1s(true);
+                w.set_subfs(prediv_s as u16 + 1 - ticks);
+            });
+        });
+    }
+
     #[cfg(not(rtc_v2f2))]
     /// Return the current instant.
     fn instant(&self) -> Result<RtcInstant, RtcError> {
@@ -313,12 +432,37 @@ impl Rtc {
     }
 
     /// Enable/disable daylight savings time.
+    ///
+    /// This only records whether daylight savings time is active, see
+    /// [`adjust_daylight_savings`](Self::adjust_daylight_savings) to also change the time.
     pub fn set_daylight_savings(&mut self, daylight_savings: bool) {
         self.write(true, |rtc| {
             rtc.cr().modify(|w| w.set_bkp(daylight_savings));
         })
     }
 
+    /// Enter or leave daylight savings time, adding or subtracting one hour to the calendar.
+    ///
+    /// The adjustment is only applied if the daylight savings state actually changes, so this
+    /// can safely be called again after a reset.
+    pub fn adjust_daylight_savings(&mut self, daylight_savings: bool) {
+        if self.get_daylight_savings() == daylight_savings {
+            return;
+        }
+
+        // The calendar doesn't need to be stopped to add or subtract an hour.
+        self.write(false, |rtc| {
+            rtc.cr().modify(|w| {
+                if daylight_savings {
+                    w.set_add1h(true);
+                } else {
+                    w.set_sub1h(true);
+                }
+                w.set_bkp(daylight_savings);
+            });
+        })
+    }
+
     /// Number of backup registers of this instance.
     pub const BACKUP_REGISTER_COUNT: usize = RTC::BACKUP_REGISTER_COUNT;
 
@@ -338,6 +482,32 @@ impl Rtc {
         RTC::write_backup_register(&RTC::regs(), register, value)
     }
 
+    /// Read a value stored in the backup registers, starting at `register`.
+    ///
+    /// Returns `None` if the value doesn't fit in the backup registers.
+    pub fn read_backup<T: BackupValue>(&self, register: usize) -> Option<T> {
+        let mut regs = T::Registers::default();
+        for (i, reg) in regs.as_mut().iter_mut().enumerate() {
+            *reg = self.read_backup_register(register + i)?;
+        }
+        Some(T::from_registers(regs))
+    }
+
+    /// Store a value in the backup registers, starting at `register`.
+    ///
+    /// Nothing is written if the value doesn't fit in the backup registers.
+    pub fn write_backup<T: BackupValue>(&self, register: usize, value: T) {
+        let regs = value.into_registers();
+        let regs = regs.as_ref();
+        if register + regs.len() > Self::BACKUP_REGISTER_COUNT {
+            return;
+        }
+
+        for (i, reg) in regs.iter().enumerate() {
+            self.write_backup_register(register + i, *reg);
+        }
+    }
+
     #[cfg(feature = "low-power")]
     /// start the wakeup alarm and wtih a duration that is as close to but less than
     /// the requested duration, and record the instant the wakeup alarm was started
@@ -427,7 +597,7 @@ impl Rtc {
                 crate::pac::EXTI
                     .rpr(0)
                     .modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
-                #[cfg(all(not(stm32g0), not(stm32l5)))]
+                #[cfg(all(not(stm32g0), not(stm32l5), not(stm32u5)))]
                 crate::pac::EXTI
                     .pr(0)
                     .modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
@@ -452,8 +622,17 @@ impl Rtc {
         <RTC as crate::rtc::sealed::Instance>::WakeupInterrupt::unpend();
         unsafe { <RTC as crate::rtc::sealed::Instance>::WakeupInterrupt::enable() };
 
-        EXTI.rtsr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
-        EXTI.imr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+        // The wakeup timer has a direct interrupt line on U5.
+        #[cfg(not(stm32u5))]
+        {
+            #[cfg(exti_w)]
+            let imr = EXTI.cpu(crate::pac::CORE_INDEX).imr(0);
+            #[cfg(not(exti_w))]
+            let imr = EXTI.imr(0);
+
+            EXTI.rtsr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+            imr.modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+        }
     }
 }
 
@@ -483,7 +662,7 @@ pub(crate) mod sealed {
     pub trait Instance {
         const BACKUP_REGISTER_COUNT: usize;
 
-        #[cfg(feature = "low-power")]
+        #[cfg(all(feature = "low-power", not(stm32u5)))]
         const EXTI_WAKEUP_LINE: usize;
 
         #[cfg(feature = "low-power")]
@@ -0,0 +1,132 @@
+//! Tamper detection, through the TAMP peripheral of RTC3 chips.
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::Rtc;
+use crate::pac::TAMP;
+
+const NEW_AW: AtomicWaker = AtomicWaker::new();
+
+static TAMPER_WAKERS: [AtomicWaker; 8] = [NEW_AW; 8];
+static TAMPER_PENDING: AtomicU8 = AtomicU8::new(0);
+
+/// Tamper input.
+///
+/// The number of inputs depends on the chip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum Tamper {
+    Tamp1,
+    Tamp2,
+    Tamp3,
+    Tamp4,
+    Tamp5,
+    Tamp6,
+    Tamp7,
+    Tamp8,
+}
+
+impl Tamper {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Tamper input active edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TamperEdge {
+    /// Detect rising edges.
+    Rising,
+    /// Detect falling edges.
+    Falling,
+}
+
+/// Tamper input configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TamperConfig {
+    /// Active edge.
+    pub edge: TamperEdge,
+    /// Erase the backup registers when a tamper event is detected.
+    pub erase_backup_registers: bool,
+}
+
+impl Default for TamperConfig {
+    fn default() -> Self {
+        Self {
+            edge: TamperEdge::Rising,
+            erase_backup_registers: true,
+        }
+    }
+}
+
+pub(super) fn on_interrupt() {
+    let sr = TAMP.sr().read();
+    let ier = TAMP.ier().read();
+
+    for n in 0..8 {
+        if sr.tampf(n) && ier.tampie(n) {
+            TAMP.scr().write(|w| w.set_ctampf(n, true));
+
+            TAMPER_PENDING.fetch_or(1 << n, Ordering::Release);
+            TAMPER_WAKERS[n].wake();
+        }
+    }
+}
+
+impl Rtc {
+    /// Enable tamper detection on the given input.
+    ///
+    /// The tamper pins are taken over by the TAMP peripheral, they don't need to be configured.
+    pub fn enable_tamper(&mut self, tamper: Tamper, config: TamperConfig) {
+        let n = tamper.index();
+
+        TAMP.cr1().modify(|w| w.set_tampe(n, false));
+        TAMP.cr2().modify(|w| {
+            w.set_tamptrg(n, config.edge == TamperEdge::Falling);
+            w.set_tampnoer(n, !config.erase_backup_registers);
+        });
+
+        TAMP.scr().write(|w| w.set_ctampf(n, true));
+        TAMPER_PENDING.fetch_and(!(1 << n), Ordering::Relaxed);
+
+        TAMP.ier().modify(|w| w.set_tampie(n, true));
+        TAMP.cr1().modify(|w| w.set_tampe(n, true));
+    }
+
+    /// Disable tamper detection on the given input.
+    pub fn disable_tamper(&mut self, tamper: Tamper) {
+        let n = tamper.index();
+
+        TAMP.cr1().modify(|w| w.set_tampe(n, false));
+        TAMP.ier().modify(|w| w.set_tampie(n, false));
+
+        TAMPER_PENDING.fetch_and(!(1 << n), Ordering::Relaxed);
+    }
+
+    /// Wait for a tamper event on the given input.
+    ///
+    /// Returns immediately if an event was detected since detection was enabled or last waited
+    /// for. Requires the RTC interrupts to be enabled with
+    /// [`enable_interrupts`](Self::enable_interrupts), with the handler also bound to the tamper
+    /// interrupt.
+    pub async fn wait_for_tamper(&self, tamper: Tamper) {
+        let n = tamper.index();
+
+        poll_fn(|cx| {
+            TAMPER_WAKERS[n].register(cx.waker());
+
+            if TAMPER_PENDING.fetch_and(!(1 << n), Ordering::Acquire) & (1 << n) != 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
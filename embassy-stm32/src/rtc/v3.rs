@@ -127,6 +127,9 @@ impl super::Rtc {
 }
 
 impl sealed::Instance for crate::peripherals::RTC {
+    #[cfg(stm32g0)]
+    const BACKUP_REGISTER_COUNT: usize = 5;
+    #[cfg(not(stm32g0))]
     const BACKUP_REGISTER_COUNT: usize = 32;
 
     #[cfg(all(feature = "low-power", stm32g4))]
@@ -144,23 +147,21 @@ impl sealed::Instance for crate::peripherals::RTC {
     #[cfg(all(feature = "low-power", stm32l5))]
     const EXTI_WAKEUP_LINE: usize = 17;
 
-    #[cfg(all(feature = "low-power", stm32l5))]
+    #[cfg(all(feature = "low-power", any(stm32l5, stm32u5)))]
     type WakeupInterrupt = crate::interrupt::typelevel::RTC;
 
+    // RTC3 backup registers come from the TAMP peripheral, not RTC.
     fn read_backup_register(_rtc: &Rtc, register: usize) -> Option<u32> {
-        #[allow(clippy::if_same_then_else)]
         if register < Self::BACKUP_REGISTER_COUNT {
-            //Some(rtc.bkpr()[register].read().bits())
-            None // RTC3 backup registers come from the TAMP peripe=heral, not RTC. Not() even in the L412 PAC
+            Some(crate::pac::TAMP.bkpr(register).read().bkp())
         } else {
             None
         }
     }
 
-    fn write_backup_register(_rtc: &Rtc, register: usize, _value: u32) {
+    fn write_backup_register(_rtc: &Rtc, register: usize, value: u32) {
         if register < Self::BACKUP_REGISTER_COUNT {
-            // RTC3 backup registers come from the TAMP peripe=heral, not RTC. Not() even in the L412 PAC
-            //self.rtc.bkpr()[register].write(|w| w.bits(value))
+            crate::pac::TAMP.bkpr(register).write(|w| w.set_bkp(value));
         }
     }
 }
@@ -5,6 +5,8 @@
 use core::marker::PhantomData;
 
 use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::zerocopy_channel;
 
 use self::sealed::WhichSubBlock;
 pub use crate::dma::word;
@@ -502,6 +504,43 @@ impl Config {
     pub fn new() -> Self {
         return Default::default();
     }
+
+    /// Create a new config for a TDM frame of `slot_count` slots of `slot_size` bits.
+    ///
+    /// The frame sync is a one-bit pulse before the first bit of slot 0, and all slots are enabled.
+    /// Disable slots with `slot_enable` if only some of them are used by this sub-block.
+    pub fn tdm(slot_count: u8, slot_size: SlotSize, data_size: DataSize) -> Self {
+        assert!(slot_count >= 1 && slot_count <= 16, "TDM supports 1 to 16 slots");
+
+        let slot_bits: u16 = match (slot_size, data_size) {
+            (SlotSize::Channel16, _) => 16,
+            (SlotSize::Channel32, _) => 32,
+            (SlotSize::DataSize, DataSize::Data8) => 8,
+            (SlotSize::DataSize, DataSize::Data10) => 10,
+            (SlotSize::DataSize, DataSize::Data16) => 16,
+            (SlotSize::DataSize, DataSize::Data20) => 20,
+            (SlotSize::DataSize, DataSize::Data24) => 24,
+            (SlotSize::DataSize, DataSize::Data32) => 32,
+        };
+        let frame_length = slot_bits * slot_count as u16;
+        assert!(frame_length >= 8 && frame_length <= 255, "TDM frame must be 8 to 255 bits long");
+
+        Self {
+            protocol: Protocol::Free,
+            slot_size,
+            slot_count: word::U4(slot_count),
+            slot_enable: (1u32 << slot_count).wrapping_sub(1) as u16,
+            data_size,
+            stereo_mono: StereoMono::Stereo,
+            bit_order: BitOrder::MsbFirst,
+            frame_sync_offset: FrameSyncOffset::BeforeFirstBit,
+            frame_sync_polarity: FrameSyncPolarity::ActiveHigh,
+            frame_sync_active_level_length: word::U7(1),
+            frame_sync_definition: FrameSyncDefinition::StartOfFrame,
+            frame_length: frame_length as u8,
+            ..Default::default()
+        }
+    }
 }
 
 #[cfg(not(gpdma))]
@@ -886,6 +925,37 @@ impl<'d, T: Instance, W: word::Word> Sai<'d, T, W> {
             _ => Err(Error::NotAReceiver),
         }
     }
+
+    /// Stream sample buffers from a zero-copy channel.
+    ///
+    /// Each buffer received from the channel is copied to the DMA ringbuffer, then handed back to
+    /// the producer, so it can fill the next one while this one is being transmitted. This only
+    /// returns on error.
+    pub async fn write_from<M: RawMutex, const N: usize>(
+        &mut self,
+        receiver: &mut zerocopy_channel::Receiver<'_, M, [W; N]>,
+    ) -> Result<(), Error> {
+        loop {
+            let buf = receiver.receive().await;
+            self.write(buf).await?;
+            receiver.receive_done();
+        }
+    }
+
+    /// Stream sample buffers into a zero-copy channel.
+    ///
+    /// Buffers are taken from the channel, filled from the DMA ringbuffer, then handed to the
+    /// consumer. This only returns on error.
+    pub async fn read_into<M: RawMutex, const N: usize>(
+        &mut self,
+        sender: &mut zerocopy_channel::Sender<'_, M, [W; N]>,
+    ) -> Result<(), Error> {
+        loop {
+            let buf = sender.send().await;
+            self.read(buf).await?;
+            sender.send_done();
+        }
+    }
 }
 
 impl<'d, T: Instance, W: word::Word> Drop for Sai<'d, T, W> {
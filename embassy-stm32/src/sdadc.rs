@@ -0,0 +1,354 @@
+//! Sigma-Delta Analog to Digital Converter (SDADC)
+#![macro_use]
+
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embedded_hal_02::blocking::delay::DelayUs;
+
+use crate::dma::ringbuffer::OverrunError;
+use crate::dma::{ReadableRingBuffer, Transfer, TransferOptions};
+use crate::rcc::RccPeripheral;
+use crate::{peripherals, Peripheral};
+
+/// Reference voltage selection.
+///
+/// The reference is shared by all SDADCs and is only written by `SDADC1`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Reference {
+    /// External VREFSD+ pin.
+    External,
+    /// Internal 1.22V reference.
+    Internal1V22,
+    /// Internal 1.8V reference.
+    Internal1V8,
+    /// VDDSD supply.
+    Vddsd,
+}
+
+impl Reference {
+    fn refv(&self) -> u8 {
+        match self {
+            Reference::External => 0b00,
+            Reference::Internal1V22 => 0b01,
+            Reference::Internal1V8 => 0b10,
+            Reference::Vddsd => 0b11,
+        }
+    }
+}
+
+/// Input mode of a channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InputMode {
+    /// Differential, result is signed.
+    Differential,
+    /// Single-ended, offset mode, result is signed and centered on 0.
+    SingleEndedOffset,
+    /// Single-ended, zero reference mode, result is signed with 0V at -32768.
+    SingleEndedZeroReference,
+}
+
+impl InputMode {
+    fn se(&self) -> u8 {
+        match self {
+            InputMode::Differential => 0b00,
+            InputMode::SingleEndedOffset => 0b01,
+            InputMode::SingleEndedZeroReference => 0b11,
+        }
+    }
+}
+
+/// Programmable gain.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum Gain {
+    X0_5,
+    X1,
+    X2,
+    X4,
+    X8,
+    X16,
+    X32,
+}
+
+impl Gain {
+    fn gain(&self) -> u8 {
+        match self {
+            Gain::X1 => 0b000,
+            Gain::X2 => 0b001,
+            Gain::X4 => 0b010,
+            Gain::X8 => 0b011,
+            Gain::X16 => 0b100,
+            Gain::X32 => 0b101,
+            Gain::X0_5 => 0b111,
+        }
+    }
+}
+
+/// SDADC configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct Config {
+    /// Reference voltage.
+    pub reference: Reference,
+    /// Input mode.
+    pub input_mode: InputMode,
+    /// Gain.
+    pub gain: Gain,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reference: Reference::Internal1V22,
+            input_mode: InputMode::SingleEndedOffset,
+            gain: Gain::X1,
+        }
+    }
+}
+
+/// SDADC driver.
+///
+/// All channels share configuration 0, which is set up from [`Config`].
+pub struct Sdadc<'d, T: Instance> {
+    _peri: PeripheralRef<'d, T>,
+}
+
+impl<'d, T: Instance> Sdadc<'d, T> {
+    /// Create a new SDADC driver and run the offset calibration.
+    pub fn new(peri: impl Peripheral<P = T> + 'd, config: Config, delay: &mut impl DelayUs<u32>) -> Self {
+        into_ref!(peri);
+        T::enable_and_reset();
+
+        // The SDADC analog domain must be powered separately.
+        crate::pac::PWR.cr().modify(|w| w.set_ensd(T::INDEX, true));
+
+        let r = T::regs();
+        r.cr2().modify(|w| w.set_adon(true));
+        while r.isr().read().stabip() {}
+
+        Self::enter_init();
+
+        if T::INDEX == 0 {
+            r.cr1().modify(|w| w.set_refv(config.reference.refv()));
+            // VREFSD needs time to settle after a change.
+            delay.delay_us(5_000);
+        }
+        r.conf0r().modify(|w| {
+            w.set_se0(config.input_mode.se());
+            w.set_gain0(config.gain.gain());
+            w.set_offset0(0);
+        });
+        r.confchr1().write(|w| {
+            for n in 0..8 {
+                w.set_confch(n, 0);
+            }
+        });
+        r.confchr2().write(|w| w.set_confch(0, 0));
+        r.cr2().modify(|w| w.set_calibcnt(0));
+
+        Self::exit_init();
+
+        r.cr2().modify(|w| w.set_startcalib(true));
+        while !r.isr().read().eocalf() {}
+        r.clrisr().write(|w| w.set_clreocalf(true));
+
+        Self { _peri: peri }
+    }
+
+    fn enter_init() {
+        let r = T::regs();
+        r.cr1().modify(|w| w.set_init(true));
+        while !r.isr().read().initrdy() {}
+    }
+
+    fn exit_init() {
+        T::regs().cr1().modify(|w| w.set_init(false));
+    }
+
+    fn select_channel(channel: u8, continuous: bool, dma: bool) {
+        let r = T::regs();
+        r.cr1().modify(|w| w.set_rdmaen(dma));
+        r.cr2().modify(|w| {
+            w.set_rch(channel);
+            w.set_rcont(continuous);
+        });
+    }
+
+    /// Perform a single blocking conversion.
+    pub fn blocking_read(&mut self, pin: &mut impl SdadcPin<T>) -> i16 {
+        pin.set_as_analog();
+        Self::select_channel(pin.channel(), false, false);
+
+        let r = T::regs();
+        r.cr2().modify(|w| w.set_rswstart(true));
+        while !r.isr().read().reocf() {}
+
+        r.rdatar().read().rdata() as i16
+    }
+
+    /// Fill `buf` with consecutive conversions of `pin`, using DMA.
+    pub async fn read(
+        &mut self,
+        pin: &mut impl SdadcPin<T>,
+        dma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        buf: &mut [u16],
+    ) {
+        into_ref!(dma);
+        pin.set_as_analog();
+        Self::select_channel(pin.channel(), true, true);
+
+        let request = dma.request();
+        let r = T::regs();
+        let transfer = unsafe {
+            Transfer::new_read(
+                dma,
+                request,
+                r.rdatar().as_ptr() as *mut u16,
+                buf,
+                TransferOptions::default(),
+            )
+        };
+
+        r.cr2().modify(|w| w.set_rswstart(true));
+        transfer.await;
+
+        Self::select_channel(pin.channel(), false, false);
+    }
+
+    /// Continuously convert `pin` into a DMA ring buffer.
+    ///
+    /// Samples are raw 16 bit two's complement values, reinterpret them with `as i16`.
+    pub fn into_ring_buffered(
+        self,
+        pin: &mut impl SdadcPin<T>,
+        dma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+    ) -> RingBufferedSdadc<'d, T> {
+        into_ref!(dma);
+        pin.set_as_analog();
+        let channel = pin.channel();
+
+        let request = dma.request();
+        let opts = TransferOptions {
+            half_transfer_ir: true,
+            ..Default::default()
+        };
+        let ring_buf =
+            unsafe { ReadableRingBuffer::new(dma, request, T::regs().rdatar().as_ptr() as *mut u16, dma_buf, opts) };
+
+        RingBufferedSdadc {
+            _inner: self,
+            channel,
+            ring_buf,
+        }
+    }
+}
+
+impl<'d, T: Instance> Drop for Sdadc<'d, T> {
+    fn drop(&mut self) {
+        T::regs().cr2().modify(|w| w.set_adon(false));
+        crate::pac::PWR.cr().modify(|w| w.set_ensd(T::INDEX, false));
+        T::disable();
+    }
+}
+
+/// SDADC streaming conversions of a single channel into a DMA ring buffer.
+pub struct RingBufferedSdadc<'d, T: Instance> {
+    _inner: Sdadc<'d, T>,
+    channel: u8,
+    ring_buf: ReadableRingBuffer<'d, u16>,
+}
+
+impl<'d, T: Instance> RingBufferedSdadc<'d, T> {
+    /// Start continuous conversion.
+    pub fn start(&mut self) {
+        self.ring_buf.clear();
+        self.ring_buf.start();
+
+        Sdadc::<'d, T>::select_channel(self.channel, true, true);
+        T::regs().cr2().modify(|w| w.set_rswstart(true));
+    }
+
+    /// Stop continuous conversion.
+    pub fn stop(&mut self) {
+        Sdadc::<'d, T>::select_channel(self.channel, false, false);
+        self.ring_buf.request_stop();
+    }
+
+    /// Read exactly `buf.len()` samples, waiting for them to be converted.
+    ///
+    /// Returns the number of samples remaining in the ring buffer.
+    pub async fn read(&mut self, buf: &mut [u16]) -> Result<usize, OverrunError> {
+        self.ring_buf.read_exact(buf).await
+    }
+}
+
+impl<'d, T: Instance> Drop for RingBufferedSdadc<'d, T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+pub(crate) mod sealed {
+    pub trait Instance {
+        const INDEX: usize;
+        fn regs() -> crate::pac::sdadc::Sdadc;
+    }
+
+    pub trait SdadcPin<T: Instance> {
+        fn set_as_analog(&mut self) {}
+        fn channel(&self) -> u8;
+    }
+}
+
+/// SDADC instance trait.
+pub trait Instance: Peripheral<P = Self> + sealed::Instance + RccPeripheral + 'static {}
+
+/// SDADC input pin.
+pub trait SdadcPin<T: Instance>: sealed::SdadcPin<T> {}
+
+dma_trait!(RxDma, Instance);
+
+macro_rules! impl_sdadc {
+    ($inst:ident, $index:expr) => {
+        impl sealed::Instance for peripherals::$inst {
+            const INDEX: usize = $index;
+            fn regs() -> crate::pac::sdadc::Sdadc {
+                crate::pac::$inst
+            }
+        }
+
+        impl Instance for peripherals::$inst {}
+    };
+}
+
+foreach_peripheral!(
+    (sdadc, SDADC1) => {
+        impl_sdadc!(SDADC1, 0);
+    };
+    (sdadc, SDADC2) => {
+        impl_sdadc!(SDADC2, 1);
+    };
+    (sdadc, SDADC3) => {
+        impl_sdadc!(SDADC3, 2);
+    };
+);
+
+#[allow(unused_macros)]
+macro_rules! impl_sdadc_pin {
+    ($inst:ident, $pin:ident, $ch:expr) => {
+        impl crate::sdadc::SdadcPin<peripherals::$inst> for crate::peripherals::$pin {}
+
+        impl crate::sdadc::sealed::SdadcPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn set_as_analog(&mut self) {
+                <Self as crate::gpio::sealed::Pin>::set_as_analog(self);
+            }
+
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
@@ -0,0 +1,62 @@
+//! [`embedded_sdmmc::BlockDevice`] adapter for the SDMMC driver.
+use core::cell::RefCell;
+
+use embassy_futures::block_on;
+use embedded_sdmmc::{Block, BlockCount, BlockDevice, BlockIdx};
+
+use super::{DataBlock, Error, Instance, Sdmmc, SdmmcDma};
+
+/// Exposes an initialized card as an [`embedded_sdmmc::BlockDevice`], for use with its FAT
+/// filesystem support.
+///
+/// `BlockDevice` is a blocking interface, so each access runs the async driver to completion
+/// with [`block_on`]. The SDMMC interrupt must still be bound, since the driver is woken by it.
+pub struct SdmmcBlockDevice<'d, T: Instance, Dma: SdmmcDma<T> + 'd> {
+    sdmmc: RefCell<Sdmmc<'d, T, Dma>>,
+}
+
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> SdmmcBlockDevice<'d, T, Dma> {
+    /// Wrap a driver whose card has already been initialized with
+    /// [`init_card`](Sdmmc::init_card).
+    pub fn new(sdmmc: Sdmmc<'d, T, Dma>) -> Self {
+        Self {
+            sdmmc: RefCell::new(sdmmc),
+        }
+    }
+
+    /// Release the driver.
+    pub fn into_inner(self) -> Sdmmc<'d, T, Dma> {
+        self.sdmmc.into_inner()
+    }
+}
+
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> BlockDevice for SdmmcBlockDevice<'d, T, Dma> {
+    type Error = Error;
+
+    fn read(&self, blocks: &mut [Block], start_block_idx: BlockIdx) -> Result<(), Self::Error> {
+        let mut sdmmc = self.sdmmc.borrow_mut();
+        let mut buffer = DataBlock([0; 512]);
+
+        for (i, block) in blocks.iter_mut().enumerate() {
+            block_on(sdmmc.read_block(start_block_idx.0 + i as u32, &mut buffer))?;
+            block.contents.copy_from_slice(&buffer.0);
+        }
+        Ok(())
+    }
+
+    fn write(&self, blocks: &[Block], start_block_idx: BlockIdx) -> Result<(), Self::Error> {
+        let mut sdmmc = self.sdmmc.borrow_mut();
+        let mut buffer = DataBlock([0; 512]);
+
+        for (i, block) in blocks.iter().enumerate() {
+            buffer.0.copy_from_slice(&block.contents);
+            block_on(sdmmc.write_block(start_block_idx.0 + i as u32, &buffer))?;
+        }
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> Result<BlockCount, Self::Error> {
+        let size = self.sdmmc.borrow().card()?.size();
+        Ok(BlockCount((size / 512) as u32))
+    }
+}
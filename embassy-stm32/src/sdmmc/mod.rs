@@ -21,6 +21,11 @@ use crate::rcc::RccPeripheral;
 use crate::time::Hertz;
 use crate::{interrupt, peripherals, Peripheral};
 
+#[cfg(feature = "embedded-sdmmc")]
+mod block_device;
+#[cfg(feature = "embedded-sdmmc")]
+pub use block_device::SdmmcBlockDevice;
+
 /// Interrupt handler.
 pub struct InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -243,12 +248,16 @@ const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOp
     circular: false,
     half_transfer_ir: false,
     complete_transfer_ir: true,
+    #[cfg(dmamux)]
+    sync: None,
 };
 #[cfg(all(sdmmc_v1, not(dma)))]
 const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOptions {
     circular: false,
     half_transfer_ir: false,
     complete_transfer_ir: true,
+    #[cfg(dmamux)]
+    sync: None,
 };
 
 /// SDMMC configuration
@@ -1177,34 +1186,13 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
         };
         Self::cmd(Cmd::set_block_length(512), false)?; // CMD16
 
-        let regs = T::regs();
         let on_drop = OnDrop::new(|| Self::on_drop());
 
         let transfer = self.prepare_datapath_read(buffer, 512, 9);
         InterruptHandler::<T>::data_interrupts(true);
         Self::cmd(Cmd::read_single_block(address), true)?;
 
-        let res = poll_fn(|cx| {
-            T::state().register(cx.waker());
-            let status = regs.star().read();
-
-            if status.dcrcfail() {
-                return Poll::Ready(Err(Error::Crc));
-            }
-            if status.dtimeout() {
-                return Poll::Ready(Err(Error::Timeout));
-            }
-            #[cfg(sdmmc_v1)]
-            if status.stbiterr() {
-                return Poll::Ready(Err(Error::StBitErr));
-            }
-            if status.dataend() {
-                return Poll::Ready(Ok(()));
-            }
-            Poll::Pending
-        })
-        .await;
-        Self::clear_interrupt_flags();
+        let res = Self::complete_datapath_transfer().await;
 
         if res.is_ok() {
             on_drop.defuse();
@@ -1229,7 +1217,6 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
         };
         Self::cmd(Cmd::set_block_length(512), false)?; // CMD16
 
-        let regs = T::regs();
         let on_drop = OnDrop::new(|| Self::on_drop());
 
         // sdmmc_v1 uses different cmd/dma order than v2, but only for writes
@@ -1242,6 +1229,104 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
         #[cfg(sdmmc_v2)]
         Self::cmd(Cmd::write_single_block(address), true)?;
 
+        let res = Self::complete_datapath_transfer().await;
+
+        match res {
+            Ok(_) => {
+                on_drop.defuse();
+                Self::stop_datapath();
+                drop(transfer);
+
+                self.wait_write_complete().await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read consecutive data blocks, with a single multiple block read command.
+    pub async fn read_blocks(&mut self, block_idx: u32, buffers: &mut [DataBlock]) -> Result<(), Error> {
+        if buffers.is_empty() {
+            return Ok(());
+        }
+        let card_capacity = self.card()?.card_type;
+        let len = buffers.len();
+
+        // NOTE(unsafe) DataBlock uses align 4 and has no padding, so the blocks are contiguous
+        let buffer = unsafe { core::slice::from_raw_parts_mut(buffers.as_mut_ptr() as *mut u32, len * 128) };
+
+        // SDSC cards are byte addressed hence the blockaddress is in multiples of 512 bytes
+        let address = match card_capacity {
+            CardCapacity::SDSC => block_idx * 512,
+            _ => block_idx,
+        };
+        Self::cmd(Cmd::set_block_length(512), false)?; // CMD16
+
+        let on_drop = OnDrop::new(|| Self::on_drop());
+
+        let transfer = self.prepare_datapath_read(buffer, 512 * len as u32, 9);
+        InterruptHandler::<T>::data_interrupts(true);
+        Self::cmd(Cmd::read_multiple_blocks(address), true)?;
+
+        let res = Self::complete_datapath_transfer().await;
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+            drop(transfer);
+            Self::cmd(Cmd::stop_transmission(), false)?; // CMD12
+        }
+        res
+    }
+
+    /// Write consecutive data blocks, with a single multiple block write command.
+    pub async fn write_blocks(&mut self, block_idx: u32, buffers: &[DataBlock]) -> Result<(), Error> {
+        if buffers.is_empty() {
+            return Ok(());
+        }
+        let card_capacity = self.card()?.card_type;
+        let len = buffers.len();
+
+        // NOTE(unsafe) DataBlock uses align 4 and has no padding, so the blocks are contiguous
+        let buffer = unsafe { core::slice::from_raw_parts(buffers.as_ptr() as *const u32, len * 128) };
+
+        // SDSC cards are byte addressed hence the blockaddress is in multiples of 512 bytes
+        let address = match card_capacity {
+            CardCapacity::SDSC => block_idx * 512,
+            _ => block_idx,
+        };
+        Self::cmd(Cmd::set_block_length(512), false)?; // CMD16
+
+        let on_drop = OnDrop::new(|| Self::on_drop());
+
+        // sdmmc_v1 uses different cmd/dma order than v2, but only for writes
+        #[cfg(sdmmc_v1)]
+        Self::cmd(Cmd::write_multiple_blocks(address), true)?;
+
+        let transfer = self.prepare_datapath_write(buffer, 512 * len as u32, 9);
+        InterruptHandler::<T>::data_interrupts(true);
+
+        #[cfg(sdmmc_v2)]
+        Self::cmd(Cmd::write_multiple_blocks(address), true)?;
+
+        let res = Self::complete_datapath_transfer().await;
+
+        match res {
+            Ok(_) => {
+                on_drop.defuse();
+                Self::stop_datapath();
+                drop(transfer);
+                Self::cmd(Cmd::stop_transmission(), false)?; // CMD12
+
+                self.wait_write_complete().await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait for the data path to finish the current transfer.
+    async fn complete_datapath_transfer() -> Result<(), Error> {
+        let regs = T::regs();
+
         let res = poll_fn(|cx| {
             T::state().register(cx.waker());
             let status = regs.star().read();
@@ -1264,28 +1349,24 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
         .await;
         Self::clear_interrupt_flags();
 
-        match res {
-            Ok(_) => {
-                on_drop.defuse();
-                Self::stop_datapath();
-                drop(transfer);
+        res
+    }
 
-                // TODO: Make this configurable
-                let mut timeout: u32 = 0x00FF_FFFF;
-
-                // Try to read card status (ACMD13)
-                while timeout > 0 {
-                    match self.read_sd_status().await {
-                        Ok(_) => return Ok(()),
-                        Err(Error::Timeout) => (), // Try again
-                        Err(e) => return Err(e),
-                    }
-                    timeout -= 1;
-                }
-                Err(Error::SoftwareTimeout)
+    /// Wait for the card to finish programming written data.
+    async fn wait_write_complete(&mut self) -> Result<(), Error> {
+        // TODO: Make this configurable
+        let mut timeout: u32 = 0x00FF_FFFF;
+
+        // Try to read card status (ACMD13)
+        while timeout > 0 {
+            match self.read_sd_status().await {
+                Ok(_) => return Ok(()),
+                Err(Error::Timeout) => (), // Try again
+                Err(e) => return Err(e),
             }
-            Err(e) => Err(e),
+            timeout -= 1;
         }
+        Err(Error::SoftwareTimeout)
     }
 
     /// Get a reference to the initialized card
@@ -1370,9 +1451,9 @@ impl Cmd {
     }
 
     /// CMD12:
-    //const fn stop_transmission() -> Cmd {
-    //    Cmd::new(12, 0, Response::Short)
-    //}
+    const fn stop_transmission() -> Cmd {
+        Cmd::new(12, 0, Response::Short)
+    }
 
     /// CMD13: Ask card to send status register
     /// ACMD13: SD Status
@@ -1391,15 +1472,20 @@ impl Cmd {
     }
 
     /// CMD18: Multiple Block Read
-    //const fn read_multiple_blocks(addr: u32) -> Cmd {
-    //    Cmd::new(18, addr, Response::Short)
-    //}
+    const fn read_multiple_blocks(addr: u32) -> Cmd {
+        Cmd::new(18, addr, Response::Short)
+    }
 
     /// CMD24: Block Write
     const fn write_single_block(addr: u32) -> Cmd {
         Cmd::new(24, addr, Response::Short)
     }
 
+    /// CMD25: Multiple Block Write
+    const fn write_multiple_blocks(addr: u32) -> Cmd {
+        Cmd::new(25, addr, Response::Short)
+    }
+
     const fn app_op_cmd(arg: u32) -> Cmd {
         Cmd::new(41, arg, Response::Short)
     }
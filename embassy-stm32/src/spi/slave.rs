@@ -0,0 +1,286 @@
+//! SPI slave driver.
+
+use embassy_futures::join::join;
+use embassy_futures::select::{select, Either};
+use embassy_hal_internal::{into_ref, PeripheralRef};
+
+use super::*;
+use crate::exti::{Channel as _, ExtiInputFuture};
+use crate::gpio::Pin as GpioPin;
+use crate::pac::gpio::vals::Idr;
+
+/// SPI slave driver.
+///
+/// The NSS pin is used both as the hardware chip select and, through its EXTI channel, to detect
+/// frame boundaries: a transfer ends when the host deasserts NSS, even if the buffers aren't
+/// full yet.
+pub struct SpiSlave<'d, T: Instance, Tx, Rx> {
+    _peri: PeripheralRef<'d, T>,
+    sck: PeripheralRef<'d, AnyPin>,
+    mosi: PeripheralRef<'d, AnyPin>,
+    miso: PeripheralRef<'d, AnyPin>,
+    nss: PeripheralRef<'d, AnyPin>,
+    txdma: PeripheralRef<'d, Tx>,
+    rxdma: PeripheralRef<'d, Rx>,
+    current_word_size: word_impl::Config,
+}
+
+impl<'d, T: Instance, Tx, Rx> SpiSlave<'d, T, Tx, Rx> {
+    /// Create a new SPI slave driver.
+    ///
+    /// `config.frequency` is ignored, the clock is provided by the host.
+    pub fn new<N: CsPin<T> + GpioPin>(
+        peri: impl Peripheral<P = T> + 'd,
+        sck: impl Peripheral<P = impl SckPin<T>> + 'd,
+        mosi: impl Peripheral<P = impl MosiPin<T>> + 'd,
+        miso: impl Peripheral<P = impl MisoPin<T>> + 'd,
+        nss: impl Peripheral<P = N> + 'd,
+        nss_exti: impl Peripheral<P = N::ExtiChannel> + 'd,
+        txdma: impl Peripheral<P = Tx> + 'd,
+        rxdma: impl Peripheral<P = Rx> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(peri, sck, mosi, miso, nss, nss_exti, txdma, rxdma);
+
+        // Needed if using AnyPin+AnyChannel.
+        assert_eq!(nss.pin(), nss_exti.number());
+
+        sck.set_as_af(sck.af_num(), AFType::Input);
+        sck.set_speed(crate::gpio::Speed::VeryHigh);
+        mosi.set_as_af(mosi.af_num(), AFType::Input);
+        mosi.set_speed(crate::gpio::Speed::VeryHigh);
+        miso.set_as_af(miso.af_num(), AFType::OutputPushPull);
+        miso.set_speed(crate::gpio::Speed::VeryHigh);
+        nss.set_as_af_pull(nss.af_num(), AFType::Input, Pull::Up);
+
+        T::enable_and_reset();
+
+        let cpha = config.raw_phase();
+        let cpol = config.raw_polarity();
+        let lsbfirst = config.raw_byte_order();
+
+        #[cfg(any(spi_v1, spi_f1))]
+        {
+            T::REGS.cr2().modify(|w| {
+                w.set_ssoe(false);
+            });
+            T::REGS.cr1().modify(|w| {
+                w.set_cpha(cpha);
+                w.set_cpol(cpol);
+
+                w.set_mstr(vals::Mstr::SLAVE);
+                w.set_lsbfirst(lsbfirst);
+                w.set_ssm(false);
+                w.set_crcen(false);
+                w.set_bidimode(vals::Bidimode::UNIDIRECTIONAL);
+                w.set_dff(<u8 as sealed::Word>::CONFIG)
+            });
+        }
+        #[cfg(spi_v2)]
+        {
+            T::REGS.cr2().modify(|w| {
+                let (ds, frxth) = <u8 as sealed::Word>::CONFIG;
+                w.set_frxth(frxth);
+                w.set_ds(ds);
+                w.set_ssoe(false);
+            });
+            T::REGS.cr1().modify(|w| {
+                w.set_cpha(cpha);
+                w.set_cpol(cpol);
+
+                w.set_mstr(vals::Mstr::SLAVE);
+                w.set_lsbfirst(lsbfirst);
+                w.set_ssm(false);
+                w.set_crcen(false);
+                w.set_bidimode(vals::Bidimode::UNIDIRECTIONAL);
+            });
+        }
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        {
+            T::REGS.ifcr().write(|w| w.0 = 0xffff_ffff);
+            T::REGS.cfg2().modify(|w| {
+                w.set_ssoe(false);
+                w.set_cpha(cpha);
+                w.set_cpol(cpol);
+                w.set_lsbfirst(lsbfirst);
+                w.set_ssm(false);
+                w.set_master(vals::Master::SLAVE);
+                w.set_comm(vals::Comm::FULLDUPLEX);
+                w.set_afcntr(true);
+                w.set_ssiop(vals::Ssiop::ACTIVELOW);
+            });
+            T::REGS.cfg1().modify(|w| {
+                w.set_crcen(false);
+                w.set_dsize(<u8 as sealed::Word>::CONFIG);
+                w.set_fthlv(vals::Fthlv::ONEFRAME);
+            });
+            T::REGS.cr2().modify(|w| {
+                w.set_tsize(0);
+            });
+        }
+
+        Self {
+            _peri: peri,
+            sck: sck.map_into(),
+            mosi: mosi.map_into(),
+            miso: miso.map_into(),
+            nss: nss.map_into(),
+            txdma,
+            rxdma,
+            current_word_size: <u8 as sealed::Word>::CONFIG,
+        }
+    }
+
+    fn set_word_size(&mut self, word_size: word_impl::Config) {
+        if self.current_word_size == word_size {
+            return;
+        }
+
+        // The peripheral is only enabled during a transfer, so there's no need to suspend it.
+        #[cfg(any(spi_v1, spi_f1))]
+        T::REGS.cr1().modify(|reg| reg.set_dff(word_size));
+        #[cfg(spi_v2)]
+        T::REGS.cr2().modify(|w| {
+            w.set_frxth(word_size.1);
+            w.set_ds(word_size.0);
+        });
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        T::REGS.cfg1().modify(|w| w.set_dsize(word_size));
+
+        self.current_word_size = word_size;
+    }
+
+    /// Return whether the host currently selects this device.
+    pub fn is_selected(&self) -> bool {
+        self.nss.block().idr().read().idr(self.nss.pin() as _) == Idr::LOW
+    }
+
+    /// Wait until the host selects this device.
+    ///
+    /// Returns immediately if NSS is already asserted.
+    pub async fn wait_for_select(&mut self) {
+        let fut = ExtiInputFuture::new(self.nss.pin(), self.nss.port(), false, true);
+        if self.is_selected() {
+            return;
+        }
+        fut.await
+    }
+
+    /// Wait until the host deselects this device.
+    ///
+    /// Returns immediately if NSS is already deasserted.
+    pub async fn wait_for_deselect(&mut self) {
+        let fut = ExtiInputFuture::new(self.nss.pin(), self.nss.port(), true, false);
+        if !self.is_selected() {
+            return;
+        }
+        fut.await
+    }
+
+    /// Exchange one frame with the host, using DMA.
+    ///
+    /// `write` is shifted out on MISO while data from MOSI is stored in `read`. The transfer
+    /// completes when `read` is full, or when the host deasserts NSS, whichever comes first.
+    /// Returns the number of words received.
+    ///
+    /// If the frame ends before `write` has been sent completely, words already loaded in the
+    /// transmit FIFO may be sent at the start of the next frame.
+    pub async fn transfer<W: Word>(&mut self, read: &mut [W], write: &[W]) -> Result<usize, Error>
+    where
+        Tx: TxDma<T>,
+        Rx: RxDma<T>,
+    {
+        self.transfer_inner(read, write).await
+    }
+
+    /// In-place frame exchange, using DMA.
+    ///
+    /// See [`transfer`](Self::transfer).
+    pub async fn transfer_in_place<W: Word>(&mut self, data: &mut [W]) -> Result<usize, Error>
+    where
+        Tx: TxDma<T>,
+        Rx: RxDma<T>,
+    {
+        self.transfer_inner(data, data).await
+    }
+
+    async fn transfer_inner<W: Word>(&mut self, read: *mut [W], write: *const [W]) -> Result<usize, Error>
+    where
+        Tx: TxDma<T>,
+        Rx: RxDma<T>,
+    {
+        let (_, len) = slice_ptr_parts(read);
+        let (_, tx_len) = slice_ptr_parts(write);
+        assert_eq!(len, tx_len);
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.set_word_size(W::CONFIG);
+
+        // SPIv3 clears rxfifo on SPE=0
+        #[cfg(not(any(spi_v3, spi_v4, spi_v5)))]
+        flush_rx_fifo(T::REGS);
+
+        set_rxdmaen(T::REGS, true);
+
+        let rx_request = self.rxdma.request();
+        let rx_src = T::REGS.rx_ptr();
+        let mut rx_f = unsafe { Transfer::new_read_raw(&mut self.rxdma, rx_request, rx_src, read, Default::default()) };
+
+        let tx_request = self.txdma.request();
+        let tx_dst = T::REGS.tx_ptr();
+        let mut tx_f = unsafe { Transfer::new_write_raw(&mut self.txdma, tx_request, write, tx_dst, Default::default()) };
+
+        set_txdmaen(T::REGS, true);
+
+        // Arm the end-of-frame detection before enabling the peripheral, so a short frame isn't
+        // missed.
+        let nss_rising = ExtiInputFuture::new(self.nss.pin(), self.nss.port(), true, false);
+
+        T::REGS.cr1().modify(|w| {
+            w.set_spe(true);
+        });
+
+        let received = match select(join(&mut tx_f, &mut rx_f), nss_rising).await {
+            Either::First(_) => len,
+            Either::Second(()) => {
+                rx_f.request_stop();
+                tx_f.request_stop();
+                while rx_f.is_running() || tx_f.is_running() {}
+
+                len - rx_f.get_remaining_transfers() as usize
+            }
+        };
+        drop(rx_f);
+        drop(tx_f);
+
+        let sr = T::REGS.sr().read();
+
+        // A slave can't wait for the bus to go idle: the host may have stopped clocking in the
+        // middle of a word. Just disable the peripheral.
+        T::REGS.cr1().modify(|w| {
+            w.set_spe(false);
+        });
+        set_txdmaen(T::REGS, false);
+        set_rxdmaen(T::REGS, false);
+
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        T::REGS.ifcr().write(|w| w.0 = 0xffff_ffff);
+
+        check_error_flags(sr)?;
+
+        Ok(received)
+    }
+}
+
+impl<'d, T: Instance, Tx, Rx> Drop for SpiSlave<'d, T, Tx, Rx> {
+    fn drop(&mut self) {
+        self.sck.set_as_disconnected();
+        self.mosi.set_as_disconnected();
+        self.miso.set_as_disconnected();
+        self.nss.set_as_disconnected();
+
+        T::disable();
+    }
+}
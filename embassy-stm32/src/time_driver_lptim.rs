@@ -0,0 +1,395 @@
+#![allow(non_snake_case)]
+
+//! Time driver using a low-power timer.
+//!
+//! LPTIM can be clocked from LSE or LSI, which keep running in Stop mode, so the time base doesn't
+//! need to be paused and compensated with the RTC wakeup timer like the TIM-based driver does.
+//! The LPTIM kernel clock is selected through the RCC mux configuration, and divided down to
+//! `TICK_HZ` with the LPTIM prescaler, so it must be a power-of-two multiple (up to 128) of it.
+
+use core::cell::Cell;
+use core::sync::atomic::{compiler_fence, AtomicU32, AtomicU8, Ordering};
+use core::{mem, ptr};
+
+use critical_section::CriticalSection;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time_driver::{AlarmHandle, Driver, TICK_HZ};
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::lptim::Lptim;
+use crate::rcc::sealed::RccPeripheral;
+#[cfg(feature = "low-power")]
+use crate::rtc::Rtc;
+use crate::{interrupt, pac, peripherals};
+
+// LPTIM only has a single compare register, which is used for the alarm. Overflows are tracked
+// with the autoreload match interrupt.
+//
+// LPTIM_IER can only be written while the timer is disabled, so the compare interrupt stays enabled
+// and matches are filtered against the alarm timestamp in the interrupt handler. This costs one
+// spurious interrupt per overflow cycle at most.
+//
+// A write to LPTIM_CMP takes a few LPTIM clock cycles to be synchronized, and the register can't be
+// written again until CMPOK is set. Rather than waiting for that with interrupts disabled, a write
+// that comes in while another one is synchronizing is kept pending and done from the CMPOK interrupt.
+const ALARM_COUNT: usize = 1;
+
+#[cfg(time_driver_lptim1)]
+type T = peripherals::LPTIM1;
+#[cfg(time_driver_lptim1)]
+const LPTIM: Lptim = pac::LPTIM1;
+#[cfg(time_driver_lptim2)]
+type T = peripherals::LPTIM2;
+#[cfg(time_driver_lptim2)]
+const LPTIM: Lptim = pac::LPTIM2;
+
+foreach_interrupt! {
+    (LPTIM1, lptim, $block:ident, GLOBAL, $irq:ident) => {
+        #[cfg(time_driver_lptim1)]
+        type Irq = crate::interrupt::typelevel::$irq;
+
+        #[cfg(time_driver_lptim1)]
+        #[cfg(feature = "rt")]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+    (LPTIM2, lptim, $block:ident, GLOBAL, $irq:ident) => {
+        #[cfg(time_driver_lptim2)]
+        type Irq = crate::interrupt::typelevel::$irq;
+
+        #[cfg(time_driver_lptim2)]
+        #[cfg(feature = "rt")]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+}
+
+// The counter is 16 bits, and `period` counts its overflows. Unlike the TIM driver, there's no
+// spare compare channel to detect the half-way point, so `now()` resolves the race with an
+// overflow by checking whether the autoreload match flag is still pending.
+//
+// `period` is a 32bit integer, so it overflows on 2^32 * 2^16 / 32768 seconds of uptime, which is 272 years.
+fn calc_now(period: u32, counter: u16) -> u64 {
+    ((period as u64) << 16) + counter as u64
+}
+
+/// Read the counter. When LPTIM runs from an asynchronous clock, the counter must be read
+/// until two consecutive reads match.
+fn read_counter() -> u16 {
+    let mut cnt = LPTIM.cnt().read().cnt();
+    loop {
+        let next = LPTIM.cnt().read().cnt();
+        if next == cnt {
+            return cnt;
+        }
+        cnt = next;
+    }
+}
+
+/// State of the writes to the compare register.
+struct CompareState {
+    /// The value in the compare register, or being written to it
+    written: Cell<u16>,
+    /// Whether a write is being synchronized, so the register can't be written yet
+    busy: Cell<bool>,
+    /// The value to write once the current write is synchronized
+    pending: Cell<Option<u16>>,
+}
+
+impl CompareState {
+    const fn new() -> Self {
+        Self {
+            written: Cell::new(0),
+            busy: Cell::new(false),
+            pending: Cell::new(None),
+        }
+    }
+}
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+
+    // This is really a Option<(fn(*mut ()), *mut ())>
+    // but fn pointers aren't allowed in const yet
+    callback: Cell<*const ()>,
+    ctx: Cell<*mut ()>,
+}
+
+unsafe impl Send for AlarmState {}
+
+impl AlarmState {
+    const fn new() -> Self {
+        Self {
+            timestamp: Cell::new(u64::MAX),
+            callback: Cell::new(ptr::null()),
+            ctx: Cell::new(ptr::null_mut()),
+        }
+    }
+}
+
+pub(crate) struct RtcDriver {
+    /// Number of counter overflows since boot.
+    period: AtomicU32,
+    alarm_count: AtomicU8,
+    /// Timestamp at which to fire alarm. u64::MAX if no alarm is scheduled.
+    alarms: Mutex<CriticalSectionRawMutex, [AlarmState; ALARM_COUNT]>,
+    compare: Mutex<CriticalSectionRawMutex, CompareState>,
+}
+
+const ALARM_STATE_NEW: AlarmState = AlarmState::new();
+
+embassy_time_driver::time_driver_impl!(static DRIVER: RtcDriver = RtcDriver {
+    period: AtomicU32::new(0),
+    alarm_count: AtomicU8::new(0),
+    alarms: Mutex::const_new(CriticalSectionRawMutex::new(), [ALARM_STATE_NEW; ALARM_COUNT]),
+    compare: Mutex::const_new(CriticalSectionRawMutex::new(), CompareState::new()),
+});
+
+impl RtcDriver {
+    fn init(&'static self, cs: critical_section::CriticalSection) {
+        let r = LPTIM;
+
+        <T as RccPeripheral>::enable_and_reset_with_cs(cs);
+
+        let timer_freq = T::frequency();
+
+        let div = timer_freq.0 / TICK_HZ as u32;
+        assert!(
+            div.is_power_of_two() && div <= 128 && div * TICK_HZ as u32 == timer_freq.0,
+            "LPTIM clock must be a power-of-two multiple of TICK_HZ"
+        );
+
+        // The configuration and interrupt enables can only be written while the timer is disabled.
+        r.cr().modify(|w| w.set_enable(false));
+        r.cfgr()
+            .write(|w| w.set_presc(pac::lptim::vals::Presc::from_bits(div.trailing_zeros() as u8)));
+        r.ier().write(|w| {
+            w.set_arrmie(true);
+            w.set_cmpmie(true);
+            w.set_cmpokie(true);
+        });
+
+        // Autoreload and compare can only be written while the timer is enabled.
+        r.cr().modify(|w| w.set_enable(true));
+
+        r.icr().write(|w| w.set_arrokcf(true));
+        r.arr().write(|w| w.set_arr(u16::MAX));
+        while !r.isr().read().arrok() {}
+
+        self.write_compare(cs, u16::MAX);
+
+        // Clear all flags.
+        r.icr().write(|w| {
+            w.set_arrmcf(true);
+            w.set_cmpmcf(true);
+        });
+
+        Irq::unpend();
+        unsafe { Irq::enable() };
+
+        // Start in continuous mode.
+        r.cr().modify(|w| w.set_cntstrt(true));
+    }
+
+    fn on_interrupt(&self) {
+        let r = LPTIM;
+
+        critical_section::with(|cs| {
+            let isr = r.isr().read();
+
+            // Overflow
+            if isr.arrm() {
+                r.icr().write(|w| w.set_arrmcf(true));
+                self.next_period();
+            }
+
+            if isr.cmpok() {
+                r.icr().write(|w| w.set_cmpokcf(true));
+
+                let compare = self.compare.borrow(cs);
+                compare.busy.set(false);
+                if let Some(value) = compare.pending.take() {
+                    self.write_compare(cs, value);
+                }
+
+                // The counter may have passed the new compare value before it was synchronized,
+                // in which case it won't match until the next overflow cycle.
+                self.check_alarms(cs);
+            }
+
+            if isr.cmpm() {
+                r.icr().write(|w| w.set_cmpmcf(true));
+                self.check_alarms(cs);
+            }
+        })
+    }
+
+    /// Trigger the alarms that are due.
+    fn check_alarms(&self, cs: CriticalSection) {
+        for n in 0..ALARM_COUNT {
+            // The compare matches once per overflow, only fire if the alarm is really due.
+            if self.alarms.borrow(cs)[n].timestamp.get() <= self.now() {
+                self.trigger_alarm(n, cs);
+            }
+        }
+    }
+
+    /// Write the compare register, or leave the value to be written from the CMPOK interrupt if a
+    /// previous write is still being synchronized.
+    fn write_compare(&self, cs: CriticalSection, value: u16) {
+        let compare = self.compare.borrow(cs);
+        if compare.busy.get() {
+            compare.pending.set((value != compare.written.get()).then_some(value));
+        } else if value != compare.written.get() {
+            LPTIM.icr().write(|w| w.set_cmpokcf(true));
+            LPTIM.cmp().write(|w| w.set_cmp(value));
+            compare.written.set(value);
+            compare.busy.set(true);
+        }
+    }
+
+    fn next_period(&self) {
+        // We only modify the period from the timer interrupt, so we know this can't race.
+        let period = self.period.load(Ordering::Relaxed) + 1;
+        self.period.store(period, Ordering::Relaxed);
+    }
+
+    fn get_alarm<'a>(&'a self, cs: CriticalSection<'a>, alarm: AlarmHandle) -> &'a AlarmState {
+        // safety: we're allowed to assume the AlarmState is created by us, and
+        // we never create one that's out of bounds.
+        unsafe { self.alarms.borrow(cs).get_unchecked(alarm.id() as usize) }
+    }
+
+    fn trigger_alarm(&self, n: usize, cs: CriticalSection) {
+        let alarm = &self.alarms.borrow(cs)[n];
+        alarm.timestamp.set(u64::MAX);
+
+        // Call after clearing alarm, so the callback can set another alarm.
+
+        // safety:
+        // - we can ignore the possibility of `f` being unset (null) because of the safety contract of `allocate_alarm`.
+        // - other than that we only store valid function pointers into alarm.callback
+        let f: fn(*mut ()) = unsafe { mem::transmute(alarm.callback.get()) };
+        f(alarm.ctx.get());
+    }
+
+    /*
+        Low-power functions: LPTIM keeps counting in Stop mode, and its interrupt wakes the core
+        up, so there's nothing to pause or compensate.
+    */
+
+    #[cfg(feature = "low-power")]
+    /// The RTC isn't needed to keep time in Stop mode.
+    pub(crate) fn set_rtc(&self, _rtc: &'static Rtc) {}
+
+    #[cfg(feature = "low-power")]
+    /// The minimum pause time beyond which the executor will enter a low-power state.
+    pub(crate) const MIN_STOP_PAUSE: embassy_time::Duration = embassy_time::Duration::from_millis(0);
+
+    #[cfg(feature = "low-power")]
+    /// Time keeps running in Stop mode, so entering it is always allowed.
+    pub(crate) fn pause_time(&self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    #[cfg(feature = "low-power")]
+    /// Time keeps running in Stop mode, so there's nothing to resume.
+    pub(crate) fn resume_time(&self) {}
+}
+
+impl Driver for RtcDriver {
+    fn now(&self) -> u64 {
+        loop {
+            let period = self.period.load(Ordering::Relaxed);
+            compiler_fence(Ordering::Acquire);
+            let counter = read_counter();
+            let overflow_pending = LPTIM.isr().read().arrm();
+            compiler_fence(Ordering::Acquire);
+
+            // Retry if the interrupt handled an overflow in the meantime.
+            if self.period.load(Ordering::Relaxed) != period {
+                continue;
+            }
+
+            // The counter wrapped but the interrupt hasn't run yet. A high counter value was read
+            // before the wrap, so it still belongs to the current period.
+            let period = if overflow_pending && counter < 0x8000 {
+                period + 1
+            } else {
+                period
+            };
+
+            return calc_now(period, counter);
+        }
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        critical_section::with(|_| {
+            let id = self.alarm_count.load(Ordering::Relaxed);
+            if id < ALARM_COUNT as u8 {
+                self.alarm_count.store(id + 1, Ordering::Relaxed);
+                Some(AlarmHandle::new(id))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+
+            alarm.callback.set(callback as *const ());
+            alarm.ctx.set(ctx);
+        })
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+            alarm.timestamp.set(timestamp);
+
+            let t = self.now();
+            if timestamp <= t {
+                // If alarm timestamp has passed the alarm will not fire.
+                // Disarm the alarm and return `false` to indicate that.
+                alarm.timestamp.set(u64::MAX);
+
+                return false;
+            }
+
+            // If the alarm is more than one overflow cycle away, the compare will match early
+            // and the interrupt handler will ignore it.
+            self.write_compare(cs, timestamp as u16);
+
+            // Reevaluate if the alarm timestamp is still in the future
+            let t = self.now();
+            if timestamp <= t {
+                // If alarm timestamp has passed since we set it, we have a race condition and
+                // the alarm may or may not have fired.
+                // Disarm the alarm and return `false` to indicate that.
+                // It is the caller's responsibility to handle this ambiguity.
+                alarm.timestamp.set(u64::MAX);
+
+                return false;
+            }
+
+            // We're confident the alarm will ring in the future.
+            true
+        })
+    }
+}
+
+#[cfg(feature = "low-power")]
+pub(crate) fn get_driver() -> &'static RtcDriver {
+    &DRIVER
+}
+
+pub(crate) fn init(cs: CriticalSection) {
+    DRIVER.init(cs)
+}
@@ -0,0 +1,314 @@
+//! Input capture driver.
+//!
+//! Timestamps are extended to 32 bits by counting counter overflows in the timer interrupt, so
+//! edges further apart than one counter period (for example a slow tachometer) are still
+//! measured correctly.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::*;
+#[allow(unused_imports)]
+use crate::gpio::sealed::{AFType, Pin};
+use crate::gpio::AnyPin;
+use crate::interrupt::typelevel::Interrupt;
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// Channel 1 marker type.
+pub enum Ch1 {}
+/// Channel 2 marker type.
+pub enum Ch2 {}
+/// Channel 3 marker type.
+pub enum Ch3 {}
+/// Channel 4 marker type.
+pub enum Ch4 {}
+
+/// Capture pin wrapper.
+///
+/// This wraps a pin to make it usable with input capture.
+pub struct CapturePin<'d, T, C> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<(T, C)>,
+}
+
+macro_rules! channel_impl {
+    ($new_chx:ident, $channel:ident, $pin_trait:ident) => {
+        impl<'d, T: CaptureCompare16bitInstance> CapturePin<'d, T, $channel> {
+            #[doc = concat!("Create a new ", stringify!($channel), " capture pin instance.")]
+            pub fn $new_chx(pin: impl Peripheral<P = impl $pin_trait<T>> + 'd) -> Self {
+                into_ref!(pin);
+                critical_section::with(|_| {
+                    pin.set_as_af(pin.af_num(), AFType::Input);
+                    #[cfg(gpio_v2)]
+                    pin.set_speed(crate::gpio::Speed::VeryHigh);
+                });
+                CapturePin {
+                    _pin: pin.map_into(),
+                    phantom: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+channel_impl!(new_ch1, Ch1, Channel1Pin);
+channel_impl!(new_ch2, Ch2, Channel2Pin);
+channel_impl!(new_ch3, Ch3, Channel3Pin);
+channel_impl!(new_ch4, Ch4, Channel4Pin);
+
+/// Per-timer input capture state, shared with the interrupt handler.
+pub struct State {
+    overflows: AtomicU32,
+    captures: [AtomicU32; 4],
+    wakers: [AtomicWaker; 4],
+}
+
+impl State {
+    pub(crate) const fn new() -> Self {
+        Self {
+            overflows: AtomicU32::new(0),
+            captures: [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)],
+            wakers: [AtomicWaker::new(), AtomicWaker::new(), AtomicWaker::new(), AtomicWaker::new()],
+        }
+    }
+}
+
+/// Timer interrupt handler.
+///
+/// On general purpose timers this handles both counter overflows and captures.
+pub struct InterruptHandler<T: CaptureCompare16bitInstance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: CaptureCompare16bitInstance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        on_interrupt::<T>()
+    }
+}
+
+/// Capture compare interrupt handler.
+///
+/// Advanced control timers have a separate capture compare interrupt, which must be bound to
+/// this handler in addition to [`InterruptHandler`].
+#[cfg(not(stm32l0))]
+pub struct CaptureCompareInterruptHandler<T: ComplementaryCaptureCompare16bitInstance> {
+    _phantom: PhantomData<T>,
+}
+
+#[cfg(not(stm32l0))]
+impl<T: ComplementaryCaptureCompare16bitInstance> interrupt::typelevel::Handler<T::CaptureCompareInterrupt>
+    for CaptureCompareInterruptHandler<T>
+{
+    unsafe fn on_interrupt() {
+        on_interrupt::<T>()
+    }
+}
+
+unsafe fn on_interrupt<T: CaptureCompare16bitInstance>() {
+    // The update and capture compare handlers may run at different priorities on advanced
+    // timers, keep the overflow count and the captures consistent.
+    critical_section::with(|_| {
+        let regs = T::regs_gp16();
+        let state = T::state();
+
+        let sr = regs.sr().read();
+        let dier = regs.dier().read();
+
+        let overflowed = sr.uif();
+        if overflowed {
+            regs.sr().modify(|w| w.set_uif(false));
+        }
+        let overflows = state.overflows.load(Ordering::Relaxed);
+
+        for n in 0..4 {
+            if !(sr.ccif(n) && dier.ccie(n)) {
+                continue;
+            }
+
+            // Reading the capture register clears the flag.
+            let value = regs.ccr(n).read().ccr();
+
+            // If an overflow is pending as well, a capture in the lower half of the counter range
+            // happened after the wrap around, one in the upper half before it.
+            let high = if overflowed && value < 0x8000 {
+                overflows.wrapping_add(1)
+            } else {
+                overflows
+            };
+
+            state.captures[n].store((high << 16) | value as u32, Ordering::Relaxed);
+            regs.dier().modify(|w| w.set_ccie(n, false));
+            state.wakers[n].wake();
+        }
+
+        if overflowed {
+            state.overflows.store(overflows.wrapping_add(1), Ordering::Relaxed);
+        }
+    })
+}
+
+/// Input capture driver.
+pub struct InputCapture<'d, T> {
+    inner: PeripheralRef<'d, T>,
+}
+
+impl<'d, T: CaptureCompare16bitInstance> InputCapture<'d, T> {
+    /// Create a new input capture driver.
+    ///
+    /// The counter runs freely at `tick_freq`, which is the resolution of all timestamps.
+    /// Channels with a pin are enabled and capture rising edges.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        ch1: Option<CapturePin<'d, T, Ch1>>,
+        ch2: Option<CapturePin<'d, T, Ch2>>,
+        ch3: Option<CapturePin<'d, T, Ch3>>,
+        ch4: Option<CapturePin<'d, T, Ch4>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        tick_freq: Hertz,
+    ) -> Self {
+        into_ref!(tim);
+
+        T::enable_and_reset();
+
+        let this = Self { inner: tim };
+
+        let timer_f = T::frequency().0;
+        assert!(tick_freq.0 > 0 && tick_freq.0 <= timer_f);
+        let psc: u16 = unwrap!((timer_f / tick_freq.0 - 1).try_into());
+
+        let regs = T::regs_gp16();
+        regs.psc().write(|w| w.set_psc(psc));
+        regs.arr().write(|w| w.set_arr(u16::MAX));
+
+        regs.cr1().modify(|r| r.set_urs(vals::Urs::COUNTERONLY));
+        regs.egr().write(|r| r.set_ug(true));
+        regs.cr1().modify(|r| r.set_urs(vals::Urs::ANYEVENT));
+
+        T::state().overflows.store(0, Ordering::Relaxed);
+
+        let channels = [
+            (Channel::Ch1, ch1.is_some()),
+            (Channel::Ch2, ch2.is_some()),
+            (Channel::Ch3, ch3.is_some()),
+            (Channel::Ch4, ch4.is_some()),
+        ];
+        for (channel, used) in channels {
+            this.inner.set_input_ti_selection(channel, InputTISelection::Normal);
+            this.inner.set_input_capture_mode(channel, InputCaptureMode::Rising);
+            this.inner.enable_channel(channel, used);
+        }
+
+        this.inner.clear_update_interrupt();
+        this.inner.enable_update_interrupt(true);
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        this.inner.start();
+
+        this
+    }
+
+    /// Enable the given channel.
+    pub fn enable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, true);
+    }
+
+    /// Disable the given channel.
+    pub fn disable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, false);
+    }
+
+    /// Check whether given channel is enabled
+    pub fn is_enabled(&self, channel: Channel) -> bool {
+        self.inner.get_channel_enable_state(channel)
+    }
+
+    /// Set the edges captured on the given channel.
+    pub fn set_capture_mode(&mut self, channel: Channel, mode: InputCaptureMode) {
+        self.inner.set_input_capture_mode(channel, mode);
+    }
+
+    /// Set the digital input filter of the given channel.
+    pub fn set_filter(&mut self, channel: Channel, filter: vals::FilterValue) {
+        self.inner.set_input_capture_filter(channel, filter);
+    }
+
+    /// Set the input capture prescaler of the given channel, capturing every 1, 2, 4 or 8 edges
+    /// (`factor` 0 to 3).
+    pub fn set_prescaler(&mut self, channel: Channel, factor: u8) {
+        self.inner.set_input_capture_prescaler(channel, factor);
+    }
+
+    /// Get the counter tick frequency.
+    pub fn tick_frequency(&self) -> Hertz {
+        let psc = T::regs_gp16().psc().read().psc();
+        T::frequency() / (psc as u32 + 1)
+    }
+
+    /// Wait for the next capture event on `channel`, and return its 32-bit timestamp in ticks.
+    pub async fn wait_for_capture(&mut self, channel: Channel) -> u32 {
+        let n = channel.index();
+        let regs = T::regs_gp16();
+        let state = T::state();
+
+        // Discard any capture that happened before the call.
+        let _ = regs.ccr(n).read();
+        regs.sr().modify(|w| w.set_ccof(n, false));
+
+        let on_drop = OnDrop::new(|| regs.dier().modify(|w| w.set_ccie(n, false)));
+        regs.dier().modify(|w| w.set_ccie(n, true));
+
+        let timestamp = poll_fn(|cx| {
+            state.wakers[n].register(cx.waker());
+
+            if regs.dier().read().ccie(n) {
+                Poll::Pending
+            } else {
+                Poll::Ready(state.captures[n].load(Ordering::Relaxed))
+            }
+        })
+        .await;
+
+        on_drop.defuse();
+        timestamp
+    }
+
+    /// Measure the time between two consecutive rising edges on `channel`, in ticks.
+    pub async fn measure_period(&mut self, channel: Channel) -> u32 {
+        self.inner.set_input_capture_mode(channel, InputCaptureMode::Rising);
+
+        let start = self.wait_for_capture(channel).await;
+        let end = self.wait_for_capture(channel).await;
+        end.wrapping_sub(start)
+    }
+
+    /// Measure the width of the next high pulse on `channel`, in ticks.
+    ///
+    /// The channel is left capturing rising edges.
+    pub async fn measure_pulse_width(&mut self, channel: Channel) -> u32 {
+        self.inner.set_input_capture_mode(channel, InputCaptureMode::Rising);
+        let start = self.wait_for_capture(channel).await;
+
+        self.inner.set_input_capture_mode(channel, InputCaptureMode::Falling);
+        let end = self.wait_for_capture(channel).await;
+
+        self.inner.set_input_capture_mode(channel, InputCaptureMode::Rising);
+        end.wrapping_sub(start)
+    }
+}
+
+impl<'d, T: CaptureCompare16bitInstance> Drop for InputCapture<'d, T> {
+    fn drop(&mut self) {
+        T::Interrupt::disable();
+        self.inner.stop();
+        T::disable();
+    }
+}
@@ -33,6 +33,7 @@
 
 #[cfg(not(stm32l0))]
 pub mod complementary_pwm;
+pub mod input_capture;
 pub mod qei;
 pub mod simple_pwm;
 
@@ -217,6 +218,9 @@ pub(crate) mod sealed {
         /// more capable timers.
         fn regs_gp16() -> crate::pac::timer::TimGp16;
 
+        /// Get the input capture state of this timer.
+        fn state() -> &'static super::input_capture::State;
+
         /// Set counting mode.
         fn set_counting_mode(&self, mode: CountingMode) {
             let (cms, dir) = mode.into();
@@ -795,6 +799,11 @@ macro_rules! impl_gp16_timer {
             fn regs_gp16() -> crate::pac::timer::TimGp16 {
                 unsafe { crate::pac::timer::TimGp16::from_ptr(crate::pac::$inst.as_ptr()) }
             }
+
+            fn state() -> &'static input_capture::State {
+                static STATE: input_capture::State = input_capture::State::new();
+                &STATE
+            }
         }
     };
 }
@@ -55,6 +55,10 @@ channel_impl!(new_ch2, Ch2, Channel2Pin);
 /// Quadrature decoder driver.
 pub struct Qei<'d, T> {
     _inner: PeripheralRef<'d, T>,
+    last_count: u16,
+    position: i32,
+    #[cfg(feature = "time")]
+    last_sample: Option<(embassy_time::Instant, i32)>,
 }
 
 impl<'d, T: CaptureCompare16bitInstance> Qei<'d, T> {
@@ -90,7 +94,13 @@ impl<'d, T: CaptureCompare16bitInstance> Qei<'d, T> {
         T::regs_gp16().arr().modify(|w| w.set_arr(u16::MAX));
         T::regs_gp16().cr1().modify(|w| w.set_cen(true));
 
-        Self { _inner: tim }
+        Self {
+            _inner: tim,
+            last_count: 0,
+            position: 0,
+            #[cfg(feature = "time")]
+            last_sample: None,
+        }
     }
 
     /// Get direction.
@@ -105,4 +115,55 @@ impl<'d, T: CaptureCompare16bitInstance> Qei<'d, T> {
     pub fn count(&self) -> u16 {
         T::regs_gp16().cnt().read().cnt()
     }
+
+    /// Get the signed position, in counts since the driver was created or the position was last
+    /// set.
+    ///
+    /// The 16-bit counter wraps around, so this must be called at least once every 32767
+    /// counts for the position to stay correct.
+    pub fn position(&mut self) -> i32 {
+        let count = self.count();
+        let delta = count.wrapping_sub(self.last_count) as i16;
+
+        self.last_count = count;
+        self.position = self.position.wrapping_add(delta as i32);
+        self.position
+    }
+
+    /// Set the current position.
+    pub fn set_position(&mut self, position: i32) {
+        self.last_count = self.count();
+        self.position = position;
+
+        #[cfg(feature = "time")]
+        {
+            self.last_sample = None;
+        }
+    }
+
+    /// Estimate the speed, in counts per second, since the previous call.
+    ///
+    /// Returns `None` on the first call, as there's nothing to compare with yet. Call this at a
+    /// regular interval, for example from a control loop ticker: a longer interval gives a
+    /// finer speed resolution at low speeds.
+    #[cfg(feature = "time")]
+    pub fn speed(&mut self) -> Option<f32> {
+        let now = embassy_time::Instant::now();
+        let position = self.position();
+
+        let speed = match self.last_sample {
+            Some((then, last_position)) => {
+                let elapsed = now.duration_since(then).as_micros();
+                if elapsed == 0 {
+                    return None;
+                }
+                let delta = position.wrapping_sub(last_position) as f32;
+                Some(delta * 1_000_000.0 / elapsed as f32)
+            }
+            None => None,
+        };
+
+        self.last_sample = Some((now, position));
+        speed
+    }
 }
@@ -162,8 +162,58 @@ impl<'d, T: BasicInstance> RingBufferedUartRx<'d, T> {
         }
     }
 
+    /// Read a whole packet, delimited by an idle line, into `buf`.
+    ///
+    /// Unlike [`read`](Self::read), which returns as soon as some bytes are available, this keeps
+    /// reading until the line goes idle or `buf` is full, so a packet split over the two halves
+    /// of the DMA buffer is still returned at once. Returns the number of bytes read.
+    ///
+    /// Background receive is started if `start()` has not been previously called.
+    ///
+    /// Receive in the background is terminated if an error is returned.
+    /// It must then manually be started again by calling `start()` or by re-calling `read()`.
+    pub async fn read_until_idle(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let r = T::regs();
+
+        // Start background receive if it was not already started
+        if !r.cr3().read().dmar() {
+            self.start()?;
+        }
+
+        let mut len = 0;
+        loop {
+            match self.ring_buf.read(&mut buf[len..]) {
+                Ok((n, _)) => len += n,
+                Err(_) => {
+                    return self.stop(Error::Overrun);
+                }
+            }
+
+            if len == buf.len() {
+                return Ok(len);
+            }
+
+            match self.wait_for_data_or_idle().await {
+                // An idle line before any data is the end of a previous packet, keep waiting.
+                Ok(true) if len > 0 => {
+                    // The DMA may still hold the last bytes of the packet.
+                    match self.ring_buf.read(&mut buf[len..]) {
+                        Ok((n, _)) => return Ok(len + n),
+                        Err(_) => return self.stop(Error::Overrun),
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    return self.stop(err);
+                }
+            }
+        }
+    }
+
     /// Wait for uart idle or dma half-full or full
-    async fn wait_for_data_or_idle(&mut self) -> Result<(), Error> {
+    ///
+    /// Returns whether an idle line was detected.
+    async fn wait_for_data_or_idle(&mut self) -> Result<bool, Error> {
         compiler_fence(Ordering::SeqCst);
 
         let mut dma_init = false;
@@ -202,8 +252,8 @@ impl<'d, T: BasicInstance> RingBufferedUartRx<'d, T> {
         });
 
         match select(dma, uart).await {
-            Either::Left(((), _)) => Ok(()),
-            Either::Right((result, _)) => result,
+            Either::Left(((), _)) => Ok(false),
+            Either::Right((result, _)) => result.map(|()| true),
         }
     }
 }
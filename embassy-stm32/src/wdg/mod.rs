@@ -6,6 +6,16 @@ use stm32_metapac::iwdg::vals::{Key, Pr};
 
 use crate::rcc::LSI_FREQ;
 
+#[cfg(feature = "time")]
+mod petter;
+#[cfg(wwdg)]
+mod wwdg;
+
+#[cfg(feature = "time")]
+pub use petter::{Heartbeat, Watchdog, WatchdogPetter};
+#[cfg(wwdg)]
+pub use wwdg::{WindowWatchdog, WwdgInstance};
+
 /// Independent watchdog (IWDG) driver.
 pub struct IndependentWatchdog<'d, T: Instance> {
     wdg: PhantomData<&'d mut T>,
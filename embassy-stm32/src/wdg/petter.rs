@@ -0,0 +1,74 @@
+//! Feeding a watchdog only when all critical tasks are alive.
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+
+/// A watchdog that can be petted by a [`WatchdogPetter`].
+pub trait Watchdog {
+    /// Pet (reload, refresh) the watchdog.
+    fn pet(&mut self);
+}
+
+impl<'d, T: super::Instance> Watchdog for super::IndependentWatchdog<'d, T> {
+    fn pet(&mut self) {
+        super::IndependentWatchdog::pet(self)
+    }
+}
+
+#[cfg(wwdg)]
+impl<'d, T: super::WwdgInstance> Watchdog for super::WindowWatchdog<'d, T> {
+    fn pet(&mut self) {
+        super::WindowWatchdog::pet(self)
+    }
+}
+
+/// Heartbeat signal, to be signaled periodically by a task monitored by a [`WatchdogPetter`].
+pub type Heartbeat<M> = Signal<M, ()>;
+
+/// Pets a watchdog only once all monitored tasks have signaled their heartbeat.
+///
+/// Each critical task periodically signals its [`Heartbeat`]. The petter checks the heartbeats
+/// every `interval`, and pets the watchdog once every heartbeat has been seen since the last pet.
+/// If a task hangs, the watchdog is no longer petted and resets the MCU.
+///
+/// The watchdog should be unleashed before running the petter, and its timeout must be longer
+/// than the interval at which the slowest task signals its heartbeat. With a [`WindowWatchdog`](super::WindowWatchdog),
+/// `interval` must also be longer than the window.
+pub struct WatchdogPetter<'a, M: RawMutex, W: Watchdog, const N: usize> {
+    watchdog: W,
+    heartbeats: [&'a Heartbeat<M>; N],
+    interval: Duration,
+}
+
+impl<'a, M: RawMutex, W: Watchdog, const N: usize> WatchdogPetter<'a, M, W, N> {
+    /// Create a new petter, checking the `heartbeats` every `interval`.
+    pub fn new(watchdog: W, heartbeats: [&'a Heartbeat<M>; N], interval: Duration) -> Self {
+        Self {
+            watchdog,
+            heartbeats,
+            interval,
+        }
+    }
+
+    /// Run the petter. This is meant to be run in a dedicated task.
+    pub async fn run(&mut self) -> ! {
+        let mut seen = [false; N];
+
+        loop {
+            Timer::after(self.interval).await;
+
+            for (seen, heartbeat) in seen.iter_mut().zip(self.heartbeats.iter()) {
+                if heartbeat.try_take().is_some() {
+                    *seen = true;
+                }
+            }
+
+            if seen.iter().all(|s| *s) {
+                self.watchdog.pet();
+                seen = [false; N];
+            } else {
+                trace!("watchdog petter: waiting for heartbeats");
+            }
+        }
+    }
+}
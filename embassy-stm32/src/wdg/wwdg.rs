@@ -0,0 +1,106 @@
+//! Window watchdog (WWDG)
+use core::marker::PhantomData;
+
+use embassy_hal_internal::{into_ref, Peripheral};
+
+use crate::pac::wwdg::vals::Wdgtb;
+use crate::rcc::sealed::RccPeripheral;
+
+/// Window watchdog (WWDG) driver.
+///
+/// The WWDG resets the MCU if it's not petted before the timeout, but also if it's petted too
+/// early, before the window opens.
+pub struct WindowWatchdog<'d, T: WwdgInstance> {
+    counter: u8,
+    wdg: PhantomData<&'d mut T>,
+}
+
+// The counter is 7 bits, and the reset happens when bit 6 gets cleared, so 64 ticks are usable.
+const MAX_TICKS: u32 = 64;
+
+impl<'d, T: WwdgInstance> WindowWatchdog<'d, T> {
+    /// Creates a WWDG (Window Watchdog) instance.
+    ///
+    /// Once started with [Self::unleash()], the watchdog must be petted with [Self::pet()] within
+    /// `timeout_us`, but not sooner than `window_us` after the previous pet. Pass a `window_us` of
+    /// 0 to allow petting at any time.
+    pub fn new(_instance: impl Peripheral<P = T> + 'd, timeout_us: u32, window_us: u32) -> Self {
+        into_ref!(_instance);
+
+        assert!(window_us < timeout_us, "WWDG window must be shorter than the timeout");
+
+        T::enable_and_reset();
+
+        // The counter is clocked by PCLK / 4096 / 2^WDGTB.
+        let pclk = T::frequency().0 as u64;
+        let tick_us = |tb: u32| 4096 * (1u64 << tb) * 1_000_000 / pclk;
+
+        // Find lowest prescaler value, which makes the watchdog period longer or equal to timeout.
+        let tb = unwrap!((0..=3).find(|tb| timeout_us as u64 <= tick_us(*tb) * MAX_TICKS as u64));
+        let tick = tick_us(tb);
+
+        let ticks = (timeout_us as u64 / tick).clamp(1, MAX_TICKS as u64) as u8;
+        let counter = 0x40 + ticks - 1;
+
+        // Petting is only allowed once the counter went below the window value.
+        let window_ticks = ((window_us as u64 + tick - 1) / tick) as u8;
+        let window = counter.saturating_sub(window_ticks).max(0x40);
+
+        let wdg = T::regs();
+        wdg.cfr().write(|w| {
+            w.set_wdgtb(Wdgtb::from_bits(tb as u8));
+            w.set_w(if window_us == 0 { 0x7F } else { window });
+        });
+
+        trace!(
+            "Window watchdog configured with {}us timeout, desired was {}us (WDGTB={}, T={}, W={})",
+            tick * ticks as u64,
+            timeout_us,
+            tb,
+            counter,
+            window
+        );
+
+        WindowWatchdog {
+            counter,
+            wdg: PhantomData,
+        }
+    }
+
+    /// Unleash (start) the watchdog.
+    ///
+    /// The watchdog can't be stopped once started, other than by a reset.
+    pub fn unleash(&mut self) {
+        self.pet();
+    }
+
+    /// Pet (reload, refresh) the watchdog.
+    pub fn pet(&mut self) {
+        // WDGA can only be set by software, writing it again has no effect once started.
+        T::regs().cr().write(|w| {
+            w.set_t(self.counter);
+            w.set_wdga(true);
+        });
+    }
+}
+
+mod sealed {
+    pub trait WwdgInstance {
+        fn regs() -> crate::pac::wwdg::Wwdg;
+    }
+}
+
+/// WWDG instance trait.
+pub trait WwdgInstance: sealed::WwdgInstance + crate::rcc::RccPeripheral {}
+
+foreach_peripheral!(
+    (wwdg, $inst:ident) => {
+        impl sealed::WwdgInstance for crate::peripherals::$inst {
+            fn regs() -> crate::pac::wwdg::Wwdg {
+                crate::pac::$inst
+            }
+        }
+
+        impl WwdgInstance for crate::peripherals::$inst {}
+    };
+);
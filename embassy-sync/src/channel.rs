@@ -19,11 +19,14 @@
 //!
 
 use core::cell::RefCell;
-use core::future::Future;
+use core::future::{poll_fn, Future};
+use core::mem::{ManuallyDrop, MaybeUninit};
 use core::pin::Pin;
-use core::task::{Context, Poll};
+use core::task::{Context, Poll, Waker};
 
-use heapless::Deque;
+#[cfg(feature = "time")]
+use embassy_time::{Duration, Timer};
+use heapless::{Deque, Vec};
 
 use crate::blocking_mutex::raw::RawMutex;
 use crate::blocking_mutex::Mutex;
@@ -66,12 +69,109 @@ where
         self.channel.try_send(message)
     }
 
+    /// Immediately send a message, dropping the oldest one if the channel is full.
+    ///
+    /// See [`Channel::try_send_overwrite()`]
+    pub fn try_send_overwrite(&self, message: T) -> Option<T> {
+        self.channel.try_send_overwrite(message)
+    }
+
     /// Allows a poll_fn to poll until the channel is ready to send
     ///
     /// See [`Channel::poll_ready_to_send()`]
     pub fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()> {
         self.channel.poll_ready_to_send(cx)
     }
+
+    /// Sends a value, failing if it couldn't be sent before the timeout expired.
+    ///
+    /// See [`Channel::send_timeout()`]
+    #[cfg(feature = "time")]
+    pub fn send_timeout(&self, message: T, timeout: Duration) -> SendTimeoutFuture<'ch, M, T, N> {
+        self.channel.send_timeout(message, timeout)
+    }
+
+    /// Wait for space in the channel and reserve it, to send a value later.
+    ///
+    /// See [`Channel::reserve()`]
+    pub async fn reserve(&self) -> Result<SendPermit<'ch, M, T, N>, SendError<()>> {
+        self.channel.reserve().await
+    }
+
+    /// Attempt to immediately reserve space in the channel, to send a value later.
+    ///
+    /// See [`Channel::try_reserve()`]
+    pub fn try_reserve(&self) -> Result<SendPermit<'ch, M, T, N>, TrySendError<()>> {
+        self.channel.try_reserve()
+    }
+
+    /// Sends all values of an iterator, moving as many as fit at once.
+    ///
+    /// See [`Channel::send_iter()`]
+    pub async fn send_iter(&self, messages: impl IntoIterator<Item = T>) {
+        self.channel.send_iter(messages).await
+    }
+
+    /// Close the channel, signaling that no more values will be sent.
+    ///
+    /// See [`Channel::close()`]
+    pub fn close(&self) {
+        self.channel.close()
+    }
+
+    /// Returns whether the channel is closed.
+    ///
+    /// See [`Channel::is_closed()`]
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+
+    /// The amount of values in the channel.
+    ///
+    /// See [`Channel::len()`]
+    pub fn len(&self) -> usize {
+        self.channel.len()
+    }
+
+    /// Returns whether the channel is empty.
+    ///
+    /// See [`Channel::is_empty()`]
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_empty()
+    }
+
+    /// The amount of values that can be sent before the channel is full.
+    ///
+    /// See [`Channel::free_capacity()`]
+    pub fn free_capacity(&self) -> usize {
+        self.channel.free_capacity()
+    }
+}
+
+/// Sends values like [`Sender::try_send()`], after waiting for capacity in `poll_ready`.
+///
+/// `start_send` fails if another sender filled the channel since `poll_ready`, or if the channel is closed.
+impl<'ch, M, T, const N: usize> futures_util::Sink<T> for Sender<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    type Error = TrySendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.channel.poll_ready_to_send(cx).map(Ok)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.channel.try_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
 }
 
 /// Send-only access to a [`Channel`] without knowing channel size.
@@ -79,6 +179,13 @@ pub struct DynamicSender<'ch, T> {
     pub(crate) channel: &'ch dyn DynamicChannel<T>,
 }
 
+/// Send-only access to any channel of `T`, without being generic over its mutex or size.
+///
+/// This is the name used by the pubsub module for its type-erased handles, see [`DynamicSender`].
+/// Senders of [`Channel`], [`BufferChannel`] and [`PriorityChannel`](crate::priority_channel::PriorityChannel)
+/// all convert into it, which lets a driver take a channel endpoint without becoming generic itself.
+pub type DynSender<'ch, T> = DynamicSender<'ch, T>;
+
 impl<'ch, T> Clone for DynamicSender<'ch, T> {
     fn clone(&self) -> Self {
         DynamicSender { channel: self.channel }
@@ -172,6 +279,85 @@ where
     pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
         self.channel.poll_receive(cx)
     }
+
+    /// Receive the next value, failing if none was sent before the timeout expired.
+    ///
+    /// See [`Channel::receive_timeout()`]
+    #[cfg(feature = "time")]
+    pub fn receive_timeout(&self, timeout: Duration) -> ReceiveTimeoutFuture<'ch, M, T, N> {
+        self.channel.receive_timeout(timeout)
+    }
+
+    /// Receive as many values as fit in `buf` at once, returning how many were received.
+    ///
+    /// See [`Channel::receive_into()`]
+    pub async fn receive_into(&self, buf: &mut [T]) -> usize {
+        self.channel.receive_into(buf).await
+    }
+
+    /// Wait for a value and return a clone of it, without receiving it.
+    ///
+    /// See [`Channel::peek()`]
+    pub async fn peek(&self) -> T
+    where
+        T: Clone,
+    {
+        self.channel.peek().await
+    }
+
+    /// Attempt to immediately return a clone of the next value, without receiving it.
+    ///
+    /// See [`Channel::try_peek()`]
+    pub fn try_peek(&self) -> Result<T, TryReceiveError>
+    where
+        T: Clone,
+    {
+        self.channel.try_peek()
+    }
+
+    /// Returns whether the channel is closed.
+    ///
+    /// See [`Channel::is_closed()`]
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+
+    /// The amount of values in the channel.
+    ///
+    /// See [`Channel::len()`]
+    pub fn len(&self) -> usize {
+        self.channel.len()
+    }
+
+    /// Returns whether the channel is empty.
+    ///
+    /// See [`Channel::is_empty()`]
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_empty()
+    }
+
+    /// The amount of values that can be sent before the channel is full.
+    ///
+    /// See [`Channel::free_capacity()`]
+    pub fn free_capacity(&self) -> usize {
+        self.channel.free_capacity()
+    }
+}
+
+/// Receives values until the channel is closed and empty.
+impl<'ch, M, T, const N: usize> futures_util::Stream for Receiver<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.channel.try_receive_with_context(Some(cx)) {
+            Ok(message) => Poll::Ready(Some(message)),
+            Err(TryReceiveError::Empty) => Poll::Pending,
+            Err(TryReceiveError::Closed) => Poll::Ready(None),
+        }
+    }
 }
 
 /// Receive-only access to a [`Channel`] without knowing channel size.
@@ -179,6 +365,11 @@ pub struct DynamicReceiver<'ch, T> {
     pub(crate) channel: &'ch dyn DynamicChannel<T>,
 }
 
+/// Receive-only access to any channel of `T`, without being generic over its mutex or size.
+///
+/// This is the name used by the pubsub module for its type-erased handles, see [`DynamicReceiver`].
+pub type DynReceiver<'ch, T> = DynamicReceiver<'ch, T>;
+
 impl<'ch, T> Clone for DynamicReceiver<'ch, T> {
     fn clone(&self) -> Self {
         DynamicReceiver { channel: self.channel }
@@ -226,6 +417,228 @@ where
     }
 }
 
+/// Send-only access to a [`Channel`] that is counted by the channel.
+///
+/// When the last tracked sender is dropped, the channel is closed, so receivers get a `Closed` error
+/// after receiving the remaining values. Cloning it creates another tracked sender.
+pub struct TrackedSender<'ch, M, T, const N: usize>
+where
+    M: RawMutex,
+{
+    channel: &'ch Channel<M, T, N>,
+}
+
+impl<'ch, M, T, const N: usize> TrackedSender<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    /// Sends a value, failing if the channel is closed, which it is once all tracked receivers are dropped.
+    ///
+    /// See [`Channel::send()`]
+    pub fn send(&self, message: T) -> SendFuture<'ch, M, T, N> {
+        self.channel.send(message)
+    }
+
+    /// Attempt to immediately send a message.
+    ///
+    /// See [`Channel::try_send()`]
+    pub fn try_send(&self, message: T) -> Result<(), TrySendError<T>> {
+        self.channel.try_send(message)
+    }
+
+    /// Close the channel, signaling that no more values will be sent.
+    ///
+    /// See [`Channel::close()`]
+    pub fn close(&self) {
+        self.channel.close()
+    }
+
+    /// Returns whether the channel is closed.
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+}
+
+impl<'ch, M, T, const N: usize> Clone for TrackedSender<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    fn clone(&self) -> Self {
+        self.channel.tracked_sender()
+    }
+}
+
+impl<'ch, M, T, const N: usize> Drop for TrackedSender<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    fn drop(&mut self) {
+        self.channel.lock(|c| {
+            c.tracked_senders -= 1;
+            if c.tracked_senders == 0 {
+                c.close();
+            }
+        })
+    }
+}
+
+/// Receive-only access to a [`Channel`] that is counted by the channel.
+///
+/// When the last tracked receiver is dropped, the channel is closed, so sending fails right away
+/// instead of waiting for space that never comes. Cloning it creates another tracked receiver.
+pub struct TrackedReceiver<'ch, M, T, const N: usize>
+where
+    M: RawMutex,
+{
+    channel: &'ch Channel<M, T, N>,
+}
+
+impl<'ch, M, T, const N: usize> TrackedReceiver<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    /// Receive the next value, failing once the channel is closed and empty, which it is once all tracked
+    /// senders are dropped.
+    ///
+    /// See [`Channel::receive()`]
+    pub fn receive(&self) -> ReceiveFuture<'_, M, T, N> {
+        self.channel.receive()
+    }
+
+    /// Attempt to immediately receive the next value.
+    ///
+    /// See [`Channel::try_receive()`]
+    pub fn try_receive(&self) -> Result<T, TryReceiveError> {
+        self.channel.try_receive()
+    }
+
+    /// Returns whether the channel is closed.
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+}
+
+impl<'ch, M, T, const N: usize> Clone for TrackedReceiver<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    fn clone(&self) -> Self {
+        self.channel.tracked_receiver()
+    }
+}
+
+impl<'ch, M, T, const N: usize> Drop for TrackedReceiver<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    fn drop(&mut self) {
+        self.channel.lock(|c| {
+            c.tracked_receivers -= 1;
+            if c.tracked_receivers == 0 {
+                c.close();
+            }
+        })
+    }
+}
+
+/// A place reserved in a [`Channel`], which allows sending a value without waiting.
+///
+/// Created by [`Channel::reserve()`]. Dropping it without sending gives the place back.
+#[must_use = "the reserved place is given back when the permit is dropped"]
+pub struct SendPermit<'ch, M, T, const N: usize>
+where
+    M: RawMutex,
+{
+    channel: &'ch Channel<M, T, N>,
+}
+
+impl<'ch, M, T, const N: usize> SendPermit<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    /// Send a value in the reserved place.
+    ///
+    /// This never fails, even if the channel was closed after reserving the place.
+    pub fn send(self, message: T) {
+        let permit = ManuallyDrop::new(self);
+        permit.channel.lock(|c| c.send_reserved(message))
+    }
+}
+
+impl<'ch, M, T, const N: usize> Drop for SendPermit<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    fn drop(&mut self) {
+        self.channel.lock(|c| c.release_reserved())
+    }
+}
+
+/// A set of receivers that can be waited on at once, returning a value from whichever channel has one.
+///
+/// Up to `N` receivers of channels with the same value type can be added, in any combination of channel
+/// sizes. Channels carrying different kinds of values can be combined by sending an enum of them.
+/// The channels are checked in turns, starting after the one that was last received from, so a busy
+/// channel can't starve the others.
+pub struct QueueSet<'ch, T, const N: usize> {
+    receivers: heapless::Vec<DynamicReceiver<'ch, T>, N>,
+    /// The index of the receiver that is checked first
+    next: usize,
+}
+
+impl<'ch, T, const N: usize> QueueSet<'ch, T, N> {
+    /// Create an empty set
+    pub const fn new() -> Self {
+        Self {
+            receivers: heapless::Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Add a receiver to the set, returning its index, which is returned with the values received from it.
+    ///
+    /// If the set is full, the receiver is returned as an error.
+    pub fn add(&mut self, receiver: impl Into<DynamicReceiver<'ch, T>>) -> Result<usize, DynamicReceiver<'ch, T>> {
+        self.receivers.push(receiver.into())?;
+        Ok(self.receivers.len() - 1)
+    }
+
+    /// Receive the next value from any of the channels, along with the index of its receiver.
+    pub async fn receive_any(&mut self) -> (usize, T) {
+        poll_fn(|cx| self.poll_receive_any(cx)).await
+    }
+
+    /// Attempt to immediately receive a value from any of the channels, along with the index of its receiver.
+    pub fn try_receive_any(&mut self) -> Option<(usize, T)> {
+        self.receive_any_with_context(None)
+    }
+
+    /// Poll the channels for a value, along with the index of its receiver.
+    pub fn poll_receive_any(&mut self, cx: &mut Context<'_>) -> Poll<(usize, T)> {
+        match self.receive_any_with_context(Some(cx)) {
+            Some(received) => Poll::Ready(received),
+            None => Poll::Pending,
+        }
+    }
+
+    fn receive_any_with_context(&mut self, mut cx: Option<&mut Context<'_>>) -> Option<(usize, T)> {
+        let len = self.receivers.len();
+        for i in (self.next..len).chain(0..self.next) {
+            if let Ok(message) = self.receivers[i].channel.try_receive_with_context(cx.as_deref_mut()) {
+                self.next = (i + 1) % len;
+                return Some((i, message));
+            }
+        }
+        None
+    }
+}
+
+impl<'ch, T, const N: usize> Default for QueueSet<'ch, T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Future returned by [`Channel::receive`] and  [`Receiver::receive`].
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct ReceiveFuture<'ch, M, T, const N: usize>
@@ -233,16 +646,40 @@ where
     M: RawMutex,
 {
     channel: &'ch Channel<M, T, N>,
+    /// The place of this future in the line of waiting receivers
+    ticket: Option<u64>,
 }
 
 impl<'ch, M, T, const N: usize> Future for ReceiveFuture<'ch, M, T, N>
 where
     M: RawMutex,
 {
-    type Output = T;
+    type Output = Result<T, ReceiveError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.channel
+            .lock(|c| match c.try_receive_in_line(Some(&mut this.ticket), Some(cx)) {
+                Ok(message) => Poll::Ready(Ok(message)),
+                Err(TryReceiveError::Empty) => Poll::Pending,
+                Err(TryReceiveError::Closed) => Poll::Ready(Err(ReceiveError::Closed)),
+            })
+    }
+}
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
-        self.channel.poll_receive(cx)
+impl<'ch, M, T, const N: usize> Drop for ReceiveFuture<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    fn drop(&mut self) {
+        if self.ticket.is_some() {
+            self.channel.lock(|c| {
+                // Pass on the wakeup this future got and won't use
+                if !c.leave_line(&mut self.ticket) {
+                    c.wake_receivers(1);
+                }
+            });
+        }
     }
 }
 
@@ -253,12 +690,13 @@ pub struct DynamicReceiveFuture<'ch, T> {
 }
 
 impl<'ch, T> Future for DynamicReceiveFuture<'ch, T> {
-    type Output = T;
+    type Output = Result<T, ReceiveError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.channel.try_receive_with_context(Some(cx)) {
-            Ok(v) => Poll::Ready(v),
+            Ok(v) => Poll::Ready(Ok(v)),
             Err(TryReceiveError::Empty) => Poll::Pending,
+            Err(TryReceiveError::Closed) => Poll::Ready(Err(ReceiveError::Closed)),
         }
     }
 }
@@ -277,16 +715,17 @@ impl<'ch, M, T, const N: usize> Future for SendFuture<'ch, M, T, N>
 where
     M: RawMutex,
 {
-    type Output = ();
+    type Output = Result<(), SendError<T>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.message.take() {
             Some(m) => match self.channel.try_send_with_context(m, Some(cx)) {
-                Ok(..) => Poll::Ready(()),
+                Ok(..) => Poll::Ready(Ok(())),
                 Err(TrySendError::Full(m)) => {
                     self.message = Some(m);
                     Poll::Pending
                 }
+                Err(TrySendError::Closed(m)) => Poll::Ready(Err(SendError::Closed(m))),
             },
             None => panic!("Message cannot be None"),
         }
@@ -295,6 +734,83 @@ where
 
 impl<'ch, M, T, const N: usize> Unpin for SendFuture<'ch, M, T, N> where M: RawMutex {}
 
+/// Future returned by [`Channel::send_timeout`] and [`Sender::send_timeout`].
+#[cfg(feature = "time")]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendTimeoutFuture<'ch, M, T, const N: usize>
+where
+    M: RawMutex,
+{
+    channel: &'ch Channel<M, T, N>,
+    message: Option<T>,
+    timer: Timer,
+}
+
+#[cfg(feature = "time")]
+impl<'ch, M, T, const N: usize> Future for SendTimeoutFuture<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    type Output = Result<(), SendTimeoutError<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let message = match self.message.take() {
+            Some(m) => m,
+            None => panic!("Message cannot be None"),
+        };
+        // Space that is already there wins over the timeout
+        let message = match self.channel.try_send_with_context(message, Some(cx)) {
+            Ok(..) => return Poll::Ready(Ok(())),
+            Err(TrySendError::Closed(m)) => return Poll::Ready(Err(SendTimeoutError::Closed(m))),
+            Err(TrySendError::Full(m)) => m,
+        };
+
+        match Pin::new(&mut self.timer).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(SendTimeoutError::Timeout(message))),
+            Poll::Pending => {
+                self.message = Some(message);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl<'ch, M, T, const N: usize> Unpin for SendTimeoutFuture<'ch, M, T, N> where M: RawMutex {}
+
+/// Future returned by [`Channel::receive_timeout`] and [`Receiver::receive_timeout`].
+#[cfg(feature = "time")]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReceiveTimeoutFuture<'ch, M, T, const N: usize>
+where
+    M: RawMutex,
+{
+    channel: &'ch Channel<M, T, N>,
+    timer: Timer,
+}
+
+#[cfg(feature = "time")]
+impl<'ch, M, T, const N: usize> Future for ReceiveTimeoutFuture<'ch, M, T, N>
+where
+    M: RawMutex,
+{
+    type Output = Result<T, ReceiveTimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // A value that is already there wins over the timeout
+        match self.channel.try_receive_with_context(Some(cx)) {
+            Ok(v) => return Poll::Ready(Ok(v)),
+            Err(TryReceiveError::Closed) => return Poll::Ready(Err(ReceiveTimeoutError::Closed)),
+            Err(TryReceiveError::Empty) => {}
+        }
+
+        match Pin::new(&mut self.timer).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(ReceiveTimeoutError::Timeout)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 /// Future returned by [`DynamicSender::send`].
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct DynamicSendFuture<'ch, T> {
@@ -303,16 +819,17 @@ pub struct DynamicSendFuture<'ch, T> {
 }
 
 impl<'ch, T> Future for DynamicSendFuture<'ch, T> {
-    type Output = ();
+    type Output = Result<(), SendError<T>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.message.take() {
             Some(m) => match self.channel.try_send_with_context(m, Some(cx)) {
-                Ok(..) => Poll::Ready(()),
+                Ok(..) => Poll::Ready(Ok(())),
                 Err(TrySendError::Full(m)) => {
                     self.message = Some(m);
                     Poll::Pending
                 }
+                Err(TrySendError::Closed(m)) => Poll::Ready(Err(SendError::Closed(m))),
             },
             None => panic!("Message cannot be None"),
         }
@@ -338,6 +855,8 @@ pub(crate) trait DynamicChannel<T> {
 pub enum TryReceiveError {
     /// A message could not be received because the channel is empty.
     Empty,
+    /// A message could not be received because the channel is empty and closed.
+    Closed,
 }
 
 /// Error returned by [`try_send`](Channel::try_send).
@@ -347,93 +866,538 @@ pub enum TrySendError<T> {
     /// The data could not be sent on the channel because the channel is
     /// currently full and sending would require blocking.
     Full(T),
+    /// The data could not be sent on the channel because the channel is closed.
+    Closed(T),
+}
+
+/// Error returned by [`receive`](Channel::receive).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReceiveError {
+    /// The channel is closed and all values sent before closing it have been received.
+    Closed,
+}
+
+/// Error returned by [`receive_timeout`](Channel::receive_timeout).
+#[cfg(feature = "time")]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReceiveTimeoutError {
+    /// No value was sent before the timeout expired.
+    Timeout,
+    /// The channel is closed and all values sent before closing it have been received.
+    Closed,
+}
+
+/// Error returned by [`send_timeout`](Channel::send_timeout).
+#[cfg(feature = "time")]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendTimeoutError<T> {
+    /// The data could not be sent before the timeout expired, because the channel stayed full.
+    Timeout(T),
+    /// The data could not be sent on the channel because the channel is closed.
+    Closed(T),
+}
+
+/// Error returned by [`send`](Channel::send).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendError<T> {
+    /// The data could not be sent on the channel because the channel is closed.
+    Closed(T),
+}
+
+/// The storage of the values in a channel
+trait Queue {
+    type Item;
+
+    fn push_back(&mut self, item: Self::Item) -> Result<(), Self::Item>;
+    fn pop_front(&mut self) -> Option<Self::Item>;
+    fn front(&self) -> Option<&Self::Item>;
+    fn is_empty(&self) -> bool;
+    fn is_full(&self) -> bool;
+    fn len(&self) -> usize;
+    fn capacity(&self) -> usize;
+}
+
+impl<T, const N: usize> Queue for Deque<T, N> {
+    type Item = T;
+
+    fn push_back(&mut self, item: T) -> Result<(), T> {
+        Deque::push_back(self, item)
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        Deque::pop_front(self)
+    }
+
+    fn front(&self) -> Option<&T> {
+        Deque::front(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        Deque::is_empty(self)
+    }
+
+    fn is_full(&self) -> bool {
+        Deque::is_full(self)
+    }
+
+    fn len(&self) -> usize {
+        Deque::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        Deque::capacity(self)
+    }
+}
+
+/// A queue of values stored in a buffer provided by the user
+struct BufferQueue<'a, T> {
+    buf: &'a mut [MaybeUninit<T>],
+    /// Index of the oldest value
+    front: usize,
+    /// Amount of values in the queue, which are initialized
+    len: usize,
+}
+
+impl<'a, T> BufferQueue<'a, T> {
+    fn new(buf: &'a mut [MaybeUninit<T>]) -> Self {
+        assert!(!buf.is_empty());
+        Self { buf, front: 0, len: 0 }
+    }
+}
+
+impl<'a, T> Queue for BufferQueue<'a, T> {
+    type Item = T;
+
+    fn push_back(&mut self, item: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(item);
+        }
+        let back = (self.front + self.len) % self.buf.len();
+        self.buf[back].write(item);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        // Safety: the values from the front index on are initialized, and the read value is no longer counted
+        let item = unsafe { self.buf[self.front].assume_init_read() };
+        self.front = (self.front + 1) % self.buf.len();
+        self.len -= 1;
+        Some(item)
+    }
+
+    fn front(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        // Safety: the value at the front index is initialized
+        Some(unsafe { self.buf[self.front].assume_init_ref() })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == self.buf.len()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
 }
 
-struct ChannelState<T, const N: usize> {
-    queue: Deque<T, N>,
+impl<'a, T> Drop for BufferQueue<'a, T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+struct State<Q, const W: usize> {
+    queue: Q,
     receiver_waker: WakerRegistration,
     senders_waker: WakerRegistration,
+    closed: bool,
+    /// The amount of [`TrackedSender`]s
+    tracked_senders: usize,
+    /// The amount of [`TrackedReceiver`]s
+    tracked_receivers: usize,
+    /// The amount of free places in the queue that are reserved by [`SendPermit`]s
+    reserved: usize,
+    /// The tickets and wakers of the [`ReceiveFuture`]s waiting for a value, in the order they started waiting.
+    /// Receive futures that don't fit wait on `receiver_waker` instead.
+    waiting_receivers: Vec<(u64, Waker), W>,
+    /// The ticket the next receive future that starts waiting gets
+    next_ticket: u64,
+    /// The highest amount of values that were in the queue at once
+    high_watermark: usize,
 }
 
+/// A channel has a place in line for as many receive futures as it can hold values, so every value
+/// can have a receiver woken for it.
+type ChannelState<T, const N: usize> = State<Deque<T, N>, N>;
+
 impl<T, const N: usize> ChannelState<T, N> {
     const fn new() -> Self {
-        ChannelState {
+        State {
             queue: Deque::new(),
             receiver_waker: WakerRegistration::new(),
             senders_waker: WakerRegistration::new(),
+            closed: false,
+            tracked_senders: 0,
+            tracked_receivers: 0,
+            reserved: 0,
+            waiting_receivers: Vec::new(),
+            next_ticket: 0,
+            high_watermark: 0,
+        }
+    }
+}
+
+impl<'a, T> State<BufferQueue<'a, T>, 0> {
+    fn with_buffer(buf: &'a mut [MaybeUninit<T>]) -> Self {
+        State {
+            queue: BufferQueue::new(buf),
+            receiver_waker: WakerRegistration::new(),
+            senders_waker: WakerRegistration::new(),
+            closed: false,
+            tracked_senders: 0,
+            tracked_receivers: 0,
+            reserved: 0,
+            waiting_receivers: Vec::new(),
+            next_ticket: 0,
+            high_watermark: 0,
         }
     }
+}
+
+impl<Q: Queue, const W: usize> State<Q, W> {
+    /// Whether there is no place for another value, counting the reserved places as taken
+    fn is_full(&self) -> bool {
+        self.queue.len() + self.reserved >= self.queue.capacity()
+    }
+
+    /// The amount of values that can be sent before the queue is full, not counting the reserved places
+    fn free_capacity(&self) -> usize {
+        self.queue.capacity() - self.queue.len() - self.reserved
+    }
+
+    /// Put a message in the queue, which must have space for it
+    fn push(&mut self, message: Q::Item) {
+        self.queue.push_back(message).ok().unwrap();
+        self.high_watermark = self.high_watermark.max(self.queue.len());
+    }
 
-    fn try_receive(&mut self) -> Result<T, TryReceiveError> {
+    fn try_receive(&mut self) -> Result<Q::Item, TryReceiveError> {
         self.try_receive_with_context(None)
     }
 
-    fn try_receive_with_context(&mut self, cx: Option<&mut Context<'_>>) -> Result<T, TryReceiveError> {
-        if self.queue.is_full() {
-            self.senders_waker.wake();
-        }
+    fn try_receive_with_context(&mut self, cx: Option<&mut Context<'_>>) -> Result<Q::Item, TryReceiveError> {
+        self.try_receive_in_line(None, cx)
+    }
 
-        if let Some(message) = self.queue.pop_front() {
+    /// Receive a value.
+    ///
+    /// With a `ticket`, the receiver gets in line to be woken when it has to wait, and leaves the line once it's done.
+    fn try_receive_in_line(
+        &mut self,
+        ticket: Option<&mut Option<u64>>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Result<Q::Item, TryReceiveError> {
+        if !self.queue.is_empty() {
+            if self.is_full() {
+                self.senders_waker.wake();
+            }
+            // We just did a check for this
+            let message = self.queue.pop_front().unwrap();
+            if let Some(ticket) = ticket {
+                self.leave_line(ticket);
+            }
+            self.wake_receivers_if_closed();
             Ok(message)
+        } else if self.closed {
+            if let Some(ticket) = ticket {
+                self.leave_line(ticket);
+            }
+            Err(TryReceiveError::Closed)
         } else {
             if let Some(cx) = cx {
-                self.receiver_waker.register(cx.waker());
+                match ticket {
+                    Some(ticket) => self.wait_in_line(ticket, cx),
+                    None => self.receiver_waker.register(cx.waker()),
+                }
             }
             Err(TryReceiveError::Empty)
         }
     }
 
-    fn poll_receive(&mut self, cx: &mut Context<'_>) -> Poll<T> {
-        if self.queue.is_full() {
-            self.senders_waker.wake();
+    fn poll_receive(&mut self, cx: &mut Context<'_>) -> Poll<Q::Item> {
+        match self.try_receive_in_line(None, Some(cx)) {
+            Ok(message) => Poll::Ready(message),
+            Err(_) => Poll::Pending,
         }
+    }
 
-        if let Some(message) = self.queue.pop_front() {
-            Poll::Ready(message)
-        } else {
+    /// Queue up a receiver that has to wait, giving it a ticket if it doesn't have one yet.
+    ///
+    /// A receiver that was woken but found no value keeps its ticket, so it gets back in line ahead
+    /// of the receivers that started waiting after it.
+    fn wait_in_line(&mut self, ticket: &mut Option<u64>, cx: &mut Context<'_>) {
+        let ticket = *ticket.get_or_insert_with(|| {
+            self.next_ticket += 1;
+            self.next_ticket - 1
+        });
+
+        if let Some((_, waker)) = self.waiting_receivers.iter_mut().find(|(t, _)| *t == ticket) {
+            if !waker.will_wake(cx.waker()) {
+                *waker = cx.waker().clone();
+            }
+            return;
+        }
+
+        let position = self
+            .waiting_receivers
+            .iter()
+            .position(|(t, _)| *t > ticket)
+            .unwrap_or(self.waiting_receivers.len());
+        if self
+            .waiting_receivers
+            .insert(position, (ticket, cx.waker().clone()))
+            .is_err()
+        {
+            // The line is full, so wait with the receivers that race for the values
             self.receiver_waker.register(cx.waker());
-            Poll::Pending
+        }
+    }
+
+    /// Take a receiver out of the line of waiting receivers.
+    ///
+    /// Returns `false` if it wasn't in line anymore, because it was taken out to be woken.
+    fn leave_line(&mut self, ticket: &mut Option<u64>) -> bool {
+        let Some(ticket) = ticket.take() else {
+            return true;
+        };
+        match self.waiting_receivers.iter().position(|(t, _)| *t == ticket) {
+            Some(position) => {
+                self.waiting_receivers.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wake the `amount` receivers that have waited in line the longest, taking them out of the line.
+    /// Receivers that aren't in line are woken whenever a value is sent.
+    fn wake_receivers(&mut self, amount: usize) {
+        let amount = amount.min(self.queue.len()).min(self.waiting_receivers.len());
+        for _ in 0..amount {
+            self.waiting_receivers.remove(0).1.wake();
+        }
+    }
+
+    /// Wake all receivers in line once the channel is closed and empty, so they see it's closed
+    fn wake_receivers_if_closed(&mut self) {
+        if self.closed && self.queue.is_empty() {
+            while let Some((_, waker)) = self.waiting_receivers.pop() {
+                waker.wake();
+            }
         }
     }
 
     fn poll_ready_to_receive(&mut self, cx: &mut Context<'_>) -> Poll<()> {
         self.receiver_waker.register(cx.waker());
 
-        if !self.queue.is_empty() {
+        if !self.queue.is_empty() || self.closed {
             Poll::Ready(())
         } else {
             Poll::Pending
         }
     }
 
-    fn try_send(&mut self, message: T) -> Result<(), TrySendError<T>> {
+    fn try_send(&mut self, message: Q::Item) -> Result<(), TrySendError<Q::Item>> {
         self.try_send_with_context(message, None)
     }
 
-    fn try_send_with_context(&mut self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
-        match self.queue.push_back(message) {
-            Ok(()) => {
-                self.receiver_waker.wake();
-                Ok(())
+    fn try_send_with_context(
+        &mut self,
+        message: Q::Item,
+        cx: Option<&mut Context<'_>>,
+    ) -> Result<(), TrySendError<Q::Item>> {
+        if self.closed {
+            return Err(TrySendError::Closed(message));
+        }
+
+        if self.is_full() {
+            if let Some(cx) = cx {
+                self.senders_waker.register(cx.waker());
+            }
+            return Err(TrySendError::Full(message));
+        }
+
+        // We just did a check for this
+        self.push(message);
+        self.wake_receivers(1);
+        self.receiver_waker.wake();
+        Ok(())
+    }
+
+    /// Send a message, popping the oldest one to make room if needed
+    fn try_send_overwrite(&mut self, message: Q::Item) -> Option<Q::Item> {
+        if self.closed {
+            return Some(message);
+        }
+
+        let oldest = if self.is_full() {
+            match self.queue.pop_front() {
+                Some(oldest) => Some(oldest),
+                // All places are reserved
+                None => return Some(message),
+            }
+        } else {
+            None
+        };
+
+        // There is a place now
+        self.push(message);
+        self.wake_receivers(1);
+        self.receiver_waker.wake();
+        oldest
+    }
+
+    /// Reserve a place in the queue for a [`SendPermit`]
+    fn try_reserve_with_context(&mut self, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<()>> {
+        if self.closed {
+            return Err(TrySendError::Closed(()));
+        }
+
+        if self.is_full() {
+            if let Some(cx) = cx {
+                self.senders_waker.register(cx.waker());
+            }
+            return Err(TrySendError::Full(()));
+        }
+
+        self.reserved += 1;
+        Ok(())
+    }
+
+    /// Send a value in a reserved place, which is there even if the channel got closed since reserving it
+    fn send_reserved(&mut self, message: Q::Item) {
+        self.reserved -= 1;
+        // The place was reserved for this
+        self.push(message);
+        self.wake_receivers(1);
+        self.receiver_waker.wake();
+    }
+
+    /// Give back a reserved place that wasn't used
+    fn release_reserved(&mut self) {
+        self.reserved -= 1;
+        self.senders_waker.wake();
+    }
+
+    fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.senders_waker.register(cx.waker());
+
+        if !self.is_full() || self.closed {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Send messages from `messages` until it runs out or the queue is full, waking the receiver once.
+    ///
+    /// Returns `true` when there are no messages left to send, or the channel is closed.
+    fn send_iter(&mut self, messages: &mut impl Iterator<Item = Q::Item>, cx: &mut Context<'_>) -> bool {
+        let mut sent = 0;
+        let done = loop {
+            if self.closed {
+                break true;
             }
-            Err(message) => {
-                if let Some(cx) = cx {
-                    self.senders_waker.register(cx.waker());
+            // Only take a message out if there is space for it
+            if self.is_full() {
+                self.senders_waker.register(cx.waker());
+                break false;
+            }
+            match messages.next() {
+                Some(message) => {
+                    // We just did a check for this
+                    self.push(message);
+                    sent += 1;
                 }
-                Err(TrySendError::Full(message))
+                None => break true,
+            }
+        };
+
+        if sent != 0 {
+            self.wake_receivers(sent);
+            self.receiver_waker.wake();
+        }
+        done
+    }
+
+    fn try_peek_with_context(&mut self, cx: Option<&mut Context<'_>>) -> Result<Q::Item, TryReceiveError>
+    where
+        Q::Item: Clone,
+    {
+        if let Some(message) = self.queue.front() {
+            Ok(message.clone())
+        } else if self.closed {
+            Err(TryReceiveError::Closed)
+        } else {
+            if let Some(cx) = cx {
+                self.receiver_waker.register(cx.waker());
             }
+            Err(TryReceiveError::Empty)
+        }
+    }
+
+    /// Move as many messages as fit into `buf`, waking the senders once.
+    fn receive_into(&mut self, buf: &mut [Q::Item], cx: &mut Context<'_>) -> Poll<usize> {
+        if buf.is_empty() {
+            return Poll::Ready(0);
+        }
+
+        if self.is_full() {
+            self.senders_waker.wake();
         }
-    }
 
-    fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<()> {
-        self.senders_waker.register(cx.waker());
+        let mut received = 0;
+        for slot in buf.iter_mut() {
+            match self.queue.pop_front() {
+                Some(message) => *slot = message,
+                None => break,
+            }
+            received += 1;
+        }
+        self.wake_receivers_if_closed();
 
-        if !self.queue.is_full() {
-            Poll::Ready(())
+        if received != 0 || (self.closed && self.queue.is_empty()) {
+            Poll::Ready(received)
         } else {
+            self.receiver_waker.register(cx.waker());
             Poll::Pending
         }
     }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.wake_receivers_if_closed();
+        self.receiver_waker.wake();
+        self.senders_waker.wake();
+    }
 }
 
 /// A bounded channel for communicating between asynchronous tasks
@@ -507,6 +1471,22 @@ where
         Receiver { channel: self }
     }
 
+    /// Get a sender for this channel that is counted, so the channel is closed when all of them are dropped.
+    ///
+    /// See [`TrackedSender`]. Untracked senders don't keep the channel open.
+    pub fn tracked_sender(&self) -> TrackedSender<'_, M, T, N> {
+        self.lock(|c| c.tracked_senders += 1);
+        TrackedSender { channel: self }
+    }
+
+    /// Get a receiver for this channel that is counted, so the channel is closed when all of them are dropped.
+    ///
+    /// See [`TrackedReceiver`]. Untracked receivers don't keep the channel open.
+    pub fn tracked_receiver(&self) -> TrackedReceiver<'_, M, T, N> {
+        self.lock(|c| c.tracked_receivers += 1);
+        TrackedReceiver { channel: self }
+    }
+
     /// Get a sender for this channel using dynamic dispatch.
     pub fn dyn_sender(&self) -> DynamicSender<'_, T> {
         DynamicSender { channel: self }
@@ -521,6 +1501,8 @@ where
     ///
     /// Sending completes when the value has been pushed to the channel's queue.
     /// This doesn't mean the value has been received yet.
+    ///
+    /// Fails if the channel is closed, also when it gets closed while waiting, returning the value in the error.
     pub fn send(&self, message: T) -> SendFuture<'_, M, T, N> {
         SendFuture {
             channel: self,
@@ -542,12 +1524,29 @@ where
         self.lock(|c| c.try_send(message))
     }
 
+    /// Immediately send a message, receiving the oldest message to make room for it if the channel is full.
+    ///
+    /// This turns the channel into a history of the latest `N` values, for data where the newest
+    /// value matters most. Returns the message that was dropped, if any. When the message can't be
+    /// sent, because the channel is closed or all its places are reserved by [`SendPermit`]s, the
+    /// message itself is returned.
+    pub fn try_send_overwrite(&self, message: T) -> Option<T> {
+        self.lock(|c| c.try_send_overwrite(message))
+    }
+
     /// Receive the next value.
     ///
     /// If there are no messages in the channel's buffer, this method will
-    /// wait until a message is sent.
+    /// wait until a message is sent. Fails once the channel is closed and all values sent before
+    /// closing it have been received, which makes it possible to end a pipeline without sending a sentinel value.
+    ///
+    /// When several receive futures wait for a value, each sent value wakes the one that has waited the
+    /// longest, instead of waking all of them to race for it.
     pub fn receive(&self) -> ReceiveFuture<'_, M, T, N> {
-        ReceiveFuture { channel: self }
+        ReceiveFuture {
+            channel: self,
+            ticket: None,
+        }
     }
 
     /// Attempt to immediately receive a message.
@@ -557,6 +1556,162 @@ where
     pub fn try_receive(&self) -> Result<T, TryReceiveError> {
         self.lock(|c| c.try_receive())
     }
+
+    /// Send a value, waiting until there is capacity for at most `timeout`.
+    ///
+    /// If the value couldn't be sent in time, or the channel is closed, it is returned in the error.
+    ///
+    /// Requires the `time` feature.
+    #[cfg(feature = "time")]
+    pub fn send_timeout(&self, message: T, timeout: Duration) -> SendTimeoutFuture<'_, M, T, N> {
+        SendTimeoutFuture {
+            channel: self,
+            message: Some(message),
+            timer: Timer::after(timeout),
+        }
+    }
+
+    /// Receive the next value, waiting for at most `timeout`.
+    ///
+    /// Fails if no value was sent in time, or if the channel is closed and empty.
+    ///
+    /// Requires the `time` feature.
+    #[cfg(feature = "time")]
+    pub fn receive_timeout(&self, timeout: Duration) -> ReceiveTimeoutFuture<'_, M, T, N> {
+        ReceiveTimeoutFuture {
+            channel: self,
+            timer: Timer::after(timeout),
+        }
+    }
+
+    /// Wait until there is space in the channel and reserve it, returning a [`SendPermit`] to send a value
+    /// in it later without waiting.
+    ///
+    /// This allows waiting for capacity before building a value that is expensive to make.
+    /// Reserved places count as taken for other senders, until the permit is used or dropped.
+    /// Fails if the channel is closed.
+    pub async fn reserve(&self) -> Result<SendPermit<'_, M, T, N>, SendError<()>> {
+        poll_fn(|cx| match self.lock(|c| c.try_reserve_with_context(Some(cx))) {
+            Ok(()) => Poll::Ready(Ok(SendPermit { channel: self })),
+            Err(TrySendError::Full(())) => Poll::Pending,
+            Err(TrySendError::Closed(())) => Poll::Ready(Err(SendError::Closed(()))),
+        })
+        .await
+    }
+
+    /// Attempt to immediately reserve space in the channel, returning a [`SendPermit`] to send a value in it later.
+    ///
+    /// See [`reserve`](Channel::reserve).
+    pub fn try_reserve(&self) -> Result<SendPermit<'_, M, T, N>, TrySendError<()>> {
+        self.lock(|c| c.try_reserve_with_context(None))?;
+        Ok(SendPermit { channel: self })
+    }
+
+    /// Send all values of an iterator, waiting for capacity when the channel is full.
+    ///
+    /// As many values as fit are moved into the channel under a single lock, and the receiver is
+    /// woken once for them, which is cheaper than sending them one by one. The iterator is only
+    /// advanced when there is space for its next value. If the channel is closed, the remaining values
+    /// are dropped.
+    pub async fn send_iter(&self, messages: impl IntoIterator<Item = T>) {
+        let mut messages = messages.into_iter();
+        poll_fn(|cx| {
+            if self.lock(|c| c.send_iter(&mut messages, cx)) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Receive as many values as fit in `buf`, waiting until there is at least one.
+    ///
+    /// The values are moved out of the channel under a single lock, and the senders are woken once
+    /// for them. Returns how many values were written to the start of `buf`, which is only 0 if `buf`
+    /// is empty or the channel is closed and empty.
+    pub async fn receive_into(&self, buf: &mut [T]) -> usize {
+        poll_fn(|cx| self.lock(|c| c.receive_into(buf, cx))).await
+    }
+
+    /// Wait for a value and return a clone of it, without receiving it.
+    ///
+    /// The value stays in the channel, so the next receive returns it, possibly in another receiver.
+    /// Once the channel is closed and empty, this waits forever.
+    pub async fn peek(&self) -> T
+    where
+        T: Clone,
+    {
+        poll_fn(|cx| match self.lock(|c| c.try_peek_with_context(Some(cx))) {
+            Ok(message) => Poll::Ready(message),
+            Err(_) => Poll::Pending,
+        })
+        .await
+    }
+
+    /// Attempt to immediately return a clone of the next value, without receiving it.
+    ///
+    /// This returns an error if the channel is empty, like [`try_receive`](Channel::try_receive).
+    pub fn try_peek(&self) -> Result<T, TryReceiveError>
+    where
+        T: Clone,
+    {
+        self.lock(|c| c.try_peek_with_context(None))
+    }
+
+    /// Close the channel, signaling that no more values will be sent.
+    ///
+    /// Sending to a closed channel fails with a `Closed` error. The values that were sent before
+    /// closing can still be received, after which receiving fails with a `Closed` error.
+    /// A channel can't be reopened.
+    pub fn close(&self) {
+        self.lock(|c| c.close())
+    }
+
+    /// Returns whether the channel is closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock(|c| c.closed)
+    }
+
+    /// Total value capacity of the channel.
+    ///
+    /// This is the same as the `N` generic param.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The amount of values in the channel
+    pub fn len(&self) -> usize {
+        self.lock(|c| c.queue.len())
+    }
+
+    /// Returns whether the channel is empty
+    pub fn is_empty(&self) -> bool {
+        self.lock(|c| c.queue.is_empty())
+    }
+
+    /// Returns whether the channel is full, counting the places reserved by [`SendPermit`]s as taken
+    pub fn is_full(&self) -> bool {
+        self.lock(|c| c.is_full())
+    }
+
+    /// The amount of values that can be sent before the channel is full
+    pub fn free_capacity(&self) -> usize {
+        self.lock(|c| c.free_capacity())
+    }
+
+    /// The highest amount of values that were in the channel at once.
+    ///
+    /// This makes it possible to size a channel by how full it gets in practice, or to report
+    /// diagnostics when it comes close to [`capacity`](Self::capacity).
+    pub fn high_watermark(&self) -> usize {
+        self.lock(|c| c.high_watermark)
+    }
+
+    /// Start tracking the [`high_watermark`](Self::high_watermark) again from the current length
+    pub fn reset_high_watermark(&self) {
+        self.lock(|c| c.high_watermark = c.queue.len())
+    }
 }
 
 /// Implements the DynamicChannel to allow creating types that are unaware of the queue size with the
@@ -586,6 +1741,181 @@ where
     }
 }
 
+/// A bounded channel like [`Channel`], which stores its values in a buffer provided by the user.
+///
+/// The capacity is the length of the buffer instead of being part of the type, so the buffer can be
+/// placed in a specific memory region, like DMA-capable or core-coupled RAM, with linker attributes.
+/// Sending and receiving is done through [`DynamicSender`]s and [`DynamicReceiver`]s.
+///
+/// ```
+/// use core::mem::MaybeUninit;
+///
+/// use embassy_sync::channel::BufferChannel;
+/// use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+///
+/// let mut buf = [MaybeUninit::<u32>::uninit(); 3];
+/// let channel = BufferChannel::<NoopRawMutex, u32>::new(&mut buf);
+/// channel.try_send(1).unwrap();
+/// assert_eq!(channel.receiver().try_receive(), Ok(1));
+/// ```
+pub struct BufferChannel<'a, M, T>
+where
+    M: RawMutex,
+{
+    inner: Mutex<M, RefCell<State<BufferQueue<'a, T>, 0>>>,
+}
+
+impl<'a, M, T> BufferChannel<'a, M, T>
+where
+    M: RawMutex,
+{
+    /// Establish a new bounded channel that stores its values in `buf`, so its capacity is the length of `buf`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is empty.
+    pub fn new(buf: &'a mut [MaybeUninit<T>]) -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(State::with_buffer(buf))),
+        }
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut State<BufferQueue<'a, T>, 0>) -> R) -> R {
+        self.inner.lock(|rc| f(&mut *unwrap!(rc.try_borrow_mut())))
+    }
+
+    /// Poll the channel for the next message
+    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+        self.lock(|c| c.poll_receive(cx))
+    }
+
+    /// Allows a poll_fn to poll until the channel is ready to receive
+    pub fn poll_ready_to_receive(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.lock(|c| c.poll_ready_to_receive(cx))
+    }
+
+    /// Allows a poll_fn to poll until the channel is ready to send
+    pub fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.lock(|c| c.poll_ready_to_send(cx))
+    }
+
+    /// Get a sender for this channel.
+    pub fn sender(&self) -> DynamicSender<'_, T> {
+        DynamicSender { channel: self }
+    }
+
+    /// Get a receiver for this channel.
+    pub fn receiver(&self) -> DynamicReceiver<'_, T> {
+        DynamicReceiver { channel: self }
+    }
+
+    /// Send a value, waiting until there is capacity.
+    ///
+    /// See [`Channel::send()`]
+    pub fn send(&self, message: T) -> DynamicSendFuture<'_, T> {
+        DynamicSendFuture {
+            channel: self,
+            message: Some(message),
+        }
+    }
+
+    /// Attempt to immediately send a message.
+    ///
+    /// See [`Channel::try_send()`]
+    pub fn try_send(&self, message: T) -> Result<(), TrySendError<T>> {
+        self.lock(|c| c.try_send(message))
+    }
+
+    /// Receive the next value.
+    ///
+    /// See [`Channel::receive()`]
+    pub fn receive(&self) -> DynamicReceiveFuture<'_, T> {
+        DynamicReceiveFuture { channel: self }
+    }
+
+    /// Attempt to immediately receive a message.
+    ///
+    /// See [`Channel::try_receive()`]
+    pub fn try_receive(&self) -> Result<T, TryReceiveError> {
+        self.lock(|c| c.try_receive())
+    }
+
+    /// Close the channel, signaling that no more values will be sent.
+    ///
+    /// See [`Channel::close()`]
+    pub fn close(&self) {
+        self.lock(|c| c.close())
+    }
+
+    /// Returns whether the channel is closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock(|c| c.closed)
+    }
+
+    /// Total value capacity of the channel, which is the length of its buffer
+    pub fn capacity(&self) -> usize {
+        self.lock(|c| c.queue.capacity())
+    }
+
+    /// The amount of values in the channel
+    pub fn len(&self) -> usize {
+        self.lock(|c| c.queue.len())
+    }
+
+    /// Returns whether the channel is empty
+    pub fn is_empty(&self) -> bool {
+        self.lock(|c| c.queue.is_empty())
+    }
+
+    /// Returns whether the channel is full, counting the places reserved by [`SendPermit`]s as taken
+    pub fn is_full(&self) -> bool {
+        self.lock(|c| c.is_full())
+    }
+
+    /// The amount of values that can be sent before the channel is full
+    pub fn free_capacity(&self) -> usize {
+        self.lock(|c| c.free_capacity())
+    }
+
+    /// The highest amount of values that were in the channel at once.
+    ///
+    /// This makes it possible to size a channel by how full it gets in practice, or to report
+    /// diagnostics when it comes close to [`capacity`](Self::capacity).
+    pub fn high_watermark(&self) -> usize {
+        self.lock(|c| c.high_watermark)
+    }
+
+    /// Start tracking the [`high_watermark`](Self::high_watermark) again from the current length
+    pub fn reset_high_watermark(&self) {
+        self.lock(|c| c.high_watermark = c.queue.len())
+    }
+}
+
+impl<'a, M, T> DynamicChannel<T> for BufferChannel<'a, M, T>
+where
+    M: RawMutex,
+{
+    fn try_send_with_context(&self, m: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
+        self.lock(|c| c.try_send_with_context(m, cx))
+    }
+
+    fn try_receive_with_context(&self, cx: Option<&mut Context<'_>>) -> Result<T, TryReceiveError> {
+        self.lock(|c| c.try_receive_with_context(cx))
+    }
+
+    fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()> {
+        BufferChannel::poll_ready_to_send(self, cx)
+    }
+
+    fn poll_ready_to_receive(&self, cx: &mut Context<'_>) -> Poll<()> {
+        BufferChannel::poll_ready_to_receive(self, cx)
+    }
+
+    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+        BufferChannel::poll_receive(self, cx)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use core::time::Duration;
@@ -677,6 +2007,290 @@ mod tests {
         assert_eq!(r.try_receive().unwrap(), 1);
     }
 
+    #[test]
+    fn dyn_handles_of_any_channel() {
+        use crate::priority_channel::{Min, PriorityChannel};
+
+        struct Driver<'a> {
+            tx: DynSender<'a, u32>,
+            rx: DynReceiver<'a, u32>,
+        }
+
+        impl<'a> Driver<'a> {
+            fn new(tx: impl Into<DynSender<'a, u32>>, rx: impl Into<DynReceiver<'a, u32>>) -> Self {
+                Self {
+                    tx: tx.into(),
+                    rx: rx.into(),
+                }
+            }
+
+            fn echo(&self, value: u32) -> u32 {
+                self.tx.try_send(value).unwrap();
+                self.rx.try_receive().unwrap()
+            }
+        }
+
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        assert_eq!(Driver::new(c.sender(), c.receiver()).echo(1), 1);
+
+        let mut buf = [MaybeUninit::uninit(); 2];
+        let b = BufferChannel::<NoopRawMutex, u32>::new(&mut buf);
+        assert_eq!(Driver::new(b.sender(), b.receiver()).echo(2), 2);
+
+        let p = PriorityChannel::<NoopRawMutex, u32, Min, 3>::new();
+        assert_eq!(Driver::new(p.sender(), p.receiver()).echo(3), 3);
+    }
+
+    #[futures_test::test]
+    async fn bulk_send_and_receive() {
+        let executor = ThreadPool::new().unwrap();
+
+        static CHANNEL: StaticCell<Channel<CriticalSectionRawMutex, u32, 3>> = StaticCell::new();
+        let c = &*CHANNEL.init(Channel::new());
+
+        // More values than fit in the channel, so the sender has to wait for the receiver
+        let send_task = executor
+            .spawn_with_handle(async move { c.sender().send_iter(0..5).await })
+            .unwrap();
+
+        let mut received = [0; 5];
+        let mut count = 0;
+        while count < received.len() {
+            count += c.receiver().receive_into(&mut received[count..]).await;
+        }
+        send_task.await;
+        assert_eq!(received, [0, 1, 2, 3, 4]);
+
+        c.close();
+        assert_eq!(c.receive_into(&mut received).await, 0);
+    }
+
+    #[futures_test::test]
+    async fn peeking() {
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        let r = c.receiver();
+
+        assert_eq!(r.try_peek(), Err(TryReceiveError::Empty));
+        c.try_send(1).unwrap();
+        c.try_send(2).unwrap();
+        assert_eq!(r.peek().await, 1);
+        assert_eq!(r.try_peek(), Ok(1));
+        assert_eq!(r.try_receive(), Ok(1));
+        assert_eq!(c.try_peek(), Ok(2));
+    }
+
+    #[futures_test::test]
+    async fn buffer_channel() {
+        static BUF: StaticCell<[MaybeUninit<u32>; 2]> = StaticCell::new();
+        let c = BufferChannel::<NoopRawMutex, u32>::new(BUF.init([MaybeUninit::uninit(); 2]));
+        let s = c.sender();
+        let r = c.receiver();
+
+        // Wrap around the end of the buffer
+        for i in 0..3 {
+            s.send(i).await.unwrap();
+            s.send(i + 10).await.unwrap();
+            assert_eq!(s.try_send(0), Err(TrySendError::Full(0)));
+            assert_eq!(r.receive().await, Ok(i));
+            assert_eq!(r.try_receive(), Ok(i + 10));
+        }
+        assert_eq!(r.try_receive(), Err(TryReceiveError::Empty));
+    }
+
+    #[futures_test::test]
+    async fn tracked_handles_disconnect() {
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        let s1 = c.tracked_sender();
+        let s2 = s1.clone();
+        let r = c.tracked_receiver();
+
+        s1.send(1).await.unwrap();
+        drop(s1);
+        assert!(!r.is_closed());
+        s2.send(2).await.unwrap();
+        drop(s2);
+
+        // The receiver gets the remaining values, then learns that no more will come
+        assert_eq!(r.receive().await, Ok(1));
+        assert_eq!(r.receive().await, Ok(2));
+        assert_eq!(r.receive().await, Err(ReceiveError::Closed));
+
+        let c = Channel::<NoopRawMutex, u32, 1>::new();
+        let s = c.tracked_sender();
+        let r = c.tracked_receiver();
+        s.send(1).await.unwrap();
+        drop(r);
+        // Sending doesn't wait for space anymore
+        assert_eq!(s.send(2).await, Err(SendError::Closed(2)));
+    }
+
+    #[futures_test::test]
+    async fn stream_and_sink() {
+        use futures_util::{SinkExt, StreamExt};
+
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        let mut s = c.sender();
+        s.send_all(&mut futures_util::stream::iter([1, 2, 3]).map(Ok))
+            .await
+            .unwrap();
+        c.close();
+        assert_eq!(SinkExt::send(&mut s, 4).await, Err(TrySendError::Closed(4)));
+
+        let received: heapless::Vec<u32, 3> = c.receiver().collect().await;
+        assert_eq!(received, [1, 2, 3]);
+    }
+
+    #[futures_test::test]
+    async fn queue_set_rotates() {
+        let c1 = Channel::<NoopRawMutex, u32, 3>::new();
+        let c2 = Channel::<NoopRawMutex, u32, 1>::new();
+        let mut buf = [MaybeUninit::uninit(); 2];
+        let c3 = BufferChannel::<NoopRawMutex, u32>::new(&mut buf);
+
+        let mut set = QueueSet::<u32, 2>::new();
+        assert_eq!(set.add(c1.receiver()).ok(), Some(0));
+        assert_eq!(set.add(c2.dyn_receiver()).ok(), Some(1));
+        assert!(set.add(c3.receiver()).is_err());
+        assert_eq!(set.try_receive_any(), None);
+
+        c1.try_send(1).unwrap();
+        c1.try_send(2).unwrap();
+        c2.try_send(10).unwrap();
+        // The busy first channel doesn't starve the second one
+        assert_eq!(set.receive_any().await, (0, 1));
+        assert_eq!(set.receive_any().await, (1, 10));
+        assert_eq!(set.receive_any().await, (0, 2));
+        assert_eq!(set.try_receive_any(), None);
+    }
+
+    #[futures_test::test]
+    async fn send_permits() {
+        let c = Channel::<NoopRawMutex, u32, 2>::new();
+        let s = c.sender();
+
+        let permit = s.reserve().await.unwrap();
+        c.try_send(1).unwrap();
+        // The reserved place is taken
+        assert_eq!(c.try_send(2), Err(TrySendError::Full(2)));
+        assert!(matches!(s.try_reserve(), Err(TrySendError::Full(()))));
+
+        c.close();
+        permit.send(3);
+        assert_eq!(c.try_receive(), Ok(1));
+        assert_eq!(c.try_receive(), Ok(3));
+
+        // Dropping a permit gives its place back
+        let c = Channel::<NoopRawMutex, u32, 1>::new();
+        drop(c.try_reserve().unwrap());
+        assert_eq!(c.try_send(1), Ok(()));
+    }
+
+    #[test]
+    fn send_overwrite() {
+        let c = Channel::<NoopRawMutex, u32, 2>::new();
+        assert_eq!(c.try_send_overwrite(1), None);
+        assert_eq!(c.sender().try_send_overwrite(2), None);
+        assert_eq!(c.try_send_overwrite(3), Some(1));
+        assert_eq!(c.try_receive(), Ok(2));
+        assert_eq!(c.try_receive(), Ok(3));
+
+        // A message that can't be sent is given back
+        let permits = (c.try_reserve().unwrap(), c.try_reserve().unwrap());
+        assert_eq!(c.try_send_overwrite(4), Some(4));
+        drop(permits);
+        c.close();
+        assert_eq!(c.try_send_overwrite(5), Some(5));
+    }
+
+    #[test]
+    fn occupancy() {
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        assert_eq!(c.capacity(), 3);
+        assert!(c.is_empty());
+
+        c.try_send(1).unwrap();
+        c.try_send(2).unwrap();
+        let permit = c.try_reserve().unwrap();
+        assert_eq!(c.len(), 2);
+        assert_eq!(c.free_capacity(), 0);
+        assert!(c.is_full());
+        drop(permit);
+        assert_eq!(c.receiver().free_capacity(), 1);
+
+        c.try_receive().unwrap();
+        c.try_receive().unwrap();
+        assert_eq!(c.high_watermark(), 2);
+        c.reset_high_watermark();
+        assert_eq!(c.high_watermark(), 0);
+        c.try_send(3).unwrap();
+        assert_eq!(c.high_watermark(), 1);
+    }
+
+    #[test]
+    fn waiting_receivers_are_fifo() {
+        use futures_test::task::new_count_waker;
+        use futures_util::FutureExt;
+
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        let (first_waker, first_count) = new_count_waker();
+        let (second_waker, second_count) = new_count_waker();
+        let (third_waker, third_count) = new_count_waker();
+
+        let mut first = c.receive();
+        let mut second = c.receive();
+        assert!(first.poll_unpin(&mut Context::from_waker(&first_waker)).is_pending());
+        assert!(second.poll_unpin(&mut Context::from_waker(&second_waker)).is_pending());
+
+        // Only the receiver that has waited the longest is woken for a value
+        c.try_send(1).unwrap();
+        assert_eq!(first_count.get(), 1);
+        assert_eq!(second_count.get(), 0);
+
+        // The value isn't held back for it, and it gets back in line first when it finds none
+        assert_eq!(c.try_receive(), Ok(1));
+        assert!(first.poll_unpin(&mut Context::from_waker(&first_waker)).is_pending());
+        c.try_send(2).unwrap();
+        assert_eq!(first_count.get(), 2);
+        assert_eq!(second_count.get(), 0);
+        assert_eq!(
+            first.poll_unpin(&mut Context::from_waker(&first_waker)),
+            Poll::Ready(Ok(2))
+        );
+
+        // A woken receiver that stops waiting passes its wakeup on
+        let mut third = c.receive();
+        assert!(third.poll_unpin(&mut Context::from_waker(&third_waker)).is_pending());
+        c.try_send(3).unwrap();
+        assert_eq!(second_count.get(), 1);
+        assert_eq!(third_count.get(), 0);
+        drop(second);
+        assert_eq!(third_count.get(), 1);
+        assert_eq!(
+            third.poll_unpin(&mut Context::from_waker(&third_waker)),
+            Poll::Ready(Ok(3))
+        );
+    }
+
+    #[futures_test::test]
+    async fn closing() {
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        let s = c.sender();
+        let r = c.receiver();
+
+        s.send(1).await.unwrap();
+        s.send(2).await.unwrap();
+        s.close();
+        assert!(r.is_closed());
+        assert_eq!(s.try_send(3), Err(TrySendError::Closed(3)));
+        assert_eq!(s.send(4).await, Err(SendError::Closed(4)));
+
+        // The values sent before closing can still be received
+        assert_eq!(r.receive().await, Ok(1));
+        assert_eq!(r.try_receive(), Ok(2));
+        assert_eq!(r.try_receive(), Err(TryReceiveError::Closed));
+        assert_eq!(r.receive().await, Err(ReceiveError::Closed));
+    }
+
     #[futures_test::test]
     async fn receiver_receives_given_try_send_async() {
         let executor = ThreadPool::new().unwrap();
@@ -689,14 +2303,14 @@ mod tests {
                 assert!(c2.try_send(1).is_ok());
             })
             .is_ok());
-        assert_eq!(c.receive().await, 1);
+        assert_eq!(c.receive().await, Ok(1));
     }
 
     #[futures_test::test]
     async fn sender_send_completes_if_capacity() {
         let c = Channel::<CriticalSectionRawMutex, u32, 1>::new();
-        c.send(1).await;
-        assert_eq!(c.receive().await, 1);
+        c.send(1).await.unwrap();
+        assert_eq!(c.receive().await, Ok(1));
     }
 
     #[futures_test::test]
@@ -714,15 +2328,15 @@ mod tests {
         // Wish I could think of a means of determining that the async send is waiting instead.
         // However, I've used the debugger to observe that the send does indeed wait.
         Delay::new(Duration::from_millis(500)).await;
-        assert_eq!(c.receive().await, 1);
+        assert_eq!(c.receive().await, Ok(1));
         assert!(executor
             .spawn(async move {
                 loop {
-                    c.receive().await;
+                    c.receive().await.unwrap();
                 }
             })
             .is_ok());
-        send_task_1.unwrap().await;
-        send_task_2.unwrap().await;
+        send_task_1.unwrap().await.unwrap();
+        send_task_2.unwrap().await.unwrap();
     }
 }
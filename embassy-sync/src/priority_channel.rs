@@ -1,26 +1,77 @@
 //! A queue for sending values between asynchronous tasks.
 //!
 //! Similar to a [`Channel`](crate::channel::Channel), however [`PriorityChannel`] sifts higher priority items to the front of the queue.
-//! Priority is determined by the `Ord` trait. Priority behavior is determined by the [`Kind`](heapless::binary_heap::Kind) parameter of the channel.
+//! Priority is determined by the [`Kind`] parameter of the channel: [`Max`] and [`Min`] order values by
+//! their `Ord` implementation, and other kinds can order values in any way.
 
 use core::cell::RefCell;
+use core::cmp::Ordering;
 use core::future::Future;
+use core::marker::PhantomData;
 use core::pin::Pin;
 use core::task::{Context, Poll};
 
-pub use heapless::binary_heap::{Kind, Max, Min};
-use heapless::BinaryHeap;
+pub use heapless::binary_heap::{Max, Min};
+use heapless::Vec;
 
 use crate::blocking_mutex::raw::RawMutex;
 use crate::blocking_mutex::Mutex;
-use crate::channel::{DynamicChannel, DynamicReceiver, DynamicSender, TryReceiveError, TrySendError};
+use crate::channel::{DynamicChannel, DynamicReceiver, DynamicSender, SendError, TryReceiveError, TrySendError};
 use crate::waitqueue::WakerRegistration;
 
+/// The order in which a [`PriorityChannel`] hands out its values.
+///
+/// [`Max`] receives the greatest value first, and [`Min`] the smallest. Values that don't implement
+/// `Ord`, or that have a priority field, can be ordered by a kind of their own:
+///
+/// ```
+/// use core::cmp::Ordering;
+///
+/// use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+/// use embassy_sync::priority_channel::{Kind, PriorityChannel};
+///
+/// struct Job {
+///     deadline: u64,
+///     name: &'static str,
+/// }
+///
+/// /// Receive the job with the earliest deadline first
+/// struct EarliestDeadline;
+///
+/// impl Kind<Job> for EarliestDeadline {
+///     fn cmp(a: &Job, b: &Job) -> Ordering {
+///         b.deadline.cmp(&a.deadline)
+///     }
+/// }
+///
+/// let channel = PriorityChannel::<NoopRawMutex, Job, EarliestDeadline, 3>::new();
+/// channel.try_send(Job { deadline: 20, name: "later" }).ok();
+/// channel.try_send(Job { deadline: 10, name: "sooner" }).ok();
+/// assert_eq!(channel.try_receive().ok().map(|job| job.name), Some("sooner"));
+/// ```
+pub trait Kind<T> {
+    /// Compare two values, where [`Ordering::Greater`] means that `a` is received before `b`.
+    ///
+    /// Values that compare as equal are received in an unspecified order.
+    fn cmp(a: &T, b: &T) -> Ordering;
+}
+
+impl<T: Ord> Kind<T> for Max {
+    fn cmp(a: &T, b: &T) -> Ordering {
+        Ord::cmp(a, b)
+    }
+}
+
+impl<T: Ord> Kind<T> for Min {
+    fn cmp(a: &T, b: &T) -> Ordering {
+        Ord::cmp(b, a)
+    }
+}
+
 /// Send-only access to a [`PriorityChannel`].
 pub struct Sender<'ch, M, T, K, const N: usize>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     channel: &'ch PriorityChannel<M, T, K, N>,
@@ -28,8 +79,7 @@ where
 
 impl<'ch, M, T, K, const N: usize> Clone for Sender<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     fn clone(&self) -> Self {
@@ -39,16 +89,14 @@ where
 
 impl<'ch, M, T, K, const N: usize> Copy for Sender<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
 }
 
 impl<'ch, M, T, K, const N: usize> Sender<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     /// Sends a value.
@@ -75,8 +123,7 @@ where
 
 impl<'ch, M, T, K, const N: usize> From<Sender<'ch, M, T, K, N>> for DynamicSender<'ch, T>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     fn from(s: Sender<'ch, M, T, K, N>) -> Self {
@@ -87,8 +134,7 @@ where
 /// Receive-only access to a [`PriorityChannel`].
 pub struct Receiver<'ch, M, T, K, const N: usize>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     channel: &'ch PriorityChannel<M, T, K, N>,
@@ -96,8 +142,7 @@ where
 
 impl<'ch, M, T, K, const N: usize> Clone for Receiver<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     fn clone(&self) -> Self {
@@ -107,16 +152,14 @@ where
 
 impl<'ch, M, T, K, const N: usize> Copy for Receiver<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
 }
 
 impl<'ch, M, T, K, const N: usize> Receiver<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     /// Receive the next value.
@@ -150,8 +193,7 @@ where
 
 impl<'ch, M, T, K, const N: usize> From<Receiver<'ch, M, T, K, N>> for DynamicReceiver<'ch, T>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     fn from(s: Receiver<'ch, M, T, K, N>) -> Self {
@@ -163,8 +205,7 @@ where
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct ReceiveFuture<'ch, M, T, K, const N: usize>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     channel: &'ch PriorityChannel<M, T, K, N>,
@@ -172,8 +213,7 @@ where
 
 impl<'ch, M, T, K, const N: usize> Future for ReceiveFuture<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     type Output = T;
@@ -187,8 +227,7 @@ where
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct SendFuture<'ch, M, T, K, const N: usize>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     channel: &'ch PriorityChannel<M, T, K, N>,
@@ -197,20 +236,20 @@ where
 
 impl<'ch, M, T, K, const N: usize> Future for SendFuture<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
-    type Output = ();
+    type Output = Result<(), SendError<T>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.message.take() {
             Some(m) => match self.channel.try_send_with_context(m, Some(cx)) {
-                Ok(..) => Poll::Ready(()),
+                Ok(..) => Poll::Ready(Ok(())),
                 Err(TrySendError::Full(m)) => {
                     self.message = Some(m);
                     Poll::Pending
                 }
+                Err(TrySendError::Closed(m)) => Poll::Ready(Err(SendError::Closed(m))),
             },
             None => panic!("Message cannot be None"),
         }
@@ -219,31 +258,83 @@ where
 
 impl<'ch, M, T, K, const N: usize> Unpin for SendFuture<'ch, M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
 }
 
 struct ChannelState<T, K, const N: usize> {
-    queue: BinaryHeap<T, K, N>,
+    /// A binary heap, with the value that is received next at the front
+    queue: Vec<T, N>,
     receiver_waker: WakerRegistration,
     senders_waker: WakerRegistration,
+    kind: PhantomData<K>,
 }
 
 impl<T, K, const N: usize> ChannelState<T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
 {
     const fn new() -> Self {
         ChannelState {
-            queue: BinaryHeap::new(),
+            queue: Vec::new(),
             receiver_waker: WakerRegistration::new(),
             senders_waker: WakerRegistration::new(),
+            kind: PhantomData,
         }
     }
 
+    /// Whether the value at `a` is received before the value at `b`
+    fn before(&self, a: usize, b: usize) -> bool {
+        K::cmp(&self.queue[a], &self.queue[b]) == Ordering::Greater
+    }
+
+    /// Add a message to the heap, or give it back if the heap is full
+    fn push(&mut self, message: T) -> Result<(), T> {
+        self.queue.push(message)?;
+
+        // Sift the message up until its parent comes before it
+        let mut i = self.queue.len() - 1;
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !self.before(i, parent) {
+                break;
+            }
+            self.queue.swap(i, parent);
+            i = parent;
+        }
+        Ok(())
+    }
+
+    /// Take the message that comes first out of the heap
+    fn pop(&mut self) -> Option<T> {
+        if self.queue.is_empty() {
+            return None;
+        }
+        let message = self.queue.swap_remove(0);
+
+        // Sift the moved message down until its children come after it
+        let mut i = 0;
+        loop {
+            let left = 2 * i + 1;
+            if left >= self.queue.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.queue.len() && self.before(right, left) {
+                right
+            } else {
+                left
+            };
+            if !self.before(child, i) {
+                break;
+            }
+            self.queue.swap(i, child);
+            i = child;
+        }
+        Some(message)
+    }
+
     fn try_receive(&mut self) -> Result<T, TryReceiveError> {
         self.try_receive_with_context(None)
     }
@@ -253,7 +344,7 @@ where
             self.senders_waker.wake();
         }
 
-        if let Some(message) = self.queue.pop() {
+        if let Some(message) = self.pop() {
             Ok(message)
         } else {
             if let Some(cx) = cx {
@@ -268,7 +359,7 @@ where
             self.senders_waker.wake();
         }
 
-        if let Some(message) = self.queue.pop() {
+        if let Some(message) = self.pop() {
             Poll::Ready(message)
         } else {
             self.receiver_waker.register(cx.waker());
@@ -291,7 +382,7 @@ where
     }
 
     fn try_send_with_context(&mut self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
-        match self.queue.push(message) {
+        match self.push(message) {
             Ok(()) => {
                 self.receiver_waker.wake();
                 Ok(())
@@ -328,8 +419,7 @@ where
 /// containing `u32`'s, data sent in the following order `[1, 2, 3]` will be received as `[3, 2, 1]`.
 pub struct PriorityChannel<M, T, K, const N: usize>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     inner: Mutex<M, RefCell<ChannelState<T, K, N>>>,
@@ -337,8 +427,7 @@ where
 
 impl<M, T, K, const N: usize> PriorityChannel<M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     /// Establish a new bounded channel. For example, to create one with a NoopMutex:
@@ -396,7 +485,7 @@ where
     /// Send a value, waiting until there is capacity.
     ///
     /// Sending completes when the value has been pushed to the channel's queue.
-    /// This doesn't mean the value has been received yet.
+    /// This doesn't mean the value has been received yet. Fails if the channel is closed, giving the value back.
     pub fn send(&self, message: T) -> SendFuture<'_, M, T, K, N> {
         SendFuture {
             channel: self,
@@ -439,8 +528,7 @@ where
 /// tradeoff cost of dynamic dispatch.
 impl<M, T, K, const N: usize> DynamicChannel<T> for PriorityChannel<M, T, K, N>
 where
-    T: Ord,
-    K: Kind,
+    K: Kind<T>,
     M: RawMutex,
 {
     fn try_send_with_context(&self, m: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
@@ -471,7 +559,7 @@ mod tests {
     use futures_executor::ThreadPool;
     use futures_timer::Delay;
     use futures_util::task::SpawnExt;
-    use heapless::binary_heap::{Kind, Max};
+    use heapless::binary_heap::Max;
     use static_cell::StaticCell;
 
     use super::*;
@@ -479,8 +567,7 @@ mod tests {
 
     fn capacity<T, K, const N: usize>(c: &ChannelState<T, K, N>) -> usize
     where
-        T: Ord,
-        K: Kind,
+        K: Kind<T>,
     {
         c.queue.capacity() - c.queue.len()
     }
@@ -517,6 +604,38 @@ mod tests {
         assert_eq!(c.try_receive().unwrap(), 1);
     }
 
+    #[test]
+    fn custom_kind() {
+        // Not `Ord`, ordered by its priority field
+        struct Message {
+            priority: u8,
+        }
+
+        struct ByPriority;
+
+        impl Kind<Message> for ByPriority {
+            fn cmp(a: &Message, b: &Message) -> Ordering {
+                a.priority.cmp(&b.priority)
+            }
+        }
+
+        let mut c = ChannelState::<Message, ByPriority, 8>::new();
+        for priority in [3, 7, 1, 8, 5, 2, 6, 4] {
+            assert!(c.try_send(Message { priority }).is_ok());
+        }
+        for priority in (1..=8).rev() {
+            assert_eq!(c.try_receive().ok().map(|m| m.priority), Some(priority));
+        }
+
+        let mut c = ChannelState::<u32, Min, 8>::new();
+        for value in [3, 7, 1, 8, 5, 2, 6, 4] {
+            assert!(c.try_send(value).is_ok());
+        }
+        for value in 1..=8 {
+            assert_eq!(c.try_receive().unwrap(), value);
+        }
+    }
+
     #[test]
     fn receiving_once_with_one_send() {
         let mut c = ChannelState::<u32, Max, 3>::new();
@@ -580,7 +699,7 @@ mod tests {
     #[futures_test::test]
     async fn sender_send_completes_if_capacity() {
         let c = PriorityChannel::<CriticalSectionRawMutex, u32, Max, 1>::new();
-        c.send(1).await;
+        c.send(1).await.unwrap();
         assert_eq!(c.receive().await, 1);
     }
 
@@ -607,7 +726,7 @@ mod tests {
                 }
             })
             .is_ok());
-        send_task_1.unwrap().await;
-        send_task_2.unwrap().await;
+        send_task_1.unwrap().await.unwrap();
+        send_task_2.unwrap().await.unwrap();
     }
 }
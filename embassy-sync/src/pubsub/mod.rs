@@ -3,21 +3,23 @@
 #![deny(missing_docs)]
 
 use core::cell::RefCell;
+use core::cmp::Ordering;
 use core::fmt::Debug;
-use core::task::{Context, Poll};
+use core::ops::Deref;
+use core::task::{Context, Poll, Waker};
 
-use heapless::Deque;
+use heapless::{Deque, Vec};
 
 use self::publisher::{ImmediatePub, Pub};
 use self::subscriber::Sub;
-use crate::blocking_mutex::raw::RawMutex;
+use crate::blocking_mutex::raw::{CriticalSectionRawMutex, RawMutex};
 use crate::blocking_mutex::Mutex;
-use crate::waitqueue::MultiWakerRegistration;
+use crate::waitqueue::{MultiWakerRegistration, WakerRegistration};
 
 pub mod publisher;
 pub mod subscriber;
 
-pub use publisher::{DynImmediatePublisher, DynPublisher, ImmediatePublisher, Publisher};
+pub use publisher::{DynImmediatePublisher, DynPublisher, ImmediatePublisher, Publisher, RawImmediatePub};
 pub use subscriber::{DynSubscriber, Subscriber};
 
 /// A broadcast channel implementation where multiple publishers can send messages to multiple subscribers
@@ -26,10 +28,15 @@ pub use subscriber::{DynSubscriber, Subscriber};
 /// A publisher can choose how it sends its message.
 ///
 /// - With [Pub::publish()] the publisher has to wait until there is space in the internal message queue.
+/// When space frees up, it is kept for the publishers that have been waiting for the longest, which are woken for it.
 /// - With [Pub::publish_immediate()] the publisher doesn't await and instead lets the oldest message
 /// in the queue drop if necessary. This will cause any [Subscriber] that missed the message to receive
 /// an error to indicate that it has lagged.
 ///
+/// Subscribers receive clones of the messages, except for the last subscriber to read a message, which takes it.
+/// Messages don't have to be `Clone` when they are received with [Sub::next_message_with()], which gives out
+/// a [Lease] of the message instead.
+///
 /// ## Example
 ///
 /// ```
@@ -49,7 +56,7 @@ pub use subscriber::{DynSubscriber, Subscriber};
 /// let pub0 = channel.publisher().unwrap();
 ///
 /// // Publish a message, but wait if the queue is full
-/// pub0.publish(42).await;
+/// pub0.publish(42).await.unwrap();
 ///
 /// // Publish a message, but if the queue is full, just kick out the oldest message.
 /// // This may cause some subscribers to miss a message
@@ -71,17 +78,28 @@ pub use subscriber::{DynSubscriber, Subscriber};
 /// # block_on(test);
 /// ```
 ///
-pub struct PubSubChannel<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> {
+pub struct PubSubChannel<M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> {
     inner: Mutex<M, RefCell<PubSubState<T, CAP, SUBS, PUBS>>>,
 }
 
-impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize>
-    PubSubChannel<M, T, CAP, SUBS, PUBS>
-{
+impl<M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> PubSubChannel<M, T, CAP, SUBS, PUBS> {
     /// Create a new channel
     pub const fn new() -> Self {
         Self {
-            inner: Mutex::const_new(M::INIT, RefCell::new(PubSubState::new())),
+            inner: Mutex::const_new(M::INIT, RefCell::new(PubSubState::new(SUBS, PUBS))),
+        }
+    }
+
+    /// Create a new channel that allows at most `subscribers` subscribers and `publishers` publishers at a time.
+    ///
+    /// The limits can't be higher than `SUBS` and `PUBS`. Creating more subscribers or publishers returns
+    /// [Error::MaximumSubscribersReached] or [Error::MaximumPublishersReached], just like when
+    /// all slots are used.
+    pub const fn with_limits(subscribers: usize, publishers: usize) -> Self {
+        let subscribers = if subscribers < SUBS { subscribers } else { SUBS };
+        let publishers = if publishers < PUBS { publishers } else { PUBS };
+        Self {
+            inner: Mutex::const_new(M::INIT, RefCell::new(PubSubState::new(subscribers, publishers))),
         }
     }
 
@@ -89,62 +107,131 @@ impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usi
     ///
     /// If there are no subscriber slots left, an error will be returned.
     pub fn subscriber(&self) -> Result<Subscriber<M, T, CAP, SUBS, PUBS>, Error> {
-        self.inner.lock(|inner| {
-            let mut s = inner.borrow_mut();
-
-            if s.subscriber_count >= SUBS {
-                Err(Error::MaximumSubscribersReached)
-            } else {
-                s.subscriber_count += 1;
-                Ok(Subscriber(Sub::new(s.next_message_id, self)))
-            }
-        })
+        let (next_message_id, slot) = self.register_subscriber(None, false)?;
+        Ok(Subscriber(Sub::new(next_message_id, slot, self)))
     }
 
     /// Create a new subscriber. It will only receive messages that are published after its creation.
     ///
     /// If there are no subscriber slots left, an error will be returned.
     pub fn dyn_subscriber(&self) -> Result<DynSubscriber<'_, T>, Error> {
+        let (next_message_id, slot) = self.register_subscriber(None, false)?;
+        Ok(DynSubscriber(Sub::new(next_message_id, slot, self)))
+    }
+
+    /// Create a new subscriber that only receives the messages for which `filter` returns `true`.
+    /// It will only receive messages that are published after its creation.
+    ///
+    /// The filter is evaluated when a message is published, so messages it rejects don't take up
+    /// space for this subscriber and don't wake it. It may be evaluated more than once for the same message,
+    /// so it must always give the same answer for it.
+    /// Lag amounts include the missed messages that the filter would have rejected.
+    ///
+    /// The filter is a plain function, so state it depends on, like the set of ids a task is interested in,
+    /// has to be kept in a `static`. It is called with the channel locked, so it should be short.
+    ///
+    /// If there are no subscriber slots left, an error will be returned.
+    pub fn subscriber_filtered(&self, filter: fn(&T) -> bool) -> Result<Subscriber<M, T, CAP, SUBS, PUBS>, Error> {
+        let (next_message_id, slot) = self.register_subscriber(Some(filter), false)?;
+        Ok(Subscriber(Sub::new(next_message_id, slot, self)))
+    }
+
+    /// Create a new subscriber that only receives the messages for which `filter` returns `true`.
+    /// See [Self::subscriber_filtered()].
+    ///
+    /// If there are no subscriber slots left, an error will be returned.
+    pub fn dyn_subscriber_filtered(&self, filter: fn(&T) -> bool) -> Result<DynSubscriber<'_, T>, Error> {
+        let (next_message_id, slot) = self.register_subscriber(Some(filter), false)?;
+        Ok(DynSubscriber(Sub::new(next_message_id, slot, self)))
+    }
+
+    /// Create a new subscriber that only receives the most recent message.
+    /// It will only receive messages that are published after its creation.
+    ///
+    /// When a message is published, the older messages this subscriber hasn't read yet are skipped without
+    /// reporting lag, like a watch. So it never holds back publishers by more than one message.
+    ///
+    /// If there are no subscriber slots left, an error will be returned.
+    pub fn subscriber_conflated(&self) -> Result<Subscriber<M, T, CAP, SUBS, PUBS>, Error> {
+        let (next_message_id, slot) = self.register_subscriber(None, true)?;
+        Ok(Subscriber(Sub::new(next_message_id, slot, self)))
+    }
+
+    /// Create a new subscriber that only receives the most recent message.
+    /// See [Self::subscriber_conflated()].
+    ///
+    /// If there are no subscriber slots left, an error will be returned.
+    pub fn dyn_subscriber_conflated(&self) -> Result<DynSubscriber<'_, T>, Error> {
+        let (next_message_id, slot) = self.register_subscriber(None, true)?;
+        Ok(DynSubscriber(Sub::new(next_message_id, slot, self)))
+    }
+
+    /// Register a subscriber, returning its next message id and its slot.
+    fn register_subscriber(
+        &self,
+        filter: Option<fn(&T) -> bool>,
+        conflated: bool,
+    ) -> Result<(u64, Option<usize>), Error> {
         self.inner.lock(|inner| {
             let mut s = inner.borrow_mut();
 
-            if s.subscriber_count >= SUBS {
+            if s.subscriber_count >= s.max_subscribers {
                 Err(Error::MaximumSubscribersReached)
             } else {
                 s.subscriber_count += 1;
-                Ok(DynSubscriber(Sub::new(s.next_message_id, self)))
+                let next_message_id = s.next_message_id;
+                let slot = s.alloc_slot(Slot {
+                    filter,
+                    conflated,
+                    ..Slot::new(next_message_id)
+                });
+                Ok((next_message_id, Some(slot)))
             }
         })
     }
 
-    /// Create a new publisher
+    /// Create a new publisher, with the [LagPolicy::Backpressure] policy
     ///
     /// If there are no publisher slots left, an error will be returned.
     pub fn publisher(&self) -> Result<Publisher<M, T, CAP, SUBS, PUBS>, Error> {
+        self.publisher_with_policy(LagPolicy::Backpressure)
+    }
+
+    /// Create a new publisher with the given [LagPolicy]
+    ///
+    /// If there are no publisher slots left, an error will be returned.
+    pub fn publisher_with_policy(&self, policy: LagPolicy) -> Result<Publisher<M, T, CAP, SUBS, PUBS>, Error> {
         self.inner.lock(|inner| {
             let mut s = inner.borrow_mut();
 
-            if s.publisher_count >= PUBS {
+            if s.publisher_count >= s.max_publishers {
                 Err(Error::MaximumPublishersReached)
             } else {
                 s.publisher_count += 1;
-                Ok(Publisher(Pub::new(self)))
+                Ok(Publisher(Pub::new(self, policy)))
             }
         })
     }
 
-    /// Create a new publisher
+    /// Create a new publisher, with the [LagPolicy::Backpressure] policy
     ///
     /// If there are no publisher slots left, an error will be returned.
     pub fn dyn_publisher(&self) -> Result<DynPublisher<'_, T>, Error> {
+        self.dyn_publisher_with_policy(LagPolicy::Backpressure)
+    }
+
+    /// Create a new publisher with the given [LagPolicy]
+    ///
+    /// If there are no publisher slots left, an error will be returned.
+    pub fn dyn_publisher_with_policy(&self, policy: LagPolicy) -> Result<DynPublisher<'_, T>, Error> {
         self.inner.lock(|inner| {
             let mut s = inner.borrow_mut();
 
-            if s.publisher_count >= PUBS {
+            if s.publisher_count >= s.max_publishers {
                 Err(Error::MaximumPublishersReached)
             } else {
                 s.publisher_count += 1;
-                Ok(DynPublisher(Pub::new(self)))
+                Ok(DynPublisher(Pub::new(self, policy)))
             }
         })
     }
@@ -160,53 +247,240 @@ impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usi
     pub fn dyn_immediate_publisher(&self) -> DynImmediatePublisher<T> {
         DynImmediatePublisher(ImmediatePub::new(self))
     }
-}
 
-impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> PubSubBehavior<T>
-    for PubSubChannel<M, T, CAP, SUBS, PUBS>
-{
-    fn get_message_with_context(&self, next_message_id: &mut u64, cx: Option<&mut Context<'_>>) -> Poll<WaitResult<T>> {
+    /// Drop all messages in the queue.
+    ///
+    /// Subscribers that hadn't read all of them receive [WaitResult::Lagged] for the dropped messages.
+    /// Waiting publishers and subscribers are woken.
+    pub fn clear(&self) {
         self.inner.lock(|s| {
             let mut s = s.borrow_mut();
+            s.clear()
+        })
+    }
+
+    /// Return whether the message queue is full
+    pub fn is_full(&self) -> bool {
+        self.len() == CAP
+    }
+
+    /// Return whether the message queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total message capacity of the queue.
+    ///
+    /// This is the same as the `CAP` generic param.
+    pub fn capacity(&self) -> usize {
+        CAP
+    }
+
+    /// The amount of messages in the queue, which haven't been read by all subscribers yet
+    pub fn len(&self) -> usize {
+        self.inner.lock(|s| s.borrow().queue.len())
+    }
+
+    /// The amount of subscribers that are active
+    pub fn subscriber_count(&self) -> usize {
+        self.inner.lock(|s| s.borrow().subscriber_count)
+    }
+
+    /// The amount of publishers that are active, not counting immediate publishers
+    pub fn publisher_count(&self) -> usize {
+        self.inner.lock(|s| s.borrow().publisher_count)
+    }
+
+    /// Statistics about the use of the channel since it was created
+    pub fn stats(&self) -> Stats {
+        self.inner.lock(|s| s.borrow().stats)
+    }
+
+    /// Set a function that is called whenever a subscriber lagged, with the amount of messages it missed.
+    ///
+    /// This makes it possible to notice slow subscribers in the field, for example by logging or
+    /// signaling from the hook. It is called by the subscriber that noticed the lag, after the channel
+    /// is unlocked again.
+    pub fn set_lag_hook(&self, on_lag: fn(u64)) {
+        self.inner.lock(|s| s.borrow_mut().on_lag = Some(on_lag))
+    }
+
+    /// Run `f` with the state locked, then call the lag hook for the lag it recorded once the state is unlocked
+    fn with_lag_hook<R>(&self, f: impl FnOnce(&mut PubSubState<T, CAP, SUBS, PUBS>) -> R) -> R {
+        let (result, lag) = self.inner.lock(|s| {
+            let mut s = s.borrow_mut();
+            let result = f(&mut s);
+            (result, s.take_lag())
+        });
+
+        if let Some((on_lag, amount)) = lag {
+            on_lag(amount);
+        }
+        result
+    }
+
+    /// Try to get the next message of a subscriber and read it with `read`, registering its waker if there is none.
+    ///
+    /// If `consume` is `false`, the message is only peeked and stays unread.
+    fn poll_message<R>(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+        consume: bool,
+        read: impl FnOnce(Lease<'_, T>) -> R,
+    ) -> Poll<WaitResult<R>> {
+        self.with_lag_hook(|s| {
+            // A publisher may have lagged us because we exceeded our maximum backlog or were conflated
+            let amount = s.sync_slot(next_message_id, slot);
+            if amount != 0 {
+                s.record_lag(amount);
+                // Conflated subscribers only want the most recent message anyway
+                if !s.is_conflated(slot) {
+                    return Poll::Ready(WaitResult::Lagged(amount));
+                }
+            }
 
             // Check if we can read a message
-            match s.get_message(*next_message_id) {
+            let result = match s.get_message(next_message_id, slot, consume, read) {
                 // Yes, so we are done polling
-                Some(WaitResult::Message(message)) => {
-                    *next_message_id += 1;
-                    Poll::Ready(WaitResult::Message(message))
+                Some(WaitResult::Lagged(amount)) => {
+                    s.record_lag(amount);
+                    Poll::Ready(WaitResult::Lagged(amount))
                 }
+                Some(result) => Poll::Ready(result),
                 // No, so we need to reregister our waker and sleep again
                 None => {
                     if let Some(cx) = cx {
-                        s.subscriber_wakers.register(cx.waker());
+                        // The members of a group share a slot, so they can't share its waker
+                        match slot.and_then(|slot| s.slots[slot].as_mut()) {
+                            Some(slot) if slot.members == 1 => slot.waker.register(cx.waker()),
+                            _ => s.subscriber_wakers.register(cx.waker()),
+                        }
                     }
                     Poll::Pending
                 }
-                // We missed a couple of messages. We must do our internal bookkeeping and return that we lagged
-                Some(WaitResult::Lagged(amount)) => {
-                    *next_message_id += amount;
-                    Poll::Ready(WaitResult::Lagged(amount))
-                }
+            };
+
+            s.update_slot(*next_message_id, slot);
+            result
+        })
+    }
+}
+
+impl<T, const CAP: usize, const SUBS: usize, const PUBS: usize>
+    PubSubChannel<CriticalSectionRawMutex, T, CAP, SUBS, PUBS>
+{
+    /// Create a new immediate publisher that can be used from interrupt handlers.
+    /// This kind of publisher does not take up a publisher slot.
+    pub fn raw_immediate_publisher(&self) -> RawImmediatePub<T, CAP, SUBS, PUBS> {
+        RawImmediatePub::new(self)
+    }
+}
+
+impl<M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> PubSubBehavior<T>
+    for PubSubChannel<M, T, CAP, SUBS, PUBS>
+{
+    fn get_message_with_context(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<WaitResult<T>>
+    where
+        T: Clone,
+    {
+        self.poll_message(next_message_id, slot, cx, true, |lease| lease.into_owned())
+    }
+
+    fn peek_message_with_context(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<WaitResult<T>>
+    where
+        T: Clone,
+    {
+        self.poll_message(next_message_id, slot, cx, false, |lease| lease.into_owned())
+    }
+
+    fn lease_message_with_context(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+        read: &mut dyn FnMut(Lease<'_, T>),
+    ) -> Poll<WaitResult<()>> {
+        self.poll_message(next_message_id, slot, cx, true, read)
+    }
+
+    fn available(&self, next_message_id: u64, slot: Option<usize>) -> u64 {
+        self.inner.lock(|s| {
+            let s = s.borrow();
+            let next_message_id = match slot.and_then(|slot| s.slots[slot].as_ref()) {
+                Some(slot) => slot.next_message_id.max(next_message_id),
+                None => next_message_id,
+            };
+
+            // Only count the messages in the queue that this subscriber wants
+            let start_id = s.next_message_id - s.queue.len() as u64;
+            let queued = s
+                .queue
+                .iter()
+                .skip(next_message_id.saturating_sub(start_id) as usize)
+                .filter(|(message, _)| s.wants(slot, message))
+                .count() as u64;
+            let urgent = slot
+                .and_then(|slot| s.slots[slot].as_ref())
+                .map_or(0, |slot| slot.urgent_message_id.is_some() as u64);
+            let available = start_id.saturating_sub(next_message_id) + queued + urgent;
+            if s.is_conflated(slot) {
+                available.min(1)
+            } else {
+                available
             }
         })
     }
 
-    fn available(&self, next_message_id: u64) -> u64 {
-        self.inner.lock(|s| s.borrow().next_message_id - next_message_id)
+    fn skip(&self, next_message_id: &mut u64, slot: Option<usize>, amount: u64) -> u64 {
+        self.with_lag_hook(|s| s.skip(next_message_id, slot, amount))
+    }
+
+    fn set_max_backlog(&self, next_message_id: u64, slot: &mut Option<usize>, max_backlog: usize) {
+        self.inner.lock(|s| {
+            let mut s = s.borrow_mut();
+            s.set_max_backlog(next_message_id, slot, max_backlog)
+        })
+    }
+
+    fn register_group_member(&self, next_message_id: u64, slot: &mut Option<usize>) -> Result<(), Error> {
+        self.inner.lock(|s| {
+            let mut s = s.borrow_mut();
+            s.register_group_member(next_message_id, slot)
+        })
     }
 
-    fn publish_with_context(&self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), T> {
+    fn publish_with_context(
+        &self,
+        message: T,
+        ticket: &mut Option<u64>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Result<(), T> {
         self.inner.lock(|s| {
             let mut s = s.borrow_mut();
+
             // Try to publish the message
-            match s.try_publish(message) {
+            match s.try_publish(message, *ticket) {
                 // We did it, we are ready
-                Ok(()) => Ok(()),
+                Ok(()) => {
+                    s.end_turn(ticket);
+                    Ok(())
+                }
                 // The queue is full, so we need to reregister our waker and go to sleep
                 Err(message) => {
                     if let Some(cx) = cx {
-                        s.publisher_wakers.register(cx.waker());
+                        s.wait_turn(ticket, cx);
                     }
                     Err(message)
                 }
@@ -214,6 +488,28 @@ impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usi
         })
     }
 
+    fn publish_all_with_context(
+        &self,
+        next: &mut dyn FnMut() -> Option<T>,
+        ticket: &mut Option<u64>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<()> {
+        self.inner.lock(|s| {
+            let mut s = s.borrow_mut();
+            // Publish as many messages as fit
+            if s.publish_all(next, *ticket) {
+                s.end_turn(ticket);
+                Poll::Ready(())
+            } else {
+                // The queue is full, so we need to reregister our waker and go to sleep
+                if let Some(cx) = cx {
+                    s.wait_turn(ticket, cx);
+                }
+                Poll::Pending
+            }
+        })
+    }
+
     fn publish_immediate(&self, message: T) {
         self.inner.lock(|s| {
             let mut s = s.borrow_mut();
@@ -221,17 +517,54 @@ impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usi
         })
     }
 
+    fn publish_urgent(&self, message: T) {
+        self.inner.lock(|s| {
+            let mut s = s.borrow_mut();
+            s.publish_urgent(message)
+        })
+    }
+
     fn space(&self) -> usize {
         self.inner.lock(|s| {
             let s = s.borrow();
-            s.queue.capacity() - s.queue.len()
+            s.free_space(None)
+        })
+    }
+
+    fn capacity(&self) -> usize {
+        CAP
+    }
+
+    fn len(&self) -> usize {
+        PubSubChannel::len(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        PubSubChannel::is_empty(self)
+    }
+
+    fn subscriber_count(&self) -> usize {
+        PubSubChannel::subscriber_count(self)
+    }
+
+    fn publisher_count(&self) -> usize {
+        PubSubChannel::publisher_count(self)
+    }
+
+    fn unregister_subscriber(&self, subscriber_next_message_id: u64, slot: Option<usize>) {
+        self.inner.lock(|s| {
+            let mut s = s.borrow_mut();
+            s.unregister_subscriber(subscriber_next_message_id, slot)
         })
     }
 
-    fn unregister_subscriber(&self, subscriber_next_message_id: u64) {
+    fn unregister_waiting_publisher(&self, ticket: &mut Option<u64>) {
         self.inner.lock(|s| {
             let mut s = s.borrow_mut();
-            s.unregister_subscriber(subscriber_next_message_id)
+            // A publisher that was woken for free space passes it on to the next one in line
+            if !s.end_turn(ticket) {
+                s.wake_publisher();
+            }
         })
     }
 
@@ -244,129 +577,736 @@ impl<M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usi
 }
 
 /// Internal state for the PubSub channel
-struct PubSubState<T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> {
+struct PubSubState<T, const CAP: usize, const SUBS: usize, const PUBS: usize> {
     /// The queue contains the last messages that have been published and a countdown of how many subscribers are yet to read it
     queue: Deque<(T, usize), CAP>,
     /// Every message has an id.
     /// Don't worry, we won't run out.
     /// If a million messages were published every second, then the ID's would run out in about 584942 years.
     next_message_id: u64,
-    /// Collection of wakers for Subscribers that are waiting.  
+    /// Collection of wakers for Subscribers that are waiting and don't have a slot.
     subscriber_wakers: MultiWakerRegistration<SUBS>,
-    /// Collection of wakers for Publishers that are waiting.  
-    publisher_wakers: MultiWakerRegistration<PUBS>,
+    /// The tickets and wakers of the publishers that are waiting for space, in the order they started waiting.
+    waiting_publishers: Vec<(u64, Waker), PUBS>,
+    /// The tickets of the publishers that were woken for free space, which is kept for them until they
+    /// publish or stop waiting
+    reserved_publishers: Vec<u64, PUBS>,
+    /// Wakers of the waiting publish futures that didn't fit in `waiting_publishers`,
+    /// because some publisher publishes from multiple futures at once.
+    overflow_publishers: MultiWakerRegistration<PUBS>,
+    /// The ticket the next publisher that starts waiting gets
+    next_ticket: u64,
     /// The amount of subscribers that are active
     subscriber_count: usize,
     /// The amount of publishers that are active
     publisher_count: usize,
+    /// The maximum amount of subscribers that can be active at a time
+    max_subscribers: usize,
+    /// The maximum amount of publishers that can be active at a time
+    max_publishers: usize,
+    /// Statistics about the use of the channel
+    stats: Stats,
+    /// Called whenever a subscriber lagged, with the amount of messages it missed
+    on_lag: Option<fn(u64)>,
+    /// The lag recorded since `on_lag` was last called
+    unreported_lag: u64,
+    /// The subscribers and the groups of subscribers
+    slots: [Option<Slot<T>>; SUBS],
+}
+
+/// State of a subscriber or a group of subscribers, tracked by the channel so publishers can take it into account
+struct Slot<T> {
+    /// The message id of the next message the subscriber is yet to receive
+    next_message_id: u64,
+    /// The maximum amount of unread messages the subscriber keeps when the queue is full
+    max_backlog: Option<usize>,
+    /// Only the messages for which the filter returns `true` are received by the subscriber
+    filter: Option<fn(&T) -> bool>,
+    /// Whether the subscriber only receives the most recent message
+    conflated: bool,
+    /// The amount of subscribers sharing the slot as a group, which together read every message once
+    members: usize,
+    /// The waker of the subscriber, so it is only woken for messages it wants
+    waker: WakerRegistration,
+    /// The id of an urgent message that was published behind the subscriber, which it reads before its next message
+    urgent_message_id: Option<u64>,
+    /// The amount of urgent messages published behind the subscriber since it last synced,
+    /// which moved up the id of its next message without it lagging
+    shifted: u64,
+    /// The amount of messages publishers lagged the subscriber past since it last synced
+    lagged: u64,
+}
+
+impl<T> Slot<T> {
+    const fn new(next_message_id: u64) -> Self {
+        Self {
+            next_message_id,
+            max_backlog: None,
+            filter: None,
+            conflated: false,
+            members: 1,
+            waker: WakerRegistration::new(),
+            urgent_message_id: None,
+            shifted: 0,
+            lagged: 0,
+        }
+    }
+
+    /// Whether the subscriber wants to receive the message
+    fn wants(&self, message: &T) -> bool {
+        self.filter.map_or(true, |filter| filter(message))
+    }
 }
 
-impl<T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> PubSubState<T, CAP, SUBS, PUBS> {
+impl<T, const CAP: usize, const SUBS: usize, const PUBS: usize> PubSubState<T, CAP, SUBS, PUBS> {
+    const EMPTY_SLOT: Option<Slot<T>> = None;
+
     /// Create a new internal channel state
-    const fn new() -> Self {
+    const fn new(max_subscribers: usize, max_publishers: usize) -> Self {
         Self {
             queue: Deque::new(),
             next_message_id: 0,
             subscriber_wakers: MultiWakerRegistration::new(),
-            publisher_wakers: MultiWakerRegistration::new(),
+            waiting_publishers: Vec::new(),
+            reserved_publishers: Vec::new(),
+            overflow_publishers: MultiWakerRegistration::new(),
+            next_ticket: 0,
             subscriber_count: 0,
             publisher_count: 0,
+            max_subscribers,
+            max_publishers,
+            stats: Stats {
+                high_watermark: 0,
+                lagged: 0,
+            },
+            on_lag: None,
+            unreported_lag: 0,
+            slots: [Self::EMPTY_SLOT; SUBS],
+        }
+    }
+
+    /// Whether the subscriber with the given slot wants to receive the message
+    fn wants(&self, slot: Option<usize>, message: &T) -> bool {
+        match slot.and_then(|slot| self.slots[slot].as_ref()) {
+            Some(slot) => slot.wants(message),
+            None => true,
+        }
+    }
+
+    /// The amount of subscribers that want to receive the message, counting every group once
+    fn readers(&self, message: &T) -> usize {
+        let with_slot: usize = self.slots.iter().flatten().map(|slot| slot.members).sum();
+        let wanted = self.slots.iter().flatten().filter(|slot| slot.wants(message)).count();
+        self.subscriber_count - with_slot + wanted
+    }
+
+    /// Wake the subscribers with a slot that want to receive the message
+    fn wake_slots(&mut self, message: &T) {
+        for slot in self.slots.iter_mut().flatten() {
+            if slot.wants(message) {
+                slot.waker.wake();
+            }
+        }
+    }
+
+    /// Whether the subscriber with the given slot only receives the most recent message
+    fn is_conflated(&self, slot: Option<usize>) -> bool {
+        slot.and_then(|slot| self.slots[slot].as_ref())
+            .map_or(false, |slot| slot.conflated)
+    }
+
+    /// Release the unread messages of the conflated subscribers that want the message about to be published,
+    /// so they only keep the most recent one.
+    ///
+    /// Returns `true` if space was made in the queue.
+    fn conflate(&mut self, message: &T) -> bool {
+        for i in 0..SUBS {
+            let from = match &self.slots[i] {
+                Some(slot) if slot.conflated && slot.wants(message) => slot.next_message_id,
+                _ => continue,
+            };
+            self.release_urgent(Some(i));
+            self.release(Some(i), from, self.next_message_id);
+            self.lag_slot(i, self.next_message_id - from);
+            self.update_slot(self.next_message_id, Some(i));
+        }
+
+        self.pop_released()
+    }
+
+    /// Queue up a publisher that has to wait, giving it a ticket if it doesn't have one yet.
+    ///
+    /// A publisher that was woken but has to wait again, because its space was taken by an immediate message,
+    /// gets its place in line back.
+    fn wait_turn(&mut self, ticket: &mut Option<u64>, cx: &mut Context<'_>) {
+        let new_ticket = match *ticket {
+            Some(ticket) => {
+                if let Some((_, waker)) = self.waiting_publishers.iter_mut().find(|(t, _)| *t == ticket) {
+                    if !waker.will_wake(cx.waker()) {
+                        *waker = cx.waker().clone();
+                    }
+                    return;
+                }
+                self.reserved_publishers.retain(|t| *t != ticket);
+                ticket
+            }
+            None => self.next_ticket,
+        };
+
+        let position = self
+            .waiting_publishers
+            .iter()
+            .position(|(t, _)| *t > new_ticket)
+            .unwrap_or(self.waiting_publishers.len());
+        match self
+            .waiting_publishers
+            .insert(position, (new_ticket, cx.waker().clone()))
+        {
+            Ok(()) => {
+                if ticket.is_none() {
+                    *ticket = Some(new_ticket);
+                    self.next_ticket += 1;
+                }
+            }
+            // There are more waiting publish futures than publishers, because some publisher publishes
+            // from multiple futures at once. Those can't get in line, so they are woken with everyone else.
+            Err(_) => self.overflow_publishers.register(cx.waker()),
+        }
+    }
+
+    /// Take a publisher out of the line of waiting publishers, and give up the space kept for it.
+    ///
+    /// Returns `false` if the publisher wasn't in line anymore, because it was woken and space was kept for it.
+    fn end_turn(&mut self, ticket: &mut Option<u64>) -> bool {
+        let Some(ticket) = ticket.take() else {
+            return true;
+        };
+        if let Some(i) = self.waiting_publishers.iter().position(|(t, _)| *t == ticket) {
+            self.waiting_publishers.remove(i);
+            return true;
+        }
+        match self.reserved_publishers.iter().position(|t| *t == ticket) {
+            Some(i) => {
+                self.reserved_publishers.remove(i);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Wake the publishers that have been waiting for the longest, one for every free space in the queue,
+    /// keeping that space for them.
+    fn wake_publisher(&mut self) {
+        let space = self.free_space(None);
+        let woken = space.min(self.waiting_publishers.len());
+        for _ in 0..woken {
+            let (ticket, waker) = self.waiting_publishers.remove(0);
+            // Every woken publisher was in line, so there is room for it
+            self.reserved_publishers.push(ticket).ok().unwrap();
+            waker.wake();
         }
+        if space > woken {
+            self.overflow_publishers.wake();
+        }
+    }
+
+    /// The amount of messages the publisher with `ticket` can publish before the queue is full,
+    /// not counting the space kept for other woken publishers
+    fn free_space(&self, ticket: Option<u64>) -> usize {
+        let reserved = self.reserved_publishers.iter().filter(|t| Some(**t) != ticket).count();
+        (self.queue.capacity() - self.queue.len()).saturating_sub(reserved)
+    }
+
+    /// Make space for the publisher with `ticket` to publish a message, by lagging the slow subscribers if needed.
+    ///
+    /// Returns `true` if there is space.
+    fn make_space(&mut self, ticket: Option<u64>) -> bool {
+        self.free_space(ticket) != 0 || (self.lag_slow_subscribers() && self.free_space(ticket) != 0)
     }
 
-    fn try_publish(&mut self, message: T) -> Result<(), T> {
-        if self.subscriber_count == 0 {
+    fn try_publish(&mut self, message: T, ticket: Option<u64>) -> Result<(), T> {
+        let readers = self.readers(&message);
+        if readers == 0 {
             // We don't need to publish anything because there is no one to receive it
             return Ok(());
         }
 
-        if self.queue.is_full() {
+        self.conflate(&message);
+        if !self.make_space(ticket) {
             return Err(message);
         }
-        // We just did a check for this
-        self.queue.push_back((message, self.subscriber_count)).ok().unwrap();
-
-        self.next_message_id += 1;
+        self.wake_slots(&message);
+        self.push(message, readers);
 
-        // Wake all of the subscribers
+        // Wake all of the subscribers that aren't woken through their slot
         self.subscriber_wakers.wake();
 
         Ok(())
     }
 
-    fn publish_immediate(&mut self, message: T) {
-        // Make space in the queue if required
-        if self.queue.is_full() {
-            self.queue.pop_front();
+    /// Add a message to the back of the queue, which must have space for it
+    fn push(&mut self, message: T, readers: usize) {
+        // We just did a check for this
+        self.queue.push_back((message, readers)).ok().unwrap();
+        self.next_message_id += 1;
+        self.stats.high_watermark = self.stats.high_watermark.max(self.queue.len());
+    }
+
+    /// Record that a subscriber lagged by `amount` messages
+    fn record_lag(&mut self, amount: u64) {
+        self.stats.lagged += amount;
+        self.unreported_lag += amount;
+    }
+
+    /// Take the lag recorded since the lag hook was last called, along with the hook to call
+    fn take_lag(&mut self) -> Option<(fn(u64), u64)> {
+        let amount = core::mem::take(&mut self.unreported_lag);
+        match self.on_lag {
+            Some(on_lag) if amount != 0 => Some((on_lag, amount)),
+            _ => None,
+        }
+    }
+
+    /// Publish messages taken from `next` until it returns `None` or the queue is full.
+    ///
+    /// Subscribers that aren't woken through their slot are woken once, after all messages are published.
+    /// Returns `true` if all messages were published.
+    fn publish_all(&mut self, next: &mut dyn FnMut() -> Option<T>, ticket: Option<u64>) -> bool {
+        let mut published = false;
+        let done = loop {
+            // Only take a message out if there is space for it
+            if self.subscriber_count != 0 && !self.make_space(ticket) {
+                break false;
+            }
+            match next() {
+                Some(message) => {
+                    let readers = self.readers(&message);
+                    // We don't need to publish anything if there is no one to receive it
+                    if readers != 0 {
+                        self.conflate(&message);
+                        self.wake_slots(&message);
+                        self.push(message, readers);
+                        published = true;
+                    }
+                }
+                None => break true,
+            }
+        };
+
+        if published {
+            // Wake all of the subscribers that aren't woken through their slot
+            self.subscriber_wakers.wake();
         }
 
-        // This will succeed because we made sure there is space
-        self.try_publish(message).ok().unwrap();
+        done
     }
 
-    fn get_message(&mut self, message_id: u64) -> Option<WaitResult<T>> {
-        let start_id = self.next_message_id - self.queue.len() as u64;
+    /// Lag the subscribers that have more unread messages than their maximum backlog, releasing their slots.
+    ///
+    /// Returns `true` if space was made in the queue.
+    fn lag_slow_subscribers(&mut self) -> bool {
+        for i in 0..SUBS {
+            let (from, max_backlog) = match &self.slots[i] {
+                Some(Slot {
+                    next_message_id,
+                    max_backlog: Some(max_backlog),
+                    ..
+                }) => (*next_message_id, *max_backlog),
+                _ => continue,
+            };
+
+            let min_id = self.next_message_id.saturating_sub(max_backlog as u64);
+            if from < min_id {
+                // The skipped messages won't be read by this subscriber anymore
+                self.release(Some(i), from, min_id);
+                self.lag_slot(i, min_id - from);
+                self.update_slot(min_id, Some(i));
+            }
+        }
+
+        self.pop_released()
+    }
 
-        if message_id < start_id {
-            return Some(WaitResult::Lagged(start_id - message_id));
+    /// Decrement the counters of the messages with an id in `from..to` that the subscriber wants,
+    /// as if it had read them.
+    fn release(&mut self, slot: Option<usize>, from: u64, to: u64) {
+        let start_id = self.next_message_id - self.queue.len() as u64;
+        let from = from.max(start_id);
+        if from >= to {
+            return;
         }
 
-        let current_message_index = (message_id - start_id) as usize;
+        let slot = slot.and_then(|slot| self.slots[slot].as_ref());
+        self.queue
+            .iter_mut()
+            .skip((from - start_id) as usize)
+            .take((to - from) as usize)
+            .filter(|(message, _)| slot.map_or(true, |slot| slot.wants(message)))
+            .for_each(|(_, counter)| *counter -= 1);
+    }
 
-        if current_message_index >= self.queue.len() {
-            return None;
+    /// Remove the oldest messages that have been read by all subscribers.
+    ///
+    /// Returns `true` if space was made in the queue.
+    fn pop_released(&mut self) -> bool {
+        let mut made_space = false;
+        while let Some((_, count)) = self.queue.front() {
+            if *count == 0 {
+                self.queue.pop_front().unwrap();
+                made_space = true;
+            } else {
+                break;
+            }
         }
+        made_space
+    }
 
-        // We've checked that the index is valid
-        let queue_item = self.queue.iter_mut().nth(current_message_index).unwrap();
+    /// Move the subscriber to the position of its slot, which publishers may have lagged it to
+    /// or the other members of its group may have read up to.
+    ///
+    /// Returns the amount of messages the subscriber lagged by.
+    fn sync_slot(&mut self, next_message_id: &mut u64, slot: Option<usize>) -> u64 {
+        match slot.and_then(|slot| self.slots[slot].as_mut()) {
+            Some(slot) => {
+                // Urgent messages published behind us moved up our messages
+                *next_message_id += core::mem::take(&mut slot.shifted);
+                *next_message_id = slot.next_message_id.max(*next_message_id);
+                core::mem::take(&mut slot.lagged)
+            }
+            None => 0,
+        }
+    }
 
-        // We're reading this item, so decrement the counter
-        queue_item.1 -= 1;
+    /// Record that publishers lagged the subscriber in `slot` past `amount` messages, for when it next syncs
+    fn lag_slot(&mut self, slot: usize, amount: u64) {
+        if let Some(slot) = self.slots[slot].as_mut() {
+            slot.lagged += amount;
+        }
+    }
 
-        let message = if current_message_index == 0 && queue_item.1 == 0 {
-            let (message, _) = self.queue.pop_front().unwrap();
-            self.publisher_wakers.wake();
-            // Return pop'd message without clone
-            message
-        } else {
-            queue_item.0.clone()
+    /// Release the urgent message the subscriber still has to read, if any, as if it had read it.
+    ///
+    /// Returns `true` if there was one.
+    fn release_urgent(&mut self, slot: Option<usize>) -> bool {
+        let id = match slot.and_then(|slot| self.slots[slot].as_mut()) {
+            Some(slot) => match slot.urgent_message_id.take() {
+                Some(id) => id,
+                None => return false,
+            },
+            None => return false,
         };
 
-        Some(WaitResult::Message(message))
+        // It may have been dropped from the queue already
+        let start_id = self.next_message_id - self.queue.len() as u64;
+        if id >= start_id {
+            self.queue.iter_mut().nth((id - start_id) as usize).unwrap().1 -= 1;
+        }
+        true
     }
 
-    fn unregister_subscriber(&mut self, subscriber_next_message_id: u64) {
-        self.subscriber_count -= 1;
+    /// Skip up to `amount` messages of a subscriber, as if it had read them.
+    /// Messages the subscriber doesn't want are skipped without being counted.
+    ///
+    /// Returns the amount of messages that were skipped, including the ones the subscriber lagged by.
+    fn skip(&mut self, next_message_id: &mut u64, slot: Option<usize>, mut amount: u64) -> u64 {
+        let mut skipped = self.sync_slot(next_message_id, slot);
+        self.record_lag(skipped);
+
+        // An urgent message published behind us is the next one
+        if amount != 0 && self.release_urgent(slot) {
+            amount -= 1;
+            skipped += 1;
+        }
 
-        // All messages that haven't been read yet by this subscriber must have their counter decremented
+        // The messages that are no longer in the queue were already released
         let start_id = self.next_message_id - self.queue.len() as u64;
-        if subscriber_next_message_id >= start_id {
-            let current_message_index = (subscriber_next_message_id - start_id) as usize;
-            self.queue
-                .iter_mut()
-                .skip(current_message_index)
-                .for_each(|(_, counter)| *counter -= 1);
-
-            let mut wake_publishers = false;
-            while let Some((_, count)) = self.queue.front() {
-                if *count == 0 {
-                    self.queue.pop_front().unwrap();
-                    wake_publishers = true;
-                } else {
+        let lagged = start_id.saturating_sub(*next_message_id).min(amount);
+        *next_message_id += lagged;
+        skipped += lagged;
+        self.record_lag(lagged);
+
+        let mut end_id = *next_message_id;
+        let mut remaining = amount - lagged;
+        if end_id >= start_id {
+            let slot_state = slot.and_then(|slot| self.slots[slot].as_ref());
+            for (message, counter) in self.queue.iter_mut().skip((end_id - start_id) as usize) {
+                if remaining == 0 {
                     break;
                 }
+                if slot_state.map_or(true, |slot| slot.wants(message)) {
+                    *counter -= 1;
+                    remaining -= 1;
+                    skipped += 1;
+                }
+                end_id += 1;
             }
+        }
 
-            if wake_publishers {
-                self.publisher_wakers.wake();
-            }
+        if self.pop_released() {
+            self.wake_publisher();
         }
-    }
 
-    fn unregister_publisher(&mut self) {
-        self.publisher_count -= 1;
+        *next_message_id = end_id;
+        self.update_slot(*next_message_id, slot);
+        skipped
     }
-}
+
+    /// Record the new position of a subscriber
+    fn update_slot(&mut self, next_message_id: u64, slot: Option<usize>) {
+        if let Some(slot) = slot.and_then(|slot| self.slots[slot].as_mut()) {
+            slot.next_message_id = next_message_id;
+        }
+    }
+
+    /// Give a subscriber a slot.
+    ///
+    /// There are at most SUBS subscribers, so there is always a free slot for a registered subscriber.
+    fn alloc_slot(&mut self, slot: Slot<T>) -> usize {
+        let index = self.slots.iter().position(|slot| slot.is_none()).unwrap();
+        self.slots[index] = Some(slot);
+        index
+    }
+
+    fn set_max_backlog(&mut self, next_message_id: u64, slot: &mut Option<usize>, max_backlog: usize) {
+        match slot.and_then(|slot| self.slots[slot].as_mut()) {
+            Some(slot) => slot.max_backlog = Some(max_backlog),
+            None => {
+                *slot = Some(self.alloc_slot(Slot {
+                    max_backlog: Some(max_backlog),
+                    ..Slot::new(next_message_id)
+                }))
+            }
+        }
+    }
+
+    /// Register a new member of the group of a subscriber, giving the subscriber a slot if it doesn't have one yet
+    fn register_group_member(&mut self, next_message_id: u64, slot: &mut Option<usize>) -> Result<(), Error> {
+        if self.subscriber_count >= self.max_subscribers {
+            return Err(Error::MaximumSubscribersReached);
+        }
+        self.subscriber_count += 1;
+
+        let index = match *slot {
+            Some(index) => index,
+            None => self.alloc_slot(Slot::new(next_message_id)),
+        };
+        *slot = Some(index);
+
+        // The slot waker is no longer used, so make its subscriber register again
+        let slot = self.slots[index].as_mut().unwrap();
+        slot.members += 1;
+        slot.waker.wake();
+        Ok(())
+    }
+
+    fn publish_immediate(&mut self, message: T) {
+        let readers = self.readers(&message);
+        if readers == 0 {
+            // We don't need to publish anything because there is no one to receive it
+            return;
+        }
+
+        // Make space in the queue if required, even if it was kept for a waiting publisher
+        if !self.conflate(&message) && self.queue.is_full() {
+            self.queue.pop_front();
+        }
+
+        self.wake_slots(&message);
+        self.push(message, readers);
+
+        // Wake all of the subscribers that aren't woken through their slot
+        self.subscriber_wakers.wake();
+    }
+
+    /// Publish a message that the subscribers read before the messages they haven't read yet.
+    ///
+    /// It is inserted at the position of the slowest subscriber that wants it. The subscribers that are further
+    /// along keep their place, and read it out of order.
+    fn publish_urgent(&mut self, message: T) {
+        let readers = self.readers(&message);
+        if readers == 0 {
+            // We don't need to publish anything because there is no one to receive it
+            return;
+        }
+
+        // Make space in the queue if required
+        if !self.conflate(&message) && self.queue.is_full() {
+            self.queue.pop_front();
+        }
+
+        // A subscriber reads only one urgent message out of order, so this one can't go before the
+        // subscribers that still have to read an earlier one
+        let start_id = self.next_message_id - self.queue.len() as u64;
+        let mut insert_id = self.next_message_id;
+        let mut min_id = start_id;
+        for slot in self.slots.iter().flatten().filter(|slot| slot.wants(&message)) {
+            insert_id = insert_id.min(slot.next_message_id);
+            if slot.urgent_message_id.is_some() {
+                min_id = min_id.max(slot.next_message_id);
+            }
+        }
+        let insert_id = insert_id.max(min_id);
+
+        // The messages from there on move up by one, so the subscribers past it are moved up with them
+        for slot in self.slots.iter_mut().flatten() {
+            if let Some(id) = slot.urgent_message_id.as_mut() {
+                if *id >= insert_id {
+                    *id += 1;
+                }
+            }
+            if slot.next_message_id > insert_id {
+                slot.next_message_id += 1;
+                slot.shifted += 1;
+                if slot.wants(&message) {
+                    slot.urgent_message_id = Some(insert_id);
+                }
+            }
+        }
+
+        self.wake_slots(&message);
+        self.push(message, readers);
+        self.move_newest_to((insert_id - start_id) as usize);
+
+        // Wake all of the subscribers that aren't woken through their slot
+        self.subscriber_wakers.wake();
+    }
+
+    /// Move the newest message in the queue to `index`, shifting the messages from there on back by one
+    fn move_newest_to(&mut self, index: usize) {
+        let mut i = self.queue.len() - 1;
+        let (front, back) = self.queue.as_mut_slices();
+        while i > index {
+            match i.cmp(&front.len()) {
+                Ordering::Less => front.swap(i - 1, i),
+                Ordering::Greater => back.swap(i - 1 - front.len(), i - front.len()),
+                // The two messages are on both sides of the wrap around
+                Ordering::Equal => core::mem::swap(&mut front[i - 1], &mut back[0]),
+            }
+            i -= 1;
+        }
+    }
+
+    /// Read the next message the subscriber wants with `read`, moving it past the read message.
+    /// If `consume` is `false`, the message is only borrowed and the subscriber is moved up to it instead.
+    ///
+    /// Messages the subscriber doesn't want were never counted for it, so they are passed over.
+    fn get_message<R>(
+        &mut self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        consume: bool,
+        read: impl FnOnce(Lease<'_, T>) -> R,
+    ) -> Option<WaitResult<R>> {
+        let start_id = self.next_message_id - self.queue.len() as u64;
+
+        // An urgent message published behind us goes first
+        let urgent_message_id = slot
+            .and_then(|slot| self.slots[slot].as_ref())
+            .and_then(|slot| slot.urgent_message_id);
+        let current_message_index = match urgent_message_id {
+            Some(id) if id >= start_id => (id - start_id) as usize,
+            Some(_) => {
+                // It was dropped before we got to it
+                self.release_urgent(slot);
+                return Some(WaitResult::Lagged(1));
+            }
+            None => {
+                if *next_message_id < start_id {
+                    // We missed a couple of messages, which doesn't matter if we only want the most recent one
+                    let amount = start_id - *next_message_id;
+                    *next_message_id = start_id;
+                    if !self.is_conflated(slot) {
+                        return Some(WaitResult::Lagged(amount));
+                    }
+                    self.record_lag(amount);
+                }
+
+                let offset = (*next_message_id - start_id) as usize;
+                let current_message_index = match self
+                    .queue
+                    .iter()
+                    .skip(offset)
+                    .position(|(message, _)| self.wants(slot, message))
+                {
+                    Some(position) => offset + position,
+                    None => return None,
+                };
+                *next_message_id = start_id + current_message_index as u64;
+                current_message_index
+            }
+        };
+
+        if !consume {
+            // We've checked that the index is valid
+            let (message, _) = self.queue.iter().nth(current_message_index).unwrap();
+            return Some(WaitResult::Message(read(Lease::Shared(message))));
+        }
+        match urgent_message_id {
+            Some(_) => {
+                self.release_urgent(slot);
+            }
+            None => {
+                *next_message_id += 1;
+                // We're reading this item, so decrement the counter
+                self.queue.iter_mut().nth(current_message_index).unwrap().1 -= 1;
+            }
+        }
+
+        let output = if current_message_index == 0 && self.queue.front().unwrap().1 == 0 {
+            let (message, _) = self.queue.pop_front().unwrap();
+            self.pop_released();
+            self.wake_publisher();
+            // We were the last reader, so we get the pop'd message without clone
+            read(Lease::Owned(message))
+        } else {
+            read(Lease::Shared(&self.queue.iter().nth(current_message_index).unwrap().0))
+        };
+
+        Some(WaitResult::Message(output))
+    }
+
+    fn clear(&mut self) {
+        // Subscribers see that the dropped messages are no longer in the queue and lag past them
+        self.queue.clear();
+        self.wake_publisher();
+
+        // Wake the subscribers, so they notice their lag without waiting for the next message
+        for slot in self.slots.iter_mut().flatten() {
+            slot.waker.wake();
+        }
+        self.subscriber_wakers.wake();
+    }
+
+    fn unregister_subscriber(&mut self, mut subscriber_next_message_id: u64, slot: Option<usize>) {
+        // The other members of the group keep reading the messages of the group
+        if let Some(slot) = slot.and_then(|slot| self.slots[slot].as_mut()) {
+            if slot.members > 1 {
+                slot.members -= 1;
+                self.subscriber_count -= 1;
+                return;
+            }
+        }
+
+        // The messages the subscriber was lagged past were already released
+        self.sync_slot(&mut subscriber_next_message_id, slot);
+
+        // All messages that haven't been read yet by this subscriber must have their counter decremented
+        self.release_urgent(slot);
+        self.release(slot, subscriber_next_message_id, self.next_message_id);
+        if let Some(slot) = slot {
+            self.slots[slot] = None;
+        }
+        self.subscriber_count -= 1;
+
+        if self.pop_released() {
+            self.wake_publisher();
+        }
+    }
+
+    fn unregister_publisher(&mut self) {
+        self.publisher_count -= 1;
+    }
+}
 
 /// Error type for the [PubSubChannel]
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -380,31 +1320,138 @@ pub enum Error {
     MaximumPublishersReached,
 }
 
+/// Statistics about the use of a [PubSubChannel], see [PubSubChannel::stats()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// The highest amount of messages that were in the queue at once
+    pub high_watermark: usize,
+    /// The total amount of messages that subscribers missed because they lagged
+    pub lagged: u64,
+}
+
+/// What [Pub::publish()] does when the message queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LagPolicy {
+    /// Wait for all subscribers to have read the oldest message
+    #[default]
+    Backpressure,
+    /// Drop the oldest message, so publishing always succeeds. Subscribers that missed it will lag.
+    DropOldest,
+    /// Reject the new message, returning it in an error. Subscribers never lag.
+    RejectNewest,
+}
+
 /// 'Middle level' behaviour of the pubsub channel.
 /// This trait is used so that Sub and Pub can be generic over the channel.
 pub trait PubSubBehavior<T> {
     /// Try to get a message from the queue with the given message id.
     ///
     /// If the message is not yet present and a context is given, then its waker is registered in the subsriber wakers.
-    fn get_message_with_context(&self, next_message_id: &mut u64, cx: Option<&mut Context<'_>>) -> Poll<WaitResult<T>>;
+    /// `slot` is the slot of the subscriber in the channel, if it has a filter or a maximum backlog.
+    fn get_message_with_context(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<WaitResult<T>>
+    where
+        T: Clone;
+
+    /// Try to get a clone of the message with the given message id, without reading it.
+    ///
+    /// Lag is reported and skipped like with [Self::get_message_with_context()], but the message stays unread.
+    /// If the message is not yet present and a context is given, then its waker is registered in the subsriber wakers.
+    fn peek_message_with_context(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<WaitResult<T>>
+    where
+        T: Clone;
+
+    /// Try to get the message with the given message id and read it with `read`, without cloning it.
+    ///
+    /// `read` is called with the channel locked. It gets ownership of the message if this subscriber was the
+    /// last one to read it. Otherwise this works like [Self::get_message_with_context()].
+    fn lease_message_with_context(
+        &self,
+        next_message_id: &mut u64,
+        slot: Option<usize>,
+        cx: Option<&mut Context<'_>>,
+        read: &mut dyn FnMut(Lease<'_, T>),
+    ) -> Poll<WaitResult<()>>;
 
     /// Get the amount of messages that are between the given the next_message_id and the most recent message.
     /// This is not necessarily the amount of messages a subscriber can still received as it may have lagged.
-    fn available(&self, next_message_id: u64) -> u64;
+    fn available(&self, next_message_id: u64, slot: Option<usize>) -> u64;
+
+    /// Skip up to `amount` messages of a subscriber, releasing them as if it had read them.
+    ///
+    /// Returns the amount of messages that were skipped.
+    fn skip(&self, next_message_id: &mut u64, slot: Option<usize>, amount: u64) -> u64;
+
+    /// Set the maximum backlog of a subscriber.
+    ///
+    /// If the subscriber has no slot yet, one is allocated and stored in `slot`.
+    fn set_max_backlog(&self, next_message_id: u64, slot: &mut Option<usize>, max_backlog: usize);
+
+    /// Register a new subscriber in the group of a subscriber, sharing its slot.
+    ///
+    /// If the subscriber has no slot yet, one is allocated and stored in `slot`.
+    fn register_group_member(&self, next_message_id: u64, slot: &mut Option<usize>) -> Result<(), Error>;
 
     /// Try to publish a message to the queue.
     ///
-    /// If the queue is full and a context is given, then its waker is registered in the publisher wakers.
-    fn publish_with_context(&self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), T>;
+    /// If the queue is full and a context is given, then the publisher waits in line with its `ticket`,
+    /// which is set back to `None` once it published. When space frees up, it is kept for the publishers that
+    /// have been waiting for the longest, which are woken for it.
+    fn publish_with_context(&self, message: T, ticket: &mut Option<u64>, cx: Option<&mut Context<'_>>)
+        -> Result<(), T>;
+
+    /// Publish messages taken from `next` until it returns `None`, under a single lock.
+    ///
+    /// If the queue gets full and a context is given, then the publisher waits in line with its `ticket`,
+    /// like with [Self::publish_with_context()].
+    /// Returns `Poll::Ready` once `next` returned `None`.
+    fn publish_all_with_context(
+        &self,
+        next: &mut dyn FnMut() -> Option<T>,
+        ticket: &mut Option<u64>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<()>;
 
     /// Publish a message immediately
     fn publish_immediate(&self, message: T);
 
+    /// Publish a message immediately, ahead of the messages the subscribers haven't read yet
+    fn publish_urgent(&self, message: T);
+
     /// The amount of messages that can still be published without having to wait or without having to lag the subscribers
     fn space(&self) -> usize;
 
+    /// The maximum amount of messages in the queue
+    fn capacity(&self) -> usize;
+
+    /// The amount of messages in the queue
+    fn len(&self) -> usize;
+
+    /// Whether there are no messages in the queue
+    fn is_empty(&self) -> bool;
+
+    /// The amount of subscribers that are active
+    fn subscriber_count(&self) -> usize;
+
+    /// The amount of publishers that are active, not counting immediate publishers
+    fn publisher_count(&self) -> usize;
+
     /// Let the channel know that a subscriber has dropped
-    fn unregister_subscriber(&self, subscriber_next_message_id: u64);
+    fn unregister_subscriber(&self, subscriber_next_message_id: u64, slot: Option<usize>);
+
+    /// Let the channel know that a publisher that was waiting in line with `ticket` stopped waiting
+    fn unregister_waiting_publisher(&self, ticket: &mut Option<u64>);
 
     /// Let the channel know that a publisher has dropped
     fn unregister_publisher(&self);
@@ -421,8 +1468,67 @@ pub enum WaitResult<T> {
     Message(T),
 }
 
+impl<T> WaitResult<T> {
+    /// Convert the result into a `Result`, turning lag into an error
+    pub fn into_result(self) -> Result<T, LagError> {
+        match self {
+            WaitResult::Lagged(amount) => Err(LagError(amount)),
+            WaitResult::Message(message) => Ok(message),
+        }
+    }
+}
+
+/// Error for a subscriber that did not receive all messages, with the amount of messages it missed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LagError(pub u64);
+
+/// Access to a message read by a subscriber, without requiring the message to be `Clone`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lease<'m, T> {
+    /// Other subscribers still have to read the message, so it can only be borrowed
+    Shared(&'m T),
+    /// This subscriber was the last one to read the message, so it takes ownership of it
+    Owned(T),
+}
+
+impl<'m, T> Lease<'m, T> {
+    /// Take ownership of the message, if this subscriber was the last one to read it
+    pub fn into_inner(self) -> Option<T> {
+        match self {
+            Lease::Shared(_) => None,
+            Lease::Owned(message) => Some(message),
+        }
+    }
+}
+
+impl<'m, T: Clone> Lease<'m, T> {
+    /// Take ownership of the message, cloning it if other subscribers still have to read it
+    pub fn into_owned(self) -> T {
+        match self {
+            Lease::Shared(message) => message.clone(),
+            Lease::Owned(message) => message,
+        }
+    }
+}
+
+impl<'m, T> Deref for Lease<'m, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Lease::Shared(message) => message,
+            Lease::Owned(message) => message,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use futures_executor::ThreadPool;
+    use futures_util::task::SpawnExt;
+    use static_cell::StaticCell;
+
     use super::*;
     use crate::blocking_mutex::raw::NoopRawMutex;
 
@@ -434,7 +1540,7 @@ mod tests {
         let mut sub1 = channel.dyn_subscriber().unwrap();
         let pub0 = channel.dyn_publisher().unwrap();
 
-        pub0.publish(42).await;
+        pub0.publish(42).await.unwrap();
 
         assert_eq!(sub0.next_message().await, WaitResult::Message(42));
         assert_eq!(sub1.next_message().await, WaitResult::Message(42));
@@ -443,6 +1549,696 @@ mod tests {
         assert_eq!(sub1.try_next_message(), None);
     }
 
+    #[futures_test::test]
+    async fn into_dyn_keeps_registration() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 1, 1>::new();
+
+        let mut sub0: DynSubscriber<'_, u32> = channel.subscriber().unwrap().into();
+        let pub0: DynPublisher<'_, u32> = channel.publisher().unwrap().into();
+
+        // The slots are still taken
+        assert!(channel.subscriber().is_err());
+        assert!(channel.publisher().is_err());
+
+        pub0.publish(42).await.unwrap();
+        assert_eq!(sub0.next_message().await, WaitResult::Message(42));
+
+        drop(sub0);
+        drop(pub0);
+
+        // Dropping the dyn handles frees the slots
+        assert!(channel.subscriber().is_ok());
+        assert!(channel.publisher().is_ok());
+    }
+
+    #[futures_test::test]
+    async fn sink_publishes() {
+        use futures_util::SinkExt;
+
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut pub0 = channel.publisher().unwrap();
+
+        pub0.send(42).await.unwrap();
+        pub0.feed(43).await.unwrap();
+        pub0.flush().await.unwrap();
+
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(42)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(43)));
+        assert_eq!(sub0.try_next_message(), None);
+    }
+
+    #[futures_test::test]
+    async fn publish_all() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        // Only the messages that fit are published
+        assert_eq!(pub0.try_publish_all(&[1, 2, 3, 4, 5]), 4);
+        assert_eq!(pub0.space(), 0);
+
+        for i in 1..=4 {
+            assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(i)));
+        }
+
+        pub0.publish_all([5, 6, 7]).await;
+        assert_eq!(sub0.available(), 3);
+        for i in 5..=7 {
+            assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(i)));
+        }
+    }
+
+    #[futures_test::test]
+    async fn max_backlog_lags_slow_subscriber() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut slow = channel.subscriber().unwrap();
+        let mut fast = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        slow.set_max_backlog(2);
+
+        for i in 0..4 {
+            pub0.try_publish(i).unwrap();
+            assert_eq!(fast.try_next_message(), Some(WaitResult::Message(i)));
+        }
+        assert_eq!(slow.available(), 4);
+
+        // The queue is full, the slow subscriber is lagged to its 2 most recent messages
+        pub0.try_publish(4).unwrap();
+        assert_eq!(slow.available(), 3);
+        assert_eq!(slow.try_next_message(), Some(WaitResult::Lagged(2)));
+        assert_eq!(slow.try_next_message(), Some(WaitResult::Message(2)));
+        assert_eq!(slow.try_next_message(), Some(WaitResult::Message(3)));
+        assert_eq!(slow.try_next_message(), Some(WaitResult::Message(4)));
+        assert_eq!(fast.try_next_message(), Some(WaitResult::Message(4)));
+
+        // The released slots can be reused
+        drop(slow);
+        assert!(channel.subscriber().is_ok());
+        assert_eq!(pub0.space(), 4);
+    }
+
+    #[futures_test::test]
+    async fn skip_to_latest() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        for i in 0..4 {
+            pub0.try_publish(i).unwrap();
+        }
+
+        assert_eq!(sub0.skip(1), 1);
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(1)));
+        assert_eq!(pub0.space(), 2);
+
+        assert_eq!(sub0.skip_to_latest(), 2);
+        assert_eq!(sub0.available(), 0);
+        assert_eq!(sub0.try_next_message(), None);
+
+        // The skipped messages were released
+        assert_eq!(pub0.space(), 4);
+        assert_eq!(sub0.skip_to_latest(), 0);
+    }
+
+    #[futures_test::test]
+    async fn immediate_publisher_is_copy() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 1>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.immediate_publisher();
+        let pub1 = pub0;
+
+        pub0.publish_immediate(42);
+        pub1.publish_immediate(43);
+
+        // Immediate publishers don't take up a publisher slot
+        assert!(channel.publisher().is_ok());
+
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(42)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(43)));
+    }
+
+    #[futures_test::test]
+    async fn lag_policies() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 2, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let drop_oldest = channel.publisher_with_policy(LagPolicy::DropOldest).unwrap();
+        let reject_newest = channel.publisher_with_policy(LagPolicy::RejectNewest).unwrap();
+
+        assert_eq!(reject_newest.publish(1).await, Ok(()));
+        assert_eq!(reject_newest.publish(2).await, Ok(()));
+        assert_eq!(reject_newest.publish(3).await, Err(3));
+
+        assert_eq!(drop_oldest.publish(3).await, Ok(()));
+
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Lagged(1)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(2)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(3)));
+    }
+
+    #[futures_test::test]
+    async fn filtered_subscriber() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut even = channel.subscriber_filtered(|message| message % 2 == 0).unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        for i in 0..6 {
+            pub0.try_publish(i).unwrap();
+        }
+
+        // Messages nobody wants aren't queued
+        assert_eq!(pub0.space(), 1);
+        assert_eq!(even.available(), 3);
+
+        let mut all = channel.subscriber().unwrap();
+        pub0.try_publish(6).unwrap();
+        pub0.try_publish(7).unwrap_err();
+        assert_eq!(even.available(), 4);
+        assert_eq!(all.available(), 1);
+
+        assert_eq!(even.try_next_message(), Some(WaitResult::Message(0)));
+        assert_eq!(even.skip(1), 1);
+        assert_eq!(even.try_next_message(), Some(WaitResult::Message(4)));
+        assert_eq!(pub0.space(), 3);
+
+        drop(even);
+        assert_eq!(pub0.space(), 3);
+        assert_eq!(all.try_next_message(), Some(WaitResult::Message(6)));
+        assert_eq!(pub0.space(), 4);
+    }
+
+    #[futures_test::test]
+    async fn peek_does_not_receive() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        assert_eq!(sub0.try_peek(), None);
+
+        pub0.publish(42).await.unwrap();
+
+        assert_eq!(sub0.peek().await, WaitResult::Message(42));
+        assert_eq!(sub0.try_peek(), Some(WaitResult::Message(42)));
+        assert_eq!(sub0.available(), 1);
+        assert_eq!(pub0.space(), 3);
+
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(42)));
+        assert_eq!(sub0.try_peek(), None);
+        assert_eq!(pub0.space(), 4);
+    }
+
+    #[futures_test::test]
+    async fn introspection() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 2, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+        let _pub1 = channel.dyn_publisher().unwrap();
+        let _immediate = channel.immediate_publisher();
+
+        assert_eq!(channel.capacity(), 2);
+        assert_eq!(channel.subscriber_count(), 1);
+        assert_eq!(channel.publisher_count(), 2);
+        assert_eq!(sub0.publisher_count(), 2);
+        assert_eq!(pub0.subscriber_count(), 1);
+        assert!(pub0.is_empty());
+
+        pub0.publish(1).await.unwrap();
+        pub0.publish(2).await.unwrap();
+        assert_eq!(sub0.len(), 2);
+        assert!(pub0.is_full());
+        assert!(channel.is_full());
+
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(1)));
+        assert_eq!(channel.len(), 1);
+        assert!(!sub0.is_full());
+    }
+
+    #[futures_test::test]
+    async fn clear_lags_subscribers() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut sub1 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        for i in 0..4 {
+            pub0.try_publish(i).unwrap();
+        }
+        assert_eq!(sub1.try_next_message(), Some(WaitResult::Message(0)));
+
+        let (waker, count) = futures_test::task::new_count_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut sub2 = channel.subscriber().unwrap();
+        assert_eq!(sub2.poll_next_message(&mut cx), Poll::Pending);
+
+        channel.clear();
+        assert_eq!(pub0.space(), 4);
+        assert_eq!(count, 1);
+
+        pub0.try_publish(4).unwrap();
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Lagged(4)));
+        assert_eq!(sub1.try_next_message(), Some(WaitResult::Lagged(3)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(4)));
+        assert_eq!(sub1.try_next_message(), Some(WaitResult::Message(4)));
+    }
+
+    #[futures_test::test]
+    async fn raw_immediate_publisher_from_interrupt() {
+        fn assert_send_copy<P: Send + Copy>(_: &P) {}
+
+        static CHANNEL: PubSubChannel<CriticalSectionRawMutex, u32, 4, 1, 1> = PubSubChannel::new();
+        static PUBLISHER: StaticCell<RawImmediatePub<'static, u32, 4, 1, 1>> = StaticCell::new();
+
+        let mut sub0 = CHANNEL.subscriber().unwrap();
+        let pub0 = *PUBLISHER.init(CHANNEL.raw_immediate_publisher());
+        assert_send_copy(&pub0);
+
+        // Another thread stands in for an interrupt handler
+        let executor = ThreadPool::new().unwrap();
+        executor
+            .spawn_with_handle(async move {
+                for i in 0..3 {
+                    pub0.publish_immediate(i);
+                }
+            })
+            .unwrap()
+            .await;
+
+        for i in 0..3 {
+            assert_eq!(sub0.next_message_pure().await, i);
+        }
+    }
+
+    #[futures_test::test]
+    async fn lag_statistics() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        static LAGGED: AtomicU64 = AtomicU64::new(0);
+
+        let channel = PubSubChannel::<NoopRawMutex, u32, 2, 4, 4>::new();
+        channel.set_lag_hook(|amount| {
+            LAGGED.fetch_add(amount, Ordering::Relaxed);
+        });
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut sub1 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        for i in 0..5 {
+            pub0.publish_immediate(i);
+        }
+        assert_eq!(sub0.next_message().await, WaitResult::Lagged(3));
+        assert_eq!(sub0.next_message().await, WaitResult::Message(3));
+        assert_eq!(sub1.next_message().await, WaitResult::Lagged(3));
+        pub0.publish_immediate(5);
+        pub0.publish_immediate(6);
+        assert_eq!(sub0.next_message().await, WaitResult::Lagged(1));
+
+        assert_eq!(sub0.lagged(), 4);
+        assert_eq!(sub1.lagged(), 3);
+        assert_eq!(
+            channel.stats(),
+            Stats {
+                high_watermark: 2,
+                lagged: 7
+            }
+        );
+        assert_eq!(LAGGED.load(Ordering::Relaxed), 7);
+    }
+
+    #[futures_test::test]
+    async fn lag_statistics_count_every_lag() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        static CHANNEL: PubSubChannel<CriticalSectionRawMutex, u32, 2, 4, 4> = PubSubChannel::new();
+        static LAGGED: AtomicU64 = AtomicU64::new(0);
+
+        // The hook is called with the channel unlocked, so it can use it
+        CHANNEL.set_lag_hook(|_| LAGGED.store(CHANNEL.stats().lagged, Ordering::Relaxed));
+
+        let mut conflated = CHANNEL.subscriber_conflated().unwrap();
+        let mut worker0 = CHANNEL.subscriber().unwrap();
+        let mut worker1 = worker0.new_group_member().unwrap();
+        worker0.set_max_backlog(1);
+        let pub0 = CHANNEL.publisher().unwrap();
+
+        for i in 0..4 {
+            pub0.try_publish(i).unwrap();
+        }
+
+        // Conflated subscribers don't receive their lag, but it is counted
+        assert_eq!(conflated.try_next_message(), Some(WaitResult::Message(3)));
+        assert_eq!(CHANNEL.stats().lagged, 3);
+        assert_eq!(LAGGED.load(Ordering::Relaxed), 3);
+
+        // The lag of a group is received by the member that reads next
+        assert_eq!(worker1.try_next_message(), Some(WaitResult::Lagged(2)));
+        assert_eq!(worker0.try_next_message(), Some(WaitResult::Message(2)));
+        assert_eq!(worker1.try_next_message(), Some(WaitResult::Message(3)));
+        assert_eq!(CHANNEL.stats().lagged, 5);
+
+        // Skipping counts the messages that were already missed, but not the skipped ones
+        let mut skipping = CHANNEL.subscriber().unwrap();
+        for i in 4..8 {
+            pub0.publish_immediate(i);
+        }
+        assert_eq!(skipping.skip_to_latest(), 4);
+        assert_eq!(CHANNEL.stats().lagged, 7);
+        assert_eq!(LAGGED.load(Ordering::Relaxed), 7);
+    }
+
+    #[futures_test::test]
+    async fn limits() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::with_limits(1, 2);
+
+        let sub0 = channel.subscriber().unwrap();
+        assert_eq!(
+            channel.subscriber_conflated().err(),
+            Some(Error::MaximumSubscribersReached)
+        );
+        let _pub0 = channel.publisher().unwrap();
+        let _pub1 = channel.dyn_publisher().unwrap();
+        assert_eq!(channel.publisher().err(), Some(Error::MaximumPublishersReached));
+
+        drop(sub0);
+        let _sub1 = channel.dyn_subscriber().unwrap();
+
+        // The limits can't exceed the capacity
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 1, 1>::with_limits(2, 2);
+        let _sub0 = channel.subscriber().unwrap();
+        assert_eq!(channel.subscriber().err(), Some(Error::MaximumSubscribersReached));
+    }
+
+    #[futures_test::test]
+    async fn strict_receive() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 2, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        pub0.publish_immediate(0);
+        assert_eq!(sub0.next_message_strict().await, Ok(0));
+
+        for i in 1..5 {
+            pub0.publish_immediate(i);
+        }
+        assert_eq!(sub0.next_message_strict().await, Err(LagError(2)));
+        assert_eq!(sub0.next_message_strict().await, Ok(3));
+        assert_eq!(WaitResult::Message(4).into_result(), Ok(4));
+    }
+
+    #[futures_test::test]
+    async fn message_ids() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+        pub0.try_publish(10).unwrap();
+        let mut sub1 = channel.subscriber().unwrap();
+        assert_eq!(sub0.next_id(), 0);
+        assert_eq!(sub1.next_id(), 1);
+
+        pub0.try_publish(11).unwrap();
+        assert_eq!(sub0.next_message_with_id().await, WaitResult::Message((0, 10)));
+        assert_eq!(sub0.try_next_message_with_id(), Some(WaitResult::Message((1, 11))));
+        assert_eq!(sub1.try_next_message_with_id(), Some(WaitResult::Message((1, 11))));
+        assert_eq!(sub0.try_next_message_with_id(), None);
+        assert_eq!(sub0.next_id(), 2);
+    }
+
+    #[futures_test::test]
+    async fn subscriber_group() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut worker0 = channel.subscriber().unwrap();
+        let mut worker1 = worker0.new_group_member().unwrap();
+        let pub0 = channel.publisher().unwrap();
+        assert_eq!(channel.subscriber_count(), 3);
+
+        for i in 0..4 {
+            pub0.try_publish(i).unwrap();
+        }
+
+        // Every message goes to one worker, but all of them to the normal subscriber
+        assert_eq!(worker0.try_next_message(), Some(WaitResult::Message(0)));
+        assert_eq!(worker1.try_next_message(), Some(WaitResult::Message(1)));
+        assert_eq!(worker0.try_next_message(), Some(WaitResult::Message(2)));
+        assert_eq!(worker0.try_next_message(), Some(WaitResult::Message(3)));
+        assert_eq!(worker1.try_next_message(), None);
+        for i in 0..4 {
+            assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(i)));
+        }
+        assert!(channel.is_empty());
+
+        // The group keeps its messages until the last member is dropped
+        pub0.try_publish(4).unwrap();
+        drop(worker0);
+        assert_eq!(channel.len(), 1);
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(4)));
+        assert_eq!(channel.len(), 1);
+        drop(worker1);
+        assert!(channel.is_empty());
+        assert_eq!(channel.subscriber_count(), 1);
+    }
+
+    #[futures_test::test]
+    async fn conflated_subscriber() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 2, 4, 4>::new();
+
+        let mut latest = channel.subscriber_conflated().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        // The conflated subscriber never holds back the publisher
+        for i in 0..5 {
+            pub0.try_publish(i).unwrap();
+        }
+        assert_eq!(pub0.space(), 1);
+        assert_eq!(latest.available(), 1);
+        assert_eq!(latest.try_next_message(), Some(WaitResult::Message(4)));
+        assert_eq!(latest.try_next_message(), None);
+
+        // Dropped messages aren't reported as lag either
+        let _sub1 = channel.subscriber().unwrap();
+        pub0.publish_immediate(5);
+        pub0.publish_immediate(6);
+        pub0.publish_immediate(7);
+        assert_eq!(latest.try_next_message(), Some(WaitResult::Message(7)));
+    }
+
+    #[test]
+    fn waiting_publishers_are_fifo() {
+        use futures_util::FutureExt;
+
+        let channel = PubSubChannel::<NoopRawMutex, u32, 1, 4, 4>::new();
+        let (waker0, count0) = futures_test::task::new_count_waker();
+        let (waker1, count1) = futures_test::task::new_count_waker();
+        let mut cx0 = Context::from_waker(&waker0);
+        let mut cx1 = Context::from_waker(&waker1);
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+        let pub1 = channel.publisher().unwrap();
+
+        pub0.try_publish(0).unwrap();
+        let mut first = pub0.publish(1);
+        let mut second = pub1.publish(2);
+        assert!(first.poll_unpin(&mut cx0).is_pending());
+        assert!(second.poll_unpin(&mut cx1).is_pending());
+
+        // Only the publisher that has been waiting for the longest is woken for the free space
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(0)));
+        assert_eq!((count0.get(), count1.get()), (1, 0));
+
+        // The free space is kept for it, so publishing without waiting can't take it
+        assert_eq!(pub1.space(), 0);
+        assert_eq!(pub1.try_publish(3), Err(3));
+        assert!(second.poll_unpin(&mut cx1).is_pending());
+        assert_eq!(first.poll_unpin(&mut cx0), Poll::Ready(Ok(())));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(1)));
+        assert_eq!((count0.get(), count1.get()), (1, 1));
+
+        // A woken publisher that stops waiting passes the free space on
+        let mut third = pub0.publish(4);
+        assert!(third.poll_unpin(&mut cx0).is_pending());
+        drop(second);
+        assert_eq!(count0.get(), 2);
+        assert_eq!(pub1.try_publish(5), Err(5));
+        assert_eq!(third.poll_unpin(&mut cx0), Poll::Ready(Ok(())));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(4)));
+    }
+
+    #[test]
+    fn more_waiting_futures_than_publishers() {
+        use futures_util::FutureExt;
+
+        let channel = PubSubChannel::<NoopRawMutex, u32, 1, 1, 1>::new();
+        let (waker, count) = futures_test::task::new_count_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        pub0.try_publish(0).unwrap();
+        let mut first = pub0.publish(1);
+        let mut second = pub0.publish(2);
+        assert!(first.poll_unpin(&mut cx).is_pending());
+        assert!(second.poll_unpin(&mut cx).is_pending());
+
+        // The future that didn't fit in line is only woken for space that isn't kept for the one in line
+        assert_eq!(count.get(), 0);
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(0)));
+        assert_eq!(count.get(), 1);
+        assert!(second.poll_unpin(&mut cx).is_pending());
+        assert_eq!(first.poll_unpin(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(1)));
+        assert_eq!(count.get(), 2);
+        assert_eq!(second.poll_unpin(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(2)));
+    }
+
+    #[futures_test::test]
+    async fn lease_non_clone_messages() {
+        #[derive(Debug, PartialEq)]
+        struct NotClone(u32);
+
+        let channel = PubSubChannel::<NoopRawMutex, NotClone, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut sub1 = channel.dyn_subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        pub0.publish(NotClone(42)).await.unwrap();
+
+        // The first reader can only borrow the message, the last one takes it
+        let result = sub0.next_message_with(|lease| {
+            assert_eq!(lease.0, 42);
+            lease.into_inner()
+        });
+        assert_eq!(result.await, WaitResult::Message(None));
+        assert_eq!(
+            sub1.try_next_message_with(|lease| lease.into_inner()),
+            Some(WaitResult::Message(Some(NotClone(42))))
+        );
+        assert_eq!(sub1.try_next_message_with(|lease| lease.into_inner()), None);
+        assert_eq!(pub0.space(), 4);
+    }
+
+    #[futures_test::test]
+    async fn urgent_messages_jump_the_queue() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut sub1 = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        for i in 0..3 {
+            pub0.try_publish(i).unwrap();
+        }
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(0)));
+
+        // The urgent message is the next one for both subscribers
+        pub0.publish_urgent(100);
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(100)));
+        assert_eq!(sub1.try_next_message(), Some(WaitResult::Message(100)));
+        assert_eq!(sub1.try_next_message(), Some(WaitResult::Message(0)));
+        for sub in [&mut sub0, &mut sub1] {
+            assert_eq!(sub.try_next_message(), Some(WaitResult::Message(1)));
+            assert_eq!(sub.try_next_message(), Some(WaitResult::Message(2)));
+            assert_eq!(sub.try_next_message(), None);
+        }
+        assert_eq!(pub0.space(), 4);
+
+        // When the queue is full, the oldest message is dropped
+        for i in 0..4 {
+            pub0.try_publish(i).unwrap();
+        }
+        channel.immediate_publisher().publish_urgent(100);
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Lagged(1)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(100)));
+        assert_eq!(sub0.try_next_message(), Some(WaitResult::Message(1)));
+    }
+
+    #[test]
+    fn manual_polling() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 1, 4, 4>::new();
+        let mut cx = futures_test::task::noop_context();
+
+        let mut sub0 = channel.subscriber().unwrap();
+        let mut pub0 = channel.publisher().unwrap();
+
+        assert_eq!(sub0.poll_next_message(&mut cx), Poll::Pending);
+
+        let mut message = Some(42);
+        assert_eq!(pub0.poll_publish(&mut cx, &mut message), Poll::Ready(Ok(())));
+        assert_eq!(message, None);
+
+        let mut message = Some(43);
+        assert_eq!(pub0.poll_publish(&mut cx, &mut message), Poll::Pending);
+        assert_eq!(message, Some(43));
+
+        assert_eq!(sub0.poll_next_message(&mut cx), Poll::Ready(WaitResult::Message(42)));
+        assert_eq!(pub0.poll_publish(&mut cx, &mut message), Poll::Ready(Ok(())));
+        assert_eq!(sub0.poll_next_message(&mut cx), Poll::Ready(WaitResult::Message(43)));
+    }
+
+    #[futures_test::test]
+    async fn urgent_messages_reach_subscribers_mid_queue() {
+        let channel = PubSubChannel::<NoopRawMutex, u32, 8, 4, 4>::new();
+
+        let mut done = channel.subscriber().unwrap();
+        let mut mid = channel.subscriber().unwrap();
+        let mut start = channel.subscriber().unwrap();
+        let pub0 = channel.publisher().unwrap();
+
+        for i in 0..3 {
+            pub0.try_publish(i).unwrap();
+        }
+        for i in 0..3 {
+            assert_eq!(done.try_next_message(), Some(WaitResult::Message(i)));
+        }
+        assert_eq!(mid.try_next_message(), Some(WaitResult::Message(0)));
+
+        // The subscribers past the slowest one read the urgent message out of order
+        pub0.publish_urgent(100);
+        assert_eq!(done.available(), 1);
+        assert_eq!(mid.available(), 3);
+        assert_eq!(start.available(), 4);
+        assert_eq!(done.try_next_message(), Some(WaitResult::Message(100)));
+        assert_eq!(done.try_next_message(), None);
+
+        // Urgent messages don't go before a subscriber that hasn't read its previous one yet
+        pub0.publish_urgent(101);
+        assert_eq!(done.try_next_message(), Some(WaitResult::Message(101)));
+        for message in [100, 101, 1, 2] {
+            assert_eq!(mid.try_next_message(), Some(WaitResult::Message(message)));
+        }
+        for message in [100, 0, 101, 1, 2] {
+            assert_eq!(start.try_next_message(), Some(WaitResult::Message(message)));
+        }
+        assert_eq!(pub0.space(), 8);
+
+        // Dropping a subscriber releases its urgent message
+        pub0.try_publish(3).unwrap();
+        assert_eq!(start.try_next_message(), Some(WaitResult::Message(3)));
+        pub0.publish_urgent(102);
+        drop(start);
+        assert_eq!(mid.skip(2), 2);
+        assert_eq!(done.try_next_message(), Some(WaitResult::Message(102)));
+        assert_eq!(done.try_next_message(), Some(WaitResult::Message(3)));
+        assert_eq!(pub0.space(), 8);
+    }
+
     #[futures_test::test]
     async fn all_subscribers_receive() {
         let channel = PubSubChannel::<NoopRawMutex, u32, 4, 4, 4>::new();
@@ -451,7 +2247,7 @@ mod tests {
         let mut sub1 = channel.subscriber().unwrap();
         let pub0 = channel.publisher().unwrap();
 
-        pub0.publish(42).await;
+        pub0.publish(42).await.unwrap();
 
         assert_eq!(sub0.next_message().await, WaitResult::Message(42));
         assert_eq!(sub1.next_message().await, WaitResult::Message(42));
@@ -558,7 +2354,7 @@ mod tests {
         assert_eq!(sub0.available(), 0);
         assert_eq!(sub1.available(), 0);
 
-        pub0.publish(42).await;
+        pub0.publish(42).await.unwrap();
 
         assert_eq!(sub0.available(), 1);
         assert_eq!(sub1.available(), 1);
@@ -567,7 +2363,7 @@ mod tests {
 
         assert_eq!(sub1.available(), 0);
 
-        pub0.publish(42).await;
+        pub0.publish(42).await.unwrap();
 
         assert_eq!(sub0.available(), 2);
         assert_eq!(sub1.available(), 1);
@@ -583,11 +2379,11 @@ mod tests {
 
         assert_eq!(pub0.space(), 4);
 
-        pub0.publish(42).await;
+        pub0.publish(42).await.unwrap();
 
         assert_eq!(pub0.space(), 3);
 
-        pub0.publish(42).await;
+        pub0.publish(42).await.unwrap();
 
         assert_eq!(pub0.space(), 2);
 
@@ -612,8 +2408,8 @@ mod tests {
 
         assert_eq!(4, pub0.space());
 
-        pub0.publish(1).await;
-        pub0.publish(2).await;
+        pub0.publish(1).await.unwrap();
+        pub0.publish(2).await.unwrap();
 
         assert_eq!(2, channel.space());
 
@@ -635,6 +2431,7 @@ mod tests {
         assert_eq!(4, channel.space());
     }
 
+    #[derive(Debug)]
     struct CloneCallCounter(usize);
 
     impl Clone for CloneCallCounter {
@@ -650,7 +2447,7 @@ mod tests {
         let mut sub0 = channel.subscriber().unwrap();
         let mut sub1 = channel.subscriber().unwrap();
 
-        pub0.publish(CloneCallCounter(0)).await;
+        pub0.publish(CloneCallCounter(0)).await.unwrap();
 
         assert_eq!(1, sub0.try_next_message_pure().unwrap().0);
         assert_eq!(0, sub1.try_next_message_pure().unwrap().0);
@@ -1,25 +1,35 @@
 //! Implementation of anything directly publisher related
 
-use core::future::Future;
+use core::future::{poll_fn, Future};
 use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 use core::ops::{Deref, DerefMut};
 use core::pin::Pin;
 use core::task::{Context, Poll};
 
-use super::{PubSubBehavior, PubSubChannel};
-use crate::blocking_mutex::raw::RawMutex;
+use super::{LagPolicy, PubSubBehavior, PubSubChannel};
+use crate::blocking_mutex::raw::{CriticalSectionRawMutex, RawMutex};
 
 /// A publisher to a channel
-pub struct Pub<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> {
+pub struct Pub<'a, PSB: PubSubBehavior<T> + ?Sized, T> {
     /// The channel we are a publisher for
     channel: &'a PSB,
+    /// What `publish` does when the queue is full
+    policy: LagPolicy,
+    /// The message given to the `Sink` implementation, waiting for space in the queue
+    sink_message: Option<T>,
+    /// The place in line of the `Sink` implementation and `poll_publish`, while they wait for space in the queue
+    poll_ticket: Option<u64>,
     _phantom: PhantomData<T>,
 }
 
-impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Pub<'a, PSB, T> {
-    pub(super) fn new(channel: &'a PSB) -> Self {
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Pub<'a, PSB, T> {
+    pub(super) fn new(channel: &'a PSB, policy: LagPolicy) -> Self {
         Self {
             channel,
+            policy,
+            sink_message: None,
+            poll_ticket: None,
             _phantom: Default::default(),
         }
     }
@@ -30,17 +40,110 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Pub<'a, PSB, T> {
         self.channel.publish_immediate(message)
     }
 
-    /// Publish a message. But if the message queue is full, wait for all subscribers to have read the last message
+    /// Publish a message right now, ahead of the messages that the subscribers haven't read yet.
+    /// Use this for messages that must not wait behind a backlog, like alarms.
+    ///
+    /// Subscribers receive it next, unless a subscriber hasn't read the previous urgent message yet:
+    /// then the slower subscribers receive it once they caught up with that one.
+    ///
+    /// Like [Self::publish_immediate()], this may cause a subscriber to miss an older message if the queue is full.
+    pub fn publish_urgent(&self, message: T) {
+        self.channel.publish_urgent(message)
+    }
+
+    /// Publish a message. What happens if the message queue is full depends on the [LagPolicy] of this publisher:
+    ///
+    /// - [LagPolicy::Backpressure]: wait for all subscribers to have read the last message.
+    ///   When space frees up, it is kept for the publishers that have been waiting for the longest.
+    /// - [LagPolicy::DropOldest]: drop the oldest message, like [Self::publish_immediate()].
+    /// - [LagPolicy::RejectNewest]: return the message in `Err` right away.
     pub fn publish<'s>(&'s self, message: T) -> PublisherWaitFuture<'s, 'a, PSB, T> {
         PublisherWaitFuture {
             message: Some(message),
+            ticket: None,
             publisher: self,
         }
     }
 
-    /// Publish a message if there is space in the message queue
+    /// Publish a message if there is space in the message queue that isn't kept for a waiting publisher
     pub fn try_publish(&self, message: T) -> Result<(), T> {
-        self.channel.publish_with_context(message, None)
+        self.channel.publish_with_context(message, &mut None, None)
+    }
+
+    /// Poll to publish the message in `message`, for hand-written futures.
+    ///
+    /// This works like [Self::publish()]: the message is taken out of `message` once it is published or rejected
+    /// and `Poll::Ready` is returned. While waiting for space, `Poll::Pending` is returned and the message stays
+    /// in `message`, so the same one is passed to the next poll.
+    /// If `message` is `None`, there is nothing to publish and `Poll::Ready(Ok(()))` is returned.
+    pub fn poll_publish(&mut self, cx: &mut Context<'_>, message: &mut Option<T>) -> Poll<Result<(), T>> {
+        let mut ticket = self.poll_ticket.take();
+        let result = self.poll_publish_with_ticket(cx, message, &mut ticket);
+        self.poll_ticket = ticket;
+        result
+    }
+
+    fn poll_publish_with_ticket(
+        &self,
+        cx: &mut Context<'_>,
+        message: &mut Option<T>,
+        ticket: &mut Option<u64>,
+    ) -> Poll<Result<(), T>> {
+        let Some(to_publish) = message.take() else {
+            return Poll::Ready(Ok(()));
+        };
+        match self.policy {
+            LagPolicy::Backpressure => match self.channel.publish_with_context(to_publish, ticket, Some(cx)) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(rejected) => {
+                    *message = Some(rejected);
+                    Poll::Pending
+                }
+            },
+            LagPolicy::DropOldest => {
+                self.channel.publish_immediate(to_publish);
+                Poll::Ready(Ok(()))
+            }
+            LagPolicy::RejectNewest => Poll::Ready(self.channel.publish_with_context(to_publish, &mut None, None)),
+        }
+    }
+
+    /// Publish all messages. If the message queue gets full, wait for all subscribers to have read the oldest message.
+    ///
+    /// As many messages as fit are published at once, under a single lock, and subscribers are woken once per batch.
+    /// The iterator is advanced with the channel locked, so it should be cheap.
+    pub async fn publish_all(&self, messages: impl IntoIterator<Item = T>) {
+        let mut messages = messages.into_iter();
+        let mut waiting = WaitingPublisher {
+            channel: self.channel,
+            ticket: None,
+            _phantom: PhantomData,
+        };
+        poll_fn(|cx| {
+            self.channel
+                .publish_all_with_context(&mut || messages.next(), &mut waiting.ticket, Some(cx))
+        })
+        .await
+    }
+
+    /// Publish as many messages as there is space for in the message queue, under a single lock.
+    ///
+    /// Returns the amount of messages that were published, which are the first ones of `messages`.
+    pub fn try_publish_all(&self, messages: &[T]) -> usize
+    where
+        T: Clone,
+    {
+        let mut published = 0;
+        let _ = self.channel.publish_all_with_context(
+            &mut || {
+                let message = messages.get(published).cloned();
+                published += message.is_some() as usize;
+                message
+            },
+            &mut None,
+            None,
+        );
+        published
     }
 
     /// The amount of messages that can still be published without having to wait or without having to lag the subscribers
@@ -50,18 +153,95 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Pub<'a, PSB, T> {
     pub fn space(&self) -> usize {
         self.channel.space()
     }
+
+    /// The lag policy of this publisher, used by [Self::publish()]
+    pub fn lag_policy(&self) -> LagPolicy {
+        self.policy
+    }
+
+    /// The maximum amount of messages in the queue
+    pub fn capacity(&self) -> usize {
+        self.channel.capacity()
+    }
+
+    /// The amount of messages in the queue, which haven't been read by all subscribers yet
+    pub fn len(&self) -> usize {
+        self.channel.len()
+    }
+
+    /// Return whether the message queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_empty()
+    }
+
+    /// Return whether the message queue is full
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// The amount of subscribers that are active
+    pub fn subscriber_count(&self) -> usize {
+        self.channel.subscriber_count()
+    }
+
+    /// The amount of publishers that are active, not counting immediate publishers
+    pub fn publisher_count(&self) -> usize {
+        self.channel.publisher_count()
+    }
 }
 
-impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Drop for Pub<'a, PSB, T> {
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Drop for Pub<'a, PSB, T> {
     fn drop(&mut self) {
+        if self.poll_ticket.is_some() {
+            self.channel.unregister_waiting_publisher(&mut self.poll_ticket);
+        }
         self.channel.unregister_publisher()
     }
 }
 
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Unpin for Pub<'a, PSB, T> {}
+
+/// The sink implementation always applies backpressure, regardless of the [LagPolicy]: a message is only
+/// accepted once the previous one has been published, which waits until there is space in the queue.
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> futures_util::Sink<T> for Pub<'a, PSB, T> {
+    type Error = core::convert::Infallible;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.sink_message = Some(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let message = match this.sink_message.take() {
+            Some(message) => message,
+            None => return Poll::Ready(Ok(())),
+        };
+        match this
+            .channel
+            .publish_with_context(message, &mut this.poll_ticket, Some(cx))
+        {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(message) => {
+                this.sink_message = Some(message);
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
 /// A publisher that holds a dynamic reference to the channel
-pub struct DynPublisher<'a, T: Clone>(pub(super) Pub<'a, dyn PubSubBehavior<T> + 'a, T>);
+pub struct DynPublisher<'a, T>(pub(super) Pub<'a, dyn PubSubBehavior<T> + 'a, T>);
 
-impl<'a, T: Clone> Deref for DynPublisher<'a, T> {
+impl<'a, T> Deref for DynPublisher<'a, T> {
     type Target = Pub<'a, dyn PubSubBehavior<T> + 'a, T>;
 
     fn deref(&self) -> &Self::Target {
@@ -69,18 +249,35 @@ impl<'a, T: Clone> Deref for DynPublisher<'a, T> {
     }
 }
 
-impl<'a, T: Clone> DerefMut for DynPublisher<'a, T> {
+impl<'a, T> DerefMut for DynPublisher<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize>
+    From<Publisher<'a, M, T, CAP, SUBS, PUBS>> for DynPublisher<'a, T>
+{
+    /// Erase the channel type of a publisher.
+    fn from(value: Publisher<'a, M, T, CAP, SUBS, PUBS>) -> Self {
+        // The publisher stays registered with the channel, so it must not be dropped.
+        let mut publisher = ManuallyDrop::new(value.0);
+        DynPublisher(Pub {
+            channel: publisher.channel,
+            policy: publisher.policy,
+            sink_message: publisher.sink_message.take(),
+            poll_ticket: publisher.poll_ticket.take(),
+            _phantom: Default::default(),
+        })
+    }
+}
+
 /// A publisher that holds a generic reference to the channel
-pub struct Publisher<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize>(
+pub struct Publisher<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize>(
     pub(super) Pub<'a, PubSubChannel<M, T, CAP, SUBS, PUBS>, T>,
 );
 
-impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> Deref
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Deref
     for Publisher<'a, M, T, CAP, SUBS, PUBS>
 {
     type Target = Pub<'a, PubSubChannel<M, T, CAP, SUBS, PUBS>, T>;
@@ -90,7 +287,7 @@ impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS:
     }
 }
 
-impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> DerefMut
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> DerefMut
     for Publisher<'a, M, T, CAP, SUBS, PUBS>
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
@@ -100,13 +297,15 @@ impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS:
 
 /// A publisher that can only use the `publish_immediate` function, but it doesn't have to be registered with the channel.
 /// (So an infinite amount is possible)
-pub struct ImmediatePub<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> {
+///
+/// It is `Copy` and needs no cleanup, so it can be freely passed around, for example to interrupt handlers.
+pub struct ImmediatePub<'a, PSB: PubSubBehavior<T> + ?Sized, T> {
     /// The channel we are a publisher for
     channel: &'a PSB,
     _phantom: PhantomData<T>,
 }
 
-impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> ImmediatePub<'a, PSB, T> {
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> ImmediatePub<'a, PSB, T> {
     pub(super) fn new(channel: &'a PSB) -> Self {
         Self {
             channel,
@@ -119,9 +318,20 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> ImmediatePub<'a, PSB, T> {
         self.channel.publish_immediate(message)
     }
 
-    /// Publish a message if there is space in the message queue
+    /// Publish a message right now, ahead of the messages that the subscribers haven't read yet.
+    /// Use this for messages that must not wait behind a backlog, like alarms.
+    ///
+    /// Subscribers receive it next, unless a subscriber hasn't read the previous urgent message yet:
+    /// then the slower subscribers receive it once they caught up with that one.
+    ///
+    /// Like [Self::publish_immediate()], this may cause a subscriber to miss an older message if the queue is full.
+    pub fn publish_urgent(&self, message: T) {
+        self.channel.publish_urgent(message)
+    }
+
+    /// Publish a message if there is space in the message queue that isn't kept for a waiting publisher
     pub fn try_publish(&self, message: T) -> Result<(), T> {
-        self.channel.publish_with_context(message, None)
+        self.channel.publish_with_context(message, &mut None, None)
     }
 
     /// The amount of messages that can still be published without having to wait or without having to lag the subscribers
@@ -133,10 +343,18 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> ImmediatePub<'a, PSB, T> {
     }
 }
 
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Clone for ImmediatePub<'a, PSB, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Copy for ImmediatePub<'a, PSB, T> {}
+
 /// An immediate publisher that holds a dynamic reference to the channel
-pub struct DynImmediatePublisher<'a, T: Clone>(pub(super) ImmediatePub<'a, dyn PubSubBehavior<T> + 'a, T>);
+pub struct DynImmediatePublisher<'a, T>(pub(super) ImmediatePub<'a, dyn PubSubBehavior<T> + 'a, T>);
 
-impl<'a, T: Clone> Deref for DynImmediatePublisher<'a, T> {
+impl<'a, T> Deref for DynImmediatePublisher<'a, T> {
     type Target = ImmediatePub<'a, dyn PubSubBehavior<T> + 'a, T>;
 
     fn deref(&self) -> &Self::Target {
@@ -144,18 +362,35 @@ impl<'a, T: Clone> Deref for DynImmediatePublisher<'a, T> {
     }
 }
 
-impl<'a, T: Clone> DerefMut for DynImmediatePublisher<'a, T> {
+impl<'a, T> DerefMut for DynImmediatePublisher<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
+impl<'a, T> Clone for DynImmediatePublisher<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for DynImmediatePublisher<'a, T> {}
+
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize>
+    From<ImmediatePublisher<'a, M, T, CAP, SUBS, PUBS>> for DynImmediatePublisher<'a, T>
+{
+    /// Erase the channel type of an immediate publisher.
+    fn from(value: ImmediatePublisher<'a, M, T, CAP, SUBS, PUBS>) -> Self {
+        DynImmediatePublisher(ImmediatePub::new(value.0.channel))
+    }
+}
+
 /// An immediate publisher that holds a generic reference to the channel
-pub struct ImmediatePublisher<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize>(
+pub struct ImmediatePublisher<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize>(
     pub(super) ImmediatePub<'a, PubSubChannel<M, T, CAP, SUBS, PUBS>, T>,
 );
 
-impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> Deref
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Deref
     for ImmediatePublisher<'a, M, T, CAP, SUBS, PUBS>
 {
     type Target = ImmediatePub<'a, PubSubChannel<M, T, CAP, SUBS, PUBS>, T>;
@@ -165,7 +400,7 @@ impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS:
     }
 }
 
-impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> DerefMut
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> DerefMut
     for ImmediatePublisher<'a, M, T, CAP, SUBS, PUBS>
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
@@ -173,27 +408,99 @@ impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS:
     }
 }
 
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Clone
+    for ImmediatePublisher<'a, M, T, CAP, SUBS, PUBS>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Copy
+    for ImmediatePublisher<'a, M, T, CAP, SUBS, PUBS>
+{
+}
+
+/// An immediate publisher for a channel that uses a [CriticalSectionRawMutex], which can be called from interrupt handlers.
+///
+/// Publishing only takes a critical section and never waits or registers a waker, so it can be done from any
+/// interrupt handler, like one reacting to an ADC conversion, while tasks subscribe to the same channel.
+/// It is `Copy`, needs no cleanup and is `Send` when the messages are, so it can be kept in a `static`.
+pub struct RawImmediatePub<'a, T, const CAP: usize, const SUBS: usize, const PUBS: usize> {
+    /// The channel we are a publisher for
+    channel: &'a PubSubChannel<CriticalSectionRawMutex, T, CAP, SUBS, PUBS>,
+}
+
+impl<'a, T, const CAP: usize, const SUBS: usize, const PUBS: usize> RawImmediatePub<'a, T, CAP, SUBS, PUBS> {
+    pub(super) fn new(channel: &'a PubSubChannel<CriticalSectionRawMutex, T, CAP, SUBS, PUBS>) -> Self {
+        Self { channel }
+    }
+
+    /// Publish the message right now even when the queue is full.
+    /// This may cause a subscriber to miss an older message.
+    pub fn publish_immediate(&self, message: T) {
+        self.channel.publish_immediate(message)
+    }
+
+    /// Publish a message right now, ahead of the messages that the subscribers haven't read yet.
+    /// See [ImmediatePub::publish_urgent()].
+    ///
+    /// Like [Self::publish_immediate()], this may cause a subscriber to miss an older message if the queue is full.
+    pub fn publish_urgent(&self, message: T) {
+        self.channel.publish_urgent(message)
+    }
+}
+
+impl<'a, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Clone for RawImmediatePub<'a, T, CAP, SUBS, PUBS> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Copy for RawImmediatePub<'a, T, CAP, SUBS, PUBS> {}
+
 /// Future for the publisher wait action
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-pub struct PublisherWaitFuture<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> {
+pub struct PublisherWaitFuture<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> {
     /// The message we need to publish
     message: Option<T>,
+    /// Our place in line while we wait for space in the queue
+    ticket: Option<u64>,
     publisher: &'s Pub<'a, PSB, T>,
 }
 
-impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Future for PublisherWaitFuture<'s, 'a, PSB, T> {
-    type Output = ();
+impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> Future for PublisherWaitFuture<'s, 'a, PSB, T> {
+    type Output = Result<(), T>;
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let message = self.message.take().unwrap();
-        match self.publisher.channel.publish_with_context(message, Some(cx)) {
-            Ok(()) => Poll::Ready(()),
-            Err(message) => {
-                self.message = Some(message);
-                Poll::Pending
-            }
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.publisher
+            .poll_publish_with_ticket(cx, &mut this.message, &mut this.ticket)
+    }
+}
+
+impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> Drop for PublisherWaitFuture<'s, 'a, PSB, T> {
+    fn drop(&mut self) {
+        // Let the next publisher have our turn if we stop waiting
+        if self.ticket.is_some() {
+            self.publisher.channel.unregister_waiting_publisher(&mut self.ticket);
         }
     }
 }
 
-impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Unpin for PublisherWaitFuture<'s, 'a, PSB, T> {}
+impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> Unpin for PublisherWaitFuture<'s, 'a, PSB, T> {}
+
+/// Place in line of a publisher that waits for space in the queue, which it leaves when dropped
+struct WaitingPublisher<'a, PSB: PubSubBehavior<T> + ?Sized, T> {
+    channel: &'a PSB,
+    ticket: Option<u64>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Drop for WaitingPublisher<'a, PSB, T> {
+    fn drop(&mut self) {
+        if self.ticket.is_some() {
+            self.channel.unregister_waiting_publisher(&mut self.ticket);
+        }
+    }
+}
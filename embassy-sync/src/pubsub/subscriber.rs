@@ -1,38 +1,233 @@
 //! Implementation of anything directly subscriber related
 
-use core::future::Future;
+use core::future::{poll_fn, Future};
 use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 use core::ops::{Deref, DerefMut};
 use core::pin::Pin;
 use core::task::{Context, Poll};
 
-use super::{PubSubBehavior, PubSubChannel, WaitResult};
+#[cfg(feature = "time")]
+use embassy_time::{Duration, TimeoutError, Timer};
+
+use super::{Error, LagError, Lease, PubSubBehavior, PubSubChannel, WaitResult};
 use crate::blocking_mutex::raw::RawMutex;
 
 /// A subscriber to a channel
-pub struct Sub<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> {
+pub struct Sub<'a, PSB: PubSubBehavior<T> + ?Sized, T> {
     /// The message id of the next message we are yet to receive
     next_message_id: u64,
+    /// Our slot in the channel
+    slot: Option<usize>,
+    /// The total amount of messages we missed because we lagged
+    lagged: u64,
     /// The channel we are a subscriber to
     channel: &'a PSB,
     _phantom: PhantomData<T>,
 }
 
-impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Sub<'a, PSB, T> {
-    pub(super) fn new(next_message_id: u64, channel: &'a PSB) -> Self {
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Sub<'a, PSB, T> {
+    pub(super) fn new(next_message_id: u64, slot: Option<usize>, channel: &'a PSB) -> Self {
         Self {
             next_message_id,
+            slot,
+            lagged: 0,
             channel,
             _phantom: Default::default(),
         }
     }
 
+    /// Wait for a published message and read it with `read`, without cloning it.
+    ///
+    /// `read` gets a [Lease] of the message, which owns it if this subscriber was the last one to read it,
+    /// and borrows it otherwise. `read` is called with the channel locked, so it should be short.
+    pub async fn next_message_with<R>(&mut self, read: impl FnOnce(Lease<'_, T>) -> R) -> WaitResult<R> {
+        let mut read = Some(read);
+        poll_fn(|cx| self.poll_message_with(&mut read, Some(cx))).await
+    }
+
+    /// Try to see if there's a published message we haven't received yet and read it with `read`,
+    /// without cloning it. See [Self::next_message_with()].
+    pub fn try_next_message_with<R>(&mut self, read: impl FnOnce(Lease<'_, T>) -> R) -> Option<WaitResult<R>> {
+        match self.poll_message_with(&mut Some(read), None) {
+            Poll::Ready(result) => Some(result),
+            Poll::Pending => None,
+        }
+    }
+
+    fn poll_message_with<R>(
+        &mut self,
+        read: &mut Option<impl FnOnce(Lease<'_, T>) -> R>,
+        cx: Option<&mut Context<'_>>,
+    ) -> Poll<WaitResult<R>> {
+        let mut output = None;
+        let result = self
+            .channel
+            .lease_message_with_context(&mut self.next_message_id, self.slot, cx, &mut |lease| {
+                if let Some(read) = read.take() {
+                    output = Some(read(lease));
+                }
+            });
+        result.map(|result| match result {
+            WaitResult::Lagged(amount) => self.count_lag(WaitResult::Lagged(amount)),
+            WaitResult::Message(()) => WaitResult::Message(output.take().unwrap()),
+        })
+    }
+
+    /// Add the messages missed according to `result` to the lag of this subscriber
+    fn count_lag<R>(&mut self, result: WaitResult<R>) -> WaitResult<R> {
+        if let WaitResult::Lagged(amount) = result {
+            self.lagged += amount;
+        }
+        result
+    }
+
+    /// The total amount of messages this subscriber missed because it lagged behind
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    /// The id of the next message this subscriber is yet to receive.
+    ///
+    /// Every message published to the channel gets the next id, counting up from 0, so the ids can be used to
+    /// correlate the messages received by different subscribers. All messages before this id have been received,
+    /// skipped or lagged past. Urgent messages take the id of the position they are published at, moving the ids
+    /// of the messages after them up by one.
+    pub fn next_id(&self) -> u64 {
+        self.next_message_id
+    }
+
+    /// The amount of messages this subscriber hasn't received yet
+    pub fn available(&self) -> u64 {
+        self.channel.available(self.next_message_id, self.slot)
+    }
+
+    /// Skip up to `amount` unread messages, returning how many were skipped.
+    ///
+    /// The skipped messages are released as if they had been received, without cloning them.
+    pub fn skip(&mut self, amount: u64) -> u64 {
+        self.channel.skip(&mut self.next_message_id, self.slot, amount)
+    }
+
+    /// Skip all unread messages, returning how many were skipped.
+    ///
+    /// This is useful for subscribers that only care about the most recent messages, after not receiving
+    /// for a while. Skipped messages are not reported as lag, but the messages that were already missed count
+    /// towards the lag statistics of the channel.
+    pub fn skip_to_latest(&mut self) -> u64 {
+        self.skip(u64::MAX)
+    }
+
+    /// Set the maximum amount of unread messages this subscriber can lag behind by.
+    ///
+    /// By default, publishers using [Pub::publish()](super::publisher::Pub::publish) wait for all subscribers
+    /// to have read the oldest message when the queue is full, so a slow subscriber stalls them.
+    /// With a maximum backlog, when the queue is full, this subscriber is lagged to its `max_backlog` most recent
+    /// messages instead, releasing the older ones so publishers can make progress.
+    /// The next receive then returns [WaitResult::Lagged].
+    pub fn set_max_backlog(&mut self, max_backlog: usize) {
+        self.channel
+            .set_max_backlog(self.next_message_id, &mut self.slot, max_backlog)
+    }
+
+    /// Create a new subscriber in the group of this one, starting at the same position
+    fn new_group_member(&mut self) -> Result<Self, Error> {
+        self.channel
+            .register_group_member(self.next_message_id, &mut self.slot)?;
+        Ok(Sub::new(self.next_message_id, self.slot, self.channel))
+    }
+
+    /// The maximum amount of messages in the queue
+    pub fn capacity(&self) -> usize {
+        self.channel.capacity()
+    }
+
+    /// The amount of messages in the queue, which haven't been read by all subscribers yet
+    pub fn len(&self) -> usize {
+        self.channel.len()
+    }
+
+    /// Return whether the message queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_empty()
+    }
+
+    /// Return whether the message queue is full
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// The amount of subscribers that are active
+    pub fn subscriber_count(&self) -> usize {
+        self.channel.subscriber_count()
+    }
+
+    /// The amount of publishers that are active, not counting immediate publishers
+    pub fn publisher_count(&self) -> usize {
+        self.channel.publisher_count()
+    }
+}
+
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Sub<'a, PSB, T> {
+    /// Poll for a published message, for hand-written futures.
+    ///
+    /// This is what awaiting [Self::next_message()] does. If there is no message yet, the waker of `cx`
+    /// is registered to be woken when there is one.
+    pub fn poll_next_message(&mut self, cx: &mut Context<'_>) -> Poll<WaitResult<T>> {
+        self.channel
+            .get_message_with_context(&mut self.next_message_id, self.slot, Some(cx))
+            .map(|result| self.count_lag(result))
+    }
+
     /// Wait for a published message
     pub fn next_message<'s>(&'s mut self) -> SubscriberWaitFuture<'s, 'a, PSB, T> {
         SubscriberWaitFuture { subscriber: self }
     }
 
+    /// Wait for a published message, or for the timeout to expire.
+    ///
+    /// Returns `Err(TimeoutError)` if no message was published in time.
+    ///
+    /// Requires the `time` feature.
+    #[cfg(feature = "time")]
+    pub fn next_message_timeout<'s>(&'s mut self, timeout: Duration) -> SubscriberTimeoutFuture<'s, 'a, PSB, T> {
+        SubscriberTimeoutFuture {
+            subscriber: self,
+            timer: Timer::after(timeout),
+        }
+    }
+
+    /// Wait for a published message and return it together with its id. See [Self::next_id()].
+    pub async fn next_message_with_id(&mut self) -> WaitResult<(u64, T)> {
+        let result = self.next_message().await;
+        self.with_id(result)
+    }
+
+    /// Try to see if there's a published message we haven't received yet and return it together with its id.
+    /// See [Self::next_id()].
+    pub fn try_next_message_with_id(&mut self) -> Option<WaitResult<(u64, T)>> {
+        self.try_next_message().map(|result| self.with_id(result))
+    }
+
+    /// Pair a message that was just received with its id
+    fn with_id(&self, result: WaitResult<T>) -> WaitResult<(u64, T)> {
+        match result {
+            WaitResult::Lagged(amount) => WaitResult::Lagged(amount),
+            WaitResult::Message(message) => WaitResult::Message((self.next_message_id - 1, message)),
+        }
+    }
+
+    /// Wait for a published message, returning an error if this subscriber missed messages.
+    ///
+    /// After the error, the subscriber continues with the oldest message still in the queue.
+    pub async fn next_message_strict(&mut self) -> Result<T, LagError> {
+        self.next_message().await.into_result()
+    }
+
     /// Wait for a published message (ignoring lag results)
+    ///
+    /// Messages this subscriber missed because it lagged behind are silently lost.
+    /// Use [Self::next_message()] or [Self::next_message_strict()] to find out about them.
     pub async fn next_message_pure(&mut self) -> T {
         loop {
             match self.next_message().await {
@@ -44,10 +239,13 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Sub<'a, PSB, T> {
 
     /// Try to see if there's a published message we haven't received yet.
     ///
-    /// This function does not peek. The message is received if there is one.
+    /// This function does not peek, see [Self::try_peek()]. The message is received if there is one.
     pub fn try_next_message(&mut self) -> Option<WaitResult<T>> {
-        match self.channel.get_message_with_context(&mut self.next_message_id, None) {
-            Poll::Ready(result) => Some(result),
+        match self
+            .channel
+            .get_message_with_context(&mut self.next_message_id, self.slot, None)
+        {
+            Poll::Ready(result) => Some(self.count_lag(result)),
             Poll::Pending => None,
         }
     }
@@ -55,6 +253,7 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Sub<'a, PSB, T> {
     /// Try to see if there's a published message we haven't received yet (ignoring lag results).
     ///
     /// This function does not peek. The message is received if there is one.
+    /// Like with [Self::next_message_pure()], messages this subscriber missed are silently lost.
     pub fn try_next_message_pure(&mut self) -> Option<T> {
         loop {
             match self.try_next_message() {
@@ -65,19 +264,41 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Sub<'a, PSB, T> {
         }
     }
 
-    /// The amount of messages this subscriber hasn't received yet
-    pub fn available(&self) -> u64 {
-        self.channel.available(self.next_message_id)
+    /// Wait for a published message and return a clone of it, without receiving it.
+    ///
+    /// The next receive returns the same message. If the subscriber lagged, [WaitResult::Lagged] is returned
+    /// and the missed messages are skipped, just like when receiving.
+    pub async fn peek(&mut self) -> WaitResult<T> {
+        poll_fn(|cx| {
+            self.channel
+                .peek_message_with_context(&mut self.next_message_id, self.slot, Some(cx))
+                .map(|result| self.count_lag(result))
+        })
+        .await
+    }
+
+    /// Try to see if there's a published message we haven't received yet, without receiving it.
+    ///
+    /// The next receive returns the same message. If the subscriber lagged, [WaitResult::Lagged] is returned
+    /// and the missed messages are skipped, just like when receiving.
+    pub fn try_peek(&mut self) -> Option<WaitResult<T>> {
+        match self
+            .channel
+            .peek_message_with_context(&mut self.next_message_id, self.slot, None)
+        {
+            Poll::Ready(result) => Some(self.count_lag(result)),
+            Poll::Pending => None,
+        }
     }
 }
 
-impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Drop for Sub<'a, PSB, T> {
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Drop for Sub<'a, PSB, T> {
     fn drop(&mut self) {
-        self.channel.unregister_subscriber(self.next_message_id)
+        self.channel.unregister_subscriber(self.next_message_id, self.slot)
     }
 }
 
-impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Unpin for Sub<'a, PSB, T> {}
+impl<'a, PSB: PubSubBehavior<T> + ?Sized, T> Unpin for Sub<'a, PSB, T> {}
 
 /// Warning: The stream implementation ignores lag results and returns all messages.
 /// This might miss some messages without you knowing it.
@@ -85,10 +306,7 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> futures_util::Stream for Sub
     type Item = T;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match self
-            .channel
-            .get_message_with_context(&mut self.next_message_id, Some(cx))
-        {
+        match self.poll_next_message(cx) {
             Poll::Ready(WaitResult::Message(message)) => Poll::Ready(Some(message)),
             Poll::Ready(WaitResult::Lagged(_)) => {
                 cx.waker().wake_by_ref();
@@ -100,9 +318,9 @@ impl<'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> futures_util::Stream for Sub
 }
 
 /// A subscriber that holds a dynamic reference to the channel
-pub struct DynSubscriber<'a, T: Clone>(pub(super) Sub<'a, dyn PubSubBehavior<T> + 'a, T>);
+pub struct DynSubscriber<'a, T>(pub(super) Sub<'a, dyn PubSubBehavior<T> + 'a, T>);
 
-impl<'a, T: Clone> Deref for DynSubscriber<'a, T> {
+impl<'a, T> Deref for DynSubscriber<'a, T> {
     type Target = Sub<'a, dyn PubSubBehavior<T> + 'a, T>;
 
     fn deref(&self) -> &Self::Target {
@@ -110,18 +328,63 @@ impl<'a, T: Clone> Deref for DynSubscriber<'a, T> {
     }
 }
 
-impl<'a, T: Clone> DerefMut for DynSubscriber<'a, T> {
+impl<'a, T> DynSubscriber<'a, T> {
+    /// Create a new subscriber that shares the messages of this subscriber as a group.
+    ///
+    /// Every message this subscriber would receive is received by only one member of the group,
+    /// whichever asks for it first, while the other subscribers of the channel still receive every message.
+    /// The members share the filter, maximum backlog and conflation of the group, and messages read by
+    /// other members are not reported as lag.
+    ///
+    /// The new member counts towards the maximum amount of subscribers.
+    pub fn new_group_member(&mut self) -> Result<Self, Error> {
+        self.0.new_group_member().map(DynSubscriber)
+    }
+}
+
+impl<'a, T> DerefMut for DynSubscriber<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize>
+    From<Subscriber<'a, M, T, CAP, SUBS, PUBS>> for DynSubscriber<'a, T>
+{
+    /// Erase the channel type of a subscriber, keeping its position in the channel.
+    fn from(value: Subscriber<'a, M, T, CAP, SUBS, PUBS>) -> Self {
+        // The subscriber stays registered with the channel, so it must not be dropped.
+        let sub = ManuallyDrop::new(value.0);
+        DynSubscriber(Sub {
+            next_message_id: sub.next_message_id,
+            slot: sub.slot,
+            lagged: sub.lagged,
+            channel: sub.channel,
+            _phantom: Default::default(),
+        })
+    }
+}
+
 /// A subscriber that holds a generic reference to the channel
-pub struct Subscriber<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize>(
+pub struct Subscriber<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize>(
     pub(super) Sub<'a, PubSubChannel<M, T, CAP, SUBS, PUBS>, T>,
 );
 
-impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> Deref
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Subscriber<'a, M, T, CAP, SUBS, PUBS> {
+    /// Create a new subscriber that shares the messages of this subscriber as a group.
+    ///
+    /// Every message this subscriber would receive is received by only one member of the group,
+    /// whichever asks for it first, while the other subscribers of the channel still receive every message.
+    /// The members share the filter, maximum backlog and conflation of the group, and messages read by
+    /// other members are not reported as lag.
+    ///
+    /// The new member counts towards the maximum amount of subscribers.
+    pub fn new_group_member(&mut self) -> Result<Self, Error> {
+        self.0.new_group_member().map(Subscriber)
+    }
+}
+
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> Deref
     for Subscriber<'a, M, T, CAP, SUBS, PUBS>
 {
     type Target = Sub<'a, PubSubChannel<M, T, CAP, SUBS, PUBS>, T>;
@@ -131,7 +394,7 @@ impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS:
     }
 }
 
-impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS: usize> DerefMut
+impl<'a, M: RawMutex, T, const CAP: usize, const SUBS: usize, const PUBS: usize> DerefMut
     for Subscriber<'a, M, T, CAP, SUBS, PUBS>
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
@@ -141,7 +404,7 @@ impl<'a, M: RawMutex, T: Clone, const CAP: usize, const SUBS: usize, const PUBS:
 
 /// Future for the subscriber wait action
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-pub struct SubscriberWaitFuture<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> {
+pub struct SubscriberWaitFuture<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> {
     subscriber: &'s mut Sub<'a, PSB, T>,
 }
 
@@ -149,10 +412,36 @@ impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Future for SubscriberWai
     type Output = WaitResult<T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.subscriber
-            .channel
-            .get_message_with_context(&mut self.subscriber.next_message_id, Some(cx))
+        self.subscriber.poll_next_message(cx)
+    }
+}
+
+impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> Unpin for SubscriberWaitFuture<'s, 'a, PSB, T> {}
+
+/// Future for the subscriber wait action with a timeout
+#[cfg(feature = "time")]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SubscriberTimeoutFuture<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> {
+    subscriber: &'s mut Sub<'a, PSB, T>,
+    timer: Timer,
+}
+
+#[cfg(feature = "time")]
+impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Future for SubscriberTimeoutFuture<'s, 'a, PSB, T> {
+    type Output = Result<WaitResult<T>, TimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // A message that is already there wins over the timeout
+        if let Poll::Ready(result) = self.subscriber.poll_next_message(cx) {
+            return Poll::Ready(Ok(result));
+        }
+
+        match Pin::new(&mut self.timer).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(TimeoutError)),
+            Poll::Pending => Poll::Pending,
+        }
     }
 }
 
-impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T: Clone> Unpin for SubscriberWaitFuture<'s, 'a, PSB, T> {}
+#[cfg(feature = "time")]
+impl<'s, 'a, PSB: PubSubBehavior<T> + ?Sized, T> Unpin for SubscriberTimeoutFuture<'s, 'a, PSB, T> {}
@@ -27,6 +27,10 @@
 //!
 //! If there is none or multiple drivers in the crate tree, linking will fail.
 //!
+//! Drivers registered with [`time_driver_impl`](crate::time_driver_impl) get all the functions defined for them.
+//! Drivers that define the functions by hand must define every one of them, including
+//! `_embassy_time_hardware_tick_hz` and `_embassy_time_set_hardware_tick_hz`, added in 0.2.
+//!
 //! This method has a few key advantages for something as foundational as timekeeping:
 //!
 //! - The time driver is available everywhere easily, without having to thread the implementation
@@ -132,6 +136,30 @@ pub trait Driver: Send + Sync + 'static {
     ///
     /// Only one alarm can be active at a time for each AlarmHandle. This overwrites any previously-set alarm if any.
     fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool;
+
+    /// Return the frequency the driver's hardware counter currently runs at, in Hz.
+    ///
+    /// Timestamps are always in units of [`TICK_HZ`]. A driver whose counter runs slower than
+    /// that scales its counter value, so timestamps only advance in steps of
+    /// `TICK_HZ / hardware_tick_hz()` ticks.
+    ///
+    /// The default implementation returns [`TICK_HZ`].
+    fn hardware_tick_hz(&self) -> u64 {
+        TICK_HZ
+    }
+
+    /// Change the frequency of the driver's hardware counter, for instance to slow it down in
+    /// low-power modes. Returns `false` if the driver doesn't support the given frequency, in which
+    /// case nothing is changed.
+    ///
+    /// Implementations MUST ensure that `now()` stays monotonic across the change, and that alarms
+    /// set before it still fire no earlier than their timestamp.
+    ///
+    /// The default implementation doesn't support changing the frequency, and returns `false`.
+    fn set_hardware_tick_hz(&self, hz: u64) -> bool {
+        let _ = hz;
+        false
+    }
 }
 
 extern "Rust" {
@@ -139,6 +167,8 @@ extern "Rust" {
     fn _embassy_time_allocate_alarm() -> Option<AlarmHandle>;
     fn _embassy_time_set_alarm_callback(alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ());
     fn _embassy_time_set_alarm(alarm: AlarmHandle, timestamp: u64) -> bool;
+    fn _embassy_time_hardware_tick_hz() -> u64;
+    fn _embassy_time_set_hardware_tick_hz(hz: u64) -> bool;
 }
 
 /// See [`Driver::now`]
@@ -163,6 +193,16 @@ pub fn set_alarm(alarm: AlarmHandle, timestamp: u64) -> bool {
     unsafe { _embassy_time_set_alarm(alarm, timestamp) }
 }
 
+/// See [`Driver::hardware_tick_hz`]
+pub fn hardware_tick_hz() -> u64 {
+    unsafe { _embassy_time_hardware_tick_hz() }
+}
+
+/// See [`Driver::set_hardware_tick_hz`]
+pub fn set_hardware_tick_hz(hz: u64) -> bool {
+    unsafe { _embassy_time_set_hardware_tick_hz(hz) }
+}
+
 /// Set the time Driver implementation.
 ///
 /// See the module documentation for an example.
@@ -190,5 +230,15 @@ macro_rules! time_driver_impl {
         fn _embassy_time_set_alarm(alarm: $crate::AlarmHandle, timestamp: u64) -> bool {
             <$t as $crate::Driver>::set_alarm(&$name, alarm, timestamp)
         }
+
+        #[no_mangle]
+        fn _embassy_time_hardware_tick_hz() -> u64 {
+            <$t as $crate::Driver>::hardware_tick_hz(&$name)
+        }
+
+        #[no_mangle]
+        fn _embassy_time_set_hardware_tick_hz(hz: u64) -> bool {
+            <$t as $crate::Driver>::set_hardware_tick_hz(&$name, hz)
+        }
     };
 }
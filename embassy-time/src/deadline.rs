@@ -0,0 +1,98 @@
+use core::future::Future;
+
+use crate::{with_deadline, Duration, Instant, TimeoutError, Timer};
+
+/// A point in time by which an operation must be complete.
+///
+/// A `Deadline` is meant to be created once for a whole transaction, and passed down through the
+/// layers of driver calls it's made of, so that they all share one timeout budget instead of each
+/// applying its own timeout.
+///
+/// ``` no_run
+/// use embassy_time::{Deadline, Duration, TimeoutError};
+///
+/// async fn transaction(deadline: Deadline) -> Result<(), TimeoutError> {
+///     deadline.run(async { /* send command */ }).await?;
+///     deadline.checkpoint()?;
+///     deadline.run(async { /* wait for response */ }).await?;
+///     Ok(())
+/// }
+///
+/// # async fn example() {
+/// transaction(Deadline::after(Duration::from_millis(100))).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// A deadline that never expires.
+    pub const NEVER: Deadline = Deadline { at: Instant::MAX };
+
+    /// Create a deadline at the given instant.
+    pub const fn at(at: Instant) -> Self {
+        Self { at }
+    }
+
+    /// Create a deadline `timeout` from now.
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            at: Instant::now().saturating_add(timeout),
+        }
+    }
+
+    /// The instant at which the deadline expires.
+    pub const fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// Time left before the deadline expires, or zero if it already has.
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has expired.
+    pub fn expired(&self) -> bool {
+        self.at <= Instant::now()
+    }
+
+    /// Return an error if the deadline has expired.
+    ///
+    /// Meant to be used with `?` between the steps of a transaction.
+    pub fn checkpoint(&self) -> Result<(), TimeoutError> {
+        if self.expired() {
+            Err(TimeoutError)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The earlier of this deadline and `timeout` from now.
+    ///
+    /// Useful for a step that has a timeout of its own, but must still respect the overall
+    /// deadline.
+    pub fn min_after(&self, timeout: Duration) -> Self {
+        (*self).min(Self::after(timeout))
+    }
+
+    /// Run a future, failing with [`TimeoutError`] if it doesn't complete before the deadline.
+    ///
+    /// See [`with_deadline`].
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output, TimeoutError> {
+        with_deadline(self.at, fut).await
+    }
+
+    /// A timer that expires at the deadline.
+    pub fn timer(&self) -> Timer {
+        Timer::at(self.at)
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(at: Instant) -> Self {
+        Self::at(at)
+    }
+}
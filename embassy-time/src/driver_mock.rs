@@ -9,7 +9,13 @@ use crate::{Duration, Instant};
 /// This is useful for testing code that works with [`Instant`] and [`Duration`].
 ///
 /// This driver can also be used to test runtime functionality, such as
-/// timers, delays, etc.
+/// timers, delays, etc. Time only moves when [`advance`](MockDriver::advance) is called, which
+/// fires all alarms that became due in order, so timers complete deterministically.
+///
+/// Up to 4 alarms can be allocated, which is enough for the generic queue and a few executors.
+///
+/// The driver state is guarded by a critical section, so a `critical-section` implementation must be
+/// provided, for example by enabling its `std` feature in host tests.
 ///
 /// # Example
 ///
@@ -52,28 +58,52 @@ impl MockDriver {
     /// Advances the time by the specified [`Duration`].
     /// Calling any alarm callbacks that are due.
     pub fn advance(&self, duration: Duration) {
-        let notify = {
-            critical_section::with(|cs| {
-                let mut inner = self.0.borrow_ref_mut(cs);
+        let target = critical_section::with(|cs| self.0.borrow_ref(cs).now) + duration;
+        self.advance_to(target);
+    }
 
-                inner.now += duration;
+    /// Advances the time to the specified [`Instant`].
+    ///
+    /// Alarms that are due are fired in timestamp order, with the current time set to the alarm
+    /// timestamp while the callback runs. Alarms set by the callbacks are also fired if they are
+    /// due before `target`. Does nothing if `target` is in the past.
+    pub fn advance_to(&self, target: Instant) {
+        loop {
+            let notify = critical_section::with(|cs| {
+                let mut inner = self.0.borrow_ref_mut(cs);
+                let count = inner.alarm_count;
 
-                let now = inner.now.as_ticks();
+                let next = inner.alarms[..count]
+                    .iter_mut()
+                    .filter(|alarm| alarm.timestamp <= target.as_ticks())
+                    .min_by_key(|alarm| alarm.timestamp);
 
-                inner
-                    .alarm
-                    .as_mut()
-                    .filter(|alarm| alarm.timestamp <= now)
-                    .map(|alarm| {
+                match next {
+                    Some(alarm) => {
+                        let timestamp = alarm.timestamp;
                         alarm.timestamp = u64::MAX;
-
-                        (alarm.callback, alarm.ctx)
-                    })
-            })
-        };
-
-        if let Some((callback, ctx)) = notify {
-            (callback)(ctx);
+                        let notify = (alarm.callback, alarm.ctx);
+
+                        let at = Instant::from_ticks(timestamp);
+                        if at > inner.now {
+                            inner.now = at;
+                        }
+
+                        Some(notify)
+                    }
+                    None => {
+                        if target > inner.now {
+                            inner.now = target;
+                        }
+                        None
+                    }
+                }
+            });
+
+            match notify {
+                Some((callback, ctx)) => (callback)(ctx),
+                None => break,
+            }
         }
     }
 }
@@ -87,61 +117,71 @@ impl Driver for MockDriver {
         critical_section::with(|cs| {
             let mut inner = self.0.borrow_ref_mut(cs);
 
-            if inner.alarm.is_some() {
-                None
-            } else {
-                inner.alarm.replace(AlarmState::new());
+            let id = inner.alarm_count;
+            if id < ALARM_COUNT {
+                inner.alarm_count += 1;
+                inner.alarms[id] = AlarmState::new();
 
-                Some(AlarmHandle::new(0))
+                Some(AlarmHandle::new(id as u8))
+            } else {
+                None
             }
         })
     }
 
-    fn set_alarm_callback(&self, _alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
         critical_section::with(|cs| {
             let mut inner = self.0.borrow_ref_mut(cs);
 
-            let Some(alarm) = inner.alarm.as_mut() else {
-                panic!("Alarm not allocated");
-            };
+            let alarm = inner.alarm_mut(alarm);
 
             alarm.callback = callback;
             alarm.ctx = ctx;
         });
     }
 
-    fn set_alarm(&self, _alarm: AlarmHandle, timestamp: u64) -> bool {
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
         critical_section::with(|cs| {
             let mut inner = self.0.borrow_ref_mut(cs);
 
             if timestamp <= inner.now.as_ticks() {
+                inner.alarm_mut(alarm).timestamp = u64::MAX;
                 false
             } else {
-                let Some(alarm) = inner.alarm.as_mut() else {
-                    panic!("Alarm not allocated");
-                };
-
-                alarm.timestamp = timestamp;
+                inner.alarm_mut(alarm).timestamp = timestamp;
                 true
             }
         })
     }
 }
 
+const ALARM_COUNT: usize = 4;
+
 struct InnerMockDriver {
     now: Instant,
-    alarm: Option<AlarmState>,
+    alarm_count: usize,
+    alarms: [AlarmState; ALARM_COUNT],
 }
 
 impl InnerMockDriver {
     const fn new() -> Self {
         Self {
             now: Instant::from_ticks(0),
-            alarm: None,
+            alarm_count: 0,
+            alarms: [AlarmState::new(); ALARM_COUNT],
         }
     }
+
+    fn alarm_mut(&mut self, alarm: AlarmHandle) -> &mut AlarmState {
+        let id = alarm.id() as usize;
+        if id >= self.alarm_count {
+            panic!("Alarm not allocated");
+        }
+        &mut self.alarms[id]
+    }
 }
 
+#[derive(Clone, Copy)]
 struct AlarmState {
     timestamp: u64,
     callback: fn(*mut ()),
@@ -215,7 +255,54 @@ mod tests {
         setup();
 
         let driver = MockDriver::get();
-        assert!(unsafe { driver.allocate_alarm() }.is_some());
+        for _ in 0..ALARM_COUNT {
+            assert!(unsafe { driver.allocate_alarm() }.is_some());
+        }
         assert!(unsafe { driver.allocate_alarm() }.is_none());
     }
+
+    #[test]
+    #[serial]
+    fn test_alarms_fire_in_order() {
+        setup();
+
+        static mut FIRED: [u64; 2] = [0; 2];
+
+        let driver = MockDriver::get();
+        let alarm0 = unsafe { driver.allocate_alarm() }.unwrap();
+        let alarm1 = unsafe { driver.allocate_alarm() }.unwrap();
+        let ctx = core::ptr::null_mut();
+        driver.set_alarm_callback(alarm0, |_| unsafe { FIRED[0] = MockDriver::get().now() }, ctx);
+        driver.set_alarm_callback(alarm1, |_| unsafe { FIRED[1] = MockDriver::get().now() }, ctx);
+
+        let start = driver.now();
+        driver.set_alarm(alarm0, start + 300);
+        driver.set_alarm(alarm1, start + 100);
+
+        driver.advance(Duration::from_ticks(1000));
+        assert_eq!(unsafe { FIRED }, [start + 300, start + 100]);
+        assert_eq!(driver.now(), start + 1000);
+    }
+
+    #[test]
+    #[serial]
+    fn test_alarm_rearmed_by_callback() {
+        setup();
+
+        static mut COUNT: u32 = 0;
+
+        fn callback(_: *mut ()) {
+            unsafe { COUNT += 1 };
+            let driver = MockDriver::get();
+            driver.set_alarm(unsafe { AlarmHandle::new(0) }, driver.now() + 100);
+        }
+
+        let driver = MockDriver::get();
+        let alarm = unsafe { driver.allocate_alarm() }.unwrap();
+        driver.set_alarm_callback(alarm, callback, core::ptr::null_mut());
+        driver.set_alarm(alarm, driver.now() + 100);
+
+        driver.advance(Duration::from_ticks(350));
+        assert_eq!(unsafe { COUNT }, 3);
+    }
 }
@@ -123,6 +123,55 @@ impl Duration {
     pub fn checked_div(self, rhs: u32) -> Option<Duration> {
         self.ticks.checked_div(rhs as _).map(|ticks| Duration { ticks })
     }
+
+    /// Adds one Duration to another, saturating at [`Duration::MAX`].
+    pub const fn saturating_add(self, rhs: Duration) -> Duration {
+        Duration {
+            ticks: self.ticks.saturating_add(rhs.ticks),
+        }
+    }
+
+    /// Subtracts one Duration from another, saturating at [`Duration::MIN`].
+    pub const fn saturating_sub(self, rhs: Duration) -> Duration {
+        Duration {
+            ticks: self.ticks.saturating_sub(rhs.ticks),
+        }
+    }
+
+    /// Multiplies one Duration by a scalar u32, saturating at [`Duration::MAX`].
+    pub const fn saturating_mul(self, rhs: u32) -> Duration {
+        Duration {
+            ticks: self.ticks.saturating_mul(rhs as u64),
+        }
+    }
+
+    /// Convert the `Duration` to fractional seconds, as `f32`.
+    pub fn as_secs_f32(&self) -> f32 {
+        self.ticks as f32 / TICK_HZ as f32
+    }
+
+    /// Convert the `Duration` to fractional seconds, as `f64`.
+    pub fn as_secs_f64(&self) -> f64 {
+        self.ticks as f64 / TICK_HZ as f64
+    }
+
+    /// Creates a duration from the specified number of fractional seconds, rounding down.
+    ///
+    /// Negative and NaN values give [`Duration::MIN`], values too large give [`Duration::MAX`].
+    pub fn from_secs_f32(secs: f32) -> Duration {
+        Duration {
+            ticks: (secs * TICK_HZ as f32) as u64,
+        }
+    }
+
+    /// Creates a duration from the specified number of fractional seconds, rounding down.
+    ///
+    /// Negative and NaN values give [`Duration::MIN`], values too large give [`Duration::MAX`].
+    pub fn from_secs_f64(secs: f64) -> Duration {
+        Duration {
+            ticks: (secs * TICK_HZ as f64) as u64,
+        }
+    }
 }
 
 impl Add for Duration {
@@ -114,6 +114,20 @@ impl Instant {
     pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
         self.ticks.checked_sub(duration.ticks).map(|ticks| Instant { ticks })
     }
+
+    /// Adds one Duration to self, saturating at [`Instant::MAX`].
+    pub const fn saturating_add(&self, duration: Duration) -> Instant {
+        Instant {
+            ticks: self.ticks.saturating_add(duration.ticks),
+        }
+    }
+
+    /// Subtracts one Duration from self, saturating at [`Instant::MIN`].
+    pub const fn saturating_sub(&self, duration: Duration) -> Instant {
+        Instant {
+            ticks: self.ticks.saturating_sub(duration.ticks),
+        }
+    }
 }
 
 impl Add<Duration> for Instant {
@@ -10,10 +10,13 @@
 // This mod MUST go first, so that the others see its macros.
 pub(crate) mod fmt;
 
+mod deadline;
 mod delay;
 mod duration;
 mod instant;
+mod stopwatch;
 mod timer;
+pub mod wall_clock;
 
 #[cfg(feature = "mock-driver")]
 mod driver_mock;
@@ -28,11 +31,15 @@ mod driver_wasm;
 #[cfg(feature = "generic-queue")]
 mod queue_generic;
 
+pub use deadline::Deadline;
 pub use delay::{block_for, Delay};
 pub use duration::Duration;
 pub use embassy_time_driver::TICK_HZ;
 pub use instant::Instant;
-pub use timer::{with_deadline, with_timeout, Ticker, TimeoutError, Timer};
+#[cfg(feature = "generic-queue")]
+pub use queue_generic::set_coalescing_window;
+pub use stopwatch::{DurationStats, Stopwatch};
+pub use timer::{every, with_deadline, with_timeout, Ticker, TimeoutError, TimeoutExt, Timer};
 
 const fn gcd(a: u64, b: u64) -> u64 {
     if b == 0 {
@@ -42,6 +49,39 @@ const fn gcd(a: u64, b: u64) -> u64 {
     }
 }
 
+/// Ticks per second of the global timebase.
+///
+/// Same as [`TICK_HZ`], for code that wants to query it at runtime, for instance to report it.
+pub const fn tick_hz() -> u64 {
+    TICK_HZ
+}
+
+/// Frequency the time driver's hardware counter currently runs at, in Hz.
+///
+/// This is [`TICK_HZ`] unless the driver supports a slower counter and was switched to it with
+/// [`set_driver_tick_hz`], in which case [`Instant::now`] advances in steps of
+/// `TICK_HZ / driver_tick_hz()` ticks.
+pub fn driver_tick_hz() -> u64 {
+    embassy_time_driver::hardware_tick_hz()
+}
+
+/// Error returned by [`set_driver_tick_hz`] when the driver doesn't support the requested frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnsupportedTickRate;
+
+/// Change the frequency of the time driver's hardware counter, for instance to save power.
+///
+/// Timestamps keep their [`TICK_HZ`] units and [`Instant`]s stay monotonic across the change,
+/// only the resolution of the timebase changes.
+pub fn set_driver_tick_hz(hz: u64) -> Result<(), UnsupportedTickRate> {
+    if embassy_time_driver::set_hardware_tick_hz(hz) {
+        Ok(())
+    } else {
+        Err(UnsupportedTickRate)
+    }
+}
+
 pub(crate) const GCD_1K: u64 = gcd(TICK_HZ, 1_000);
 pub(crate) const GCD_1M: u64 = gcd(TICK_HZ, 1_000_000);
 pub(crate) const GCD_1G: u64 = gcd(TICK_HZ, 1_000_000_000);
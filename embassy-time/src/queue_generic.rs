@@ -1,4 +1,4 @@
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::cmp::{min, Ordering};
 use core::task::Waker;
 
@@ -7,7 +7,7 @@ use embassy_time_driver::{allocate_alarm, set_alarm, set_alarm_callback, AlarmHa
 use embassy_time_queue_driver::TimerQueue;
 use heapless::Vec;
 
-use crate::Instant;
+use crate::{Duration, Instant};
 
 #[cfg(feature = "generic-queue-8")]
 const QUEUE_SIZE: usize = 8;
@@ -60,7 +60,7 @@ struct InnerQueue {
 }
 
 impl InnerQueue {
-    fn schedule_wake(&mut self, at: Instant, waker: &Waker) {
+    fn schedule_wake(&mut self, at: Instant, waker: &Waker, window: Duration) {
         self.queue
             .iter_mut()
             .find(|timer| timer.waker.will_wake(waker))
@@ -87,10 +87,10 @@ impl InnerQueue {
         // dispatch all timers that are already due
         //
         // Then update the alarm if necessary
-        self.dispatch();
+        self.dispatch(window);
     }
 
-    fn dispatch(&mut self) {
+    fn dispatch(&mut self, window: Duration) {
         loop {
             let now = Instant::now();
 
@@ -108,6 +108,19 @@ impl InnerQueue {
                 }
             }
 
+            // Delay the alarm up to the last timer within the coalescing window of the first one,
+            // so they all fire on the same alarm.
+            if window.as_ticks() > 0 && next_alarm != Instant::MAX {
+                let limit = next_alarm.saturating_add(window);
+                next_alarm = self
+                    .queue
+                    .iter()
+                    .map(|timer| timer.at)
+                    .filter(|at| *at <= limit)
+                    .max()
+                    .unwrap_or(next_alarm);
+            }
+
             if self.update_alarm(next_alarm) {
                 break;
             }
@@ -122,24 +135,27 @@ impl InnerQueue {
         }
     }
 
-    fn handle_alarm(&mut self) {
-        self.dispatch();
+    fn handle_alarm(&mut self, window: Duration) {
+        self.dispatch(window);
     }
 }
 
 struct Queue {
     inner: Mutex<RefCell<Option<InnerQueue>>>,
+    coalescing_window: Mutex<Cell<Duration>>,
 }
 
 impl Queue {
     const fn new() -> Self {
         Self {
             inner: Mutex::new(RefCell::new(None)),
+            coalescing_window: Mutex::new(Cell::new(Duration::from_ticks(0))),
         }
     }
 
     fn schedule_wake(&'static self, at: Instant, waker: &Waker) {
         critical_section::with(|cs| {
+            let window = self.coalescing_window.borrow(cs).get();
             let mut inner = self.inner.borrow_ref_mut(cs);
 
             if inner.is_none() {}
@@ -153,12 +169,15 @@ impl Queue {
                         alarm: handle,
                     }
                 })
-                .schedule_wake(at, waker)
+                .schedule_wake(at, waker, window)
         });
     }
 
     fn handle_alarm(&self) {
-        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).as_mut().unwrap().handle_alarm())
+        critical_section::with(|cs| {
+            let window = self.coalescing_window.borrow(cs).get();
+            self.inner.borrow_ref_mut(cs).as_mut().unwrap().handle_alarm(window)
+        })
     }
 
     fn handle_alarm_callback(ctx: *mut ()) {
@@ -174,6 +193,17 @@ impl TimerQueue for Queue {
 
 embassy_time_queue_driver::timer_queue_impl!(static QUEUE: Queue = Queue::new());
 
+/// Set the timer coalescing window of the generic queue.
+///
+/// Timers expiring within `window` of the next one to expire are fired together, on a single
+/// alarm, at the latest of their expiration times. This reduces the number of wakeups and alarm
+/// reprogrammings, at the cost of delaying timers by up to `window`. Timers never fire early.
+///
+/// The default window is zero, which disables coalescing.
+pub fn set_coalescing_window(window: Duration) {
+    critical_section::with(|cs| QUEUE.coalescing_window.borrow(cs).set(window))
+}
+
 #[cfg(test)]
 #[cfg(feature = "mock-driver")]
 mod tests {
@@ -229,6 +259,7 @@ mod tests {
     fn setup() {
         MockDriver::get().reset();
         critical_section::with(|cs| *QUEUE.inner.borrow_ref_mut(cs) = None);
+        super::set_coalescing_window(Duration::from_ticks(0));
     }
 
     fn queue_len() -> usize {
@@ -359,4 +390,36 @@ mod tests {
         assert_eq!(queue_len(), super::QUEUE_SIZE);
         assert!(second_waker.awoken.get());
     }
+
+    #[test]
+    #[serial]
+    fn test_coalescing() {
+        setup();
+
+        super::set_coalescing_window(Duration::from_secs(1));
+
+        let waker = TestWaker::new();
+        let waker2 = TestWaker::new();
+        let waker3 = TestWaker::new();
+
+        QUEUE.schedule_wake(Instant::from_millis(100_000), &waker.waker);
+        QUEUE.schedule_wake(Instant::from_millis(100_500), &waker2.waker);
+        QUEUE.schedule_wake(Instant::from_millis(102_000), &waker3.waker);
+
+        MockDriver::get().advance(Duration::from_millis(100_000));
+
+        assert!(!waker.awoken.get());
+        assert!(!waker2.awoken.get());
+
+        MockDriver::get().advance(Duration::from_millis(500));
+
+        assert!(waker.awoken.get());
+        assert!(waker2.awoken.get());
+        assert!(!waker3.awoken.get());
+
+        MockDriver::get().advance(Duration::from_millis(1_500));
+
+        assert!(waker3.awoken.get());
+        assert_eq!(queue_len(), 0);
+    }
 }
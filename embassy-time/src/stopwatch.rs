@@ -0,0 +1,159 @@
+use crate::{Duration, Instant};
+
+/// Running statistics over a set of durations: count, minimum, maximum and mean.
+///
+/// Only integer arithmetic on ticks is used, so this is cheap enough to keep enabled in
+/// production builds, for instance to measure interrupt-to-task latency or loop jitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DurationStats {
+    count: u32,
+    total: u64,
+    min: u64,
+    max: u64,
+}
+
+impl DurationStats {
+    /// Create empty statistics.
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            total: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    /// Add a duration to the statistics.
+    pub fn record(&mut self, duration: Duration) {
+        let ticks = duration.as_ticks();
+        self.count = self.count.saturating_add(1);
+        self.total = self.total.saturating_add(ticks);
+        self.min = self.min.min(ticks);
+        self.max = self.max.max(ticks);
+    }
+
+    /// Add the duration elapsed since `start` to the statistics.
+    pub fn record_since(&mut self, start: Instant) {
+        self.record(Instant::now().saturating_duration_since(start))
+    }
+
+    /// Clear the statistics.
+    pub fn reset(&mut self) {
+        *self = Self::new()
+    }
+
+    /// Number of durations recorded.
+    pub const fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Shortest duration recorded, or `None` if no duration was recorded.
+    pub const fn min(&self) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(Duration::from_ticks(self.min))
+        }
+    }
+
+    /// Longest duration recorded, or `None` if no duration was recorded.
+    pub const fn max(&self) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(Duration::from_ticks(self.max))
+        }
+    }
+
+    /// Mean of the durations recorded, rounding down, or `None` if no duration was recorded.
+    pub const fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(Duration::from_ticks(self.total / self.count as u64))
+        }
+    }
+
+    /// Sum of the durations recorded, saturating at [`Duration::MAX`].
+    pub const fn total(&self) -> Duration {
+        Duration::from_ticks(self.total)
+    }
+}
+
+impl Default for DurationStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Measures lap times, and accumulates them in [`DurationStats`].
+///
+/// ```no_run
+/// use embassy_time::Stopwatch;
+///
+/// let mut stopwatch = Stopwatch::new();
+/// loop {
+///     // ... loop body ...
+///     stopwatch.lap();
+///     # break;
+/// }
+/// let jitter = stopwatch.stats().max().unwrap() - stopwatch.stats().min().unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stopwatch {
+    start: Instant,
+    lap_start: Instant,
+    stats: DurationStats,
+}
+
+impl Stopwatch {
+    /// Create a new stopwatch, and start it now.
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            lap_start: now,
+            stats: DurationStats::new(),
+        }
+    }
+
+    /// Restart the stopwatch now, and clear its statistics.
+    pub fn restart(&mut self) {
+        *self = Self::new()
+    }
+
+    /// Duration elapsed since the stopwatch was started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Duration elapsed since the current lap started.
+    pub fn lap_elapsed(&self) -> Duration {
+        self.lap_start.elapsed()
+    }
+
+    /// End the current lap and start a new one. Returns the duration of the lap, which is also
+    /// added to the statistics.
+    pub fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let lap = now.saturating_duration_since(self.lap_start);
+        self.lap_start = now;
+        self.stats.record(lap);
+        lap
+    }
+
+    /// Start a new lap without recording the current one.
+    ///
+    /// This allows measuring only part of a loop: call `skip` where the measured part starts, and
+    /// [`lap`](Self::lap) where it ends.
+    pub fn skip(&mut self) {
+        self.lap_start = Instant::now();
+    }
+
+    /// Statistics of the laps recorded so far.
+    pub fn stats(&self) -> &DurationStats {
+        &self.stats
+    }
+}
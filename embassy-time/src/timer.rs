@@ -39,6 +39,27 @@ pub async fn with_deadline<F: Future>(at: Instant, fut: F) -> Result<F::Output,
     }
 }
 
+/// Provides methods to run a future with a timeout or a deadline.
+///
+/// Implemented for all futures.
+pub trait TimeoutExt: Future + Sized {
+    /// Runs this future with a timeout.
+    ///
+    /// See [`with_timeout`].
+    fn timeout_after(self, timeout: Duration) -> impl Future<Output = Result<Self::Output, TimeoutError>> {
+        with_timeout(timeout, self)
+    }
+
+    /// Runs this future with a deadline time.
+    ///
+    /// See [`with_deadline`].
+    fn timeout_at(self, at: Instant) -> impl Future<Output = Result<Self::Output, TimeoutError>> {
+        with_deadline(at, self)
+    }
+}
+
+impl<F: Future> TimeoutExt for F {}
+
 /// A future that completes at a specified [Instant](struct.Instant.html).
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Timer {
@@ -203,6 +224,69 @@ impl Ticker {
             }
         })
     }
+
+    /// Waits for the next tick, skipping the ticks that were missed.
+    ///
+    /// Unlike [`next`](Self::next), which returns immediately for each missed tick to catch up,
+    /// this only returns once and keeps the ticks aligned on the original schedule. Returns the
+    /// number of ticks that were skipped.
+    pub fn next_skip_missed(&mut self) -> impl Future<Output = u64> + '_ {
+        poll_fn(|cx| {
+            let now = Instant::now();
+            if self.expires_at <= now {
+                let period = self.duration.as_ticks();
+                let missed = if period == 0 {
+                    0
+                } else {
+                    (now.as_ticks() - self.expires_at.as_ticks()) / period
+                };
+                self.expires_at += Duration::from_ticks(period.saturating_mul(missed + 1));
+                Poll::Ready(missed)
+            } else {
+                embassy_time_queue_driver::schedule_wake(self.expires_at.as_ticks(), cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Runs `f` on every tick, forever.
+    ///
+    /// The schedule doesn't drift, however long `f` takes. If `f` overruns one or more ticks, they
+    /// are skipped rather than running `f` back to back to catch up.
+    pub async fn run<F, Fut>(&mut self, mut f: F) -> !
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        loop {
+            self.next_skip_missed().await;
+            f().await;
+        }
+    }
+}
+
+/// Runs `f` every `period`, forever, starting one period from now.
+///
+/// This is a shorthand for [`Ticker::every`] followed by [`Ticker::run`]: the schedule doesn't
+/// drift, and ticks overrun by `f` are skipped.
+///
+/// ``` no_run
+/// use embassy_time::{every, Duration};
+///
+/// #[embassy_executor::task]
+/// async fn blink() {
+///     every(Duration::from_millis(500), || async {
+///         // toggle a LED
+///     })
+///     .await
+/// }
+/// ```
+pub async fn every<F, Fut>(period: Duration, f: F) -> !
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    Ticker::every(period).run(f).await
 }
 
 impl Unpin for Ticker {}
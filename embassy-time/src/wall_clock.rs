@@ -0,0 +1,255 @@
+//! Mapping of monotonic [`Instant`]s to calendar time.
+//!
+//! The time driver only provides a monotonic timebase, starting at an arbitrary point. This module
+//! keeps a global reference point, set from an external time source such as an RTC or SNTP, and
+//! uses it to convert [`Instant`]s to UTC.
+//!
+//! The drift between the time driver and the time source can be compensated, either by setting it
+//! explicitly with [`set_drift_ppb`], or by estimating it from the error observed on each call to
+//! [`resync`].
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use crate::Instant;
+
+/// A point in time, as the number of microseconds since the Unix epoch (1970-01-01 00:00:00 UTC).
+///
+/// Leap seconds are not accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnixTime {
+    micros: u64,
+}
+
+impl UnixTime {
+    /// Create a `UnixTime` from the number of seconds since the Unix epoch.
+    pub const fn from_secs(secs: u64) -> Self {
+        Self { micros: secs * 1_000_000 }
+    }
+
+    /// Create a `UnixTime` from the number of milliseconds since the Unix epoch.
+    pub const fn from_millis(millis: u64) -> Self {
+        Self { micros: millis * 1_000 }
+    }
+
+    /// Create a `UnixTime` from the number of microseconds since the Unix epoch.
+    pub const fn from_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    /// Number of whole seconds since the Unix epoch.
+    pub const fn as_secs(&self) -> u64 {
+        self.micros / 1_000_000
+    }
+
+    /// Number of milliseconds since the Unix epoch, rounding down.
+    pub const fn as_millis(&self) -> u64 {
+        self.micros / 1_000
+    }
+
+    /// Number of microseconds since the Unix epoch.
+    pub const fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    /// Fractional part of the second, in microseconds.
+    pub const fn subsec_micros(&self) -> u32 {
+        (self.micros % 1_000_000) as u32
+    }
+
+    /// Convert to a calendar date and time, in UTC.
+    pub const fn to_date_time(&self) -> DateTime {
+        let secs = self.as_secs();
+        let days = secs / 86_400;
+        let secs_of_day = secs % 86_400;
+
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year: year as u32,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+            microsecond: self.subsec_micros(),
+        }
+    }
+}
+
+/// A calendar date and time, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTime {
+    /// Year, e.g. 2024.
+    pub year: u32,
+    /// Month, 1 to 12.
+    pub month: u8,
+    /// Day of the month, 1 to 31.
+    pub day: u8,
+    /// Hour, 0 to 23.
+    pub hour: u8,
+    /// Minute, 0 to 59.
+    pub minute: u8,
+    /// Second, 0 to 59.
+    pub second: u8,
+    /// Microsecond, 0 to 999_999.
+    pub microsecond: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Reference {
+    instant: Instant,
+    time: UnixTime,
+    drift_ppb: i32,
+}
+
+static REFERENCE: Mutex<Cell<Option<Reference>>> = Mutex::new(Cell::new(None));
+
+fn reference() -> Option<Reference> {
+    critical_section::with(|cs| REFERENCE.borrow(cs).get())
+}
+
+fn convert(reference: &Reference, instant: Instant) -> UnixTime {
+    let elapsed = instant.as_micros() as i128 - reference.instant.as_micros() as i128;
+    let corrected = elapsed + elapsed * reference.drift_ppb as i128 / 1_000_000_000;
+    let micros = (reference.time.as_micros() as i128 + corrected).clamp(0, u64::MAX as i128);
+    UnixTime::from_micros(micros as u64)
+}
+
+/// Set the current time.
+///
+/// This keeps the current drift compensation.
+pub fn set_time(time: UnixTime) {
+    set_time_at(Instant::now(), time)
+}
+
+/// Set the time at a given instant, for instance the instant at which a time source was read.
+///
+/// This keeps the current drift compensation.
+pub fn set_time_at(instant: Instant, time: UnixTime) {
+    critical_section::with(|cs| {
+        let cell = REFERENCE.borrow(cs);
+        let drift_ppb = cell.get().map(|r| r.drift_ppb).unwrap_or(0);
+        cell.set(Some(Reference {
+            instant,
+            time,
+            drift_ppb,
+        }))
+    })
+}
+
+/// Set the drift of the time driver relative to real time, in parts per billion.
+///
+/// A positive value means the time driver runs slow, so its durations are stretched when
+/// converted. Does nothing if the time was never set.
+pub fn set_drift_ppb(drift_ppb: i32) {
+    critical_section::with(|cs| {
+        let cell = REFERENCE.borrow(cs);
+        if let Some(mut reference) = cell.get() {
+            // Re-anchor, so the change doesn't make the time jump.
+            let now = Instant::now();
+            reference.time = convert(&reference, now);
+            reference.instant = now;
+            reference.drift_ppb = drift_ppb;
+            cell.set(Some(reference));
+        }
+    })
+}
+
+/// Get the drift compensation, in parts per billion. Returns 0 if the time was never set.
+pub fn drift_ppb() -> i32 {
+    reference().map(|r| r.drift_ppb).unwrap_or(0)
+}
+
+/// Set the current time, and adjust the drift compensation from the error accumulated since the
+/// time was last set.
+///
+/// Returns the error between the time source and the local time, in microseconds, or `None` if
+/// the time was never set. A positive error means the local time was behind.
+pub fn resync(time: UnixTime) -> Option<i64> {
+    let now = Instant::now();
+
+    critical_section::with(|cs| {
+        let cell = REFERENCE.borrow(cs);
+        let Some(reference) = cell.get() else {
+            cell.set(Some(Reference {
+                instant: now,
+                time,
+                drift_ppb: 0,
+            }));
+            return None;
+        };
+
+        let error = time.as_micros() as i128 - convert(&reference, now).as_micros() as i128;
+        let elapsed = now.as_micros() as i128 - reference.instant.as_micros() as i128;
+
+        let drift_ppb = if elapsed > 0 {
+            let drift = reference.drift_ppb as i128 + error * 1_000_000_000 / elapsed;
+            drift.clamp(i32::MIN as i128, i32::MAX as i128) as i32
+        } else {
+            reference.drift_ppb
+        };
+
+        cell.set(Some(Reference {
+            instant: now,
+            time,
+            drift_ppb,
+        }));
+
+        Some(error.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    })
+}
+
+/// Convert an [`Instant`] to UTC. Returns `None` if the time was never set.
+pub fn to_utc(instant: Instant) -> Option<UnixTime> {
+    reference().map(|r| convert(&r, instant))
+}
+
+/// Get the current time in UTC. Returns `None` if the time was never set.
+pub fn now_utc() -> Option<UnixTime> {
+    to_utc(Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_date_time() {
+        let dt = UnixTime::from_secs(0).to_date_time();
+        assert_eq!((dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second), (1970, 1, 1, 0, 0, 0));
+
+        let dt = UnixTime::from_micros(951_782_400_250_000).to_date_time();
+        assert_eq!((dt.year, dt.month, dt.day), (2000, 2, 29));
+        assert_eq!(dt.microsecond, 250_000);
+
+        let dt = UnixTime::from_secs(1_704_067_199).to_date_time();
+        assert_eq!((dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second), (2023, 12, 31, 23, 59, 59));
+    }
+
+    #[test]
+    fn test_convert_with_drift() {
+        let reference = Reference {
+            instant: Instant::from_secs(10),
+            time: UnixTime::from_secs(1_000),
+            drift_ppb: 1_000_000,
+        };
+
+        assert_eq!(convert(&reference, Instant::from_secs(10)), UnixTime::from_secs(1_000));
+        assert_eq!(
+            convert(&reference, Instant::from_secs(1010)),
+            UnixTime::from_micros(2_001_000_000)
+        );
+    }
+}
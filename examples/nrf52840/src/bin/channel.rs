@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use defmt::unwrap;
+use defmt::{unwrap, Format};
 use embassy_executor::Spawner;
 use embassy_nrf::gpio::{Level, Output, OutputDrive};
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
@@ -9,6 +9,7 @@ use embassy_sync::channel::Channel;
 use embassy_time::Timer;
 use {defmt_rtt as _, panic_probe as _};
 
+#[derive(Format)]
 enum LedState {
     On,
     Off,
@@ -19,9 +20,9 @@ static CHANNEL: Channel<ThreadModeRawMutex, LedState, 1> = Channel::new();
 #[embassy_executor::task]
 async fn my_task() {
     loop {
-        CHANNEL.send(LedState::On).await;
+        unwrap!(CHANNEL.send(LedState::On).await);
         Timer::after_secs(1).await;
-        CHANNEL.send(LedState::Off).await;
+        unwrap!(CHANNEL.send(LedState::Off).await);
         Timer::after_secs(1).await;
     }
 }
@@ -34,7 +35,7 @@ async fn main(spawner: Spawner) {
     unwrap!(spawner.spawn(my_task()));
 
     loop {
-        match CHANNEL.receive().await {
+        match unwrap!(CHANNEL.receive().await) {
             LedState::On => led.set_high(),
             LedState::Off => led.set_low(),
         }
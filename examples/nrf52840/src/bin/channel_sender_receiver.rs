@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use defmt::unwrap;
+use defmt::{unwrap, Format};
 use embassy_executor::Spawner;
 use embassy_nrf::gpio::{AnyPin, Level, Output, OutputDrive, Pin};
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
@@ -10,6 +10,7 @@ use embassy_time::Timer;
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
+#[derive(Format)]
 enum LedState {
     On,
     Off,
@@ -20,9 +21,9 @@ static CHANNEL: StaticCell<Channel<NoopRawMutex, LedState, 1>> = StaticCell::new
 #[embassy_executor::task]
 async fn send_task(sender: Sender<'static, NoopRawMutex, LedState, 1>) {
     loop {
-        sender.send(LedState::On).await;
+        unwrap!(sender.send(LedState::On).await);
         Timer::after_secs(1).await;
-        sender.send(LedState::Off).await;
+        unwrap!(sender.send(LedState::Off).await);
         Timer::after_secs(1).await;
     }
 }
@@ -32,7 +33,7 @@ async fn recv_task(led: AnyPin, receiver: Receiver<'static, NoopRawMutex, LedSta
     let mut led = Output::new(led, Level::Low, OutputDrive::Standard);
 
     loop {
-        match receiver.receive().await {
+        match unwrap!(receiver.receive().await) {
             LedState::On => led.set_high(),
             LedState::Off => led.set_low(),
         }
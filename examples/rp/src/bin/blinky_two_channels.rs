@@ -14,6 +14,7 @@ use embassy_time::{Duration, Ticker};
 use gpio::{AnyPin, Level, Output};
 use {defmt_rtt as _, panic_probe as _};
 
+#[derive(Format)]
 enum LedState {
     Toggle,
 }
@@ -34,7 +35,7 @@ async fn main(spawner: Spawner) {
     )));
 
     loop {
-        match CHANNEL.receive().await {
+        match unwrap!(CHANNEL.receive().await) {
             LedState::Toggle => led.toggle(),
         }
     }
@@ -44,7 +45,7 @@ async fn main(spawner: Spawner) {
 async fn toggle_led(control: Sender<'static, ThreadModeRawMutex, LedState, 64>, delay: Duration) {
     let mut ticker = Ticker::every(delay);
     loop {
-        control.send(LedState::Toggle).await;
+        unwrap!(control.send(LedState::Toggle).await);
         ticker.next().await;
     }
 }
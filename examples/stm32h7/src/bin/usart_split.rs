@@ -43,7 +43,7 @@ async fn main(spawner: Spawner) -> ! {
     unwrap!(spawner.spawn(reader(rx)));
 
     loop {
-        let buf = CHANNEL.receive().await;
+        let buf = unwrap!(CHANNEL.receive().await);
         info!("writing...");
         unwrap!(tx.write(&buf).await);
     }
@@ -55,6 +55,6 @@ async fn reader(mut rx: UartRx<'static, UART7, DMA1_CH1>) {
     loop {
         info!("reading...");
         unwrap!(rx.read(&mut buf).await);
-        CHANNEL.send(buf).await;
+        unwrap!(CHANNEL.send(buf).await);
     }
 }
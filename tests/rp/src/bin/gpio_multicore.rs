@@ -35,12 +35,12 @@ async fn core0_task(p: PIN_0) {
 
     let mut pin = Output::new(p, Level::Low);
 
-    CHANNEL0.send(()).await;
-    CHANNEL1.receive().await;
+    unwrap!(CHANNEL0.send(()).await);
+    unwrap!(CHANNEL1.receive().await);
 
     pin.set_high();
 
-    CHANNEL1.receive().await;
+    unwrap!(CHANNEL1.receive().await);
 
     info!("Test OK");
     cortex_m::asm::bkpt();
@@ -50,14 +50,14 @@ async fn core0_task(p: PIN_0) {
 async fn core1_task(p: PIN_1) {
     info!("CORE1 is running");
 
-    CHANNEL0.receive().await;
+    unwrap!(CHANNEL0.receive().await);
 
     let mut pin = Input::new(p, Pull::Down);
     let wait = pin.wait_for_rising_edge();
 
-    CHANNEL1.send(()).await;
+    unwrap!(CHANNEL1.send(()).await);
 
     wait.await;
 
-    CHANNEL1.send(()).await;
+    unwrap!(CHANNEL1.send(()).await);
 }
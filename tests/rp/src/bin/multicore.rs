@@ -31,8 +31,8 @@ fn main() -> ! {
 async fn core0_task() {
     info!("CORE0 is running");
     let ping = true;
-    CHANNEL0.send(ping).await;
-    let pong = CHANNEL1.receive().await;
+    unwrap!(CHANNEL0.send(ping).await);
+    let pong = unwrap!(CHANNEL1.receive().await);
     assert_eq!(ping, pong);
 
     info!("Test OK");
@@ -42,6 +42,6 @@ async fn core0_task() {
 #[embassy_executor::task]
 async fn core1_task() {
     info!("CORE1 is running");
-    let ping = CHANNEL0.receive().await;
-    CHANNEL1.send(ping).await;
+    let ping = unwrap!(CHANNEL0.receive().await);
+    unwrap!(CHANNEL1.send(ping).await);
 }